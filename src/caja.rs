@@ -0,0 +1,158 @@
+use crate::db;
+use crate::models::{ConteoCaja, MetodoPago, SesionCaja};
+use slint::{ModelRc, SharedString, StandardListViewItem, VecModel};
+use std::rc::Rc;
+use std::sync::{Mutex, OnceLock};
+
+/// Cierres en el orden mostrado en el reporte
+static CIERRES: OnceLock<Mutex<Vec<SesionCaja>>> = OnceLock::new();
+
+fn get_cierres() -> &'static Mutex<Vec<SesionCaja>> {
+    CIERRES.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+/// Interpreta un monto no negativo; vacío = 0
+fn parse_monto(texto: &str, campo: &str) -> Result<f64, String> {
+    let texto = texto.trim();
+    if texto.is_empty() {
+        return Ok(0.0);
+    }
+    let monto: f64 = texto
+        .parse()
+        .map_err(|_| format!("{}: el monto debe ser un número válido", campo))?;
+    if !monto.is_finite() || monto < 0.0 {
+        return Err(format!("{}: el monto no puede ser negativo", campo));
+    }
+    Ok(monto)
+}
+
+/// Sesión de caja con su conteo por método de pago: lo esperado si está abierta o el arqueo
+/// guardado al cerrarla
+#[derive(Debug, Clone)]
+pub struct CajaConConteo {
+    pub sesion: SesionCaja,
+    pub conteo: Vec<ConteoCaja>,
+}
+
+/// Caja abierta del usuario con lo esperado por método de pago (Send-safe)
+pub fn estado_actual(usuario_id: i64) -> Result<Option<CajaConConteo>, Box<dyn std::error::Error>> {
+    let conn = db::obtener_conexion()?;
+    let Some(sesion) = db::caja::sesion_abierta(&conn, usuario_id)? else {
+        return Ok(None);
+    };
+    let conteo = db::caja::esperado(&conn, sesion.id)?;
+    Ok(Some(CajaConConteo { sesion, conteo }))
+}
+
+/// Abre la caja del usuario con el fondo inicial indicado. Devuelve el id de la sesión.
+pub fn abrir(usuario_id: i64, fondo: &str) -> Result<i64, Box<dyn std::error::Error>> {
+    let fondo = parse_monto(fondo, "Fondo inicial")?;
+    let conn = db::obtener_conexion()?;
+    Ok(db::caja::abrir(&conn, usuario_id, fondo)?)
+}
+
+/// Cierra la caja abierta del usuario con lo contado por método (en el orden de
+/// `MetodoPago::TODOS`). Devuelve la sesión cerrada y su arqueo.
+pub fn cerrar(
+    usuario_id: i64,
+    contado: &[&str],
+    notas: &str,
+) -> Result<CajaConConteo, Box<dyn std::error::Error>> {
+    let contado = MetodoPago::TODOS
+        .iter()
+        .zip(contado)
+        .map(|(&metodo, texto)| Ok((metodo, parse_monto(texto, metodo.etiqueta())?)))
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let mut conn = db::obtener_conexion()?;
+    let sesion_id = db::caja::id_sesion_abierta(&conn, usuario_id)?.ok_or("No tiene una caja abierta")?;
+    let conteo = db::caja::cerrar(&mut conn, sesion_id, &contado, notas)?;
+    let sesion = db::caja::obtener_sesion(&conn, sesion_id)?.ok_or("La caja cerrada no se encontró")?;
+    Ok(CajaConConteo { sesion, conteo })
+}
+
+/// Lee las sesiones cerradas y actualiza la caché del reporte (Send-safe)
+pub fn cargar_cierres() -> Result<Vec<SesionCaja>, Box<dyn std::error::Error>> {
+    let conn = db::obtener_conexion()?;
+    let cierres = db::caja::obtener_cerradas(&conn)?;
+    *get_cierres().lock().unwrap() = cierres.clone();
+    Ok(cierres)
+}
+
+/// Sesión de la fila indicada del reporte con su arqueo
+pub fn detalle_cierre(index: i32) -> Result<Option<CajaConConteo>, Box<dyn std::error::Error>> {
+    let Some(sesion) = get_cierres().lock().unwrap().get(index as usize).cloned() else {
+        return Ok(None);
+    };
+    let conn = db::obtener_conexion()?;
+    let conteo = db::caja::obtener_arqueo(&conn, sesion.id)?;
+    Ok(Some(CajaConConteo { sesion, conteo }))
+}
+
+/// Resumen de una sesión para el encabezado del panel
+pub fn resumen(sesion: &SesionCaja) -> String {
+    let mut texto = format!(
+        "CAJA #{} · {} · abierta {} · fondo ${:.2} · {} venta(s)",
+        sesion.id,
+        sesion.usuario_nombre.as_deref().unwrap_or("-"),
+        sesion.apertura.format("%d/%m/%Y %H:%M"),
+        sesion.fondo_inicial,
+        sesion.ventas
+    );
+    if let Some(cierre) = sesion.cierre {
+        texto.push_str(&format!(" · cerrada {}", cierre.format("%d/%m/%Y %H:%M")));
+    }
+    if let Some(notas) = &sesion.notas {
+        texto.push_str(&format!(" · {}", notas));
+    }
+    texto
+}
+
+/// Convierte el arqueo a filas de tabla (Método, Esperado, Contado, Diferencia).
+/// Con `contado = false` (caja abierta) solo se muestra lo esperado.
+pub fn arqueo_to_model_rows(arqueo: &[ConteoCaja], contado: bool) -> ModelRc<ModelRc<StandardListViewItem>> {
+    let rows: Vec<ModelRc<StandardListViewItem>> = arqueo
+        .iter()
+        .map(|c| {
+            let (contado, diferencia) = if contado {
+                (format!("{:.2}", c.contado), format!("{:+.2}", c.diferencia()))
+            } else {
+                (String::new(), String::new())
+            };
+            let row_data = vec![
+                StandardListViewItem::from(SharedString::from(c.metodo.etiqueta())),
+                StandardListViewItem::from(SharedString::from(format!("{:.2}", c.esperado))),
+                StandardListViewItem::from(SharedString::from(contado)),
+                StandardListViewItem::from(SharedString::from(diferencia)),
+            ];
+            ModelRc::from(Rc::new(VecModel::from(row_data)))
+        })
+        .collect();
+
+    ModelRc::from(Rc::new(VecModel::from(rows)))
+}
+
+/// Convierte los cierres a filas de tabla (N°, Cajero, Apertura, Cierre, Fondo, Ventas, Esperado, Contado, Diferencia)
+pub fn cierres_to_model_rows(cierres: &[SesionCaja]) -> ModelRc<ModelRc<StandardListViewItem>> {
+    let rows: Vec<ModelRc<StandardListViewItem>> = cierres
+        .iter()
+        .map(|s| {
+            let row_data = vec![
+                StandardListViewItem::from(SharedString::from(s.id.to_string())),
+                StandardListViewItem::from(SharedString::from(s.usuario_nombre.as_deref().unwrap_or("-"))),
+                StandardListViewItem::from(SharedString::from(s.apertura.format("%d/%m/%Y %H:%M").to_string())),
+                StandardListViewItem::from(SharedString::from(
+                    s.cierre.map(|c| c.format("%d/%m/%Y %H:%M").to_string()).unwrap_or_default(),
+                )),
+                StandardListViewItem::from(SharedString::from(format!("{:.2}", s.fondo_inicial))),
+                StandardListViewItem::from(SharedString::from(s.ventas.to_string())),
+                StandardListViewItem::from(SharedString::from(format!("{:.2}", s.total_esperado))),
+                StandardListViewItem::from(SharedString::from(format!("{:.2}", s.total_contado))),
+                StandardListViewItem::from(SharedString::from(format!("{:+.2}", s.diferencia()))),
+            ];
+            ModelRc::from(Rc::new(VecModel::from(row_data)))
+        })
+        .collect();
+
+    ModelRc::from(Rc::new(VecModel::from(rows)))
+}
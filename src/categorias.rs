@@ -0,0 +1,266 @@
+use crate::db;
+use crate::models::impuesto::etiqueta_porcentaje;
+use crate::models::{Categoria, CategoriaConConteo, Subcategoria};
+use slint::{ModelRc, SharedString, StandardListViewItem, VecModel};
+use std::rc::Rc;
+use std::sync::{Mutex, OnceLock};
+
+/// Opción del selector de subcategoría para productos sin subcategoría (NULL)
+const SIN_SUBCATEGORIA: &str = "Sin subcategoría";
+
+/// Caché de categorías en el orden mostrado (tabla de gestión y selector del formulario)
+static CATEGORIAS: OnceLock<Mutex<Vec<CategoriaConConteo>>> = OnceLock::new();
+
+/// Subcategorías de la categoría seleccionada en la vista de gestión
+static SUBCATEGORIAS: OnceLock<Mutex<Vec<Subcategoria>>> = OnceLock::new();
+
+/// Subcategorías de la categoría elegida en el formulario de producto
+static SUBCATEGORIAS_FORM: OnceLock<Mutex<Vec<Subcategoria>>> = OnceLock::new();
+
+fn get_cache() -> &'static Mutex<Vec<CategoriaConConteo>> {
+    CATEGORIAS.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+fn get_subcategorias() -> &'static Mutex<Vec<Subcategoria>> {
+    SUBCATEGORIAS.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+fn get_subcategorias_form() -> &'static Mutex<Vec<Subcategoria>> {
+    SUBCATEGORIAS_FORM.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+/// Convierte un texto vacío en None
+fn opcional(valor: &str) -> Option<String> {
+    let valor = valor.trim();
+    (!valor.is_empty()).then(|| valor.to_string())
+}
+
+// ==========================================
+// CATEGORÍAS
+// ==========================================
+
+/// Lee las categorías con sus totales y actualiza la caché (Send-safe)
+pub fn cargar() -> Result<Vec<CategoriaConConteo>, Box<dyn std::error::Error>> {
+    let conn = db::obtener_conexion()?;
+    let categorias = db::categorias::obtener_categorias_con_conteo(&conn)?;
+    *get_cache().lock().unwrap() = categorias.clone();
+    Ok(categorias)
+}
+
+/// Categoría de la fila indicada (mismo orden en la tabla y en el selector)
+pub fn get_categoria_by_index(index: i32) -> Option<CategoriaConConteo> {
+    get_cache().lock().unwrap().get(index as usize).cloned()
+}
+
+/// Índice del selector -> categoria_id
+pub fn id_categoria(index: i32) -> Option<i64> {
+    get_categoria_by_index(index).map(|c| c.id)
+}
+
+/// categoria_id -> índice del selector (0 si no se encuentra)
+pub fn indice_categoria(categoria_id: i64) -> i32 {
+    get_cache()
+        .lock()
+        .unwrap()
+        .iter()
+        .position(|c| c.id == categoria_id)
+        .map(|i| i as i32)
+        .unwrap_or(0)
+}
+
+/// Nombres para el selector de categoría del formulario de producto
+pub fn nombres_para_select(categorias: &[CategoriaConConteo]) -> ModelRc<SharedString> {
+    let nombres: Vec<SharedString> = categorias
+        .iter()
+        .map(|c| SharedString::from(c.nombre.as_str()))
+        .collect();
+    ModelRc::from(Rc::new(VecModel::from(nombres)))
+}
+
+/// Crea (id = None) o actualiza una categoría con su tasa de IVA. Devuelve el id guardado.
+pub fn guardar_categoria(
+    id: Option<i64>,
+    nombre: &str,
+    descripcion: &str,
+    impuesto_id: i64,
+) -> Result<i64, Box<dyn std::error::Error>> {
+    let nombre = nombre.trim();
+    if nombre.is_empty() {
+        return Err("El nombre de la categoría es requerido".into());
+    }
+
+    let conn = db::obtener_conexion()?;
+    if db::categorias::existe_categoria(&conn, nombre, id)? {
+        return Err(format!("Ya existe una categoría llamada '{}'", nombre).into());
+    }
+
+    match id {
+        Some(id) => {
+            let categoria = Categoria {
+                id,
+                nombre: nombre.to_string(),
+                descripcion: opcional(descripcion),
+                impuesto_id,
+            };
+            if !db::categorias::actualizar_categoria(&conn, &categoria)? {
+                return Err("La categoría ya no existe".into());
+            }
+            Ok(id)
+        }
+        None => Ok(db::categorias::crear_categoria(
+            &conn,
+            nombre,
+            opcional(descripcion).as_deref(),
+            impuesto_id,
+        )?),
+    }
+}
+
+/// Texto de confirmación antes de eliminar. Falla si la categoría aún tiene productos,
+/// porque `productos.categoria_id` es obligatorio.
+pub fn advertencia_eliminar(categoria: &CategoriaConConteo) -> Result<String, Box<dyn std::error::Error>> {
+    let conn = db::obtener_conexion()?;
+    let productos = db::categorias::contar_productos_categoria(&conn, categoria.id)?;
+    if productos > 0 {
+        return Err(format!(
+            "La categoría '{}' tiene {} producto(s). Reasígnelos a otra categoría antes de eliminarla.",
+            categoria.nombre, productos
+        )
+        .into());
+    }
+
+    Ok(if categoria.total_subcategorias == 0 {
+        format!("¿Eliminar la categoría '{}'?", categoria.nombre)
+    } else {
+        format!(
+            "¿Eliminar la categoría '{}'? También se eliminarán sus {} subcategoría(s).",
+            categoria.nombre, categoria.total_subcategorias
+        )
+    })
+}
+
+/// Elimina la categoría (y en cascada sus subcategorías)
+pub fn eliminar_categoria(id: i64) -> Result<bool, Box<dyn std::error::Error>> {
+    let conn = db::obtener_conexion()?;
+    match db::categorias::eliminar_categoria(&conn, id) {
+        Ok(eliminada) => Ok(eliminada),
+        Err(rusqlite::Error::SqliteFailure(e, _))
+            if e.code == rusqlite::ErrorCode::ConstraintViolation =>
+        {
+            Err("La categoría tiene productos asignados".into())
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Convierte las categorías a filas de tabla (Nombre, Descripción, IVA, Subcategorías, Productos)
+pub fn to_model_rows(categorias: &[CategoriaConConteo]) -> ModelRc<ModelRc<StandardListViewItem>> {
+    let rows: Vec<ModelRc<StandardListViewItem>> = categorias
+        .iter()
+        .map(|c| {
+            let row_data = vec![
+                StandardListViewItem::from(SharedString::from(c.nombre.as_str())),
+                StandardListViewItem::from(SharedString::from(c.descripcion.clone().unwrap_or_default())),
+                StandardListViewItem::from(SharedString::from(etiqueta_porcentaje(c.porcentaje_impuesto))),
+                StandardListViewItem::from(SharedString::from(c.total_subcategorias.to_string())),
+                StandardListViewItem::from(SharedString::from(c.total_productos.to_string())),
+            ];
+            ModelRc::from(Rc::new(VecModel::from(row_data)))
+        })
+        .collect();
+
+    ModelRc::from(Rc::new(VecModel::from(rows)))
+}
+
+// ==========================================
+// SUBCATEGORÍAS (VISTA DE GESTIÓN)
+// ==========================================
+
+/// Lee las subcategorías de una categoría para la vista de gestión
+pub fn cargar_subcategorias(categoria_id: i64) -> Result<Vec<Subcategoria>, Box<dyn std::error::Error>> {
+    let conn = db::obtener_conexion()?;
+    let subcategorias = db::categorias::obtener_subcategorias(&conn, categoria_id)?;
+    *get_subcategorias().lock().unwrap() = subcategorias.clone();
+    Ok(subcategorias)
+}
+
+/// Agrega una subcategoría a la categoría indicada
+pub fn agregar_subcategoria(categoria_id: i64, nombre: &str) -> Result<i64, Box<dyn std::error::Error>> {
+    let nombre = nombre.trim();
+    if nombre.is_empty() {
+        return Err("El nombre de la subcategoría es requerido".into());
+    }
+
+    let conn = db::obtener_conexion()?;
+    if db::categorias::existe_subcategoria(&conn, categoria_id, nombre, None)? {
+        return Err(format!("La subcategoría '{}' ya existe en esta categoría", nombre).into());
+    }
+    Ok(db::categorias::crear_subcategoria(&conn, categoria_id, nombre)?)
+}
+
+/// Elimina la subcategoría de la fila indicada (sus productos quedan sin subcategoría)
+pub fn eliminar_subcategoria_por_indice(index: i32) -> Result<bool, Box<dyn std::error::Error>> {
+    let subcategoria = get_subcategorias().lock().unwrap().get(index as usize).cloned();
+    match subcategoria {
+        Some(s) => {
+            let conn = db::obtener_conexion()?;
+            Ok(db::categorias::eliminar_subcategoria(&conn, s.id)?)
+        }
+        None => Ok(false),
+    }
+}
+
+/// Convierte subcategorías a filas de tabla (Nombre)
+pub fn subcategorias_to_model_rows(subcategorias: &[Subcategoria]) -> ModelRc<ModelRc<StandardListViewItem>> {
+    let rows: Vec<ModelRc<StandardListViewItem>> = subcategorias
+        .iter()
+        .map(|s| {
+            let row_data = vec![StandardListViewItem::from(SharedString::from(s.nombre.as_str()))];
+            ModelRc::from(Rc::new(VecModel::from(row_data)))
+        })
+        .collect();
+
+    ModelRc::from(Rc::new(VecModel::from(rows)))
+}
+
+// ==========================================
+// SUBCATEGORÍAS (FORMULARIO DE PRODUCTO)
+// ==========================================
+
+/// Carga las subcategorías de la categoría elegida en el formulario de producto
+/// y devuelve las opciones del selector ("Sin subcategoría" primero)
+pub fn cargar_subcategorias_form(categoria_id: Option<i64>) -> Result<Vec<SharedString>, Box<dyn std::error::Error>> {
+    let subcategorias = match categoria_id {
+        Some(id) => {
+            let conn = db::obtener_conexion()?;
+            db::categorias::obtener_subcategorias(&conn, id)?
+        }
+        None => Vec::new(),
+    };
+
+    let nombres = std::iter::once(SharedString::from(SIN_SUBCATEGORIA))
+        .chain(subcategorias.iter().map(|s| SharedString::from(s.nombre.as_str())))
+        .collect();
+    *get_subcategorias_form().lock().unwrap() = subcategorias;
+    Ok(nombres)
+}
+
+/// Índice del selector de subcategoría -> subcategoria_id (None para "Sin subcategoría")
+pub fn id_subcategoria_form(index: i32) -> Option<i64> {
+    if index <= 0 {
+        return None;
+    }
+    get_subcategorias_form()
+        .lock()
+        .unwrap()
+        .get(index as usize - 1)
+        .map(|s| s.id)
+}
+
+/// subcategoria_id -> índice del selector (0 = "Sin subcategoría")
+pub fn indice_subcategoria_form(subcategoria_id: Option<i64>) -> i32 {
+    subcategoria_id
+        .and_then(|id| get_subcategorias_form().lock().unwrap().iter().position(|s| s.id == id))
+        .map(|i| i as i32 + 1)
+        .unwrap_or(0)
+}
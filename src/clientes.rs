@@ -0,0 +1,245 @@
+use crate::db;
+use crate::models::{Cliente, ClienteNuevo, FiltroVentas, Venta};
+use slint::{ModelRc, SharedString, StandardListViewItem, VecModel};
+use std::rc::Rc;
+use std::sync::{Mutex, OnceLock};
+
+/// Máximo de coincidencias en el selector de cliente del punto de venta
+const LIMITE_BUSQUEDA: i64 = 50;
+
+/// Caché de clientes en el orden de la tabla de gestión
+static CLIENTES: OnceLock<Mutex<Vec<Cliente>>> = OnceLock::new();
+
+/// Coincidencias de la última búsqueda del punto de venta, en el orden del selector
+static CLIENTES_POS: OnceLock<Mutex<Vec<Cliente>>> = OnceLock::new();
+
+fn get_cache() -> &'static Mutex<Vec<Cliente>> {
+    CLIENTES.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+fn get_cache_pos() -> &'static Mutex<Vec<Cliente>> {
+    CLIENTES_POS.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+/// Convierte un texto vacío en None
+fn opcional(valor: &str) -> Option<String> {
+    let valor = valor.trim();
+    (!valor.is_empty()).then(|| valor.to_string())
+}
+
+/// Interpreta el límite de crédito del formulario (vacío = 0)
+fn parse_limite(texto: &str) -> Result<f64, String> {
+    let texto = texto.trim();
+    if texto.is_empty() {
+        return Ok(0.0);
+    }
+    let limite: f64 = texto
+        .parse()
+        .map_err(|_| "El límite de crédito debe ser un número válido".to_string())?;
+    if !limite.is_finite() || limite < 0.0 {
+        return Err("El límite de crédito no puede ser negativo".to_string());
+    }
+    Ok(limite)
+}
+
+/// Lee los clientes y actualiza la caché (Send-safe)
+pub fn cargar() -> Result<Vec<Cliente>, Box<dyn std::error::Error>> {
+    let conn = db::obtener_conexion()?;
+    let clientes = db::clientes::obtener_clientes(&conn)?;
+    *get_cache().lock().unwrap() = clientes.clone();
+    Ok(clientes)
+}
+
+/// Cliente de la fila indicada en la tabla de gestión
+pub fn get_cliente_by_index(index: i32) -> Option<Cliente> {
+    get_cache().lock().unwrap().get(index as usize).cloned()
+}
+
+/// Valida nombre y documento antes de guardar (requeridos/únicos).
+/// Solo el consumidor final puede quedar sin documento.
+pub fn validar(documento: &str, nombre: &str, excluir_id: Option<i64>) -> Result<(), String> {
+    if nombre.trim().is_empty() {
+        return Err("El nombre del cliente es requerido".to_string());
+    }
+    if documento.trim().is_empty() {
+        if excluir_id == Some(Cliente::CONSUMIDOR_FINAL) {
+            return Ok(());
+        }
+        return Err("La cédula o RIF del cliente es requerida".to_string());
+    }
+
+    let conn = db::obtener_conexion().map_err(|e| e.to_string())?;
+    if db::clientes::existe_documento(&conn, documento, excluir_id).map_err(|e| e.to_string())? {
+        return Err(format!("La cédula o RIF '{}' ya está registrada en otro cliente", documento.trim()));
+    }
+    Ok(())
+}
+
+/// Crea (id = None) o actualiza un cliente. Devuelve el id guardado.
+/// `limite_credito` es None si el usuario no puede asignar crédito: el cliente conserva su límite
+/// (0 si es nuevo).
+pub fn guardar(
+    id: Option<i64>,
+    documento: &str,
+    nombre: &str,
+    direccion: &str,
+    telefono: &str,
+    email: &str,
+    limite_credito: Option<&str>,
+) -> Result<i64, Box<dyn std::error::Error>> {
+    validar(documento, nombre, id)?;
+    let limite = limite_credito.map(parse_limite).transpose()?;
+    if id == Some(Cliente::CONSUMIDOR_FINAL) && limite.is_some_and(|l| l > 0.0) {
+        return Err("El consumidor final no compra a crédito".into());
+    }
+    let conn = db::obtener_conexion()?;
+
+    match id {
+        Some(id) => {
+            let Some(actual) = db::clientes::obtener_cliente(&conn, id)? else {
+                return Err("El cliente ya no existe".into());
+            };
+            let cliente = Cliente {
+                id,
+                documento: opcional(documento),
+                nombre: nombre.trim().to_string(),
+                direccion: opcional(direccion),
+                telefono: opcional(telefono),
+                email: opcional(email),
+                limite_credito: limite.unwrap_or(actual.limite_credito),
+                saldo: actual.saldo,
+            };
+            if !db::clientes::actualizar_cliente(&conn, &cliente)? {
+                return Err("El cliente ya no existe".into());
+            }
+            Ok(id)
+        }
+        None => {
+            let cliente = ClienteNuevo {
+                documento: opcional(documento),
+                nombre: nombre.trim().to_string(),
+                direccion: opcional(direccion),
+                telefono: opcional(telefono),
+                email: opcional(email),
+                limite_credito: limite.unwrap_or(0.0),
+            };
+            Ok(db::clientes::crear_cliente(&conn, &cliente)?)
+        }
+    }
+}
+
+/// Texto de confirmación antes de eliminar. Falla para el consumidor final y para los
+/// clientes con ventas, que deben conservarse como historial.
+pub fn advertencia_eliminar(cliente: &Cliente) -> Result<String, Box<dyn std::error::Error>> {
+    if cliente.id == Cliente::CONSUMIDOR_FINAL {
+        return Err("El consumidor final no puede eliminarse".into());
+    }
+    let conn = db::obtener_conexion()?;
+    let ventas = db::clientes::contar_ventas(&conn, cliente.id)?;
+    if ventas > 0 {
+        return Err(format!(
+            "El cliente '{}' tiene {} venta(s) registrada(s) y no puede eliminarse.",
+            cliente.nombre, ventas
+        )
+        .into());
+    }
+    Ok(format!("¿Eliminar el cliente '{}'?", cliente.nombre))
+}
+
+/// Elimina el cliente
+pub fn eliminar(id: i64) -> Result<bool, Box<dyn std::error::Error>> {
+    let conn = db::obtener_conexion()?;
+    match db::clientes::eliminar_cliente(&conn, id) {
+        Ok(eliminado) => Ok(eliminado),
+        Err(rusqlite::Error::SqliteFailure(e, _))
+            if e.code == rusqlite::ErrorCode::ConstraintViolation =>
+        {
+            Err("El cliente tiene ventas registradas".into())
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Ventas del cliente, de la más reciente a la más antigua (Send-safe)
+pub fn historial_compras(cliente_id: i64) -> Result<Vec<Venta>, Box<dyn std::error::Error>> {
+    let conn = db::obtener_conexion()?;
+    let filtro = FiltroVentas {
+        cliente_id: Some(cliente_id),
+        ..Default::default()
+    };
+    Ok(db::ventas::obtener_historial(&conn, &filtro)?)
+}
+
+/// Resumen de las compras del cliente: cantidad de ventas y total neto
+pub fn resumen_compras(ventas: &[Venta]) -> String {
+    if ventas.is_empty() {
+        return "Sin compras registradas".to_string();
+    }
+    let total: f64 = ventas.iter().map(|v| v.total_neto()).sum();
+    format!("{} compra(s) · Total ${:.2}", ventas.len(), total)
+}
+
+/// Busca clientes por documento o nombre para el selector del punto de venta
+/// y actualiza sus opciones (el consumidor final siempre es la primera)
+pub fn buscar_para_pos(texto: &str) -> Result<Vec<Cliente>, Box<dyn std::error::Error>> {
+    let conn = db::obtener_conexion()?;
+    let clientes = db::clientes::buscar_clientes(&conn, texto, LIMITE_BUSQUEDA)?;
+    *get_cache_pos().lock().unwrap() = clientes.clone();
+    Ok(clientes)
+}
+
+/// Opciones del selector de cliente del punto de venta ("V-12345678 · José Pérez")
+pub fn clientes_pos_para_select(clientes: &[Cliente]) -> ModelRc<SharedString> {
+    let nombres: Vec<SharedString> = clientes.iter().map(|c| SharedString::from(c.etiqueta())).collect();
+    ModelRc::from(Rc::new(VecModel::from(nombres)))
+}
+
+/// Índice del selector del punto de venta -> cliente_id (el consumidor final si no es válido)
+pub fn id_cliente_pos(index: i32) -> i64 {
+    get_cache_pos()
+        .lock()
+        .unwrap()
+        .get(index.max(0) as usize)
+        .map(|c| c.id)
+        .unwrap_or(Cliente::CONSUMIDOR_FINAL)
+}
+
+/// Convierte los clientes a filas de tabla (Cédula/RIF, Nombre, Teléfono, Email, Saldo, Límite)
+pub fn to_model_rows(clientes: &[Cliente]) -> ModelRc<ModelRc<StandardListViewItem>> {
+    let rows: Vec<ModelRc<StandardListViewItem>> = clientes
+        .iter()
+        .map(|c| {
+            let row_data = vec![
+                StandardListViewItem::from(SharedString::from(c.documento.clone().unwrap_or_else(|| "-".into()))),
+                StandardListViewItem::from(SharedString::from(c.nombre.as_str())),
+                StandardListViewItem::from(SharedString::from(c.telefono.clone().unwrap_or_default())),
+                StandardListViewItem::from(SharedString::from(c.email.clone().unwrap_or_default())),
+                StandardListViewItem::from(SharedString::from(format!("{:.2}", c.saldo))),
+                StandardListViewItem::from(SharedString::from(format!("{:.2}", c.limite_credito))),
+            ];
+            ModelRc::from(Rc::new(VecModel::from(row_data)))
+        })
+        .collect();
+
+    ModelRc::from(Rc::new(VecModel::from(rows)))
+}
+
+/// Convierte las compras del cliente a filas de tabla (N°, Fecha, Estado, Total, A crédito, Devuelto)
+pub fn compras_to_model_rows(ventas: &[Venta]) -> ModelRc<ModelRc<StandardListViewItem>> {
+    let rows: Vec<ModelRc<StandardListViewItem>> = ventas
+        .iter()
+        .map(|v| {
+            let row_data = vec![
+                StandardListViewItem::from(SharedString::from(v.id.to_string())),
+                StandardListViewItem::from(SharedString::from(v.fecha.format("%d/%m/%Y %H:%M").to_string())),
+                StandardListViewItem::from(SharedString::from(v.estado.etiqueta())),
+                StandardListViewItem::from(SharedString::from(format!("{:.2}", v.total))),
+                StandardListViewItem::from(SharedString::from(format!("{:.2}", v.monto_credito))),
+                StandardListViewItem::from(SharedString::from(format!("{:.2}", v.monto_devuelto))),
+            ];
+            ModelRc::from(Rc::new(VecModel::from(row_data)))
+        })
+        .collect();
+
+    ModelRc::from(Rc::new(VecModel::from(rows)))
+}
@@ -0,0 +1,339 @@
+use crate::db;
+use crate::lotes;
+use crate::models::medida;
+use crate::models::{
+    EstadoOrden, LineaOrdenCompra, LineaOrdenNueva, LineaRecepcion, Medida, OrdenCompra, ProductoConMarca,
+};
+use slint::{ModelRc, SharedString, StandardListViewItem, VecModel};
+use std::rc::Rc;
+use std::sync::{Mutex, OnceLock};
+
+/// Línea de la orden de compra en preparación
+#[derive(Debug, Clone)]
+pub struct LineaBorrador {
+    pub producto_id: i64,
+    pub codigo: String,
+    pub nombre: String,
+    pub cantidad: f64,
+    pub medida: Medida,
+    pub costo_unitario: f64,
+}
+
+impl LineaBorrador {
+    pub fn subtotal(&self) -> f64 {
+        self.costo_unitario * self.cantidad
+    }
+}
+
+/// Orden de compra en preparación (aún no guardada)
+static BORRADOR: OnceLock<Mutex<Vec<LineaBorrador>>> = OnceLock::new();
+
+/// Últimos resultados de búsqueda de productos para la orden
+static RESULTADOS: OnceLock<Mutex<Vec<ProductoConMarca>>> = OnceLock::new();
+
+/// Órdenes en el orden mostrado en la tabla
+static ORDENES: OnceLock<Mutex<Vec<OrdenCompra>>> = OnceLock::new();
+
+/// Líneas de la orden seleccionada
+static LINEAS: OnceLock<Mutex<Vec<LineaOrdenCompra>>> = OnceLock::new();
+
+fn get_borrador() -> &'static Mutex<Vec<LineaBorrador>> {
+    BORRADOR.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+fn get_resultados() -> &'static Mutex<Vec<ProductoConMarca>> {
+    RESULTADOS.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+fn get_ordenes() -> &'static Mutex<Vec<OrdenCompra>> {
+    ORDENES.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+fn get_lineas() -> &'static Mutex<Vec<LineaOrdenCompra>> {
+    LINEAS.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+/// Interpreta una cantidad positiva en la medida del producto (admite "500 g" para un
+/// producto por kilo); vacío = `por_defecto`
+fn parse_cantidad(texto: &str, por_defecto: f64, medida: Medida) -> Result<f64, String> {
+    if texto.trim().is_empty() {
+        return Ok(por_defecto);
+    }
+    medida::parse_cantidad(texto, medida)
+}
+
+/// Interpreta un costo no negativo; vacío = `por_defecto`
+fn parse_costo(texto: &str, por_defecto: f64) -> Result<f64, String> {
+    let costo = if texto.trim().is_empty() {
+        por_defecto
+    } else {
+        texto
+            .trim()
+            .parse()
+            .map_err(|_| "El costo debe ser un número válido".to_string())?
+    };
+    if costo < 0.0 {
+        return Err("El costo no puede ser negativo".to_string());
+    }
+    Ok(costo)
+}
+
+// ==========================================
+// ORDEN EN PREPARACIÓN
+// ==========================================
+
+/// Busca productos por SKU o nombre y guarda los resultados en caché
+pub fn buscar(texto: &str) -> Result<Vec<ProductoConMarca>, Box<dyn std::error::Error>> {
+    let resultados = if texto.trim().is_empty() {
+        Vec::new()
+    } else {
+        let conn = db::obtener_conexion()?;
+        db::productos::buscar_productos(&conn, texto)?
+    };
+
+    *get_resultados().lock().unwrap() = resultados.clone();
+    Ok(resultados)
+}
+
+/// Agrega el producto (índice en los resultados) a la orden en preparación.
+/// Sin costo se usa el `precio_neto` actual; si el producto ya está, suma la cantidad.
+pub fn agregar_al_borrador(index: i32, cantidad: &str, costo: &str) -> Result<(), String> {
+    let producto = get_resultados()
+        .lock()
+        .unwrap()
+        .get(index as usize)
+        .cloned()
+        .ok_or_else(|| "Seleccione un producto de la búsqueda".to_string())?;
+
+    let medida = Medida::from_i32(producto.unidad_venta);
+    let cantidad = parse_cantidad(cantidad, 1.0, medida)?;
+    let costo = parse_costo(costo, producto.precio_neto)?;
+
+    let mut borrador = get_borrador().lock().unwrap();
+    if let Some(linea) = borrador.iter_mut().find(|l| l.producto_id == producto.id) {
+        linea.cantidad = medida::redondear_cantidad(linea.cantidad + cantidad);
+        linea.costo_unitario = costo;
+    } else {
+        borrador.push(LineaBorrador {
+            producto_id: producto.id,
+            codigo: producto.codigo.unwrap_or_else(|| "S/C".into()),
+            nombre: producto.nombre,
+            cantidad,
+            medida,
+            costo_unitario: costo,
+        });
+    }
+    Ok(())
+}
+
+/// Quita una línea de la orden en preparación
+pub fn quitar_del_borrador(index: i32) {
+    let mut borrador = get_borrador().lock().unwrap();
+    if (index as usize) < borrador.len() {
+        borrador.remove(index as usize);
+    }
+}
+
+/// Descarta la orden en preparación y los resultados de búsqueda
+pub fn limpiar_borrador() {
+    get_borrador().lock().unwrap().clear();
+    get_resultados().lock().unwrap().clear();
+}
+
+/// Copia de las líneas en preparación (Send-safe)
+pub fn borrador() -> Vec<LineaBorrador> {
+    get_borrador().lock().unwrap().clone()
+}
+
+/// Total de la orden en preparación
+pub fn total_borrador() -> f64 {
+    get_borrador().lock().unwrap().iter().map(|l| l.subtotal()).sum()
+}
+
+/// Guarda la orden en preparación para el proveedor indicado y la vacía
+pub fn guardar_orden(
+    proveedor_id: i64,
+    notas: &str,
+    usuario_id: Option<i64>,
+) -> Result<i64, Box<dyn std::error::Error>> {
+    let lineas: Vec<LineaOrdenNueva> = borrador()
+        .iter()
+        .map(|l| LineaOrdenNueva {
+            producto_id: l.producto_id,
+            cantidad: l.cantidad,
+            costo_unitario: l.costo_unitario,
+        })
+        .collect();
+    let notas = notas.trim();
+
+    let mut conn = db::obtener_conexion()?;
+    let orden_id = db::compras::crear_orden(
+        &mut conn,
+        proveedor_id,
+        usuario_id,
+        (!notas.is_empty()).then_some(notas),
+        &lineas,
+    )?;
+    limpiar_borrador();
+    Ok(orden_id)
+}
+
+// ==========================================
+// ÓRDENES REGISTRADAS
+// ==========================================
+
+/// Lee las órdenes de compra y actualiza la caché (Send-safe)
+pub fn cargar_ordenes() -> Result<Vec<OrdenCompra>, Box<dyn std::error::Error>> {
+    let conn = db::obtener_conexion()?;
+    let ordenes = db::compras::obtener_ordenes(&conn)?;
+    *get_ordenes().lock().unwrap() = ordenes.clone();
+    Ok(ordenes)
+}
+
+/// Orden de la fila indicada en la tabla
+pub fn get_orden_by_index(index: i32) -> Option<OrdenCompra> {
+    get_ordenes().lock().unwrap().get(index as usize).cloned()
+}
+
+/// Lee las líneas de una orden y actualiza la caché
+pub fn cargar_lineas(orden_id: i64) -> Result<Vec<LineaOrdenCompra>, Box<dyn std::error::Error>> {
+    let conn = db::obtener_conexion()?;
+    let lineas = db::compras::obtener_lineas(&conn, orden_id)?;
+    *get_lineas().lock().unwrap() = lineas.clone();
+    Ok(lineas)
+}
+
+/// Recibe una línea (índice en la tabla de líneas). Sin cantidad se recibe todo lo
+/// pendiente, sin costo se usa el pactado en la orden y sin número de lote entra como
+/// stock sin lote.
+pub fn recibir_linea(
+    orden_id: i64,
+    index: i32,
+    cantidad: &str,
+    costo: &str,
+    lote: &str,
+    vencimiento: &str,
+    usuario_id: Option<i64>,
+) -> Result<EstadoOrden, Box<dyn std::error::Error>> {
+    let linea = get_lineas()
+        .lock()
+        .unwrap()
+        .get(index as usize)
+        .cloned()
+        .ok_or("Seleccione una línea de la orden")?;
+
+    let recepcion = LineaRecepcion {
+        linea_id: linea.id,
+        cantidad: parse_cantidad(cantidad, linea.pendiente(), Medida::from_i32(linea.unidad_venta))?,
+        costo_unitario: parse_costo(costo, linea.costo_unitario)?,
+        lote: lotes::parse_lote(lote, vencimiento)?,
+    };
+
+    let mut conn = db::obtener_conexion()?;
+    Ok(db::compras::recibir(&mut conn, orden_id, usuario_id, &[recepcion])?)
+}
+
+/// Recibe todo lo pendiente de la orden al costo pactado
+pub fn recibir_todo(orden_id: i64, usuario_id: Option<i64>) -> Result<EstadoOrden, Box<dyn std::error::Error>> {
+    let mut conn = db::obtener_conexion()?;
+    let recepciones: Vec<LineaRecepcion> = db::compras::obtener_lineas(&conn, orden_id)?
+        .iter()
+        .filter(|l| l.pendiente() > 0.0)
+        .map(|l| LineaRecepcion {
+            linea_id: l.id,
+            cantidad: l.pendiente(),
+            costo_unitario: l.costo_unitario,
+            lote: None,
+        })
+        .collect();
+
+    Ok(db::compras::recibir(&mut conn, orden_id, usuario_id, &recepciones)?)
+}
+
+/// Cancela una orden abierta
+pub fn cancelar(orden_id: i64) -> Result<(), Box<dyn std::error::Error>> {
+    let conn = db::obtener_conexion()?;
+    Ok(db::compras::cancelar_orden(&conn, orden_id)?)
+}
+
+// ==========================================
+// FILAS PARA LA UI
+// ==========================================
+
+/// Convierte los resultados de búsqueda a filas de tabla (Código, Producto, Costo, Stock)
+pub fn resultados_to_model_rows(resultados: &[ProductoConMarca]) -> ModelRc<ModelRc<StandardListViewItem>> {
+    let rows: Vec<ModelRc<StandardListViewItem>> = resultados
+        .iter()
+        .map(|p| {
+            let row_data = vec![
+                StandardListViewItem::from(SharedString::from(p.codigo.clone().unwrap_or_else(|| "S/C".into()))),
+                StandardListViewItem::from(SharedString::from(p.nombre.as_str())),
+                StandardListViewItem::from(SharedString::from(format!("{:.2}", p.precio_neto))),
+                StandardListViewItem::from(SharedString::from(
+                    Medida::from_i32(p.unidad_venta).formatear(p.stock),
+                )),
+            ];
+            ModelRc::from(Rc::new(VecModel::from(row_data)))
+        })
+        .collect();
+
+    ModelRc::from(Rc::new(VecModel::from(rows)))
+}
+
+/// Convierte la orden en preparación a filas de tabla (Código, Producto, Cant., Costo, Subtotal)
+pub fn borrador_to_model_rows(lineas: &[LineaBorrador]) -> ModelRc<ModelRc<StandardListViewItem>> {
+    let rows: Vec<ModelRc<StandardListViewItem>> = lineas
+        .iter()
+        .map(|l| {
+            let row_data = vec![
+                StandardListViewItem::from(SharedString::from(l.codigo.as_str())),
+                StandardListViewItem::from(SharedString::from(l.nombre.as_str())),
+                StandardListViewItem::from(SharedString::from(l.medida.formatear(l.cantidad))),
+                StandardListViewItem::from(SharedString::from(format!("{:.2}", l.costo_unitario))),
+                StandardListViewItem::from(SharedString::from(format!("{:.2}", l.subtotal()))),
+            ];
+            ModelRc::from(Rc::new(VecModel::from(row_data)))
+        })
+        .collect();
+
+    ModelRc::from(Rc::new(VecModel::from(rows)))
+}
+
+/// Convierte las órdenes a filas de tabla (N°, Fecha, Proveedor, Estado, Total)
+pub fn ordenes_to_model_rows(ordenes: &[OrdenCompra]) -> ModelRc<ModelRc<StandardListViewItem>> {
+    let rows: Vec<ModelRc<StandardListViewItem>> = ordenes
+        .iter()
+        .map(|o| {
+            let row_data = vec![
+                StandardListViewItem::from(SharedString::from(o.id.to_string())),
+                StandardListViewItem::from(SharedString::from(o.fecha.format("%d/%m/%Y").to_string())),
+                StandardListViewItem::from(SharedString::from(o.proveedor_nombre.as_str())),
+                StandardListViewItem::from(SharedString::from(o.estado.etiqueta())),
+                StandardListViewItem::from(SharedString::from(format!("{:.2}", o.total))),
+            ];
+            ModelRc::from(Rc::new(VecModel::from(row_data)))
+        })
+        .collect();
+
+    ModelRc::from(Rc::new(VecModel::from(rows)))
+}
+
+/// Convierte las líneas de una orden a filas de tabla (Producto, Pedido, Recibido, Pendiente, Costo)
+pub fn lineas_to_model_rows(lineas: &[LineaOrdenCompra]) -> ModelRc<ModelRc<StandardListViewItem>> {
+    let rows: Vec<ModelRc<StandardListViewItem>> = lineas
+        .iter()
+        .map(|l| {
+            let medida = Medida::from_i32(l.unidad_venta);
+            let row_data = vec![
+                StandardListViewItem::from(SharedString::from(l.producto_nombre.as_str())),
+                StandardListViewItem::from(SharedString::from(medida.formatear(l.cantidad_pedida))),
+                StandardListViewItem::from(SharedString::from(medida.formatear(l.cantidad_recibida))),
+                StandardListViewItem::from(SharedString::from(medida.formatear(l.pendiente()))),
+                StandardListViewItem::from(SharedString::from(format!("{:.2}", l.costo_unitario))),
+            ];
+            ModelRc::from(Rc::new(VecModel::from(row_data)))
+        })
+        .collect();
+
+    ModelRc::from(Rc::new(VecModel::from(rows)))
+}
@@ -0,0 +1,110 @@
+use crate::db;
+use crate::models::credito::TRAMOS_ANTIGUEDAD;
+use crate::models::{MetodoPago, MovimientoCuenta, SaldoPorAntiguedad};
+use crate::tasas;
+use slint::{ModelRc, SharedString, StandardListViewItem, VecModel};
+use std::rc::Rc;
+use std::sync::{Mutex, OnceLock};
+
+/// Cuentas por cobrar en el orden de la tabla del reporte
+static CUENTAS: OnceLock<Mutex<Vec<SaldoPorAntiguedad>>> = OnceLock::new();
+
+fn get_cuentas() -> &'static Mutex<Vec<SaldoPorAntiguedad>> {
+    CUENTAS.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+/// Calcula las cuentas por cobrar al día de hoy y actualiza la caché (Send-safe)
+pub fn cargar_cuentas() -> Result<Vec<SaldoPorAntiguedad>, Box<dyn std::error::Error>> {
+    let conn = db::obtener_conexion()?;
+    let cuentas = db::credito::cuentas_por_cobrar(&conn, tasas::hoy())?;
+    *get_cuentas().lock().unwrap() = cuentas.clone();
+    Ok(cuentas)
+}
+
+/// Cuenta de la fila indicada en el reporte
+pub fn get_cuenta_by_index(index: i32) -> Option<SaldoPorAntiguedad> {
+    get_cuentas().lock().unwrap().get(index as usize).cloned()
+}
+
+/// Estado de cuenta del cliente con el saldo acumulado (Send-safe)
+pub fn estado_de_cuenta(cliente_id: i64) -> Result<Vec<MovimientoCuenta>, Box<dyn std::error::Error>> {
+    let conn = db::obtener_conexion()?;
+    Ok(db::credito::estado_de_cuenta(&conn, cliente_id)?)
+}
+
+/// Registra un abono del cliente con el método indicado (índice en `MetodoPago::TODOS`)
+/// en la caja abierta del usuario. Devuelve el id del abono.
+pub fn registrar_abono(
+    cliente_id: i64,
+    usuario_id: i64,
+    metodo_index: i32,
+    monto: &str,
+    referencia: &str,
+) -> Result<i64, Box<dyn std::error::Error>> {
+    let metodo = MetodoPago::TODOS
+        .get(metodo_index.max(0) as usize)
+        .copied()
+        .ok_or("Seleccione un método de pago")?;
+    let monto: f64 = monto.trim().parse().map_err(|_| "El monto debe ser un número válido")?;
+
+    let mut conn = db::obtener_conexion()?;
+    Ok(db::credito::registrar_abono(&mut conn, cliente_id, usuario_id, metodo, monto, Some(referencia))?)
+}
+
+/// Totales del reporte: cada tramo de antigüedad y el total general
+pub fn resumen(cuentas: &[SaldoPorAntiguedad]) -> String {
+    if cuentas.is_empty() {
+        return "Sin saldos pendientes".to_string();
+    }
+    let mut tramos = [0.0; 4];
+    for c in cuentas {
+        for (total, monto) in tramos.iter_mut().zip(c.tramos) {
+            *total += monto;
+        }
+    }
+    let detalle: Vec<String> = TRAMOS_ANTIGUEDAD
+        .iter()
+        .zip(tramos)
+        .map(|(tramo, monto)| format!("{} días: {:.2}", tramo, monto))
+        .collect();
+    format!("{} · Total ${:.2}", detalle.join(" · "), tramos.iter().sum::<f64>())
+}
+
+/// Convierte las cuentas a filas de tabla (Cliente, Cédula/RIF, 0-30, 31-60, 61-90, 90+, Total, Límite)
+pub fn cuentas_to_model_rows(cuentas: &[SaldoPorAntiguedad]) -> ModelRc<ModelRc<StandardListViewItem>> {
+    let rows: Vec<ModelRc<StandardListViewItem>> = cuentas
+        .iter()
+        .map(|c| {
+            let mut row_data = vec![
+                StandardListViewItem::from(SharedString::from(c.nombre.as_str())),
+                StandardListViewItem::from(SharedString::from(c.documento.clone().unwrap_or_else(|| "-".into()))),
+            ];
+            row_data.extend(c.tramos.iter().map(|t| StandardListViewItem::from(SharedString::from(format!("{:.2}", t)))));
+            row_data.push(StandardListViewItem::from(SharedString::from(format!("{:.2}", c.total()))));
+            row_data.push(StandardListViewItem::from(SharedString::from(format!("{:.2}", c.limite_credito))));
+            ModelRc::from(Rc::new(VecModel::from(row_data)))
+        })
+        .collect();
+
+    ModelRc::from(Rc::new(VecModel::from(rows)))
+}
+
+/// Convierte el estado de cuenta a filas de tabla (Fecha, Concepto, Cargo, Abono, Saldo)
+pub fn movimientos_to_model_rows(movimientos: &[MovimientoCuenta]) -> ModelRc<ModelRc<StandardListViewItem>> {
+    let monto = |m: f64| if m > 0.0 { format!("{:.2}", m) } else { String::new() };
+    let rows: Vec<ModelRc<StandardListViewItem>> = movimientos
+        .iter()
+        .map(|m| {
+            let row_data = vec![
+                StandardListViewItem::from(SharedString::from(m.fecha.format("%d/%m/%Y %H:%M").to_string())),
+                StandardListViewItem::from(SharedString::from(m.concepto.as_str())),
+                StandardListViewItem::from(SharedString::from(monto(m.cargo))),
+                StandardListViewItem::from(SharedString::from(monto(m.abono))),
+                StandardListViewItem::from(SharedString::from(format!("{:.2}", m.saldo))),
+            ];
+            ModelRc::from(Rc::new(VecModel::from(row_data)))
+        })
+        .collect();
+
+    ModelRc::from(Rc::new(VecModel::from(rows)))
+}
@@ -0,0 +1,305 @@
+use crate::models::{ConteoCaja, EstadoCaja, MetodoPago, SesionCaja};
+use rusqlite::{params, Connection, OptionalExtension, Result, Row};
+use std::fmt;
+
+/// Errores de negocio al abrir o cerrar una caja
+#[derive(Debug)]
+pub enum CajaError {
+    /// El usuario ya tiene una sesión abierta
+    CajaYaAbierta(i64),
+    /// La sesión ya fue cerrada y no admite cambios
+    CajaCerrada(i64),
+    /// La sesión no existe
+    SesionNoEncontrada(i64),
+    /// Fondo o montos contados inválidos
+    MontoInvalido(String),
+    /// Error de SQLite
+    Db(rusqlite::Error),
+}
+
+impl fmt::Display for CajaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CajaError::CajaYaAbierta(id) => write!(f, "Ya tiene abierta la caja #{}", id),
+            CajaError::CajaCerrada(id) => write!(f, "La caja #{} ya está cerrada", id),
+            CajaError::SesionNoEncontrada(id) => write!(f, "La caja #{} no existe", id),
+            CajaError::MontoInvalido(msg) => write!(f, "{}", msg),
+            CajaError::Db(e) => write!(f, "Error de base de datos: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for CajaError {}
+
+impl From<rusqlite::Error> for CajaError {
+    fn from(e: rusqlite::Error) -> Self {
+        CajaError::Db(e)
+    }
+}
+
+/// Columnas de `SesionCaja` (alias `s` = cajas_sesiones, `u` = usuarios)
+const COLUMNAS_SESION: &str = "s.id, u.username, s.apertura, s.cierre, s.fondo_inicial, s.estado, s.notas,
+    (SELECT COUNT(*) FROM ventas v WHERE v.sesion_id = s.id),
+    COALESCE((SELECT SUM(c.esperado) FROM cajas_cierres c WHERE c.sesion_id = s.id), 0),
+    COALESCE((SELECT SUM(c.contado) FROM cajas_cierres c WHERE c.sesion_id = s.id), 0)";
+
+fn sesion_desde_fila(row: &Row) -> Result<SesionCaja> {
+    Ok(SesionCaja {
+        id: row.get(0)?,
+        usuario_nombre: row.get(1)?,
+        apertura: row.get(2)?,
+        cierre: row.get(3)?,
+        fondo_inicial: row.get(4)?,
+        estado: EstadoCaja::from(row.get::<_, String>(5)?),
+        notas: row.get(6)?,
+        ventas: row.get(7)?,
+        total_esperado: row.get(8)?,
+        total_contado: row.get(9)?,
+    })
+}
+
+/// Sesión por id
+pub fn obtener_sesion(conn: &Connection, sesion_id: i64) -> Result<Option<SesionCaja>> {
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM cajas_sesiones s LEFT JOIN usuarios u ON s.usuario_id = u.id WHERE s.id = ?1",
+            COLUMNAS_SESION
+        ),
+        params![sesion_id],
+        sesion_desde_fila,
+    )
+    .optional()
+}
+
+/// Caja abierta del usuario, si tiene una
+pub fn sesion_abierta(conn: &Connection, usuario_id: i64) -> Result<Option<SesionCaja>> {
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM cajas_sesiones s LEFT JOIN usuarios u ON s.usuario_id = u.id
+             WHERE s.usuario_id = ?1 AND s.estado = 'abierta'",
+            COLUMNAS_SESION
+        ),
+        params![usuario_id],
+        sesion_desde_fila,
+    )
+    .optional()
+}
+
+/// Id de la caja abierta del usuario (para asociarle ventas y devoluciones)
+pub fn id_sesion_abierta(conn: &Connection, usuario_id: i64) -> Result<Option<i64>> {
+    conn.query_row(
+        "SELECT id FROM cajas_sesiones WHERE usuario_id = ?1 AND estado = 'abierta'",
+        params![usuario_id],
+        |row| row.get(0),
+    )
+    .optional()
+}
+
+/// Abre una caja con el fondo inicial en efectivo. Devuelve el id de la sesión.
+pub fn abrir(conn: &Connection, usuario_id: i64, fondo_inicial: f64) -> std::result::Result<i64, CajaError> {
+    if !fondo_inicial.is_finite() || fondo_inicial < 0.0 {
+        return Err(CajaError::MontoInvalido(
+            "El fondo inicial no puede ser negativo".to_string(),
+        ));
+    }
+    if let Some(id) = id_sesion_abierta(conn, usuario_id)? {
+        return Err(CajaError::CajaYaAbierta(id));
+    }
+
+    conn.execute(
+        "INSERT INTO cajas_sesiones (usuario_id, fondo_inicial) VALUES (?1, ?2)",
+        params![usuario_id, fondo_inicial],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// Lo que debería haber en caja por método de pago (contado en 0): lo cobrado en las ventas y
+/// los abonos de clientes de la sesión, menos lo reembolsado desde esta caja. Cada reembolso se
+/// reparte entre los métodos con que se pagó la venta original, en proporción a lo cobrado por
+/// cada uno (lo devuelto a la cuenta de un cliente no sale de la caja). El efectivo suma además
+/// el fondo inicial y resta el vuelto entregado.
+pub fn esperado(conn: &Connection, sesion_id: i64) -> Result<Vec<ConteoCaja>> {
+    let ajuste_efectivo: f64 = conn.query_row(
+        "SELECT s.fondo_inicial - COALESCE((SELECT SUM(v.vuelto) FROM ventas v WHERE v.sesion_id = s.id), 0)
+         FROM cajas_sesiones s WHERE s.id = ?1",
+        params![sesion_id],
+        |row| row.get(0),
+    )?;
+
+    let mut stmt = conn.prepare(
+        "WITH reembolsos AS (
+            SELECT d.venta_id, SUM(dd.monto) - d.monto_credito AS monto
+            FROM devoluciones d JOIN devoluciones_detalle dd ON dd.devolucion_id = d.id
+            WHERE d.sesion_id = ?1
+            GROUP BY d.id
+         ),
+         -- Lo cobrado por método en cada venta reembolsada; el vuelto salió del efectivo
+         cobrado_venta AS (
+            SELECT p.venta_id, p.metodo,
+                   SUM(p.monto) - CASE WHEN p.metodo = 'efectivo' THEN v.vuelto ELSE 0 END AS neto
+            FROM pagos p JOIN ventas v ON p.venta_id = v.id
+            WHERE p.venta_id IN (SELECT venta_id FROM reembolsos)
+            GROUP BY p.venta_id, p.metodo
+         ),
+         total_venta AS (
+            SELECT venta_id, SUM(neto) AS neto FROM cobrado_venta GROUP BY venta_id HAVING SUM(neto) > 0
+         )
+         SELECT metodo, SUM(monto) FROM (
+            SELECT p.metodo, p.monto FROM pagos p
+            JOIN ventas v ON p.venta_id = v.id
+            WHERE v.sesion_id = ?1
+            UNION ALL
+            SELECT a.metodo, a.monto FROM abonos a WHERE a.sesion_id = ?1
+            UNION ALL
+            SELECT c.metodo, -r.monto * c.neto / t.neto
+            FROM reembolsos r
+            JOIN total_venta t ON t.venta_id = r.venta_id
+            JOIN cobrado_venta c ON c.venta_id = r.venta_id
+            UNION ALL
+            -- Ventas sin nada cobrado (p. ej. a crédito): lo que se reembolse sale en efectivo
+            SELECT 'efectivo', -r.monto FROM reembolsos r
+            WHERE r.venta_id NOT IN (SELECT venta_id FROM total_venta)
+         )
+         GROUP BY metodo",
+    )?;
+    let cobrado = stmt
+        .query_map(params![sesion_id], |row| {
+            Ok((MetodoPago::from(row.get::<_, String>(0)?), row.get::<_, f64>(1)?))
+        })?
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(MetodoPago::TODOS
+        .iter()
+        .map(|&metodo| {
+            let ventas: f64 = cobrado.iter().filter(|(m, _)| *m == metodo).map(|(_, monto)| monto).sum();
+            let esperado = if metodo == MetodoPago::Efectivo { ventas + ajuste_efectivo } else { ventas };
+            ConteoCaja {
+                metodo,
+                // El reparto proporcional de los reembolsos puede dejar fracciones de céntimo
+                esperado: (esperado * 100.0).round() / 100.0,
+                contado: 0.0,
+            }
+        })
+        .collect())
+}
+
+/// Cierra la caja con lo contado por método (los métodos omitidos cuentan 0) y guarda el
+/// arqueo con su diferencia. Devuelve el arqueo; la sesión queda bloqueada.
+pub fn cerrar(
+    conn: &mut Connection,
+    sesion_id: i64,
+    contado: &[(MetodoPago, f64)],
+    notas: &str,
+) -> std::result::Result<Vec<ConteoCaja>, CajaError> {
+    if contado.iter().any(|(_, monto)| !monto.is_finite() || *monto < 0.0) {
+        return Err(CajaError::MontoInvalido(
+            "Los montos contados no pueden ser negativos".to_string(),
+        ));
+    }
+
+    let tx = conn.transaction()?;
+    let sesion = obtener_sesion(&tx, sesion_id)?.ok_or(CajaError::SesionNoEncontrada(sesion_id))?;
+    if sesion.estado == EstadoCaja::Cerrada {
+        return Err(CajaError::CajaCerrada(sesion_id));
+    }
+
+    let mut arqueo = esperado(&tx, sesion_id)?;
+    for conteo in arqueo.iter_mut() {
+        conteo.contado = contado
+            .iter()
+            .filter(|(metodo, _)| *metodo == conteo.metodo)
+            .map(|(_, monto)| monto)
+            .sum();
+        tx.execute(
+            "INSERT INTO cajas_cierres (sesion_id, metodo, esperado, contado, diferencia)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                sesion_id,
+                conteo.metodo.as_str(),
+                conteo.esperado,
+                conteo.contado,
+                conteo.diferencia()
+            ],
+        )?;
+    }
+
+    let notas = notas.trim();
+    tx.execute(
+        "UPDATE cajas_sesiones SET estado = 'cerrada', cierre = CURRENT_TIMESTAMP, notas = ?1 WHERE id = ?2",
+        params![if notas.is_empty() { None } else { Some(notas) }, sesion_id],
+    )?;
+    tx.commit()?;
+    Ok(arqueo)
+}
+
+/// Sesiones cerradas, de la más reciente a la más antigua
+pub fn obtener_cerradas(conn: &Connection) -> Result<Vec<SesionCaja>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM cajas_sesiones s LEFT JOIN usuarios u ON s.usuario_id = u.id
+         WHERE s.estado = 'cerrada'
+         ORDER BY s.cierre DESC, s.id DESC",
+        COLUMNAS_SESION
+    ))?;
+    let rows = stmt.query_map([], sesion_desde_fila)?;
+    rows.collect()
+}
+
+/// Arqueo guardado al cerrar la sesión
+pub fn obtener_arqueo(conn: &Connection, sesion_id: i64) -> Result<Vec<ConteoCaja>> {
+    let mut stmt = conn.prepare(
+        "SELECT metodo, esperado, contado FROM cajas_cierres WHERE sesion_id = ?1 ORDER BY id",
+    )?;
+    let rows = stmt.query_map(params![sesion_id], |row| {
+        Ok(ConteoCaja {
+            metodo: MetodoPago::from(row.get::<_, String>(0)?),
+            esperado: row.get(1)?,
+            contado: row.get(2)?,
+        })
+    })?;
+    rows.collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::pruebas::{self, linea, pago};
+    use crate::db::{devoluciones, ventas};
+    use crate::models::medida::Medida;
+    use crate::models::{Cliente, LineaDevolucion, PagoNuevo};
+
+    #[test]
+    fn los_reembolsos_salen_por_los_metodos_de_la_venta_original() {
+        let mut conn = pruebas::base_en_memoria();
+        let cajero = pruebas::cajero_con_caja(&conn, "ana");
+        let sesion_id = id_sesion_abierta(&conn, cajero).unwrap().unwrap();
+        let queso = pruebas::producto(&conn, "Queso", 6.0, 10.0, 10.0, Medida::Kilogramo);
+        let mut vender = |cantidad: f64, pagos: &[PagoNuevo]| {
+            let venta_id = ventas::registrar_venta(
+                &mut conn,
+                cajero,
+                Cliente::CONSUMIDOR_FINAL,
+                vec![linea(queso, cantidad, 10.0)],
+                pagos,
+                0.0,
+                None,
+            )
+            .unwrap();
+            let detalle_id = ventas::obtener_venta_con_detalles(&conn, venta_id).unwrap().unwrap().detalles[0].id;
+            (venta_id, detalle_id)
+        };
+
+        // 30 pagados con 20 en tarjeta y 15 en efectivo (5 de vuelto): 10 netos en efectivo
+        let mixta = vender(3.0, &[pago(MetodoPago::Tarjeta, 20.0), pago(MetodoPago::Efectivo, 15.0)]);
+        let contado = vender(1.0, &[pago(MetodoPago::Efectivo, 10.0)]);
+        for (venta_id, detalle_id, cantidad) in [(mixta.0, mixta.1, 1.5), (contado.0, contado.1, 1.0)] {
+            let lineas = [LineaDevolucion { detalle_id, cantidad }];
+            devoluciones::registrar_devolucion(&mut conn, venta_id, cajero, "Cambio", &lineas).unwrap();
+        }
+
+        let esperado = esperado(&conn, sesion_id).unwrap();
+        let de = |metodo| esperado.iter().find(|c| c.metodo == metodo).unwrap().esperado;
+        assert_eq!(de(MetodoPago::Efectivo), 5.0);
+        assert_eq!(de(MetodoPago::Tarjeta), 10.0);
+        assert_eq!(de(MetodoPago::Transferencia), 0.0);
+    }
+}
@@ -0,0 +1,167 @@
+use crate::models::{Categoria, CategoriaConConteo, Subcategoria};
+use rusqlite::{params, Connection, Result};
+
+/// Inserta las categorías que antes estaban fijas en la UI, conservando sus IDs
+/// (los productos existentes ya guardan 1, 2 y 3)
+pub fn seed_categorias(conn: &Connection) -> Result<()> {
+    let count: i64 = conn.query_row("SELECT COUNT(*) FROM categorias", [], |row| row.get(0))?;
+
+    if count == 0 {
+        conn.execute(
+            "INSERT INTO categorias (id, nombre, descripcion) VALUES
+                (1, 'General', 'Categoría por defecto'),
+                (2, 'Electrónica', NULL),
+                (3, 'Hogar', NULL)",
+            [],
+        )?;
+        conn.execute(
+            "INSERT INTO subcategorias (id, categoria_id, nombre) VALUES
+                (1, 1, 'Varios'),
+                (2, 3, 'Limpieza'),
+                (3, 2, 'Accesorios')",
+            [],
+        )?;
+        println!("Categorías iniciales insertadas correctamente.");
+    }
+    Ok(())
+}
+
+// ==========================================
+// CATEGORÍAS
+// ==========================================
+
+/// Obtener las categorías con su cantidad de subcategorías y productos
+pub fn obtener_categorias_con_conteo(conn: &Connection) -> Result<Vec<CategoriaConConteo>> {
+    let mut stmt = conn.prepare(
+        "SELECT c.id, c.nombre, c.descripcion,
+                (SELECT COUNT(*) FROM subcategorias s WHERE s.categoria_id = c.id),
+                (SELECT COUNT(*) FROM productos p WHERE p.categoria_id = c.id),
+                c.impuesto_id, i.porcentaje
+         FROM categorias c
+         JOIN impuestos i ON c.impuesto_id = i.id
+         ORDER BY c.nombre ASC",
+    )?;
+
+    let iter = stmt.query_map([], |row| {
+        Ok(CategoriaConConteo {
+            id: row.get(0)?,
+            nombre: row.get(1)?,
+            descripcion: row.get(2)?,
+            total_subcategorias: row.get(3)?,
+            total_productos: row.get(4)?,
+            impuesto_id: row.get(5)?,
+            porcentaje_impuesto: row.get(6)?,
+        })
+    })?;
+
+    let mut resultado = Vec::new();
+    for categoria in iter {
+        resultado.push(categoria?);
+    }
+    Ok(resultado)
+}
+
+/// Crear una nueva categoría
+pub fn crear_categoria(
+    conn: &Connection,
+    nombre: &str,
+    descripcion: Option<&str>,
+    impuesto_id: i64,
+) -> Result<i64> {
+    conn.execute(
+        "INSERT INTO categorias (nombre, descripcion, impuesto_id) VALUES (?1, ?2, ?3)",
+        params![nombre, descripcion, impuesto_id],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// Actualizar una categoría existente
+pub fn actualizar_categoria(conn: &Connection, c: &Categoria) -> Result<bool> {
+    let filas = conn.execute(
+        "UPDATE categorias SET nombre = ?1, descripcion = ?2, impuesto_id = ?3 WHERE id = ?4",
+        params![c.nombre, c.descripcion, c.impuesto_id, c.id],
+    )?;
+    Ok(filas > 0)
+}
+
+/// Cantidad de productos asignados a la categoría
+pub fn contar_productos_categoria(conn: &Connection, id: i64) -> Result<i64> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM productos WHERE categoria_id = ?1",
+        params![id],
+        |row| row.get(0),
+    )
+}
+
+/// Eliminar una categoría y sus subcategorías.
+/// Falla (FOREIGN KEY) si todavía hay productos en ella: deben reasignarse antes.
+pub fn eliminar_categoria(conn: &Connection, id: i64) -> Result<bool> {
+    let filas = conn.execute("DELETE FROM categorias WHERE id = ?1", params![id])?;
+    Ok(filas > 0)
+}
+
+/// Verificar si ya existe otra categoría con el mismo nombre
+pub fn existe_categoria(conn: &Connection, nombre: &str, excluir_id: Option<i64>) -> Result<bool> {
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM categorias WHERE nombre = ?1 AND id != ?2",
+        params![nombre.trim(), excluir_id.unwrap_or(0)],
+        |row| row.get(0),
+    )?;
+    Ok(count > 0)
+}
+
+// ==========================================
+// SUBCATEGORÍAS
+// ==========================================
+
+/// Obtener las subcategorías de una categoría ordenadas por nombre
+pub fn obtener_subcategorias(conn: &Connection, categoria_id: i64) -> Result<Vec<Subcategoria>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, nombre FROM subcategorias
+         WHERE categoria_id = ?1
+         ORDER BY nombre ASC",
+    )?;
+
+    let iter = stmt.query_map(params![categoria_id], |row| {
+        Ok(Subcategoria {
+            id: row.get(0)?,
+            nombre: row.get(1)?,
+        })
+    })?;
+
+    let mut resultado = Vec::new();
+    for subcategoria in iter {
+        resultado.push(subcategoria?);
+    }
+    Ok(resultado)
+}
+
+/// Crear una subcategoría dentro de una categoría
+pub fn crear_subcategoria(conn: &Connection, categoria_id: i64, nombre: &str) -> Result<i64> {
+    conn.execute(
+        "INSERT INTO subcategorias (categoria_id, nombre) VALUES (?1, ?2)",
+        params![categoria_id, nombre],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// Eliminar una subcategoría (los productos que la usaban quedan con subcategoria_id = NULL)
+pub fn eliminar_subcategoria(conn: &Connection, id: i64) -> Result<bool> {
+    let filas = conn.execute("DELETE FROM subcategorias WHERE id = ?1", params![id])?;
+    Ok(filas > 0)
+}
+
+/// Verificar si la categoría ya tiene otra subcategoría con el mismo nombre
+pub fn existe_subcategoria(
+    conn: &Connection,
+    categoria_id: i64,
+    nombre: &str,
+    excluir_id: Option<i64>,
+) -> Result<bool> {
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM subcategorias WHERE categoria_id = ?1 AND nombre = ?2 AND id != ?3",
+        params![categoria_id, nombre.trim(), excluir_id.unwrap_or(0)],
+        |row| row.get(0),
+    )?;
+    Ok(count > 0)
+}
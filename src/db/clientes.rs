@@ -0,0 +1,111 @@
+use super::credito::SALDO_CLIENTE;
+use crate::models::{Cliente, ClienteNuevo};
+use rusqlite::{params, Connection, OptionalExtension, Result, Row};
+
+fn cliente_desde_fila(row: &Row) -> Result<Cliente> {
+    Ok(Cliente {
+        id: row.get(0)?,
+        documento: row.get(1)?,
+        nombre: row.get(2)?,
+        direccion: row.get(3)?,
+        telefono: row.get(4)?,
+        email: row.get(5)?,
+        limite_credito: row.get(6)?,
+        saldo: row.get(7)?,
+    })
+}
+
+/// Columnas de `Cliente` (alias `c` = clientes)
+fn columnas_cliente() -> String {
+    format!(
+        "c.id, c.documento, c.nombre, c.direccion, c.telefono, c.email, c.limite_credito, {}",
+        SALDO_CLIENTE
+    )
+}
+
+/// Obtener todos los clientes: primero el consumidor final y luego por nombre
+pub fn obtener_clientes(conn: &Connection) -> Result<Vec<Cliente>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM clientes c ORDER BY c.id != ?1, c.nombre COLLATE NOCASE ASC",
+        columnas_cliente()
+    ))?;
+    let iter = stmt.query_map(params![Cliente::CONSUMIDOR_FINAL], cliente_desde_fila)?;
+
+    let mut resultado = Vec::new();
+    for cliente in iter {
+        resultado.push(cliente?);
+    }
+    Ok(resultado)
+}
+
+/// Buscar un cliente por su ID
+pub fn obtener_cliente(conn: &Connection, id: i64) -> Result<Option<Cliente>> {
+    conn.query_row(
+        &format!("SELECT {} FROM clientes c WHERE c.id = ?1", columnas_cliente()),
+        params![id],
+        cliente_desde_fila,
+    )
+    .optional()
+}
+
+/// Crear un nuevo cliente
+pub fn crear_cliente(conn: &Connection, c: &ClienteNuevo) -> Result<i64> {
+    conn.execute(
+        "INSERT INTO clientes (documento, nombre, direccion, telefono, email, limite_credito)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![c.documento, c.nombre, c.direccion, c.telefono, c.email, c.limite_credito],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// Actualizar un cliente existente (el saldo es calculado y no se toca)
+pub fn actualizar_cliente(conn: &Connection, c: &Cliente) -> Result<bool> {
+    let filas = conn.execute(
+        "UPDATE clientes SET documento = ?1, nombre = ?2, direccion = ?3, telefono = ?4, email = ?5,
+                limite_credito = ?6
+         WHERE id = ?7",
+        params![c.documento, c.nombre, c.direccion, c.telefono, c.email, c.limite_credito, c.id],
+    )?;
+    Ok(filas > 0)
+}
+
+/// Cantidad de ventas registradas a nombre del cliente
+pub fn contar_ventas(conn: &Connection, id: i64) -> Result<i64> {
+    conn.query_row("SELECT COUNT(*) FROM ventas WHERE cliente_id = ?1", params![id], |row| row.get(0))
+}
+
+/// Eliminar un cliente. Falla (FOREIGN KEY) si tiene ventas.
+pub fn eliminar_cliente(conn: &Connection, id: i64) -> Result<bool> {
+    let filas = conn.execute("DELETE FROM clientes WHERE id = ?1", params![id])?;
+    Ok(filas > 0)
+}
+
+/// Verificar si ya existe otro cliente con el mismo documento
+pub fn existe_documento(conn: &Connection, documento: &str, excluir_id: Option<i64>) -> Result<bool> {
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM clientes WHERE documento = ?1 AND id != ?2",
+        params![documento.trim(), excluir_id.unwrap_or(0)],
+        |row| row.get(0),
+    )?;
+    Ok(count > 0)
+}
+
+/// Clientes cuyo documento o nombre contiene `texto` (el consumidor final siempre primero),
+/// para el selector del punto de venta
+pub fn buscar_clientes(conn: &Connection, texto: &str, limite: i64) -> Result<Vec<Cliente>> {
+    let patron = format!("%{}%", texto.trim());
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM clientes c
+         WHERE c.id = ?1 OR c.documento LIKE ?2 OR c.nombre LIKE ?2
+         ORDER BY c.id != ?1, c.nombre COLLATE NOCASE ASC
+         LIMIT ?3",
+        columnas_cliente()
+    ))?;
+    let iter = stmt.query_map(params![Cliente::CONSUMIDOR_FINAL, patron, limite], cliente_desde_fila)?;
+
+    let mut resultado = Vec::new();
+    for cliente in iter {
+        resultado.push(cliente?);
+    }
+    Ok(resultado)
+}
@@ -0,0 +1,336 @@
+use super::lotes::{self, LoteError};
+use super::movimientos::{self, MovimientoError};
+use crate::models::medida::{formatear_cantidad, redondear_cantidad};
+use crate::models::{EstadoOrden, LineaOrdenCompra, LineaOrdenNueva, LineaRecepcion, MotivoMovimiento, OrdenCompra};
+use rusqlite::{params, Connection, OptionalExtension, Result};
+use std::fmt;
+
+/// Errores de negocio de las órdenes de compra
+#[derive(Debug)]
+pub enum CompraError {
+    /// La orden no tiene líneas o alguna cantidad/costo no es válido
+    OrdenInvalida(String),
+    /// La orden no existe
+    OrdenNoEncontrada(i64),
+    /// La orden ya fue recibida por completo o cancelada
+    OrdenCerrada { orden_id: i64, estado: EstadoOrden },
+    /// Se intenta recibir más de lo que falta en la línea
+    ExcedePendiente {
+        producto: String,
+        pendiente: f64,
+        recibido: f64,
+    },
+    /// Error de SQLite
+    Db(rusqlite::Error),
+}
+
+impl fmt::Display for CompraError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompraError::OrdenInvalida(msg) => write!(f, "{}", msg),
+            CompraError::OrdenNoEncontrada(id) => write!(f, "La orden de compra #{} no existe", id),
+            CompraError::OrdenCerrada { orden_id, estado } => write!(
+                f,
+                "La orden #{} está {} y no admite recepciones",
+                orden_id,
+                estado.etiqueta().to_lowercase()
+            ),
+            CompraError::ExcedePendiente { producto, pendiente, recibido } => write!(
+                f,
+                "'{}': se intentan recibir {} pero solo faltan {}",
+                producto,
+                formatear_cantidad(*recibido),
+                formatear_cantidad(*pendiente)
+            ),
+            CompraError::Db(e) => write!(f, "Error de base de datos: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for CompraError {}
+
+impl From<rusqlite::Error> for CompraError {
+    fn from(e: rusqlite::Error) -> Self {
+        CompraError::Db(e)
+    }
+}
+
+impl From<LoteError> for CompraError {
+    fn from(e: LoteError) -> Self {
+        match e {
+            LoteError::Db(e) => CompraError::Db(e),
+            otro => CompraError::OrdenInvalida(otro.to_string()),
+        }
+    }
+}
+
+impl From<MovimientoError> for CompraError {
+    fn from(e: MovimientoError) -> Self {
+        match e {
+            MovimientoError::Db(e) => CompraError::Db(e),
+            // Una entrada no puede dejar stock negativo: solo queda el producto inexistente
+            otro => CompraError::OrdenInvalida(otro.to_string()),
+        }
+    }
+}
+
+/// Registra una orden de compra con sus líneas (todo o nada)
+pub fn crear_orden(
+    conn: &mut Connection,
+    proveedor_id: i64,
+    usuario_id: Option<i64>,
+    notas: Option<&str>,
+    lineas: &[LineaOrdenNueva],
+) -> std::result::Result<i64, CompraError> {
+    if lineas.is_empty() {
+        return Err(CompraError::OrdenInvalida("La orden no tiene productos".to_string()));
+    }
+    if let Some(l) = lineas.iter().find(|l| l.cantidad <= 0.0 || l.costo_unitario < 0.0) {
+        return Err(CompraError::OrdenInvalida(format!(
+            "Cantidad o costo inválido para el producto con ID {}",
+            l.producto_id
+        )));
+    }
+
+    let tx = conn.transaction()?;
+    tx.execute(
+        "INSERT INTO ordenes_compra (proveedor_id, usuario_id, notas) VALUES (?1, ?2, ?3)",
+        params![proveedor_id, usuario_id, notas],
+    )?;
+    let orden_id = tx.last_insert_rowid();
+
+    for l in lineas {
+        tx.execute(
+            "INSERT INTO ordenes_compra_detalle (orden_id, producto_id, cantidad_pedida, costo_unitario)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![orden_id, l.producto_id, l.cantidad, l.costo_unitario],
+        )?;
+    }
+
+    tx.commit()?;
+    Ok(orden_id)
+}
+
+/// Órdenes de compra de la más reciente a la más antigua
+pub fn obtener_ordenes(conn: &Connection) -> Result<Vec<OrdenCompra>> {
+    let mut stmt = conn.prepare(
+        "SELECT o.id, p.nombre, o.fecha, o.estado,
+                COALESCE((SELECT SUM(d.cantidad_pedida * d.costo_unitario)
+                          FROM ordenes_compra_detalle d WHERE d.orden_id = o.id), 0)
+         FROM ordenes_compra o
+         JOIN proveedores p ON o.proveedor_id = p.id
+         ORDER BY o.fecha DESC, o.id DESC",
+    )?;
+
+    let iter = stmt.query_map([], |row| {
+        Ok(OrdenCompra {
+            id: row.get(0)?,
+            proveedor_nombre: row.get(1)?,
+            fecha: row.get(2)?,
+            estado: EstadoOrden::from(row.get::<_, String>(3)?),
+            total: row.get(4)?,
+        })
+    })?;
+
+    let mut resultado = Vec::new();
+    for orden in iter {
+        resultado.push(orden?);
+    }
+    Ok(resultado)
+}
+
+/// Líneas de una orden con el nombre de cada producto
+pub fn obtener_lineas(conn: &Connection, orden_id: i64) -> Result<Vec<LineaOrdenCompra>> {
+    let mut stmt = conn.prepare(
+        "SELECT d.id, d.producto_id, p.nombre, d.cantidad_pedida,
+                d.cantidad_recibida, d.costo_unitario, p.unidad_venta
+         FROM ordenes_compra_detalle d
+         JOIN productos p ON d.producto_id = p.id
+         WHERE d.orden_id = ?1
+         ORDER BY d.id ASC",
+    )?;
+
+    let iter = stmt.query_map(params![orden_id], |row| {
+        Ok(LineaOrdenCompra {
+            id: row.get(0)?,
+            producto_id: row.get(1)?,
+            producto_nombre: row.get(2)?,
+            cantidad_pedida: row.get(3)?,
+            cantidad_recibida: row.get(4)?,
+            costo_unitario: row.get(5)?,
+            unidad_venta: row.get(6)?,
+        })
+    })?;
+
+    let mut resultado = Vec::new();
+    for linea in iter {
+        resultado.push(linea?);
+    }
+    Ok(resultado)
+}
+
+/// Estado actual de una orden
+fn estado_orden(conn: &Connection, orden_id: i64) -> std::result::Result<EstadoOrden, CompraError> {
+    conn.query_row(
+        "SELECT estado FROM ordenes_compra WHERE id = ?1",
+        params![orden_id],
+        |row| row.get::<_, String>(0),
+    )
+    .optional()?
+    .map(EstadoOrden::from)
+    .ok_or(CompraError::OrdenNoEncontrada(orden_id))
+}
+
+/// Recibe mercancía de una orden (total o parcialmente) en una sola transacción:
+/// suma las cantidades a la línea, registra una entrada "compra" en el kardex,
+/// actualiza `precio_neto` con el costo recibido y recalcula el estado de la orden.
+/// Devuelve el nuevo estado.
+pub fn recibir(
+    conn: &mut Connection,
+    orden_id: i64,
+    usuario_id: Option<i64>,
+    recepciones: &[LineaRecepcion],
+) -> std::result::Result<EstadoOrden, CompraError> {
+    let tx = conn.transaction()?;
+
+    let estado = estado_orden(&tx, orden_id)?;
+    if !estado.abierta() {
+        return Err(CompraError::OrdenCerrada { orden_id, estado });
+    }
+
+    let mut recibidas = 0;
+
+    for r in recepciones.iter().filter(|r| r.cantidad != 0.0) {
+        if r.cantidad < 0.0 || r.costo_unitario < 0.0 {
+            return Err(CompraError::OrdenInvalida(
+                "La cantidad y el costo recibidos no pueden ser negativos".to_string(),
+            ));
+        }
+
+        // La línea debe pertenecer a esta orden
+        let linea = obtener_lineas(&tx, orden_id)?
+            .into_iter()
+            .find(|l| l.id == r.linea_id)
+            .ok_or_else(|| {
+                CompraError::OrdenInvalida(format!("La línea {} no pertenece a la orden #{}", r.linea_id, orden_id))
+            })?;
+
+        let cantidad = redondear_cantidad(r.cantidad);
+        if cantidad > linea.pendiente() {
+            return Err(CompraError::ExcedePendiente {
+                pendiente: linea.pendiente(),
+                producto: linea.producto_nombre,
+                recibido: cantidad,
+            });
+        }
+
+        tx.execute(
+            "UPDATE ordenes_compra_detalle
+             SET cantidad_recibida = ROUND(cantidad_recibida + ?1, 3), costo_recibido = ?2
+             WHERE id = ?3",
+            params![cantidad, r.costo_unitario, r.linea_id],
+        )?;
+        let referencia = match &r.lote {
+            Some(lote) => {
+                lotes::ingresar(&tx, linea.producto_id, lote, cantidad)?;
+                format!("OC #{} · Lote {}", orden_id, lote.numero.trim())
+            }
+            None => format!("OC #{}", orden_id),
+        };
+        movimientos::registrar_movimiento(
+            &tx,
+            linea.producto_id,
+            cantidad,
+            MotivoMovimiento::Compra,
+            usuario_id,
+            Some(&referencia),
+            Some(r.costo_unitario),
+        )?;
+        // El costo del producto pasa a ser el de la última mercancía recibida
+        tx.execute(
+            "UPDATE productos SET precio_neto = ?1 WHERE id = ?2",
+            params![r.costo_unitario, linea.producto_id],
+        )?;
+        recibidas += 1;
+    }
+
+    if recibidas == 0 {
+        return Err(CompraError::OrdenInvalida("Indique al menos una cantidad a recibir".to_string()));
+    }
+
+    let faltan: i64 = tx.query_row(
+        "SELECT COUNT(*) FROM ordenes_compra_detalle
+         WHERE orden_id = ?1 AND ROUND(cantidad_recibida, 3) < ROUND(cantidad_pedida, 3)",
+        params![orden_id],
+        |row| row.get(0),
+    )?;
+    let nuevo_estado = if faltan == 0 { EstadoOrden::Recibida } else { EstadoOrden::Parcial };
+    tx.execute(
+        "UPDATE ordenes_compra SET estado = ?1 WHERE id = ?2",
+        params![nuevo_estado.as_str(), orden_id],
+    )?;
+
+    tx.commit()?;
+    Ok(nuevo_estado)
+}
+
+/// Cancela una orden abierta. Lo ya recibido permanece en el inventario.
+pub fn cancelar_orden(conn: &Connection, orden_id: i64) -> std::result::Result<(), CompraError> {
+    let estado = estado_orden(conn, orden_id)?;
+    if !estado.abierta() {
+        return Err(CompraError::OrdenCerrada { orden_id, estado });
+    }
+    conn.execute(
+        "UPDATE ordenes_compra SET estado = 'cancelada' WHERE id = ?1",
+        params![orden_id],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::pruebas;
+    use crate::models::medida::Medida;
+
+    /// Costo promedio del último movimiento del kardex
+    fn costo_promedio(conn: &Connection, producto_id: i64) -> f64 {
+        movimientos::obtener_kardex(conn, producto_id).unwrap().last().unwrap().costo_promedio
+    }
+
+    #[test]
+    fn recibir_por_partes_suma_stock_y_pondera_el_costo() {
+        let mut conn = pruebas::base_en_memoria();
+        conn.execute("INSERT INTO proveedores (id, nombre) VALUES (1, 'Distribuidora')", []).unwrap();
+        let arroz = pruebas::producto(&conn, "Arroz", 2.0, 3.0, 10.0, Medida::Unidad);
+        let orden_id = crear_orden(
+            &mut conn,
+            1,
+            None,
+            None,
+            &[LineaOrdenNueva { producto_id: arroz, cantidad: 10.0, costo_unitario: 3.0 }],
+        )
+        .unwrap();
+        let linea_id = obtener_lineas(&conn, orden_id).unwrap()[0].id;
+        let recepcion = |cantidad, costo_unitario| LineaRecepcion { linea_id, cantidad, costo_unitario, lote: None };
+
+        assert_eq!(recibir(&mut conn, orden_id, None, &[recepcion(4.0, 3.0)]).unwrap(), EstadoOrden::Parcial);
+        assert_eq!(pruebas::stock(&conn, arroz), 14.0);
+        assert!((costo_promedio(&conn, arroz) - 32.0 / 14.0).abs() < 1e-9);
+
+        let exceso = recibir(&mut conn, orden_id, None, &[recepcion(7.0, 3.0)]);
+        assert!(matches!(exceso, Err(CompraError::ExcedePendiente { .. })));
+        assert_eq!(pruebas::stock(&conn, arroz), 14.0);
+
+        assert_eq!(recibir(&mut conn, orden_id, None, &[recepcion(6.0, 4.0)]).unwrap(), EstadoOrden::Recibida);
+        assert_eq!(pruebas::stock(&conn, arroz), 20.0);
+        assert!((costo_promedio(&conn, arroz) - 2.8).abs() < 1e-9);
+        let precio_neto: f64 = conn
+            .query_row("SELECT precio_neto FROM productos WHERE id = ?1", params![arroz], |r| r.get(0))
+            .unwrap();
+        assert_eq!(precio_neto, 4.0);
+
+        let cerrada = recibir(&mut conn, orden_id, None, &[recepcion(1.0, 4.0)]);
+        assert!(matches!(cerrada, Err(CompraError::OrdenCerrada { estado: EstadoOrden::Recibida, .. })));
+    }
+}
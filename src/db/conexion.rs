@@ -0,0 +1,88 @@
+use super::ubicacion;
+use rusqlite::{Connection, Result};
+use std::ops::{Deref, DerefMut};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+/// Conexiones inactivas que se conservan para reutilizar
+const MAX_INACTIVAS: usize = 4;
+
+/// Tiempo que una conexión espera a que se libere un bloqueo antes de fallar
+/// con SQLITE_BUSY (p. ej. una venta escribiendo mientras se refresca la tabla)
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Conexiones ya configuradas y libres
+static POOL: OnceLock<Mutex<Vec<Connection>>> = OnceLock::new();
+
+fn get_pool() -> &'static Mutex<Vec<Connection>> {
+    POOL.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+/// Conexión prestada por el gestor. Se usa como un `Connection` normal
+/// (`&conn`, `&mut conn`) y vuelve al pool al salir de alcance.
+pub struct Conexion {
+    conn: Option<Connection>,
+}
+
+impl Deref for Conexion {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        self.conn.as_ref().expect("conexión ya devuelta al pool")
+    }
+}
+
+impl DerefMut for Conexion {
+    fn deref_mut(&mut self) -> &mut Connection {
+        self.conn.as_mut().expect("conexión ya devuelta al pool")
+    }
+}
+
+impl Drop for Conexion {
+    fn drop(&mut self) {
+        let Some(conn) = self.conn.take() else { return };
+        // Una transacción sin cerrar indica un error a medias: no reutilizarla
+        if !conn.is_autocommit() {
+            return;
+        }
+        if let Ok(mut pool) = get_pool().lock() {
+            if pool.len() < MAX_INACTIVAS {
+                pool.push(conn);
+            }
+        }
+    }
+}
+
+/// Entrega una conexión lista para usar, reutilizando una libre si la hay.
+/// Es `Send`, así que puede pedirse y usarse dentro de `tokio::spawn`.
+pub fn obtener() -> Result<Conexion> {
+    let libre = get_pool().lock().unwrap().pop();
+    let conn = match libre {
+        Some(conn) => conn,
+        None => nueva()?,
+    };
+    Ok(Conexion { conn: Some(conn) })
+}
+
+/// Descarta las conexiones libres (al cambiar la ubicación de la base)
+pub fn vaciar() {
+    get_pool().lock().unwrap().clear();
+}
+
+/// Abre y configura una conexión nueva
+fn nueva() -> Result<Connection> {
+    let conn = ubicacion::abrir(&ubicacion::actual())?;
+    configurar(&conn)?;
+    Ok(conn)
+}
+
+/// Ajustes que SQLite aplica por conexión y no se guardan en el archivo
+fn configurar(conn: &Connection) -> Result<()> {
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    conn.pragma_update(None, "foreign_keys", true)?;
+    // WAL permite leer mientras otra conexión escribe. Las bases en memoria
+    // no lo soportan y responden "memory", lo cual es correcto.
+    let _modo: String = conn.pragma_update_and_check(None, "journal_mode", "WAL", |r| r.get(0))?;
+    conn.pragma_update(None, "synchronous", "NORMAL")?;
+    Ok(())
+}
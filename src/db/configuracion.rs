@@ -0,0 +1,65 @@
+use crate::models::{AnchoPapel, ConfiguracionRecibo, DatosTienda};
+use rusqlite::{params, Connection, OptionalExtension, Result};
+
+/// Valor de un ajuste, si fue guardado
+pub fn obtener(conn: &Connection, clave: &str) -> Result<Option<String>> {
+    conn.query_row(
+        "SELECT valor FROM configuracion WHERE clave = ?1",
+        params![clave],
+        |row| row.get(0),
+    )
+    .optional()
+}
+
+/// Guarda (o reemplaza) un ajuste
+pub fn guardar(conn: &Connection, clave: &str, valor: &str) -> Result<()> {
+    conn.execute(
+        "INSERT INTO configuracion (clave, valor) VALUES (?1, ?2)
+         ON CONFLICT(clave) DO UPDATE SET valor = excluded.valor",
+        params![clave, valor],
+    )?;
+    Ok(())
+}
+
+/// Configuración de recibos; los ajustes que falten toman su valor por defecto
+pub fn obtener_recibo(conn: &Connection) -> Result<ConfiguracionRecibo> {
+    let texto = |clave: &str| -> Result<String> { Ok(obtener(conn, clave)?.unwrap_or_default()) };
+    let opcional = |clave: &str| -> Result<Option<String>> {
+        Ok(obtener(conn, clave)?.filter(|v| !v.trim().is_empty()))
+    };
+
+    Ok(ConfiguracionRecibo {
+        tienda: DatosTienda {
+            nombre: obtener(conn, "tienda.nombre")?.unwrap_or_else(|| "BODEX".to_string()),
+            rif: texto("tienda.rif")?,
+            direccion: texto("tienda.direccion")?,
+            telefono: texto("tienda.telefono")?,
+            pie: obtener(conn, "tienda.pie")?
+                .unwrap_or_else(|| "¡Gracias por su compra!".to_string()),
+        },
+        ancho: AnchoPapel::from(texto("recibo.ancho")?),
+        impresora: opcional("recibo.impresora")?,
+        carpeta_pdf: opcional("recibo.carpeta_pdf")?,
+    })
+}
+
+/// Guarda la configuración de recibos completa
+pub fn guardar_recibo(conn: &Connection, c: &ConfiguracionRecibo) -> Result<()> {
+    guardar(conn, "tienda.nombre", &c.tienda.nombre)?;
+    guardar(conn, "tienda.rif", &c.tienda.rif)?;
+    guardar(conn, "tienda.direccion", &c.tienda.direccion)?;
+    guardar(conn, "tienda.telefono", &c.tienda.telefono)?;
+    guardar(conn, "tienda.pie", &c.tienda.pie)?;
+    guardar(conn, "recibo.ancho", c.ancho.as_str())?;
+    guardar(
+        conn,
+        "recibo.impresora",
+        c.impresora.as_deref().unwrap_or_default(),
+    )?;
+    guardar(
+        conn,
+        "recibo.carpeta_pdf",
+        c.carpeta_pdf.as_deref().unwrap_or_default(),
+    )?;
+    Ok(())
+}
@@ -0,0 +1,208 @@
+use super::caja;
+use crate::models::credito::repartir_por_antiguedad;
+use crate::models::{CargoCredito, Cliente, MetodoPago, MovimientoCuenta, SaldoPorAntiguedad};
+use chrono::NaiveDate;
+use rusqlite::{params, Connection, Result};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Saldo de la cuenta del cliente `c.id` (alias `c` = clientes): lo vendido a crédito, menos lo
+/// devuelto a la cuenta y lo abonado
+pub const SALDO_CLIENTE: &str = "(COALESCE((SELECT SUM(v.monto_credito) FROM ventas v WHERE v.cliente_id = c.id), 0)
+    - COALESCE((SELECT SUM(d.monto_credito) FROM devoluciones d
+                JOIN ventas v ON d.venta_id = v.id
+                WHERE v.cliente_id = c.id), 0)
+    - COALESCE((SELECT SUM(a.monto) FROM abonos a WHERE a.cliente_id = c.id), 0))";
+
+/// Errores de negocio al registrar un abono
+#[derive(Debug)]
+pub enum CreditoError {
+    /// El cliente no existe
+    ClienteNoEncontrado(i64),
+    /// Quien cobra no tiene una caja abierta en la cual recibir el abono
+    SinCajaAbierta,
+    /// Monto no positivo o mayor que lo adeudado
+    MontoInvalido(String),
+    /// Error de SQLite
+    Db(rusqlite::Error),
+}
+
+impl fmt::Display for CreditoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CreditoError::ClienteNoEncontrado(id) => write!(f, "El cliente con ID {} no existe", id),
+            CreditoError::SinCajaAbierta => write!(f, "No hay una caja abierta: abra la caja antes de cobrar"),
+            CreditoError::MontoInvalido(msg) => write!(f, "{}", msg),
+            CreditoError::Db(e) => write!(f, "Error de base de datos: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for CreditoError {}
+
+impl From<rusqlite::Error> for CreditoError {
+    fn from(e: rusqlite::Error) -> Self {
+        CreditoError::Db(e)
+    }
+}
+
+/// Registra un abono a la cuenta del cliente en la caja abierta de quien lo recibe.
+/// El abono no puede superar lo adeudado.
+pub fn registrar_abono(
+    conn: &mut Connection,
+    cliente_id: i64,
+    usuario_id: i64,
+    metodo: MetodoPago,
+    monto: f64,
+    referencia: Option<&str>,
+) -> std::result::Result<i64, CreditoError> {
+    if !monto.is_finite() || monto <= 0.0 {
+        return Err(CreditoError::MontoInvalido("El monto del abono debe ser mayor a 0".to_string()));
+    }
+
+    let tx = conn.transaction()?;
+    let sesion_id = caja::id_sesion_abierta(&tx, usuario_id)?.ok_or(CreditoError::SinCajaAbierta)?;
+    let saldo = tx
+        .query_row(
+            &format!("SELECT {} FROM clientes c WHERE c.id = ?1", SALDO_CLIENTE),
+            params![cliente_id],
+            |row| row.get::<_, f64>(0),
+        )
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => CreditoError::ClienteNoEncontrado(cliente_id),
+            e => CreditoError::Db(e),
+        })?;
+
+    let centimos = |monto: f64| (monto * 100.0).round() as i64;
+    if centimos(monto) > centimos(saldo) {
+        return Err(CreditoError::MontoInvalido(format!(
+            "El abono de {:.2} supera el saldo del cliente ({:.2})",
+            monto, saldo
+        )));
+    }
+
+    tx.execute(
+        "INSERT INTO abonos (cliente_id, monto, metodo, referencia, usuario_id, sesion_id)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            cliente_id,
+            monto,
+            metodo.as_str(),
+            referencia.map(str::trim).filter(|r| !r.is_empty()),
+            usuario_id,
+            sesion_id
+        ],
+    )?;
+    let abono_id = tx.last_insert_rowid();
+    tx.commit()?;
+    Ok(abono_id)
+}
+
+/// Estado de cuenta del cliente: ventas a crédito (cargo), devoluciones a la cuenta y abonos,
+/// en orden cronológico con el saldo acumulado
+pub fn estado_de_cuenta(conn: &Connection, cliente_id: i64) -> Result<Vec<MovimientoCuenta>> {
+    let mut stmt = conn.prepare(
+        "SELECT fecha, concepto, cargo, abono FROM (
+            SELECT v.fecha AS fecha, 0 AS tipo, v.id AS orden, 'Venta #' || v.id AS concepto,
+                   v.monto_credito AS cargo, 0 AS abono
+            FROM ventas v WHERE v.cliente_id = ?1 AND v.monto_credito > 0
+            UNION ALL
+            SELECT d.fecha, 1, d.id, (CASE WHEN d.anulacion THEN 'Anulación' ELSE 'Devolución' END)
+                   || ' venta #' || d.venta_id, 0, d.monto_credito
+            FROM devoluciones d JOIN ventas v ON d.venta_id = v.id
+            WHERE v.cliente_id = ?1 AND d.monto_credito > 0
+            UNION ALL
+            SELECT a.fecha, 2, a.id, 'Abono #' || a.id, 0, a.monto
+            FROM abonos a WHERE a.cliente_id = ?1
+         )
+         ORDER BY fecha ASC, tipo ASC, orden ASC",
+    )?;
+    let rows = stmt.query_map(params![cliente_id], |row| {
+        Ok((row.get(0)?, row.get::<_, String>(1)?, row.get::<_, f64>(2)?, row.get::<_, f64>(3)?))
+    })?;
+
+    let mut saldo = 0.0;
+    let mut resultado = Vec::new();
+    for fila in rows {
+        let (fecha, concepto, cargo, abono) = fila?;
+        saldo = ((saldo + cargo - abono) * 100.0_f64).round() / 100.0;
+        resultado.push(MovimientoCuenta { fecha, concepto, cargo, abono, saldo });
+    }
+    Ok(resultado)
+}
+
+/// Cuentas por cobrar al día `hoy`: los clientes con saldo pendiente, con lo adeudado repartido
+/// por antigüedad de las ventas (ver `repartir_por_antiguedad`), de mayor a menor saldo
+pub fn cuentas_por_cobrar(conn: &Connection, hoy: NaiveDate) -> Result<Vec<SaldoPorAntiguedad>> {
+    // Lo vendido a crédito por venta, descontando lo devuelto a la cuenta
+    let mut stmt = conn.prepare(
+        "SELECT v.cliente_id, v.id, v.fecha,
+                v.monto_credito - COALESCE((SELECT SUM(d.monto_credito) FROM devoluciones d
+                                            WHERE d.venta_id = v.id), 0)
+         FROM ventas v
+         WHERE v.cliente_id IS NOT NULL AND v.monto_credito > 0",
+    )?;
+    let mut cargos: HashMap<i64, Vec<CargoCredito>> = HashMap::new();
+    for fila in stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            CargoCredito {
+                venta_id: row.get(1)?,
+                fecha: row.get(2)?,
+                monto: row.get(3)?,
+            },
+        ))
+    })? {
+        let (cliente_id, cargo) = fila?;
+        cargos.entry(cliente_id).or_default().push(cargo);
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT c.id, c.documento, c.nombre, c.limite_credito,
+                COALESCE((SELECT SUM(a.monto) FROM abonos a WHERE a.cliente_id = c.id), 0)
+         FROM clientes c",
+    )?;
+    let mut resultado = Vec::new();
+    for fila in stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, Option<String>>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, f64>(3)?,
+            row.get::<_, f64>(4)?,
+        ))
+    })? {
+        let (cliente_id, documento, nombre, limite_credito, abonado) = fila?;
+        let Some(cargos) = cargos.get(&cliente_id) else { continue };
+        let tramos = repartir_por_antiguedad(cargos, abonado, hoy);
+        if tramos.iter().any(|t| *t > 0.0) {
+            resultado.push(SaldoPorAntiguedad {
+                cliente_id,
+                documento,
+                nombre,
+                limite_credito,
+                tramos,
+            });
+        }
+    }
+    resultado.sort_by(|a, b| b.total().total_cmp(&a.total()).then_with(|| a.nombre.cmp(&b.nombre)));
+    Ok(resultado)
+}
+
+/// Límite de crédito y saldo actual del cliente (None para el consumidor final, que no compra
+/// a crédito, o si el cliente no existe)
+pub fn credito_disponible(conn: &Connection, cliente_id: i64) -> Result<Option<(f64, f64)>> {
+    if cliente_id == Cliente::CONSUMIDOR_FINAL {
+        return Ok(None);
+    }
+    conn.query_row(
+        &format!("SELECT c.limite_credito, {} FROM clientes c WHERE c.id = ?1", SALDO_CLIENTE),
+        params![cliente_id],
+        |row| Ok(Some((row.get(0)?, row.get(1)?))),
+    )
+    .or_else(|e| match e {
+        rusqlite::Error::QueryReturnedNoRows => Ok(None),
+        e => Err(e),
+    })
+}
+
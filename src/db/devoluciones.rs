@@ -0,0 +1,359 @@
+use super::caja;
+use super::credito;
+use super::lotes;
+use super::movimientos::{self, MovimientoError};
+use super::ventas;
+use crate::models::medida::{formatear_cantidad, redondear_cantidad};
+use crate::models::{EstadoVenta, LineaDevolucion, MotivoMovimiento, Venta};
+use rusqlite::{params, Connection, Result, Transaction};
+use std::fmt;
+
+/// Errores de negocio al devolver o anular una venta
+#[derive(Debug)]
+pub enum DevolucionError {
+    /// Falta el motivo, no hay líneas o alguna cantidad no es válida
+    DevolucionInvalida(String),
+    /// La venta no existe
+    VentaNoEncontrada(i64),
+    /// La venta ya fue anulada o devuelta por completo
+    VentaCerrada { venta_id: i64, estado: EstadoVenta },
+    /// Se intenta devolver más de lo vendido (descontando devoluciones previas)
+    ExcedeVendido {
+        producto: String,
+        devolvible: f64,
+        solicitado: f64,
+    },
+    /// Quien devuelve no tiene una caja abierta de la cual sale el reembolso
+    SinCajaAbierta,
+    /// Error de SQLite
+    Db(rusqlite::Error),
+}
+
+impl fmt::Display for DevolucionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DevolucionError::DevolucionInvalida(msg) => write!(f, "{}", msg),
+            DevolucionError::VentaNoEncontrada(id) => write!(f, "La venta #{} no existe", id),
+            DevolucionError::VentaCerrada { venta_id, estado } => write!(
+                f,
+                "La venta #{} está {} y no admite devoluciones",
+                venta_id,
+                estado.etiqueta().to_lowercase()
+            ),
+            DevolucionError::ExcedeVendido { producto, devolvible, solicitado } => write!(
+                f,
+                "'{}': se intentan devolver {} pero solo quedan {} sin devolver",
+                producto,
+                formatear_cantidad(*solicitado),
+                formatear_cantidad(*devolvible)
+            ),
+            DevolucionError::SinCajaAbierta => {
+                write!(f, "No hay una caja abierta: abra la caja antes de registrar devoluciones")
+            }
+            DevolucionError::Db(e) => write!(f, "Error de base de datos: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for DevolucionError {}
+
+impl From<rusqlite::Error> for DevolucionError {
+    fn from(e: rusqlite::Error) -> Self {
+        DevolucionError::Db(e)
+    }
+}
+
+impl From<MovimientoError> for DevolucionError {
+    fn from(e: MovimientoError) -> Self {
+        match e {
+            MovimientoError::Db(e) => DevolucionError::Db(e),
+            // Una entrada no puede dejar stock negativo: solo queda el producto inexistente
+            otro => DevolucionError::DevolucionInvalida(otro.to_string()),
+        }
+    }
+}
+
+/// Devuelve parte de una venta: repone el stock de cada línea (movimiento
+/// "devolucion" en el kardex) y deja la venta en devolución parcial o devuelta.
+/// El reembolso sale de la caja abierta de quien devuelve (`DevolucionError::SinCajaAbierta`
+/// si no tiene). Devuelve el id de la devolución.
+pub fn registrar_devolucion(
+    conn: &mut Connection,
+    venta_id: i64,
+    usuario_id: i64,
+    motivo: &str,
+    lineas: &[LineaDevolucion],
+) -> std::result::Result<i64, DevolucionError> {
+    let tx = conn.transaction()?;
+    let devolucion_id = aplicar_devolucion(&tx, venta_id, usuario_id, motivo, lineas, false)?;
+    tx.commit()?;
+    Ok(devolucion_id)
+}
+
+/// Anula la venta: devuelve todas las unidades aún no devueltas y la marca como anulada.
+/// Devuelve el id de la devolución que registra la anulación.
+pub fn anular_venta(
+    conn: &mut Connection,
+    venta_id: i64,
+    usuario_id: i64,
+    motivo: &str,
+) -> std::result::Result<i64, DevolucionError> {
+    let tx = conn.transaction()?;
+    let lineas: Vec<LineaDevolucion> = ventas::obtener_venta_con_detalles(&tx, venta_id)?
+        .ok_or(DevolucionError::VentaNoEncontrada(venta_id))?
+        .detalles
+        .iter()
+        .filter(|d| d.devolvible() > 0.0)
+        .map(|d| LineaDevolucion {
+            detalle_id: d.id,
+            cantidad: d.devolvible(),
+        })
+        .collect();
+
+    let devolucion_id = aplicar_devolucion(&tx, venta_id, usuario_id, motivo, &lineas, true)?;
+    tx.commit()?;
+    Ok(devolucion_id)
+}
+
+fn aplicar_devolucion(
+    tx: &Transaction,
+    venta_id: i64,
+    usuario_id: i64,
+    motivo: &str,
+    lineas: &[LineaDevolucion],
+    anulacion: bool,
+) -> std::result::Result<i64, DevolucionError> {
+    let motivo = motivo.trim();
+    if motivo.is_empty() {
+        return Err(DevolucionError::DevolucionInvalida(
+            "Indique el motivo de la devolución".to_string(),
+        ));
+    }
+
+    let venta = ventas::obtener_venta_con_detalles(tx, venta_id)?
+        .ok_or(DevolucionError::VentaNoEncontrada(venta_id))?;
+    if !venta.venta.estado.admite_devolucion() {
+        return Err(DevolucionError::VentaCerrada {
+            venta_id,
+            estado: venta.venta.estado,
+        });
+    }
+
+    let lineas: Vec<&LineaDevolucion> = lineas.iter().filter(|l| l.cantidad != 0.0).collect();
+    if lineas.is_empty() {
+        return Err(DevolucionError::DevolucionInvalida(
+            "Indique al menos una cantidad a devolver".to_string(),
+        ));
+    }
+
+    let sesion_id = caja::id_sesion_abierta(tx, usuario_id)?.ok_or(DevolucionError::SinCajaAbierta)?;
+    tx.execute(
+        "INSERT INTO devoluciones (venta_id, usuario_id, motivo, anulacion, sesion_id)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![venta_id, usuario_id, motivo, anulacion, sesion_id],
+    )?;
+    let devolucion_id = tx.last_insert_rowid();
+    let referencia = format!(
+        "{} #{} (Venta #{})",
+        if anulacion { "Anulación" } else { "Devolución" },
+        devolucion_id,
+        venta_id
+    );
+
+    for l in lineas {
+        if l.cantidad < 0.0 {
+            return Err(DevolucionError::DevolucionInvalida(
+                "La cantidad a devolver no puede ser negativa".to_string(),
+            ));
+        }
+
+        // La línea debe pertenecer a esta venta
+        let detalle = venta.detalles.iter().find(|d| d.id == l.detalle_id).ok_or_else(|| {
+            DevolucionError::DevolucionInvalida(format!(
+                "La línea {} no pertenece a la venta #{}",
+                l.detalle_id, venta_id
+            ))
+        })?;
+
+        let ya_devuelto: f64 = tx.query_row(
+            "SELECT COALESCE(SUM(cantidad), 0) FROM devoluciones_detalle WHERE detalle_id = ?1",
+            params![detalle.id],
+            |row| row.get(0),
+        )?;
+        let devolvible = redondear_cantidad(detalle.cantidad - ya_devuelto);
+        if l.cantidad > devolvible {
+            return Err(DevolucionError::ExcedeVendido {
+                producto: detalle.nombre_producto.clone(),
+                devolvible,
+                solicitado: l.cantidad,
+            });
+        }
+
+        tx.execute(
+            "INSERT INTO devoluciones_detalle (devolucion_id, detalle_id, cantidad, monto)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![
+                devolucion_id,
+                detalle.id,
+                l.cantidad,
+                // Parte proporcional del total de la línea (con su IVA), en céntimos
+                (detalle.subtotal * l.cantidad / detalle.cantidad * 100.0).round() / 100.0
+            ],
+        )?;
+        movimientos::registrar_movimiento(
+            tx,
+            detalle.producto_id,
+            l.cantidad,
+            MotivoMovimiento::Devolucion,
+            Some(usuario_id),
+            Some(&referencia),
+            None,
+        )?;
+        lotes::devolver_venta(tx, detalle.id, l.cantidad)?;
+    }
+
+    // Si la venta fue a crédito, lo devuelto rebaja primero lo que el cliente aún debe de ella
+    if venta.venta.monto_credito > 0.0 {
+        abonar_a_credito(tx, &venta.venta, devolucion_id)?;
+    }
+
+    let pendientes: i64 = tx.query_row(
+        "SELECT COUNT(*) FROM ventas_detalle d
+         WHERE d.venta_id = ?1
+           AND d.cantidad > ROUND(COALESCE((SELECT SUM(dd.cantidad) FROM devoluciones_detalle dd
+                                            WHERE dd.detalle_id = d.id), 0), 3)",
+        params![venta_id],
+        |row| row.get(0),
+    )?;
+    let estado = if anulacion {
+        EstadoVenta::Anulada
+    } else if pendientes == 0 {
+        EstadoVenta::Devuelta
+    } else {
+        EstadoVenta::DevolucionParcial
+    };
+    tx.execute(
+        "UPDATE ventas SET estado = ?1 WHERE id = ?2",
+        params![estado.as_str(), venta_id],
+    )?;
+
+    Ok(devolucion_id)
+}
+
+/// Descuenta la devolución de la deuda del cliente, hasta lo que sigue pendiente de la parte a
+/// crédito de la venta y sin dejarle saldo a favor; el resto se reembolsa desde la caja
+fn abonar_a_credito(tx: &Transaction, venta: &Venta, devolucion_id: i64) -> Result<()> {
+    let devuelto: f64 = tx.query_row(
+        "SELECT COALESCE(SUM(monto), 0) FROM devoluciones_detalle WHERE devolucion_id = ?1",
+        params![devolucion_id],
+        |row| row.get(0),
+    )?;
+    let ya_abonado: f64 = tx.query_row(
+        "SELECT COALESCE(SUM(monto_credito), 0) FROM devoluciones WHERE venta_id = ?1",
+        params![venta.id],
+        |row| row.get(0),
+    )?;
+    let saldo = match venta.cliente_id {
+        Some(cliente_id) => credito::credito_disponible(tx, cliente_id)?.map(|(_, saldo)| saldo).unwrap_or(0.0),
+        None => 0.0,
+    };
+
+    let a_credito = devuelto.min(venta.monto_credito - ya_abonado).min(saldo).max(0.0);
+    tx.execute(
+        "UPDATE devoluciones SET monto_credito = ?1 WHERE id = ?2",
+        params![(a_credito * 100.0).round() / 100.0, devolucion_id],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::pruebas::{self, linea, pago};
+    use crate::models::medida::Medida;
+    use crate::models::{Cliente, MetodoPago};
+
+    /// Vende 2.5 kg de queso y 3 panes en efectivo; devuelve (venta, detalle queso, detalle pan)
+    fn venta_de_prueba(conn: &mut Connection, cajero: i64, queso: i64, pan: i64) -> (i64, i64, i64) {
+        let venta_id = ventas::registrar_venta(
+            conn,
+            cajero,
+            Cliente::CONSUMIDOR_FINAL,
+            vec![linea(queso, 2.5, 10.0), linea(pan, 3.0, 1.0)],
+            &[pago(MetodoPago::Efectivo, 28.0)],
+            0.0,
+            None,
+        )
+        .unwrap();
+        let detalles = ventas::obtener_venta_con_detalles(conn, venta_id).unwrap().unwrap().detalles;
+        (venta_id, detalles[0].id, detalles[1].id)
+    }
+
+    #[test]
+    fn devoluciones_parciales_reponen_el_stock_hasta_lo_vendido() {
+        let mut conn = pruebas::base_en_memoria();
+        let cajero = pruebas::cajero_con_caja(&conn, "ana");
+        let queso = pruebas::producto(&conn, "Queso", 6.0, 10.0, 2.5, Medida::Kilogramo);
+        let pan = pruebas::producto(&conn, "Pan", 0.5, 1.0, 5.0, Medida::Unidad);
+        let (venta_id, d_queso, d_pan) = venta_de_prueba(&mut conn, cajero, queso, pan);
+
+        let linea = |detalle_id, cantidad| LineaDevolucion { detalle_id, cantidad };
+        registrar_devolucion(&mut conn, venta_id, cajero, "Vencido", &[linea(d_queso, 0.3)]).unwrap();
+        registrar_devolucion(&mut conn, venta_id, cajero, "Vencido", &[linea(d_queso, 0.455)]).unwrap();
+        assert_eq!(pruebas::stock(&conn, queso), 0.755);
+
+        let exceso = registrar_devolucion(&mut conn, venta_id, cajero, "Vencido", &[linea(d_queso, 1.75)]);
+        assert!(matches!(exceso, Err(DevolucionError::ExcedeVendido { .. })));
+        assert_eq!(pruebas::stock(&conn, queso), 0.755);
+
+        registrar_devolucion(&mut conn, venta_id, cajero, "Vencido", &[linea(d_queso, 1.745), linea(d_pan, 3.0)])
+            .unwrap();
+        let venta = ventas::obtener_venta_con_detalles(&conn, venta_id).unwrap().unwrap().venta;
+        assert_eq!(venta.estado, EstadoVenta::Devuelta);
+        assert_eq!(venta.monto_devuelto, 28.0);
+        assert_eq!(pruebas::stock(&conn, queso), 2.5);
+        assert_eq!(pruebas::stock(&conn, pan), 5.0);
+    }
+
+    #[test]
+    fn anular_devuelve_lo_pendiente_y_cierra_la_venta() {
+        let mut conn = pruebas::base_en_memoria();
+        let cajero = pruebas::cajero_con_caja(&conn, "ana");
+        let queso = pruebas::producto(&conn, "Queso", 6.0, 10.0, 2.5, Medida::Kilogramo);
+        let pan = pruebas::producto(&conn, "Pan", 0.5, 1.0, 5.0, Medida::Unidad);
+        let (venta_id, _, d_pan) = venta_de_prueba(&mut conn, cajero, queso, pan);
+        let un_pan = [LineaDevolucion { detalle_id: d_pan, cantidad: 1.0 }];
+
+        registrar_devolucion(&mut conn, venta_id, cajero, "Roto", &un_pan).unwrap();
+        anular_venta(&mut conn, venta_id, cajero, "Error de cobro").unwrap();
+
+        assert_eq!(pruebas::stock(&conn, queso), 2.5);
+        assert_eq!(pruebas::stock(&conn, pan), 5.0);
+        let otra = registrar_devolucion(&mut conn, venta_id, cajero, "Roto", &un_pan);
+        assert!(matches!(otra, Err(DevolucionError::VentaCerrada { estado: EstadoVenta::Anulada, .. })));
+    }
+
+    #[test]
+    fn sin_caja_abierta_no_se_registra_la_devolucion() {
+        let mut conn = pruebas::base_en_memoria();
+        let cajero = pruebas::cajero_con_caja(&conn, "ana");
+        let queso = pruebas::producto(&conn, "Queso", 6.0, 10.0, 2.5, Medida::Kilogramo);
+        let pan = pruebas::producto(&conn, "Pan", 0.5, 1.0, 5.0, Medida::Unidad);
+        let (venta_id, _, d_pan) = venta_de_prueba(&mut conn, cajero, queso, pan);
+        conn.execute("INSERT INTO usuarios (username, password_hash, rol) VALUES ('luis', 'x', 'Admin')", [])
+            .unwrap();
+        let sin_caja = conn.last_insert_rowid();
+
+        let resultado = registrar_devolucion(
+            &mut conn,
+            venta_id,
+            sin_caja,
+            "Roto",
+            &[LineaDevolucion { detalle_id: d_pan, cantidad: 1.0 }],
+        );
+
+        assert!(matches!(resultado, Err(DevolucionError::SinCajaAbierta)));
+        assert_eq!(pruebas::contar(&conn, "devoluciones"), 0);
+        assert_eq!(pruebas::stock(&conn, pan), 2.0);
+    }
+}
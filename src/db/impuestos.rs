@@ -0,0 +1,30 @@
+use crate::models::{Impuesto, ModoPrecios};
+use rusqlite::{Connection, Result};
+
+/// Clave de `configuracion` con el modo de los precios de venta
+const CLAVE_MODO_PRECIOS: &str = "impuestos.modo_precios";
+
+/// Tasas registradas, de mayor a menor porcentaje
+pub fn obtener_impuestos(conn: &Connection) -> Result<Vec<Impuesto>> {
+    let mut stmt = conn.prepare("SELECT id, nombre, porcentaje FROM impuestos ORDER BY porcentaje DESC, nombre ASC")?;
+    let rows = stmt.query_map([], |row| {
+        Ok(Impuesto {
+            id: row.get(0)?,
+            nombre: row.get(1)?,
+            porcentaje: row.get(2)?,
+        })
+    })?;
+    rows.collect()
+}
+
+/// Modo de los precios de venta; si nunca se configuró, los precios incluyen el IVA
+pub fn obtener_modo_precios(conn: &Connection) -> Result<ModoPrecios> {
+    Ok(super::configuracion::obtener(conn, CLAVE_MODO_PRECIOS)?
+        .map(ModoPrecios::from)
+        .unwrap_or(ModoPrecios::IvaIncluido))
+}
+
+/// Guarda el modo de los precios de venta (las ventas registradas conservan su desglose)
+pub fn guardar_modo_precios(conn: &Connection, modo: ModoPrecios) -> Result<()> {
+    super::configuracion::guardar(conn, CLAVE_MODO_PRECIOS, modo.as_str())
+}
@@ -0,0 +1,385 @@
+use crate::models::medida::{formatear_cantidad, redondear_cantidad};
+use crate::models::{Lote, LoteNuevo, LotePorVencer, Medida};
+use chrono::{Local, NaiveDate};
+use rusqlite::{params, Connection, OptionalExtension, Result, Row};
+use std::fmt;
+
+/// Errores de negocio al ingresar o asignar lotes
+#[derive(Debug)]
+pub enum LoteError {
+    /// Número de lote vacío o cantidad no positiva
+    Invalido(String),
+    /// El lote ya existe con otra fecha de vencimiento
+    VencimientoDistinto {
+        numero: String,
+        vencimiento: Option<NaiveDate>,
+    },
+    /// Se quiere asignar a un lote más stock del que está sin lote
+    ExcedeSinLote { disponible: f64, solicitado: f64 },
+    /// El producto no existe en la base de datos
+    ProductoNoEncontrado(i64),
+    /// Parte de lo vendido solo puede salir de lotes vencidos, que no se venden
+    Vencido {
+        nombre: String,
+        vendible: f64,
+        solicitado: f64,
+    },
+    /// Error de SQLite
+    Db(rusqlite::Error),
+}
+
+impl fmt::Display for LoteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoteError::Invalido(msg) => write!(f, "{}", msg),
+            LoteError::VencimientoDistinto { numero, vencimiento } => write!(
+                f,
+                "El lote '{}' ya está registrado con vencimiento {}",
+                numero,
+                vencimiento.map(|v| v.format("%d/%m/%Y").to_string()).unwrap_or_else(|| "(sin vencimiento)".into())
+            ),
+            LoteError::ExcedeSinLote { disponible, solicitado } => write!(
+                f,
+                "Solo hay {} sin lote; no se pueden asignar {}",
+                formatear_cantidad(*disponible),
+                formatear_cantidad(*solicitado)
+            ),
+            LoteError::ProductoNoEncontrado(id) => write!(f, "El producto con ID {} no existe", id),
+            LoteError::Vencido { nombre, vendible, solicitado } => write!(
+                f,
+                "'{}': solo hay {} sin vencer y se solicitan {}; retire los lotes vencidos del inventario",
+                nombre,
+                formatear_cantidad(*vendible),
+                formatear_cantidad(*solicitado)
+            ),
+            LoteError::Db(e) => write!(f, "Error de base de datos: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for LoteError {}
+
+impl From<rusqlite::Error> for LoteError {
+    fn from(e: rusqlite::Error) -> Self {
+        LoteError::Db(e)
+    }
+}
+
+/// Suma `cantidad` unidades al lote del producto, creándolo si no existe.
+/// Solo registra el lote: el stock lo mueve quien llama (`movimientos::registrar_movimiento`).
+/// Devuelve el id del lote.
+pub fn ingresar(
+    conn: &Connection,
+    producto_id: i64,
+    lote: &LoteNuevo,
+    cantidad: f64,
+) -> std::result::Result<i64, LoteError> {
+    let numero = lote.numero.trim();
+    if numero.is_empty() {
+        return Err(LoteError::Invalido("El número de lote es requerido".to_string()));
+    }
+    if cantidad <= 0.0 {
+        return Err(LoteError::Invalido("La cantidad del lote debe ser mayor a 0".to_string()));
+    }
+
+    let existente: Option<(i64, Option<NaiveDate>)> = conn
+        .query_row(
+            "SELECT id, vencimiento FROM lotes WHERE producto_id = ?1 AND numero = ?2",
+            params![producto_id, numero],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+
+    match existente {
+        Some((_, vencimiento)) if vencimiento != lote.vencimiento => Err(LoteError::VencimientoDistinto {
+            numero: numero.to_string(),
+            vencimiento,
+        }),
+        Some((id, _)) => {
+            conn.execute("UPDATE lotes SET cantidad = ROUND(cantidad + ?1, 3) WHERE id = ?2", params![cantidad, id])?;
+            Ok(id)
+        }
+        None => {
+            conn.execute(
+                "INSERT INTO lotes (producto_id, numero, vencimiento, cantidad) VALUES (?1, ?2, ?3, ?4)",
+                params![producto_id, numero, lote.vencimiento, cantidad],
+            )?;
+            Ok(conn.last_insert_rowid())
+        }
+    }
+}
+
+/// Unidades del producto que no están en ningún lote
+pub fn stock_sin_lote(conn: &Connection, producto_id: i64) -> std::result::Result<f64, LoteError> {
+    conn.query_row(
+        "SELECT ROUND(p.stock - COALESCE((SELECT SUM(l.cantidad) FROM lotes l WHERE l.producto_id = p.id), 0), 3)
+         FROM productos p WHERE p.id = ?1",
+        params![producto_id],
+        |row| row.get(0),
+    )
+    .optional()?
+    .ok_or(LoteError::ProductoNoEncontrado(producto_id))
+}
+
+/// Asigna a un lote unidades que ya están en el stock sin lote (p. ej. el stock anterior al
+/// control por lotes). No cambia el stock del producto.
+pub fn asignar_stock(
+    conn: &mut Connection,
+    producto_id: i64,
+    lote: &LoteNuevo,
+    cantidad: f64,
+) -> std::result::Result<i64, LoteError> {
+    let tx = conn.transaction()?;
+    let disponible = stock_sin_lote(&tx, producto_id)?;
+    let unidad_venta: i32 = tx.query_row(
+        "SELECT unidad_venta FROM productos WHERE id = ?1",
+        params![producto_id],
+        |row| row.get(0),
+    )?;
+    if !Medida::from_i32(unidad_venta).fraccionable() && cantidad.fract() != 0.0 {
+        return Err(LoteError::Invalido(
+            "El producto se vende por unidades: la cantidad debe ser entera".to_string(),
+        ));
+    }
+    if cantidad > disponible {
+        return Err(LoteError::ExcedeSinLote {
+            disponible: disponible.max(0.0),
+            solicitado: cantidad,
+        });
+    }
+    let lote_id = ingresar(&tx, producto_id, lote, cantidad)?;
+    tx.commit()?;
+    Ok(lote_id)
+}
+
+/// Descuenta hasta `cantidad` unidades de los lotes del producto, primero los que vencen antes
+/// (FEFO; los que no vencen al final). Con `vigentes_al` se saltan los lotes vencidos antes de
+/// esa fecha. Lo que no alcanza a cubrirse sale del stock sin lote.
+/// Devuelve (lote_id, unidades) de cada lote tocado.
+fn consumir_fefo(
+    conn: &Connection,
+    producto_id: i64,
+    cantidad: f64,
+    vigentes_al: Option<NaiveDate>,
+) -> Result<Vec<(i64, f64)>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, cantidad FROM lotes
+         WHERE producto_id = ?1 AND cantidad > 0
+           AND (?2 IS NULL OR vencimiento IS NULL OR vencimiento >= ?2)
+         ORDER BY vencimiento IS NULL, vencimiento ASC, id ASC",
+    )?;
+    let lotes: Vec<(i64, f64)> = stmt
+        .query_map(params![producto_id, vigentes_al], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<_>>()?;
+
+    let mut pendiente = cantidad;
+    let mut consumidos = Vec::new();
+    for (lote_id, disponible) in lotes {
+        if pendiente <= 0.0 {
+            break;
+        }
+        let tomado = pendiente.min(disponible);
+        conn.execute("UPDATE lotes SET cantidad = ROUND(cantidad - ?1, 3) WHERE id = ?2", params![tomado, lote_id])?;
+        consumidos.push((lote_id, tomado));
+        pendiente = redondear_cantidad(pendiente - tomado);
+    }
+    Ok(consumidos)
+}
+
+/// Descuenta de los lotes (FEFO) las unidades vendidas en una línea de venta y deja registrado
+/// de qué lotes salieron. Llamar después de descontar la venta del stock del producto.
+/// Los lotes vencidos no se venden: si lo que falta no está en el stock sin lote devuelve
+/// `LoteError::Vencido`.
+pub fn consumir_venta(
+    conn: &Connection,
+    detalle_id: i64,
+    producto_id: i64,
+    cantidad: f64,
+) -> std::result::Result<(), LoteError> {
+    let hoy = Local::now().date_naive();
+    for (lote_id, tomado) in consumir_fefo(conn, producto_id, cantidad, Some(hoy))? {
+        conn.execute(
+            "INSERT INTO ventas_lotes (detalle_id, lote_id, cantidad) VALUES (?1, ?2, ?3)",
+            params![detalle_id, lote_id, tomado],
+        )?;
+    }
+
+    // Lo que no salió de lotes vigentes salió del stock sin lote; si no alcanzó, eran unidades vencidas
+    let faltante = -stock_sin_lote(conn, producto_id)?;
+    if faltante > 0.0 {
+        let nombre = conn.query_row("SELECT nombre FROM productos WHERE id = ?1", params![producto_id], |row| {
+            row.get(0)
+        })?;
+        return Err(LoteError::Vencido {
+            nombre,
+            vendible: redondear_cantidad(cantidad - faltante),
+            solicitado: cantidad,
+        });
+    }
+    Ok(())
+}
+
+/// Devuelve a sus lotes las unidades devueltas de una línea de venta, empezando por el lote que
+/// vence más tarde. Si la línea se vendió (en parte) sin lote, el resto vuelve al stock sin lote.
+pub fn devolver_venta(conn: &Connection, detalle_id: i64, cantidad: f64) -> Result<()> {
+    let mut stmt = conn.prepare(
+        "SELECT vl.lote_id, vl.cantidad - vl.devuelta
+         FROM ventas_lotes vl JOIN lotes l ON vl.lote_id = l.id
+         WHERE vl.detalle_id = ?1 AND vl.cantidad > vl.devuelta
+         ORDER BY l.vencimiento IS NULL DESC, l.vencimiento DESC, l.id DESC",
+    )?;
+    let consumidos: Vec<(i64, f64)> = stmt
+        .query_map(params![detalle_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<_>>()?;
+
+    let mut pendiente = cantidad;
+    for (lote_id, devolvible) in consumidos {
+        if pendiente <= 0.0 {
+            break;
+        }
+        let devuelto = pendiente.min(devolvible);
+        conn.execute("UPDATE lotes SET cantidad = ROUND(cantidad + ?1, 3) WHERE id = ?2", params![devuelto, lote_id])?;
+        conn.execute(
+            "UPDATE ventas_lotes SET devuelta = ROUND(devuelta + ?1, 3) WHERE detalle_id = ?2 AND lote_id = ?3",
+            params![devuelto, detalle_id, lote_id],
+        )?;
+        pendiente = redondear_cantidad(pendiente - devuelto);
+    }
+    Ok(())
+}
+
+/// Si una salida que no es venta (p. ej. un ajuste) dejó menos stock que lo asignado a lotes,
+/// descuenta la diferencia de los lotes que vencen primero
+pub fn ajustar_a_stock(conn: &Connection, producto_id: i64) -> std::result::Result<(), LoteError> {
+    let sin_lote = stock_sin_lote(conn, producto_id)?;
+    if sin_lote < 0.0 {
+        consumir_fefo(conn, producto_id, -sin_lote, None)?;
+    }
+    Ok(())
+}
+
+/// Lotes del producto: primero los que tienen unidades, por vencimiento (FEFO)
+pub fn obtener_lotes(conn: &Connection, producto_id: i64) -> Result<Vec<Lote>> {
+    let mut stmt = conn.prepare(
+        "SELECT numero, vencimiento, cantidad, ingreso FROM lotes
+         WHERE producto_id = ?1
+         ORDER BY cantidad = 0, vencimiento IS NULL, vencimiento ASC, id ASC",
+    )?;
+    let lotes = stmt.query_map(params![producto_id], mapear_lote)?;
+    lotes.collect()
+}
+
+/// Lotes con unidades que vencen hasta `hoy + dias` (incluye los ya vencidos),
+/// del que vence primero al último
+pub fn por_vencer(conn: &Connection, hoy: NaiveDate, dias: i64) -> Result<Vec<LotePorVencer>> {
+    let limite = hoy + chrono::Duration::days(dias);
+    let mut stmt = conn.prepare(
+        "SELECT l.numero, l.vencimiento, l.cantidad, l.ingreso, p.codigo, p.nombre
+         FROM lotes l JOIN productos p ON l.producto_id = p.id
+         WHERE l.cantidad > 0 AND l.vencimiento IS NOT NULL AND l.vencimiento <= ?1
+         ORDER BY l.vencimiento ASC, p.nombre ASC",
+    )?;
+    let lotes = stmt.query_map(params![limite], |row| {
+        Ok(LotePorVencer {
+            lote: mapear_lote(row)?,
+            producto_codigo: row.get(4)?,
+            producto_nombre: row.get(5)?,
+        })
+    })?;
+    lotes.collect()
+}
+
+fn mapear_lote(row: &Row) -> Result<Lote> {
+    Ok(Lote {
+        numero: row.get(0)?,
+        vencimiento: row.get(1)?,
+        cantidad: row.get(2)?,
+        ingreso: row.get(3)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::movimientos;
+    use crate::db::pruebas::{self, linea, pago};
+    use crate::db::ventas::{self, VentaError};
+    use crate::models::{Cliente, LineaDevolucion, MetodoPago, MotivoMovimiento};
+
+    /// Entrada de `cantidad` unidades al lote `numero` que vence `dias` días después de hoy
+    fn entrada(conn: &Connection, producto_id: i64, numero: &str, dias: Option<i64>, cantidad: f64) {
+        let vencimiento = dias.map(|d| Local::now().date_naive() + chrono::Duration::days(d));
+        let lote = LoteNuevo { numero: numero.to_string(), vencimiento };
+        movimientos::registrar_movimiento(conn, producto_id, cantidad, MotivoMovimiento::Compra, None, None, None)
+            .unwrap();
+        ingresar(conn, producto_id, &lote, cantidad).unwrap();
+    }
+
+    fn cantidad_lote(conn: &Connection, producto_id: i64, numero: &str) -> f64 {
+        conn.query_row(
+            "SELECT cantidad FROM lotes WHERE producto_id = ?1 AND numero = ?2",
+            params![producto_id, numero],
+            |r| r.get(0),
+        )
+        .unwrap()
+    }
+
+    fn vender(
+        conn: &mut Connection,
+        cajero: i64,
+        producto_id: i64,
+        cantidad: f64,
+    ) -> std::result::Result<i64, VentaError> {
+        ventas::registrar_venta(
+            conn,
+            cajero,
+            Cliente::CONSUMIDOR_FINAL,
+            vec![linea(producto_id, cantidad, 1.0)],
+            &[pago(MetodoPago::Efectivo, cantidad)],
+            0.0,
+            None,
+        )
+    }
+
+    #[test]
+    fn la_venta_sale_primero_del_lote_que_vence_antes() {
+        let mut conn = pruebas::base_en_memoria();
+        let cajero = pruebas::cajero_con_caja(&conn, "ana");
+        let leche = pruebas::producto(&conn, "Leche", 0.5, 1.0, 0.0, Medida::Unidad);
+        entrada(&conn, leche, "A", Some(30), 5.0);
+        entrada(&conn, leche, "B", Some(10), 3.0);
+        entrada(&conn, leche, "C", None, 4.0);
+
+        let venta_id = vender(&mut conn, cajero, leche, 6.0).unwrap();
+        assert_eq!(cantidad_lote(&conn, leche, "B"), 0.0);
+        assert_eq!(cantidad_lote(&conn, leche, "A"), 2.0);
+        assert_eq!(cantidad_lote(&conn, leche, "C"), 4.0);
+
+        // La devolución vuelve primero al lote que vence más tarde
+        let detalle_id = ventas::obtener_venta_con_detalles(&conn, venta_id).unwrap().unwrap().detalles[0].id;
+        let devolucion = [LineaDevolucion { detalle_id, cantidad: 4.0 }];
+        crate::db::devoluciones::registrar_devolucion(&mut conn, venta_id, cajero, "Cambio", &devolucion).unwrap();
+        assert_eq!(cantidad_lote(&conn, leche, "A"), 5.0);
+        assert_eq!(cantidad_lote(&conn, leche, "B"), 1.0);
+        assert_eq!(pruebas::stock(&conn, leche), 10.0);
+    }
+
+    #[test]
+    fn los_lotes_vencidos_no_se_venden() {
+        let mut conn = pruebas::base_en_memoria();
+        let cajero = pruebas::cajero_con_caja(&conn, "ana");
+        let leche = pruebas::producto(&conn, "Leche", 0.5, 1.0, 1.0, Medida::Unidad);
+        entrada(&conn, leche, "V", Some(-1), 5.0);
+        entrada(&conn, leche, "W", Some(5), 2.0);
+
+        vender(&mut conn, cajero, leche, 3.0).unwrap();
+        assert_eq!(cantidad_lote(&conn, leche, "W"), 0.0);
+        assert_eq!(cantidad_lote(&conn, leche, "V"), 5.0);
+        assert_eq!(pruebas::stock(&conn, leche), 5.0);
+
+        let resultado = vender(&mut conn, cajero, leche, 1.0);
+        assert!(matches!(resultado, Err(VentaError::LoteVencido(_))));
+        assert_eq!(pruebas::stock(&conn, leche), 5.0);
+        assert_eq!(pruebas::contar(&conn, "ventas"), 1);
+    }
+}
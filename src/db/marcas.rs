@@ -1,33 +1,24 @@
-use crate::models::{Marca, MarcaNueva};
+use crate::models::{Marca, MarcaConProductos, MarcaNueva};
 use rusqlite::{params, Connection, Result};
 
-/// Crea la tabla de marcas si no existe
-pub fn create_table(conn: &Connection) -> Result<()> {
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS marcas (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            nombre TEXT NOT NULL UNIQUE,
-            descripcion TEXT,
-            logo TEXT,
-            rif TEXT UNIQUE
-        )",
-        [],
+/// Obtener todas las marcas con la cantidad de productos asociados, ordenadas por nombre
+pub fn obtener_marcas_con_conteo(conn: &Connection) -> Result<Vec<MarcaConProductos>> {
+    let mut stmt = conn.prepare(
+        "SELECT m.id, m.nombre, m.descripcion, m.logo, m.rif, COUNT(p.id)
+         FROM marcas m
+         LEFT JOIN productos p ON p.marca_id = m.id
+         GROUP BY m.id
+         ORDER BY m.nombre ASC",
     )?;
-    Ok(())
-}
-
-/// Obtener todas las marcas ordenadas por nombre
-pub fn obtener_marcas(conn: &Connection) -> Result<Vec<Marca>> {
-    let mut stmt =
-        conn.prepare("SELECT id, nombre, descripcion, logo, rif FROM marcas ORDER BY nombre ASC")?;
 
     let marcas_iter = stmt.query_map([], |row| {
-        Ok(Marca {
+        Ok(MarcaConProductos {
             id: row.get(0)?,
             nombre: row.get(1)?,
             descripcion: row.get(2)?,
             logo: row.get(3)?,
             rif: row.get(4)?,
+            total_productos: row.get(5)?,
         })
     })?;
 
@@ -38,6 +29,35 @@ pub fn obtener_marcas(conn: &Connection) -> Result<Vec<Marca>> {
     Ok(resultado)
 }
 
+/// Cantidad de productos que quedarían sin marca al eliminarla
+pub fn contar_productos(conn: &Connection, id: i64) -> Result<i64> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM productos WHERE marca_id = ?1",
+        params![id],
+        |row| row.get(0),
+    )
+}
+
+/// Verificar si ya existe otra marca con el mismo nombre (sin distinguir mayúsculas)
+pub fn existe_nombre(conn: &Connection, nombre: &str, excluir_id: Option<i64>) -> Result<bool> {
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM marcas WHERE nombre = ?1 COLLATE NOCASE AND id != ?2",
+        params![nombre.trim(), excluir_id.unwrap_or(0)],
+        |row| row.get(0),
+    )?;
+    Ok(count > 0)
+}
+
+/// Verificar si ya existe otra marca con el mismo RIF
+pub fn existe_rif(conn: &Connection, rif: &str, excluir_id: Option<i64>) -> Result<bool> {
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM marcas WHERE rif = ?1 AND id != ?2",
+        params![rif.trim(), excluir_id.unwrap_or(0)],
+        |row| row.get(0),
+    )?;
+    Ok(count > 0)
+}
+
 /// Crear una nueva marca
 pub fn crear_marca(conn: &Connection, m: &MarcaNueva) -> Result<i64> {
     conn.execute(
@@ -47,25 +67,6 @@ pub fn crear_marca(conn: &Connection, m: &MarcaNueva) -> Result<i64> {
     Ok(conn.last_insert_rowid())
 }
 
-/// Obtener una marca específica por su ID
-pub fn obtener_marca_por_id(conn: &Connection, id: i64) -> Result<Option<Marca>> {
-    let mut stmt =
-        conn.prepare("SELECT id, nombre, descripcion, logo, rif FROM marcas WHERE id = ?1")?;
-    let mut rows = stmt.query(params![id])?;
-
-    if let Some(row) = rows.next()? {
-        Ok(Some(Marca {
-            id: row.get(0)?,
-            nombre: row.get(1)?,
-            descripcion: row.get(2)?,
-            logo: row.get(3)?,
-            rif: row.get(4)?,
-        }))
-    } else {
-        Ok(None)
-    }
-}
-
 /// Actualizar una marca existente
 pub fn actualizar_marca(conn: &Connection, m: &Marca) -> Result<bool> {
     let filas = conn.execute(
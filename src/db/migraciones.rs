@@ -0,0 +1,1206 @@
+use rusqlite::{Connection, Transaction};
+use std::fmt;
+
+/// Un paso de migración: se aplica dentro de una transacción y, al confirmarse,
+/// deja la base de datos en `version` (PRAGMA user_version)
+struct Migracion {
+    version: i64,
+    descripcion: &'static str,
+    aplicar: fn(&Transaction) -> rusqlite::Result<()>,
+}
+
+/// Migraciones en orden estricto de versión.
+/// Nunca modificar una migración ya publicada: agregar una nueva al final.
+/// Cada paso lleva su propio DDL congelado; no debe llamar a funciones de los módulos
+/// de `db`, que siguen cambiando y romperían la migración de bases antiguas.
+const MIGRACIONES: &[Migracion] = &[
+    Migracion {
+        version: 1,
+        descripcion: "Esquema inicial (usuarios, marcas, productos, ventas)",
+        aplicar: v1_esquema_inicial,
+    },
+    Migracion {
+        version: 2,
+        descripcion: "usuarios.debe_cambiar_password",
+        aplicar: v2_usuarios_cambio_password,
+    },
+    Migracion {
+        version: 3,
+        descripcion: "productos: medidas, empaque y categorías (reemplaza peso/presentación/vencimiento)",
+        aplicar: v3_productos_medidas,
+    },
+    Migracion {
+        version: 4,
+        descripcion: "categorias y subcategorias con claves foráneas desde productos",
+        aplicar: v4_categorias,
+    },
+    Migracion {
+        version: 5,
+        descripcion: "movimientos_stock (kardex) con el saldo inicial de cada producto",
+        aplicar: v5_movimientos_stock,
+    },
+    Migracion {
+        version: 6,
+        descripcion: "proveedores y órdenes de compra",
+        aplicar: v6_compras,
+    },
+    Migracion {
+        version: 7,
+        descripcion: "movimientos_stock: costo unitario y costo promedio ponderado",
+        aplicar: v7_costos_movimientos,
+    },
+    Migracion {
+        version: 8,
+        descripcion: "ventas.estado y devoluciones/anulaciones de ventas",
+        aplicar: v8_devoluciones,
+    },
+    Migracion {
+        version: 9,
+        descripcion: "configuracion (datos de la tienda e impresión de recibos)",
+        aplicar: v9_configuracion,
+    },
+    Migracion {
+        version: 10,
+        descripcion: "sesiones de caja con arqueo; ventas y devoluciones asociadas a la caja",
+        aplicar: v10_cajas,
+    },
+    Migracion {
+        version: 11,
+        descripcion: "pagos por venta (varios métodos) y ventas.vuelto",
+        aplicar: v11_pagos,
+    },
+    Migracion {
+        version: 12,
+        descripcion: "monedas y tasas de cambio; productos.moneda y ventas.tasa_cambio",
+        aplicar: v12_monedas,
+    },
+    Migracion {
+        version: 13,
+        descripcion: "impuestos (IVA) por categoría y producto; base e IVA en cada línea de venta",
+        aplicar: v13_impuestos,
+    },
+    Migracion {
+        version: 14,
+        descripcion: "clientes (con el consumidor final) y ventas.cliente_id",
+        aplicar: v14_clientes,
+    },
+    Migracion {
+        version: 15,
+        descripcion: "ventas a crédito: límite por cliente, abonos y devoluciones a la cuenta",
+        aplicar: v15_credito,
+    },
+    Migracion {
+        version: 16,
+        descripcion: "productos.stock_minimo y productos.punto_reorden (alertas de existencias)",
+        aplicar: v16_niveles_stock,
+    },
+    Migracion {
+        version: 17,
+        descripcion: "lotes con fecha de vencimiento y lotes consumidos por cada línea de venta",
+        aplicar: v17_lotes,
+    },
+    Migracion {
+        version: 18,
+        descripcion: "productos.unidad_venta; cantidades y stock con decimales (venta por peso o volumen)",
+        aplicar: v18_cantidades_decimales,
+    },
+    Migracion {
+        version: 19,
+        descripcion: "ventas_detalle.unidad_venta: medida en que se vendió cada línea",
+        aplicar: v19_unidad_venta_detalle,
+    },
+    Migracion {
+        version: 20,
+        descripcion: "movimientos_stock y lotes: un producto con historial ya no se puede borrar",
+        aplicar: v20_historial_sin_cascada,
+    },
+];
+
+/// Versión de esquema que entiende este binario
+pub fn version_soportada() -> i64 {
+    MIGRACIONES.last().map(|m| m.version).unwrap_or(0)
+}
+
+/// Errores al migrar la base de datos
+#[derive(Debug)]
+pub enum MigracionError {
+    /// El archivo fue creado por una versión más nueva de Bodex
+    VersionNoSoportada { encontrada: i64, soportada: i64 },
+    /// Falló una migración concreta (la transacción se revierte)
+    Fallida { version: i64, error: rusqlite::Error },
+    Db(rusqlite::Error),
+}
+
+impl fmt::Display for MigracionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MigracionError::VersionNoSoportada { encontrada, soportada } => write!(
+                f,
+                "La base de datos tiene la versión de esquema {} pero esta versión de Bodex solo soporta hasta la {}. Actualice la aplicación.",
+                encontrada, soportada
+            ),
+            MigracionError::Fallida { version, error } => {
+                write!(f, "Error aplicando la migración {}: {}", version, error)
+            }
+            MigracionError::Db(e) => write!(f, "Error de base de datos: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for MigracionError {}
+
+impl From<rusqlite::Error> for MigracionError {
+    fn from(e: rusqlite::Error) -> Self {
+        MigracionError::Db(e)
+    }
+}
+
+/// Versión actual del esquema guardada en el archivo
+pub fn version_actual(conn: &Connection) -> rusqlite::Result<i64> {
+    conn.query_row("PRAGMA user_version", [], |r| r.get(0))
+}
+
+/// Aplica en orden las migraciones pendientes.
+/// Cada paso corre en su propia transacción junto con el cambio de `user_version`,
+/// de modo que un fallo deja la base en la última versión completa.
+pub fn migrar(conn: &Connection) -> Result<(), MigracionError> {
+    let actual = version_actual(conn)?;
+    let soportada = version_soportada();
+
+    if actual > soportada {
+        return Err(MigracionError::VersionNoSoportada {
+            encontrada: actual,
+            soportada,
+        });
+    }
+
+    // Las reconstrucciones de tablas requieren las claves foráneas desactivadas;
+    // este PRAGMA no tiene efecto dentro de una transacción, por eso se fija aquí
+    conn.execute("PRAGMA foreign_keys = OFF", [])?;
+    let resultado = aplicar_pendientes(conn, actual, soportada);
+    conn.execute("PRAGMA foreign_keys = ON", [])?;
+    resultado
+}
+
+/// Aplica las migraciones posteriores a `actual` hasta `hasta` inclusive
+fn aplicar_pendientes(conn: &Connection, actual: i64, hasta: i64) -> Result<(), MigracionError> {
+    for migracion in MIGRACIONES
+        .iter()
+        .filter(|m| m.version > actual && m.version <= hasta)
+    {
+        let fallida = |error| MigracionError::Fallida {
+            version: migracion.version,
+            error,
+        };
+
+        let tx = conn.unchecked_transaction().map_err(fallida)?;
+        (migracion.aplicar)(&tx).map_err(fallida)?;
+        verificar_claves_foraneas(&tx).map_err(fallida)?;
+        tx.pragma_update(None, "user_version", migracion.version)
+            .map_err(fallida)?;
+        tx.commit().map_err(fallida)?;
+
+        println!(
+            "Migración {} aplicada: {}",
+            migracion.version, migracion.descripcion
+        );
+    }
+    Ok(())
+}
+
+/// Falla si la migración dejó filas que violan alguna clave foránea
+fn verificar_claves_foraneas(conn: &Connection) -> rusqlite::Result<()> {
+    let mut stmt = conn.prepare("PRAGMA foreign_key_check")?;
+    let mut rows = stmt.query([])?;
+    if let Some(row) = rows.next()? {
+        let tabla: String = row.get(0)?;
+        return Err(rusqlite::Error::SqliteFailure(
+            rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_CONSTRAINT_FOREIGNKEY),
+            Some(format!("Clave foránea inválida en la tabla '{}'", tabla)),
+        ));
+    }
+    Ok(())
+}
+
+/// Nombres de las columnas actuales de una tabla (vacío si no existe)
+fn columnas(conn: &Connection, tabla: &str) -> rusqlite::Result<Vec<String>> {
+    let mut stmt = conn.prepare("SELECT name FROM pragma_table_info(?1)")?;
+    let rows = stmt.query_map([tabla], |r| r.get::<_, String>(0))?;
+    rows.collect()
+}
+
+// ==========================================
+// MIGRACIONES
+// ==========================================
+
+/// v1: crea las tablas base. Las bases anteriores al sistema de versiones
+/// (user_version = 0) ya las tienen y los `IF NOT EXISTS` no las tocan.
+fn v1_esquema_inicial(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS usuarios (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            username TEXT NOT NULL UNIQUE,
+            password_hash TEXT NOT NULL,
+            rol TEXT NOT NULL CHECK(rol IN ('Admin', 'Operador', 'Vendedor')),
+            activo INTEGER NOT NULL DEFAULT 1,
+            ultimo_login DATETIME,
+            debe_cambiar_password INTEGER NOT NULL DEFAULT 0
+        );
+
+        CREATE TABLE IF NOT EXISTS marcas (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            nombre TEXT NOT NULL UNIQUE,
+            descripcion TEXT,
+            logo TEXT,
+            rif TEXT UNIQUE
+        );
+
+        CREATE TABLE IF NOT EXISTS productos (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            nombre TEXT NOT NULL,
+            precio_neto REAL NOT NULL DEFAULT 0,
+            precio_venta REAL NOT NULL DEFAULT 0,
+            stock INTEGER NOT NULL DEFAULT 0,
+            descripcion TEXT,
+            codigo TEXT UNIQUE,
+            activo INTEGER NOT NULL DEFAULT 1,
+            marca_id INTEGER,
+            medida_p_id INTEGER NOT NULL,
+            cantidad_p REAL NOT NULL DEFAULT 0,
+            medida_s_id INTEGER,
+            cantidad_s REAL,
+            empaque_id INTEGER NOT NULL,
+            categoria_id INTEGER NOT NULL DEFAULT 1,
+            subcategoria_id INTEGER NOT NULL DEFAULT 1,
+            FOREIGN KEY (marca_id) REFERENCES marcas(id) ON DELETE SET NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS ventas (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            fecha DATETIME DEFAULT CURRENT_TIMESTAMP,
+            total REAL NOT NULL,
+            usuario_id INTEGER,
+            cliente_nombre TEXT,
+            FOREIGN KEY (usuario_id) REFERENCES usuarios(id)
+        );
+
+        CREATE TABLE IF NOT EXISTS ventas_detalle (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            venta_id INTEGER NOT NULL,
+            producto_id INTEGER NOT NULL,
+            cantidad INTEGER NOT NULL,
+            precio_unitario REAL NOT NULL,
+            subtotal REAL NOT NULL,
+            FOREIGN KEY (venta_id) REFERENCES ventas(id) ON DELETE CASCADE,
+            FOREIGN KEY (producto_id) REFERENCES productos(id)
+        );
+
+        -- Antes no se aplicaban las FK: una marca o un usuario borrados pudieron dejar ids huérfanos
+        UPDATE productos SET marca_id = NULL
+        WHERE marca_id IS NOT NULL AND marca_id NOT IN (SELECT id FROM marcas);
+        UPDATE ventas SET usuario_id = NULL
+        WHERE usuario_id IS NOT NULL AND usuario_id NOT IN (SELECT id FROM usuarios);",
+    )
+}
+
+/// v2: bandera de cambio obligatorio de contraseña
+fn v2_usuarios_cambio_password(tx: &Transaction) -> rusqlite::Result<()> {
+    if columnas(tx, "usuarios")?.iter().any(|c| c == "debe_cambiar_password") {
+        return Ok(());
+    }
+
+    tx.execute(
+        "ALTER TABLE usuarios ADD COLUMN debe_cambiar_password INTEGER NOT NULL DEFAULT 0",
+        [],
+    )?;
+    // El admin sembrado con la contraseña por defecto debe cambiarla
+    tx.execute(
+        "UPDATE usuarios SET debe_cambiar_password = 1
+         WHERE username = 'admin' AND password_hash = 'admin'",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Esquema de `productos` tal como quedó en la v3
+const PRODUCTOS_V3: &str = "CREATE TABLE productos (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    nombre TEXT NOT NULL,
+    precio_neto REAL NOT NULL DEFAULT 0,
+    precio_venta REAL NOT NULL DEFAULT 0,
+    stock INTEGER NOT NULL DEFAULT 0,
+    descripcion TEXT,
+    codigo TEXT UNIQUE,
+    activo INTEGER NOT NULL DEFAULT 1,
+    marca_id INTEGER,
+    medida_p_id INTEGER NOT NULL,
+    cantidad_p REAL NOT NULL DEFAULT 0,
+    medida_s_id INTEGER,
+    cantidad_s REAL,
+    empaque_id INTEGER NOT NULL,
+    categoria_id INTEGER NOT NULL DEFAULT 1,
+    subcategoria_id INTEGER NOT NULL DEFAULT 1,
+    FOREIGN KEY (marca_id) REFERENCES marcas(id) ON DELETE SET NULL
+)";
+
+/// v3: los archivos antiguos tienen `productos` con peso, tamano, unidad_medida,
+/// presentacion y fecha_vencimiento. SQLite no permite cambiar columnas NOT NULL
+/// con ALTER, así que la tabla se reconstruye conservando ids (ventas_detalle
+/// sigue apuntando a los mismos productos). El peso pasa a `cantidad_p`.
+fn v3_productos_medidas(tx: &Transaction) -> rusqlite::Result<()> {
+    let existentes = columnas(tx, "productos")?;
+    if existentes.iter().any(|c| c == "medida_p_id") {
+        return Ok(());
+    }
+
+    let tiene = |col: &str| existentes.iter().any(|c| c == col);
+    // Expresión de origen para cada columna destino
+    let origen = |col: &str, por_defecto: &str| -> String {
+        if tiene(col) {
+            col.to_string()
+        } else {
+            por_defecto.to_string()
+        }
+    };
+
+    let cantidad_p = if tiene("peso") {
+        "COALESCE(peso, 0)".to_string()
+    } else {
+        "0".to_string()
+    };
+
+    // Modo legacy: que el RENAME no reescriba las FK de ventas_detalle hacia la tabla vieja
+    tx.pragma_update(None, "legacy_alter_table", true)?;
+    tx.execute("ALTER TABLE productos RENAME TO productos_v2", [])?;
+    tx.pragma_update(None, "legacy_alter_table", false)?;
+    tx.execute(PRODUCTOS_V3, [])?;
+    tx.execute(
+        &format!(
+            "INSERT INTO productos (
+                id, nombre, precio_neto, precio_venta, stock, descripcion, codigo, activo,
+                marca_id, medida_p_id, cantidad_p, medida_s_id, cantidad_s, empaque_id,
+                categoria_id, subcategoria_id
+            )
+            SELECT id, nombre, {}, {}, {}, {}, {}, {}, {}, 1, {}, NULL, NULL, 1, 1, 1
+            FROM productos_v2",
+            origen("precio_neto", "0"),
+            origen("precio_venta", "0"),
+            origen("stock", "0"),
+            origen("descripcion", "NULL"),
+            origen("codigo", "NULL"),
+            origen("activo", "1"),
+            origen("marca_id", "NULL"),
+            cantidad_p,
+        ),
+        [],
+    )?;
+    tx.execute("DROP TABLE productos_v2", [])?;
+    Ok(())
+}
+
+/// Esquema de `productos` tal como quedó en la v4
+const PRODUCTOS_V4: &str = "CREATE TABLE productos (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    nombre TEXT NOT NULL,
+    precio_neto REAL NOT NULL DEFAULT 0,
+    precio_venta REAL NOT NULL DEFAULT 0,
+    stock INTEGER NOT NULL DEFAULT 0,
+    descripcion TEXT,
+    codigo TEXT UNIQUE,
+    activo INTEGER NOT NULL DEFAULT 1,
+    marca_id INTEGER,
+    medida_p_id INTEGER NOT NULL,
+    cantidad_p REAL NOT NULL DEFAULT 0,
+    medida_s_id INTEGER,
+    cantidad_s REAL,
+    empaque_id INTEGER NOT NULL,
+    categoria_id INTEGER NOT NULL DEFAULT 1,
+    subcategoria_id INTEGER,
+    FOREIGN KEY (marca_id) REFERENCES marcas(id) ON DELETE SET NULL,
+    FOREIGN KEY (categoria_id) REFERENCES categorias(id),
+    FOREIGN KEY (subcategoria_id) REFERENCES subcategorias(id) ON DELETE SET NULL
+)";
+
+/// v4: `categoria_id` y `subcategoria_id` dejan de ser enteros sueltos.
+/// Se crean las tablas (con las opciones que antes estaban fijas en la UI) y
+/// `productos` se reconstruye con las claves foráneas. Las referencias que no
+/// existen pasan a la categoría 1 y las subcategorías que no pertenecen a la
+/// categoría del producto quedan en NULL.
+fn v4_categorias(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS categorias (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            nombre TEXT NOT NULL UNIQUE COLLATE NOCASE,
+            descripcion TEXT
+        );
+
+        CREATE TABLE IF NOT EXISTS subcategorias (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            categoria_id INTEGER NOT NULL,
+            nombre TEXT NOT NULL COLLATE NOCASE,
+            UNIQUE (categoria_id, nombre),
+            FOREIGN KEY (categoria_id) REFERENCES categorias(id) ON DELETE CASCADE
+        );
+
+        INSERT OR IGNORE INTO categorias (id, nombre, descripcion) VALUES
+            (1, 'General', 'Categoría por defecto'),
+            (2, 'Electrónica', NULL),
+            (3, 'Hogar', NULL);
+
+        INSERT OR IGNORE INTO subcategorias (id, categoria_id, nombre) VALUES
+            (1, 1, 'Varios'),
+            (2, 3, 'Limpieza'),
+            (3, 2, 'Accesorios');",
+    )?;
+
+    // Bases creadas por binarios cuya v1 ya traía `productos` con las claves foráneas
+    let ya_tiene_fk: i64 = tx.query_row(
+        "SELECT COUNT(*) FROM pragma_foreign_key_list('productos') WHERE \"table\" = 'categorias'",
+        [],
+        |r| r.get(0),
+    )?;
+    if ya_tiene_fk > 0 {
+        return Ok(());
+    }
+
+    tx.pragma_update(None, "legacy_alter_table", true)?;
+    tx.execute("ALTER TABLE productos RENAME TO productos_v3", [])?;
+    tx.pragma_update(None, "legacy_alter_table", false)?;
+    tx.execute(PRODUCTOS_V4, [])?;
+    tx.execute(
+        "INSERT INTO productos (
+            id, nombre, precio_neto, precio_venta, stock, descripcion, codigo, activo,
+            marca_id, medida_p_id, cantidad_p, medida_s_id, cantidad_s, empaque_id,
+            categoria_id, subcategoria_id
+        )
+        SELECT p.id, p.nombre, p.precio_neto, p.precio_venta, p.stock, p.descripcion, p.codigo,
+               p.activo, p.marca_id, p.medida_p_id, p.cantidad_p, p.medida_s_id, p.cantidad_s,
+               p.empaque_id, COALESCE(c.id, 1), s.id
+        FROM productos_v3 p
+        LEFT JOIN categorias c ON c.id = p.categoria_id
+        LEFT JOIN subcategorias s ON s.id = p.subcategoria_id
+                                 AND s.categoria_id = COALESCE(c.id, 1)",
+        [],
+    )?;
+    tx.execute("DROP TABLE productos_v3", [])?;
+    Ok(())
+}
+
+/// v5: kardex. Cada producto con existencias recibe un movimiento "inicial" por su
+/// stock actual para que el saldo del kardex coincida con `productos.stock`.
+fn v5_movimientos_stock(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS movimientos_stock (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            producto_id INTEGER NOT NULL,
+            cantidad INTEGER NOT NULL,
+            stock_resultante INTEGER NOT NULL,
+            motivo TEXT NOT NULL
+                CHECK (motivo IN ('inicial', 'compra', 'venta', 'devolucion', 'ajuste')),
+            usuario_id INTEGER,
+            fecha DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            referencia TEXT,
+            FOREIGN KEY (producto_id) REFERENCES productos(id) ON DELETE CASCADE,
+            FOREIGN KEY (usuario_id) REFERENCES usuarios(id)
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_movimientos_producto
+            ON movimientos_stock (producto_id, fecha);",
+    )?;
+    tx.execute(
+        "INSERT INTO movimientos_stock (producto_id, cantidad, stock_resultante, motivo, referencia)
+         SELECT p.id, p.stock, p.stock, 'inicial', 'Saldo al activar el kardex'
+         FROM productos p
+         WHERE p.stock != 0
+           AND NOT EXISTS (SELECT 1 FROM movimientos_stock m WHERE m.producto_id = p.id)",
+        [],
+    )?;
+    Ok(())
+}
+
+/// v6: proveedores, órdenes de compra y sus líneas
+fn v6_compras(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS proveedores (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            nombre TEXT NOT NULL UNIQUE COLLATE NOCASE,
+            rif TEXT UNIQUE,
+            telefono TEXT,
+            email TEXT,
+            direccion TEXT
+        );
+
+        CREATE TABLE IF NOT EXISTS ordenes_compra (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            proveedor_id INTEGER NOT NULL,
+            fecha DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            estado TEXT NOT NULL DEFAULT 'pendiente'
+                CHECK (estado IN ('pendiente', 'parcial', 'recibida', 'cancelada')),
+            usuario_id INTEGER,
+            notas TEXT,
+            FOREIGN KEY (proveedor_id) REFERENCES proveedores(id),
+            FOREIGN KEY (usuario_id) REFERENCES usuarios(id)
+        );
+
+        CREATE TABLE IF NOT EXISTS ordenes_compra_detalle (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            orden_id INTEGER NOT NULL,
+            producto_id INTEGER NOT NULL,
+            cantidad_pedida INTEGER NOT NULL CHECK (cantidad_pedida > 0),
+            cantidad_recibida INTEGER NOT NULL DEFAULT 0,
+            costo_unitario REAL NOT NULL,
+            costo_recibido REAL,
+            FOREIGN KEY (orden_id) REFERENCES ordenes_compra(id) ON DELETE CASCADE,
+            FOREIGN KEY (producto_id) REFERENCES productos(id)
+        );",
+    )
+}
+
+/// v7: costo por movimiento del kardex. Los movimientos anteriores no tienen costo
+/// conocido: se valoran al `precio_neto` actual de su producto.
+fn v7_costos_movimientos(tx: &Transaction) -> rusqlite::Result<()> {
+    // Bases cuya v5 ya creó las columnas; igualmente sus movimientos iniciales quedaron sin costo
+    if !columnas(tx, "movimientos_stock")?.iter().any(|c| c == "costo_promedio") {
+        tx.execute(
+            "ALTER TABLE movimientos_stock ADD COLUMN costo_unitario REAL NOT NULL DEFAULT 0",
+            [],
+        )?;
+        tx.execute(
+            "ALTER TABLE movimientos_stock ADD COLUMN costo_promedio REAL NOT NULL DEFAULT 0",
+            [],
+        )?;
+    }
+    tx.execute(
+        "UPDATE movimientos_stock
+         SET costo_unitario = (SELECT p.precio_neto FROM productos p WHERE p.id = movimientos_stock.producto_id),
+             costo_promedio = (SELECT p.precio_neto FROM productos p WHERE p.id = movimientos_stock.producto_id)",
+        [],
+    )?;
+    Ok(())
+}
+
+/// v8: estado de la venta y devoluciones. Las ventas existentes quedan "completada".
+fn v8_devoluciones(tx: &Transaction) -> rusqlite::Result<()> {
+    if !columnas(tx, "ventas")?.iter().any(|c| c == "estado") {
+        tx.execute(
+            "ALTER TABLE ventas ADD COLUMN estado TEXT NOT NULL DEFAULT 'completada'
+                CHECK (estado IN ('completada', 'devolucion_parcial', 'devuelta', 'anulada'))",
+            [],
+        )?;
+    }
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS devoluciones (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            venta_id INTEGER NOT NULL,
+            fecha DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            usuario_id INTEGER,
+            motivo TEXT NOT NULL,
+            anulacion INTEGER NOT NULL DEFAULT 0,
+            FOREIGN KEY (venta_id) REFERENCES ventas(id),
+            FOREIGN KEY (usuario_id) REFERENCES usuarios(id)
+        );
+
+        CREATE TABLE IF NOT EXISTS devoluciones_detalle (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            devolucion_id INTEGER NOT NULL,
+            detalle_id INTEGER NOT NULL,
+            cantidad INTEGER NOT NULL CHECK (cantidad > 0),
+            monto REAL NOT NULL,
+            FOREIGN KEY (devolucion_id) REFERENCES devoluciones(id) ON DELETE CASCADE,
+            FOREIGN KEY (detalle_id) REFERENCES ventas_detalle(id)
+        );",
+    )
+}
+
+/// v9: ajustes clave/valor (encabezado del recibo, ancho de papel, impresora)
+fn v9_configuracion(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS configuracion (
+            clave TEXT PRIMARY KEY,
+            valor TEXT NOT NULL
+        );",
+    )
+}
+
+/// v10: sesiones de caja. Las ventas y devoluciones anteriores quedan sin caja (sesion_id NULL).
+fn v10_cajas(tx: &Transaction) -> rusqlite::Result<()> {
+    for tabla in ["ventas", "devoluciones"] {
+        if !columnas(tx, tabla)?.iter().any(|c| c == "sesion_id") {
+            tx.execute(
+                &format!(
+                    "ALTER TABLE {} ADD COLUMN sesion_id INTEGER REFERENCES cajas_sesiones(id)",
+                    tabla
+                ),
+                [],
+            )?;
+        }
+    }
+    // Una sola caja abierta por usuario. Los triggers bloquean una sesión cerrada:
+    // no se modifica, no recibe ventas y su arqueo es definitivo.
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS cajas_sesiones (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            usuario_id INTEGER NOT NULL,
+            apertura DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            fondo_inicial REAL NOT NULL CHECK (fondo_inicial >= 0),
+            cierre DATETIME,
+            estado TEXT NOT NULL DEFAULT 'abierta' CHECK (estado IN ('abierta', 'cerrada')),
+            notas TEXT,
+            FOREIGN KEY (usuario_id) REFERENCES usuarios(id)
+        );
+
+        CREATE UNIQUE INDEX IF NOT EXISTS idx_cajas_sesiones_abierta
+            ON cajas_sesiones(usuario_id) WHERE estado = 'abierta';
+
+        CREATE TABLE IF NOT EXISTS cajas_cierres (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            sesion_id INTEGER NOT NULL,
+            metodo TEXT NOT NULL,
+            esperado REAL NOT NULL,
+            contado REAL NOT NULL,
+            diferencia REAL NOT NULL,
+            UNIQUE (sesion_id, metodo),
+            FOREIGN KEY (sesion_id) REFERENCES cajas_sesiones(id)
+        );
+
+        CREATE TRIGGER IF NOT EXISTS cajas_sesiones_bloqueo_update
+        BEFORE UPDATE ON cajas_sesiones WHEN OLD.estado = 'cerrada'
+        BEGIN
+            SELECT RAISE(ABORT, 'La sesión de caja está cerrada');
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS cajas_sesiones_bloqueo_delete
+        BEFORE DELETE ON cajas_sesiones WHEN OLD.estado = 'cerrada'
+        BEGIN
+            SELECT RAISE(ABORT, 'La sesión de caja está cerrada');
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS cajas_cierres_bloqueo_insert
+        BEFORE INSERT ON cajas_cierres
+        WHEN (SELECT estado FROM cajas_sesiones WHERE id = NEW.sesion_id) = 'cerrada'
+        BEGIN
+            SELECT RAISE(ABORT, 'La sesión de caja está cerrada');
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS cajas_cierres_bloqueo_update
+        BEFORE UPDATE ON cajas_cierres
+        BEGIN
+            SELECT RAISE(ABORT, 'El arqueo de caja no puede modificarse');
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS cajas_cierres_bloqueo_delete
+        BEFORE DELETE ON cajas_cierres
+        BEGIN
+            SELECT RAISE(ABORT, 'El arqueo de caja no puede modificarse');
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS ventas_caja_cerrada
+        BEFORE INSERT ON ventas
+        WHEN NEW.sesion_id IS NOT NULL
+         AND (SELECT estado FROM cajas_sesiones WHERE id = NEW.sesion_id) = 'cerrada'
+        BEGIN
+            SELECT RAISE(ABORT, 'La sesión de caja está cerrada');
+        END;",
+    )
+}
+
+/// v11: pagos de cada venta. Hasta ahora todo se cobraba en efectivo y exacto,
+/// así que cada venta existente recibe un pago en efectivo por su total.
+fn v11_pagos(tx: &Transaction) -> rusqlite::Result<()> {
+    if !columnas(tx, "ventas")?.iter().any(|c| c == "vuelto") {
+        tx.execute("ALTER TABLE ventas ADD COLUMN vuelto REAL NOT NULL DEFAULT 0", [])?;
+    }
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS pagos (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            venta_id INTEGER NOT NULL,
+            metodo TEXT NOT NULL
+                CHECK (metodo IN ('efectivo', 'tarjeta', 'transferencia', 'pago_movil')),
+            monto REAL NOT NULL CHECK (monto > 0),
+            referencia TEXT,
+            FOREIGN KEY (venta_id) REFERENCES ventas(id) ON DELETE CASCADE
+        );",
+    )?;
+    tx.execute(
+        "INSERT INTO pagos (venta_id, metodo, monto)
+         SELECT v.id, 'efectivo', v.total FROM ventas v
+         WHERE v.total > 0 AND NOT EXISTS (SELECT 1 FROM pagos p WHERE p.venta_id = v.id)",
+        [],
+    )?;
+    Ok(())
+}
+
+/// v12: monedas (USD base y VES) con tasas por día. Los productos existentes quedan con
+/// precio en dólares y las ventas anteriores sin tasa guardada (tasa_cambio NULL).
+fn v12_monedas(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS monedas (
+            codigo TEXT PRIMARY KEY,
+            nombre TEXT NOT NULL,
+            simbolo TEXT NOT NULL,
+            es_base INTEGER NOT NULL DEFAULT 0
+        );
+
+        CREATE UNIQUE INDEX IF NOT EXISTS idx_monedas_base ON monedas(es_base) WHERE es_base = 1;
+
+        CREATE TABLE IF NOT EXISTS tasas_cambio (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            moneda TEXT NOT NULL,
+            fecha DATE NOT NULL,
+            tasa REAL NOT NULL CHECK (tasa > 0),
+            usuario_id INTEGER,
+            registrada DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            UNIQUE (moneda, fecha),
+            FOREIGN KEY (moneda) REFERENCES monedas(codigo),
+            FOREIGN KEY (usuario_id) REFERENCES usuarios(id)
+        );
+
+        INSERT OR IGNORE INTO monedas (codigo, nombre, simbolo, es_base) VALUES
+            ('USD', 'Dólar estadounidense', '$', 1),
+            ('VES', 'Bolívar', 'Bs', 0);",
+    )?;
+    if !columnas(tx, "productos")?.iter().any(|c| c == "moneda") {
+        tx.execute(
+            "ALTER TABLE productos ADD COLUMN moneda TEXT NOT NULL DEFAULT 'USD' REFERENCES monedas(codigo)",
+            [],
+        )?;
+    }
+    if !columnas(tx, "ventas")?.iter().any(|c| c == "tasa_cambio") {
+        tx.execute("ALTER TABLE ventas ADD COLUMN tasa_cambio REAL", [])?;
+    }
+    Ok(())
+}
+
+/// v13: tasas de IVA. Las categorías quedan con la tasa general y los productos heredan la
+/// de su categoría. Las líneas de ventas anteriores no desglosaban impuesto: su total queda
+/// como base exenta.
+fn v13_impuestos(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS impuestos (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            nombre TEXT NOT NULL UNIQUE COLLATE NOCASE,
+            porcentaje REAL NOT NULL CHECK (porcentaje >= 0 AND porcentaje < 100)
+        );
+
+        INSERT OR IGNORE INTO impuestos (id, nombre, porcentaje) VALUES
+            (1, 'IVA General', 16),
+            (2, 'IVA Reducido', 8),
+            (3, 'Exento', 0);",
+    )?;
+    if !columnas(tx, "categorias")?.iter().any(|c| c == "impuesto_id") {
+        tx.execute(
+            "ALTER TABLE categorias ADD COLUMN impuesto_id INTEGER NOT NULL DEFAULT 1 REFERENCES impuestos(id)",
+            [],
+        )?;
+    }
+    if !columnas(tx, "productos")?.iter().any(|c| c == "impuesto_id") {
+        tx.execute("ALTER TABLE productos ADD COLUMN impuesto_id INTEGER REFERENCES impuestos(id)", [])?;
+    }
+    if !columnas(tx, "ventas_detalle")?.iter().any(|c| c == "base") {
+        tx.execute_batch(
+            "ALTER TABLE ventas_detalle ADD COLUMN impuesto_id INTEGER REFERENCES impuestos(id);
+             ALTER TABLE ventas_detalle ADD COLUMN porcentaje_impuesto REAL NOT NULL DEFAULT 0;
+             ALTER TABLE ventas_detalle ADD COLUMN base REAL NOT NULL DEFAULT 0;
+             ALTER TABLE ventas_detalle ADD COLUMN impuesto REAL NOT NULL DEFAULT 0;
+             UPDATE ventas_detalle SET base = subtotal;",
+        )?;
+    }
+    Ok(())
+}
+
+/// v14: registro de clientes. Las ventas anteriores sin nombre de cliente pasan al consumidor
+/// final; las que tenían un nombre libre lo conservan en `cliente_nombre` sin cliente asociado.
+fn v14_clientes(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS clientes (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            documento TEXT UNIQUE COLLATE NOCASE, -- Cédula o RIF (NULL solo para el consumidor final)
+            nombre TEXT NOT NULL,
+            direccion TEXT,
+            telefono TEXT,
+            email TEXT
+        );
+
+        INSERT OR IGNORE INTO clientes (id, nombre) VALUES (1, 'Consumidor final');",
+    )?;
+    if !columnas(tx, "ventas")?.iter().any(|c| c == "cliente_id") {
+        tx.execute_batch(
+            "ALTER TABLE ventas ADD COLUMN cliente_id INTEGER REFERENCES clientes(id);
+             UPDATE ventas SET cliente_id = 1 WHERE TRIM(COALESCE(cliente_nombre, '')) = '';",
+        )?;
+    }
+    Ok(())
+}
+
+/// v15: crédito de clientes. Ningún cliente tiene límite asignado y las ventas y devoluciones
+/// anteriores se cobraron y reembolsaron completas (monto_credito = 0).
+fn v15_credito(tx: &Transaction) -> rusqlite::Result<()> {
+    if !columnas(tx, "clientes")?.iter().any(|c| c == "limite_credito") {
+        tx.execute(
+            "ALTER TABLE clientes ADD COLUMN limite_credito REAL NOT NULL DEFAULT 0 CHECK (limite_credito >= 0)",
+            [],
+        )?;
+    }
+    if !columnas(tx, "ventas")?.iter().any(|c| c == "monto_credito") {
+        tx.execute(
+            "ALTER TABLE ventas ADD COLUMN monto_credito REAL NOT NULL DEFAULT 0 CHECK (monto_credito >= 0)",
+            [],
+        )?;
+    }
+    if !columnas(tx, "devoluciones")?.iter().any(|c| c == "monto_credito") {
+        tx.execute("ALTER TABLE devoluciones ADD COLUMN monto_credito REAL NOT NULL DEFAULT 0", [])?;
+    }
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS abonos (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            cliente_id INTEGER NOT NULL,
+            fecha DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            monto REAL NOT NULL CHECK (monto > 0),
+            metodo TEXT NOT NULL
+                CHECK (metodo IN ('efectivo', 'tarjeta', 'transferencia', 'pago_movil')),
+            referencia TEXT,
+            usuario_id INTEGER,
+            sesion_id INTEGER,
+            FOREIGN KEY (cliente_id) REFERENCES clientes(id),
+            FOREIGN KEY (usuario_id) REFERENCES usuarios(id),
+            FOREIGN KEY (sesion_id) REFERENCES cajas_sesiones(id)
+        );",
+    )
+}
+
+/// v16: niveles de alerta de existencias por producto. Los productos existentes quedan sin
+/// alerta (0) hasta que se les asigne un mínimo o un punto de reorden.
+fn v16_niveles_stock(tx: &Transaction) -> rusqlite::Result<()> {
+    let existentes = columnas(tx, "productos")?;
+    if !existentes.iter().any(|c| c == "stock_minimo") {
+        tx.execute(
+            "ALTER TABLE productos ADD COLUMN stock_minimo INTEGER NOT NULL DEFAULT 0 CHECK (stock_minimo >= 0)",
+            [],
+        )?;
+    }
+    if !existentes.iter().any(|c| c == "punto_reorden") {
+        tx.execute(
+            "ALTER TABLE productos ADD COLUMN punto_reorden INTEGER NOT NULL DEFAULT 0 CHECK (punto_reorden >= 0)",
+            [],
+        )?;
+    }
+    Ok(())
+}
+
+/// v17: lotes por producto. El stock existente queda sin lote hasta que se asigne desde la
+/// pantalla de lotes del producto; las ventas anteriores no consumieron lotes.
+fn v17_lotes(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS lotes (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            producto_id INTEGER NOT NULL,
+            numero TEXT NOT NULL COLLATE NOCASE,
+            vencimiento DATE,
+            cantidad INTEGER NOT NULL DEFAULT 0 CHECK (cantidad >= 0),
+            ingreso DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            UNIQUE (producto_id, numero),
+            FOREIGN KEY (producto_id) REFERENCES productos(id) ON DELETE CASCADE
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_lotes_vencimiento ON lotes (vencimiento);
+
+        CREATE TABLE IF NOT EXISTS ventas_lotes (
+            detalle_id INTEGER NOT NULL,
+            lote_id INTEGER NOT NULL,
+            cantidad INTEGER NOT NULL CHECK (cantidad > 0),
+            devuelta INTEGER NOT NULL DEFAULT 0,
+            PRIMARY KEY (detalle_id, lote_id),
+            FOREIGN KEY (detalle_id) REFERENCES ventas_detalle(id),
+            FOREIGN KEY (lote_id) REFERENCES lotes(id)
+        );",
+    )
+}
+
+/// v18: venta por peso o volumen. Los productos existentes se venden por unidad. Las columnas de
+/// cantidad creadas como INTEGER no se reconstruyen: con la afinidad de SQLite guardan tal cual
+/// los valores con decimales (solo los enteros exactos quedan como INTEGER), así que basta con
+/// leerlas como REAL.
+fn v18_cantidades_decimales(tx: &Transaction) -> rusqlite::Result<()> {
+    if !columnas(tx, "productos")?.iter().any(|c| c == "unidad_venta") {
+        tx.execute("ALTER TABLE productos ADD COLUMN unidad_venta INTEGER NOT NULL DEFAULT 1", [])?;
+    }
+    Ok(())
+}
+
+/// v19: cada línea de venta guarda la medida en que se vendió, para que el detalle no dependa de
+/// la medida actual del producto. Las líneas existentes toman la que tiene hoy su producto.
+fn v19_unidad_venta_detalle(tx: &Transaction) -> rusqlite::Result<()> {
+    if !columnas(tx, "ventas_detalle")?.iter().any(|c| c == "unidad_venta") {
+        tx.execute_batch(
+            "ALTER TABLE ventas_detalle ADD COLUMN unidad_venta INTEGER NOT NULL DEFAULT 1;
+             UPDATE ventas_detalle
+             SET unidad_venta = (SELECT p.unidad_venta FROM productos p WHERE p.id = ventas_detalle.producto_id)
+             WHERE producto_id IN (SELECT id FROM productos);",
+        )?;
+    }
+    Ok(())
+}
+
+/// v20: borrar un producto ya no arrastra su kardex ni sus lotes (ON DELETE CASCADE); con
+/// historial solo se puede desactivar. SQLite no cambia claves foráneas con ALTER, así que
+/// ambas tablas se reconstruyen conservando ids (ventas_lotes sigue apuntando a los mismos lotes).
+fn v20_historial_sin_cascada(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "CREATE TABLE movimientos_stock_v20 (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            producto_id INTEGER NOT NULL,
+            cantidad INTEGER NOT NULL,
+            stock_resultante INTEGER NOT NULL,
+            motivo TEXT NOT NULL
+                CHECK (motivo IN ('inicial', 'compra', 'venta', 'devolucion', 'ajuste')),
+            usuario_id INTEGER,
+            fecha DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            referencia TEXT,
+            costo_unitario REAL NOT NULL DEFAULT 0,
+            costo_promedio REAL NOT NULL DEFAULT 0,
+            FOREIGN KEY (producto_id) REFERENCES productos(id),
+            FOREIGN KEY (usuario_id) REFERENCES usuarios(id)
+        );
+        INSERT INTO movimientos_stock_v20 (
+            id, producto_id, cantidad, stock_resultante, motivo, usuario_id, fecha, referencia,
+            costo_unitario, costo_promedio
+        )
+        SELECT id, producto_id, cantidad, stock_resultante, motivo, usuario_id, fecha, referencia,
+               costo_unitario, costo_promedio
+        FROM movimientos_stock;
+        DROP TABLE movimientos_stock;
+        ALTER TABLE movimientos_stock_v20 RENAME TO movimientos_stock;
+        CREATE INDEX idx_movimientos_producto ON movimientos_stock (producto_id, fecha);
+
+        CREATE TABLE lotes_v20 (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            producto_id INTEGER NOT NULL,
+            numero TEXT NOT NULL COLLATE NOCASE,
+            vencimiento DATE,
+            cantidad INTEGER NOT NULL DEFAULT 0 CHECK (cantidad >= 0),
+            ingreso DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            UNIQUE (producto_id, numero),
+            FOREIGN KEY (producto_id) REFERENCES productos(id)
+        );
+        INSERT INTO lotes_v20 (id, producto_id, numero, vencimiento, cantidad, ingreso)
+        SELECT id, producto_id, numero, vencimiento, cantidad, ingreso FROM lotes;
+        DROP TABLE lotes;
+        ALTER TABLE lotes_v20 RENAME TO lotes;
+        CREATE INDEX idx_lotes_vencimiento ON lotes (vencimiento);",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Esquema de las bases anteriores al sistema de versiones (user_version = 0)
+    const ESQUEMA_V0: &str = "
+        CREATE TABLE usuarios (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            username TEXT NOT NULL UNIQUE,
+            password_hash TEXT NOT NULL,
+            rol TEXT NOT NULL CHECK(rol IN ('Admin', 'Operador', 'Vendedor')),
+            activo INTEGER NOT NULL DEFAULT 1,
+            ultimo_login DATETIME
+        );
+        CREATE TABLE marcas (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            nombre TEXT NOT NULL UNIQUE,
+            descripcion TEXT,
+            logo TEXT,
+            rif TEXT UNIQUE
+        );
+        CREATE TABLE productos (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            nombre TEXT NOT NULL,
+            precio_neto REAL NOT NULL DEFAULT 0,
+            precio_venta REAL NOT NULL DEFAULT 0,
+            stock INTEGER NOT NULL DEFAULT 0,
+            descripcion TEXT,
+            codigo TEXT UNIQUE,
+            activo INTEGER NOT NULL DEFAULT 1,
+            marca_id INTEGER,
+            peso REAL,
+            tamano TEXT,
+            unidad_medida TEXT,
+            presentacion TEXT,
+            fecha_vencimiento TEXT,
+            FOREIGN KEY (marca_id) REFERENCES marcas(id) ON DELETE SET NULL
+        );
+        CREATE TABLE ventas (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            fecha DATETIME DEFAULT CURRENT_TIMESTAMP,
+            total REAL NOT NULL,
+            usuario_id INTEGER,
+            cliente_nombre TEXT,
+            FOREIGN KEY (usuario_id) REFERENCES usuarios(id)
+        );
+        CREATE TABLE ventas_detalle (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            venta_id INTEGER NOT NULL,
+            producto_id INTEGER NOT NULL,
+            cantidad INTEGER NOT NULL,
+            precio_unitario REAL NOT NULL,
+            subtotal REAL NOT NULL,
+            FOREIGN KEY (venta_id) REFERENCES ventas(id) ON DELETE CASCADE,
+            FOREIGN KEY (producto_id) REFERENCES productos(id)
+        );";
+
+    /// Tablas con sus columnas, claves foráneas, índices y triggers, para comparar esquemas
+    fn esquema(conn: &Connection) -> Vec<String> {
+        let mut stmt = conn
+            .prepare(
+                "SELECT m.type || ' ' || m.name || ' ' || COALESCE(c.name, '') || ' '
+                        || COALESCE(c.\"notnull\", '') || ' ' || COALESCE(c.dflt_value, '') || ' '
+                        || COALESCE(c.pk, '')
+                 FROM sqlite_master m
+                 LEFT JOIN pragma_table_info(m.name) c ON m.type = 'table'
+                 WHERE m.name NOT LIKE 'sqlite_%'
+                 UNION ALL
+                 SELECT 'fk ' || m.name || ' ' || f.\"from\" || ' ' || f.\"table\" || ' '
+                        || COALESCE(f.\"to\", '') || ' ' || f.on_delete
+                 FROM sqlite_master m
+                 JOIN pragma_foreign_key_list(m.name) f
+                 WHERE m.type = 'table'
+                 ORDER BY 1",
+            )
+            .unwrap();
+        let filas = stmt.query_map([], |r| r.get(0)).unwrap();
+        filas.collect::<rusqlite::Result<_>>().unwrap()
+    }
+
+    fn esquema_nuevo() -> Vec<String> {
+        let conn = Connection::open_in_memory().unwrap();
+        migrar(&conn).unwrap();
+        esquema(&conn)
+    }
+
+    /// Deja la base en la versión indicada, como la habría dejado un binario anterior
+    fn migrar_hasta(conn: &Connection, version: i64) {
+        conn.execute("PRAGMA foreign_keys = OFF", []).unwrap();
+        aplicar_pendientes(conn, version_actual(conn).unwrap(), version).unwrap();
+        conn.execute("PRAGMA foreign_keys = ON", []).unwrap();
+    }
+
+    fn consulta<T: rusqlite::types::FromSql>(conn: &Connection, sql: &str) -> T {
+        conn.query_row(sql, [], |r| r.get(0)).unwrap()
+    }
+
+    #[test]
+    fn migra_una_base_sin_version() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(ESQUEMA_V0).unwrap();
+        // Las versiones sin migraciones no activaban las claves foráneas
+        conn.execute_batch(
+            "PRAGMA foreign_keys = OFF;
+             INSERT INTO usuarios (id, username, password_hash, rol) VALUES (1, 'admin', 'admin', 'Admin');
+             INSERT INTO marcas (id, nombre) VALUES (1, 'Genérico');
+             INSERT INTO productos (id, nombre, precio_neto, precio_venta, stock, codigo, marca_id, peso)
+                 VALUES (7, 'Arroz', 1.5, 2.0, 10, 'A1', 1, 0.9),
+                        (8, 'Sal', 0.5, 1.0, 0, NULL, 99, NULL);
+             INSERT INTO ventas (id, total, usuario_id) VALUES (1, 4.0, 1), (2, 1.0, 5);
+             INSERT INTO ventas_detalle (venta_id, producto_id, cantidad, precio_unitario, subtotal)
+                 VALUES (1, 7, 2, 2.0, 4.0);",
+        )
+        .unwrap();
+
+        migrar(&conn).unwrap();
+
+        assert_eq!(version_actual(&conn).unwrap(), version_soportada());
+        assert_eq!(esquema(&conn), esquema_nuevo());
+        assert_eq!(consulta::<i64>(&conn, "SELECT debe_cambiar_password FROM usuarios WHERE id = 1"), 1);
+        assert_eq!(consulta::<f64>(&conn, "SELECT cantidad_p FROM productos WHERE id = 7"), 0.9);
+        // Las referencias huérfanas no sobreviven a las claves foráneas
+        assert_eq!(consulta::<Option<i64>>(&conn, "SELECT marca_id FROM productos WHERE id = 8"), None);
+        assert_eq!(
+            consulta::<f64>(&conn, "SELECT stock_resultante FROM movimientos_stock WHERE producto_id = 7"),
+            10.0
+        );
+        assert_eq!(consulta::<f64>(&conn, "SELECT monto FROM pagos WHERE venta_id = 1"), 4.0);
+        assert_eq!(consulta::<f64>(&conn, "SELECT base FROM ventas_detalle WHERE venta_id = 1"), 4.0);
+        assert_eq!(consulta::<i64>(&conn, "SELECT cliente_id FROM ventas WHERE id = 1"), 1);
+        assert_eq!(consulta::<Option<i64>>(&conn, "SELECT usuario_id FROM ventas WHERE id = 2"), None);
+    }
+
+    #[test]
+    fn migra_una_base_intermedia() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrar_hasta(&conn, 9);
+        conn.execute_batch(
+            "INSERT INTO usuarios (id, username, password_hash, rol) VALUES (1, 'ana', 'x', 'Vendedor');
+             INSERT INTO productos (id, nombre, precio_neto, precio_venta, stock, medida_p_id, empaque_id)
+                 VALUES (1, 'Café', 3.0, 5.0, 4, 1, 1);
+             INSERT INTO movimientos_stock
+                 (producto_id, cantidad, stock_resultante, motivo, costo_unitario, costo_promedio)
+                 VALUES (1, 4, 4, 'inicial', 3.0, 3.0);
+             INSERT INTO ventas (id, total, usuario_id, cliente_nombre) VALUES (1, 5.0, 1, 'Luis');
+             INSERT INTO ventas_detalle (id, venta_id, producto_id, cantidad, precio_unitario, subtotal)
+                 VALUES (1, 1, 1, 1, 5.0, 5.0);
+             INSERT INTO devoluciones (id, venta_id, usuario_id, motivo) VALUES (1, 1, 1, 'Dañado');
+             INSERT INTO devoluciones_detalle (devolucion_id, detalle_id, cantidad, monto) VALUES (1, 1, 1, 5.0);",
+        )
+        .unwrap();
+
+        migrar(&conn).unwrap();
+
+        assert_eq!(version_actual(&conn).unwrap(), version_soportada());
+        assert_eq!(esquema(&conn), esquema_nuevo());
+        assert_eq!(consulta::<String>(&conn, "SELECT moneda FROM productos WHERE id = 1"), "USD");
+        assert_eq!(consulta::<i64>(&conn, "SELECT unidad_venta FROM productos WHERE id = 1"), 1);
+        // Con nombre libre la venta no pasa al consumidor final
+        assert_eq!(consulta::<Option<i64>>(&conn, "SELECT cliente_id FROM ventas WHERE id = 1"), None);
+        assert_eq!(consulta::<f64>(&conn, "SELECT monto_credito FROM devoluciones WHERE id = 1"), 0.0);
+        assert_eq!(consulta::<i64>(&conn, "SELECT COUNT(*) FROM pagos"), 1);
+    }
+
+    #[test]
+    fn las_lineas_existentes_toman_la_medida_de_su_producto() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrar_hasta(&conn, 18);
+        conn.execute_batch(
+            "INSERT INTO productos (id, nombre, precio_neto, precio_venta, medida_p_id, empaque_id, unidad_venta)
+                 VALUES (1, 'Queso', 4.0, 6.0, 1, 1, 2);
+             INSERT INTO ventas (id, total) VALUES (1, 4.5);
+             INSERT INTO ventas_detalle (venta_id, producto_id, cantidad, precio_unitario, subtotal)
+                 VALUES (1, 1, 0.75, 6.0, 4.5);",
+        )
+        .unwrap();
+
+        migrar(&conn).unwrap();
+
+        assert_eq!(consulta::<i64>(&conn, "SELECT unidad_venta FROM ventas_detalle WHERE venta_id = 1"), 2);
+    }
+
+    #[test]
+    fn un_producto_con_historial_no_se_borra() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrar_hasta(&conn, 19);
+        conn.execute_batch(
+            "INSERT INTO productos (id, nombre, precio_neto, precio_venta, stock, medida_p_id, empaque_id)
+                 VALUES (1, 'Leche', 1.0, 1.5, 6, 1, 1);
+             INSERT INTO movimientos_stock
+                 (id, producto_id, cantidad, stock_resultante, motivo, costo_unitario, costo_promedio)
+                 VALUES (3, 1, 6, 6, 'compra', 1.0, 1.0);
+             INSERT INTO lotes (id, producto_id, numero, vencimiento, cantidad) VALUES (5, 1, 'L-01', '2026-12-01', 6);
+             INSERT INTO ventas (id, total) VALUES (1, 1.5);
+             INSERT INTO ventas_detalle (id, venta_id, producto_id, cantidad, precio_unitario, subtotal)
+                 VALUES (1, 1, 1, 1, 1.5, 1.5);
+             INSERT INTO ventas_lotes (detalle_id, lote_id, cantidad) VALUES (1, 5, 1);",
+        )
+        .unwrap();
+
+        migrar(&conn).unwrap();
+
+        assert_eq!(esquema(&conn), esquema_nuevo());
+        assert_eq!(consulta::<f64>(&conn, "SELECT costo_promedio FROM movimientos_stock WHERE id = 3"), 1.0);
+        assert_eq!(consulta::<String>(&conn, "SELECT numero FROM lotes WHERE id = 5"), "L-01");
+        // Sin ventas, el kardex y los lotes bastan para impedir el borrado
+        conn.execute_batch("DELETE FROM ventas_lotes; DELETE FROM ventas_detalle; DELETE FROM ventas;")
+            .unwrap();
+        assert!(conn.execute("DELETE FROM productos WHERE id = 1", []).is_err());
+        conn.execute("DELETE FROM movimientos_stock", []).unwrap();
+        assert!(conn.execute("DELETE FROM productos WHERE id = 1", []).is_err());
+        assert_eq!(consulta::<i64>(&conn, "SELECT COUNT(*) FROM lotes"), 1);
+    }
+}
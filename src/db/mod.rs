@@ -1,46 +1,71 @@
 // 1. Declaración de los submódulos especializados
 // "pub" permite que otros archivos (como ui_handlers) accedan a ellos
+pub mod caja;
+pub mod categorias;
+pub mod clientes;
+pub mod compras;
+pub mod conexion;
+pub mod configuracion;
+pub mod credito;
+pub mod devoluciones;
+pub mod impuestos;
+pub mod lotes;
 pub mod marcas;
+pub mod migraciones;
+pub mod monedas;
+pub mod movimientos;
+pub mod pagos;
 pub mod productos;
+pub mod proveedores;
+#[cfg(test)]
+pub mod pruebas;
+pub mod ubicacion;
 pub mod usuarios;
 pub mod ventas;
 
+use chrono::{Local, NaiveDateTime, TimeZone};
 use rusqlite::{Connection, Result};
 
-/// Abre o crea la conexión con el archivo de base de datos SQLite
-pub fn open_connection() -> Result<Connection> {
-    Connection::open("bodex.db")
-}
+pub use conexion::Conexion;
 
-/// Orquestador de la inicialización.
-/// Llama a la creación de tablas de cada módulo en el orden correcto
-/// para respetar las claves foráneas (Foreign Keys).
-pub fn init_db(conn: &Connection) -> Result<()> {
-    // Habilitar soporte para claves foráneas en esta conexión
-    conn.execute("PRAGMA foreign_keys = ON", [])?;
-
-    // Orden de creación respetando dependencias:
-    // 1. Usuarios (Independiente)
-    usuarios::create_table(conn)?;
-
-    // 2. Marcas (Independiente)
-    marcas::create_table(conn)?;
+/// Presta una conexión del gestor de conexiones con foreign_keys, WAL y
+/// busy_timeout ya configurados (ver `ubicacion::resolver` para la ruta del archivo)
+pub fn obtener_conexion() -> Result<Conexion> {
+    conexion::obtener()
+}
 
-    // 2.1 Insertar marcas iniciales (Seeder)
-    marcas::seed_marcas(conn)?;
+/// Las fechas se guardan en UTC (`CURRENT_TIMESTAMP`); convierte una fecha y hora local
+/// (la que escribe el usuario en un filtro) a esa referencia para compararla en SQL
+pub fn local_a_utc(fecha: NaiveDateTime) -> NaiveDateTime {
+    Local
+        .from_local_datetime(&fecha)
+        .earliest()
+        .map(|f| f.naive_utc())
+        .unwrap_or(fecha)
+}
 
-    // 3. Productos (Depende de Marcas)
-    productos::create_table(conn)?;
+/// Orquestador de la inicialización.
+/// Lleva el esquema a la versión actual mediante las migraciones versionadas
+/// (`PRAGMA user_version`) y luego inserta los datos iniciales.
+/// Falla si el archivo pertenece a una versión más nueva de la aplicación.
+pub fn init_db(conn: &Connection) -> std::result::Result<(), migraciones::MigracionError> {
+    let version_previa = migraciones::version_actual(conn)?;
 
-    // 4. Ventas (Independiente)
-    ventas::create_table(conn)?;
+    // Crea o actualiza las tablas respetando las claves foráneas
+    migraciones::migrar(conn)?;
 
-    // 5. Detalles de Venta (Depende de Ventas y Productos)
-    ventas::create_detalle_table(conn)?;
+    // Habilitar soporte para claves foráneas en esta conexión
+    conn.execute("PRAGMA foreign_keys = ON", [])?;
 
     // Insertar datos iniciales de configuración (Seeders)
+    marcas::seed_marcas(conn)?;
+    categorias::seed_categorias(conn)?;
     usuarios::seed_admin(conn)?;
 
-    println!("Base de datos inicializada correctamente.");
+    println!(
+        "Base de datos inicializada correctamente (esquema v{} → v{}).",
+        version_previa,
+        migraciones::version_soportada()
+    );
     Ok(())
 }
@@ -0,0 +1,121 @@
+use crate::models::{Moneda, TasaCambio};
+use chrono::NaiveDate;
+use rusqlite::{params, Connection, OptionalExtension, Result};
+use std::fmt;
+
+/// Errores de negocio al registrar una tasa de cambio
+#[derive(Debug)]
+pub enum TasaError {
+    /// La moneda no está registrada en `monedas`
+    MonedaNoEncontrada(String),
+    /// La moneda base vale siempre 1 y no lleva tasa
+    MonedaBase(String),
+    /// La tasa no es un número mayor a 0
+    TasaInvalida(String),
+    /// Error de SQLite
+    Db(rusqlite::Error),
+}
+
+impl fmt::Display for TasaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TasaError::MonedaNoEncontrada(codigo) => write!(f, "La moneda '{}' no existe", codigo),
+            TasaError::MonedaBase(codigo) => write!(f, "{} es la moneda base y no lleva tasa de cambio", codigo),
+            TasaError::TasaInvalida(msg) => write!(f, "{}", msg),
+            TasaError::Db(e) => write!(f, "Error de base de datos: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for TasaError {}
+
+impl From<rusqlite::Error> for TasaError {
+    fn from(e: rusqlite::Error) -> Self {
+        TasaError::Db(e)
+    }
+}
+
+/// Monedas registradas, la base primero
+pub fn obtener_monedas(conn: &Connection) -> Result<Vec<Moneda>> {
+    let mut stmt =
+        conn.prepare("SELECT codigo, nombre, simbolo, es_base FROM monedas ORDER BY es_base DESC, codigo ASC")?;
+    let rows = stmt.query_map([], |row| {
+        Ok(Moneda {
+            codigo: row.get(0)?,
+            nombre: row.get(1)?,
+            simbolo: row.get(2)?,
+            es_base: row.get::<_, i32>(3)? != 0,
+        })
+    })?;
+    rows.collect()
+}
+
+/// Registra la tasa de una moneda para un día; si ese día ya tenía tasa, la reemplaza.
+/// Las ventas ya registradas conservan la tasa que guardaron.
+pub fn registrar_tasa(
+    conn: &Connection,
+    moneda: &str,
+    fecha: NaiveDate,
+    tasa: f64,
+    usuario_id: Option<i64>,
+) -> std::result::Result<i64, TasaError> {
+    if !tasa.is_finite() || tasa <= 0.0 {
+        return Err(TasaError::TasaInvalida("La tasa debe ser un número mayor a 0".to_string()));
+    }
+    let es_base: bool = conn
+        .query_row("SELECT es_base FROM monedas WHERE codigo = ?1", params![moneda], |row| {
+            Ok(row.get::<_, i32>(0)? != 0)
+        })
+        .optional()?
+        .ok_or_else(|| TasaError::MonedaNoEncontrada(moneda.to_string()))?;
+    if es_base {
+        return Err(TasaError::MonedaBase(moneda.to_string()));
+    }
+
+    conn.execute(
+        "INSERT INTO tasas_cambio (moneda, fecha, tasa, usuario_id) VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT (moneda, fecha) DO UPDATE SET
+            tasa = excluded.tasa, usuario_id = excluded.usuario_id, registrada = CURRENT_TIMESTAMP",
+        params![moneda, fecha, tasa, usuario_id],
+    )?;
+    Ok(conn.query_row(
+        "SELECT id FROM tasas_cambio WHERE moneda = ?1 AND fecha = ?2",
+        params![moneda, fecha],
+        |row| row.get(0),
+    )?)
+}
+
+/// Tasa vigente de la moneda en `fecha`: la del día o, si no hay, la última anterior.
+/// La moneda base vale 1; `None` si la moneda nunca ha tenido tasa.
+pub fn tasa_vigente(conn: &Connection, moneda: &str, fecha: NaiveDate) -> Result<Option<f64>> {
+    if moneda == Moneda::BASE {
+        return Ok(Some(1.0));
+    }
+    conn.query_row(
+        "SELECT tasa FROM tasas_cambio WHERE moneda = ?1 AND fecha <= ?2 ORDER BY fecha DESC LIMIT 1",
+        params![moneda, fecha],
+        |row| row.get(0),
+    )
+    .optional()
+}
+
+/// Histórico de tasas, de la más reciente a la más antigua
+pub fn obtener_tasas(conn: &Connection, limite: i64) -> Result<Vec<TasaCambio>> {
+    let mut stmt = conn.prepare(
+        "SELECT t.moneda, t.fecha, t.tasa, u.username, t.registrada
+         FROM tasas_cambio t
+         LEFT JOIN usuarios u ON t.usuario_id = u.id
+         ORDER BY t.fecha DESC, t.moneda ASC
+         LIMIT ?1",
+    )?;
+    let rows = stmt.query_map(params![limite], |row| {
+        Ok(TasaCambio {
+            moneda: row.get(0)?,
+            fecha: row.get(1)?,
+            tasa: row.get(2)?,
+            usuario_nombre: row.get(3)?,
+            registrada: row.get(4)?,
+        })
+    })?;
+    rows.collect()
+}
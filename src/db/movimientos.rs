@@ -0,0 +1,250 @@
+use crate::models::medida::{formatear_cantidad, redondear_cantidad};
+use crate::models::{Medida, MotivoMovimiento, MovimientoStock, ValoracionProducto};
+use chrono::NaiveDateTime;
+use rusqlite::{params, Connection, OptionalExtension, Result};
+use std::fmt;
+
+/// Errores al aplicar un movimiento de stock
+#[derive(Debug)]
+pub enum MovimientoError {
+    /// La salida dejaría el stock en negativo; no se aplica nada
+    StockInsuficiente {
+        producto_id: i64,
+        nombre: String,
+        disponible: f64,
+        solicitado: f64,
+    },
+    /// Cantidad con decimales para un producto que se vende por unidades
+    CantidadFraccionaria { nombre: String, cantidad: f64 },
+    /// El producto no existe en la base de datos
+    ProductoNoEncontrado(i64),
+    /// Error de SQLite
+    Db(rusqlite::Error),
+}
+
+impl fmt::Display for MovimientoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MovimientoError::StockInsuficiente { nombre, disponible, solicitado, .. } => write!(
+                f,
+                "Stock insuficiente para '{}': disponibles {}, solicitados {}",
+                nombre,
+                formatear_cantidad(*disponible),
+                formatear_cantidad(*solicitado)
+            ),
+            MovimientoError::CantidadFraccionaria { nombre, cantidad } => write!(
+                f,
+                "'{}' se vende por unidades: la cantidad {} debe ser entera",
+                nombre,
+                formatear_cantidad(*cantidad)
+            ),
+            MovimientoError::ProductoNoEncontrado(id) => write!(f, "El producto con ID {} no existe", id),
+            MovimientoError::Db(e) => write!(f, "Error de base de datos: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for MovimientoError {}
+
+impl From<rusqlite::Error> for MovimientoError {
+    fn from(e: rusqlite::Error) -> Self {
+        MovimientoError::Db(e)
+    }
+}
+
+/// Único punto de entrada para cambiar el stock de un producto.
+/// Suma `cantidad` (negativa para salidas) a `productos.stock` y registra el
+/// movimiento en el kardex de forma atómica: usa un SAVEPOINT, así que funciona
+/// tanto sobre una conexión suelta como dentro de la transacción del llamador.
+/// Devuelve el stock resultante; una salida mayor al stock disponible falla con
+/// `MovimientoError::StockInsuficiente` sin modificar nada. Las cantidades se redondean a
+/// `DECIMALES_CANTIDAD` y deben ser enteras si el producto se vende por unidades.
+///
+/// `costo_unitario` es el costo de las unidades que entran (p. ej. el de la compra)
+/// y recalcula el costo promedio ponderado. Las salidas y las entradas sin costo
+/// (`None`) se valoran al costo promedio vigente y no lo modifican.
+pub fn registrar_movimiento(
+    conn: &Connection,
+    producto_id: i64,
+    cantidad: f64,
+    motivo: MotivoMovimiento,
+    usuario_id: Option<i64>,
+    referencia: Option<&str>,
+    costo_unitario: Option<f64>,
+) -> std::result::Result<f64, MovimientoError> {
+    conn.execute_batch("SAVEPOINT movimiento_stock")?;
+
+    match aplicar_movimiento(conn, producto_id, cantidad, motivo, usuario_id, referencia, costo_unitario) {
+        Ok(stock) => {
+            conn.execute_batch("RELEASE movimiento_stock")?;
+            Ok(stock)
+        }
+        Err(e) => {
+            conn.execute_batch("ROLLBACK TO movimiento_stock; RELEASE movimiento_stock")?;
+            Err(e)
+        }
+    }
+}
+
+fn aplicar_movimiento(
+    conn: &Connection,
+    producto_id: i64,
+    cantidad: f64,
+    motivo: MotivoMovimiento,
+    usuario_id: Option<i64>,
+    referencia: Option<&str>,
+    costo_unitario: Option<f64>,
+) -> std::result::Result<f64, MovimientoError> {
+    // Costo promedio antes del movimiento: el del último registro del kardex o,
+    // si el producto aún no tiene movimientos, su precio neto
+    let producto: Option<(f64, String, i32)> = conn
+        .query_row(
+            "SELECT COALESCE(
+                (SELECT m.costo_promedio FROM movimientos_stock m
+                 WHERE m.producto_id = p.id
+                 ORDER BY m.fecha DESC, m.id DESC LIMIT 1),
+                p.precio_neto), p.nombre, p.unidad_venta
+             FROM productos p WHERE p.id = ?1",
+            params![producto_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()?;
+    let Some((costo_anterior, nombre, unidad_venta)) = producto else {
+        return Err(MovimientoError::ProductoNoEncontrado(producto_id));
+    };
+
+    let cantidad = redondear_cantidad(cantidad);
+    if !Medida::from_i32(unidad_venta).fraccionable() && cantidad.fract() != 0.0 {
+        return Err(MovimientoError::CantidadFraccionaria { nombre, cantidad: cantidad.abs() });
+    }
+
+    // La condición evita dejar el stock en negativo (redondeado para no arrastrar decimales)
+    let stock: Option<f64> = conn
+        .query_row(
+            "UPDATE productos SET stock = ROUND(stock + ?1, 3)
+             WHERE id = ?2 AND ROUND(stock + ?1, 3) >= 0
+             RETURNING stock",
+            params![cantidad, producto_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+
+    let Some(stock) = stock else {
+        let disponible: Option<f64> = conn
+            .query_row("SELECT stock FROM productos WHERE id = ?1", params![producto_id], |row| row.get(0))
+            .optional()?;
+
+        return Err(match disponible {
+            Some(disponible) => MovimientoError::StockInsuficiente {
+                producto_id,
+                nombre,
+                disponible,
+                solicitado: -cantidad,
+            },
+            None => MovimientoError::ProductoNoEncontrado(producto_id),
+        });
+    };
+
+    let (costo, costo_promedio) = match costo_unitario {
+        Some(costo) if cantidad > 0.0 => {
+            (costo, costo_promedio_ponderado(redondear_cantidad(stock - cantidad), costo_anterior, cantidad, costo))
+        }
+        _ => (costo_anterior, costo_anterior),
+    };
+
+    conn.execute(
+        "INSERT INTO movimientos_stock (producto_id, cantidad, stock_resultante, motivo, usuario_id, referencia,
+                                        costo_unitario, costo_promedio)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            producto_id,
+            cantidad,
+            stock,
+            motivo.as_str(),
+            usuario_id,
+            referencia,
+            costo,
+            costo_promedio
+        ],
+    )?;
+    Ok(stock)
+}
+
+/// Nuevo costo promedio al sumar `entrada` unidades a `costo` sobre `existencia`
+/// unidades valoradas a `costo_actual`. Sin existencias previas vale el costo de la entrada.
+fn costo_promedio_ponderado(existencia: f64, costo_actual: f64, entrada: f64, costo: f64) -> f64 {
+    if existencia <= 0.0 {
+        return costo;
+    }
+    (existencia * costo_actual + entrada * costo) / (existencia + entrada)
+}
+
+/// Kardex de un producto: sus movimientos del más antiguo al más reciente
+pub fn obtener_kardex(conn: &Connection, producto_id: i64) -> Result<Vec<MovimientoStock>> {
+    let mut stmt = conn.prepare(
+        "SELECT m.cantidad, m.stock_resultante, m.motivo, u.username, m.fecha, m.referencia,
+                m.costo_unitario, m.costo_promedio
+         FROM movimientos_stock m
+         LEFT JOIN usuarios u ON m.usuario_id = u.id
+         WHERE m.producto_id = ?1
+         ORDER BY m.fecha ASC, m.id ASC",
+    )?;
+
+    let iter = stmt.query_map(params![producto_id], |row| {
+        Ok(MovimientoStock {
+            cantidad: row.get(0)?,
+            stock_resultante: row.get(1)?,
+            motivo: MotivoMovimiento::from(row.get::<_, String>(2)?),
+            usuario_nombre: row.get(3)?,
+            fecha: row.get(4)?,
+            referencia: row.get(5)?,
+            costo_unitario: row.get(6)?,
+            costo_promedio: row.get(7)?,
+        })
+    })?;
+
+    let mut resultado = Vec::new();
+    for movimiento in iter {
+        resultado.push(movimiento?);
+    }
+    Ok(resultado)
+}
+
+/// Valoración del inventario según el kardex: para cada producto, el saldo y el
+/// costo promedio de su último movimiento hasta `hasta` (inclusive; `None` = hoy).
+/// Solo incluye productos con existencias a esa fecha.
+pub fn valoracion_al(conn: &Connection, hasta: Option<NaiveDateTime>) -> Result<Vec<ValoracionProducto>> {
+    // `fecha` se guarda como texto 'AAAA-MM-DD HH:MM:SS' (CURRENT_TIMESTAMP)
+    let hasta = hasta.map(|f| f.format("%Y-%m-%d %H:%M:%S").to_string());
+    let mut stmt = conn.prepare(
+        "SELECT p.codigo, p.nombre, COALESCE(ma.nombre, 'Sin Marca'), COALESCE(c.nombre, 'Sin Categoría'),
+                m.stock_resultante, m.costo_promedio
+         FROM productos p
+         JOIN movimientos_stock m ON m.id = (
+             SELECT u.id FROM movimientos_stock u
+             WHERE u.producto_id = p.id AND (?1 IS NULL OR u.fecha <= ?1)
+             ORDER BY u.fecha DESC, u.id DESC LIMIT 1
+         )
+         LEFT JOIN marcas ma ON p.marca_id = ma.id
+         LEFT JOIN categorias c ON p.categoria_id = c.id
+         WHERE m.stock_resultante != 0
+         ORDER BY p.nombre ASC",
+    )?;
+
+    let iter = stmt.query_map(params![hasta], |row| {
+        Ok(ValoracionProducto {
+            codigo: row.get(0)?,
+            nombre: row.get(1)?,
+            marca: row.get(2)?,
+            categoria: row.get(3)?,
+            stock: row.get(4)?,
+            costo_promedio: row.get(5)?,
+        })
+    })?;
+
+    let mut resultado = Vec::new();
+    for fila in iter {
+        resultado.push(fila?);
+    }
+    Ok(resultado)
+}
@@ -0,0 +1,17 @@
+use crate::models::{MetodoPago, Pago};
+use rusqlite::{params, Connection, Result};
+
+/// Pagos de una venta en el orden en que se registraron
+pub fn obtener_por_venta(conn: &Connection, venta_id: i64) -> Result<Vec<Pago>> {
+    let mut stmt = conn.prepare(
+        "SELECT metodo, monto, referencia FROM pagos WHERE venta_id = ?1 ORDER BY id",
+    )?;
+    let rows = stmt.query_map(params![venta_id], |row| {
+        Ok(Pago {
+            metodo: MetodoPago::from(row.get::<_, String>(0)?),
+            monto: row.get(1)?,
+            referencia: row.get(2)?,
+        })
+    })?;
+    rows.collect()
+}
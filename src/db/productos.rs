@@ -1,45 +1,21 @@
-use crate::models::{Producto, ProductoConMarca, ProductoNuevo};
+use crate::models::{Producto, ProductoConMarca, ProductoNuevo, StockBajo};
 use rusqlite::{params, Connection, Result, Row};
 
-/// Crea la tabla de productos actualizada a la nueva arquitectura
-pub fn create_table(conn: &Connection) -> Result<()> {
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS productos (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            nombre TEXT NOT NULL,
-            precio_neto REAL NOT NULL DEFAULT 0,
-            precio_venta REAL NOT NULL DEFAULT 0,
-            stock INTEGER NOT NULL DEFAULT 0,
-            descripcion TEXT,
-            codigo TEXT UNIQUE,
-            activo INTEGER NOT NULL DEFAULT 1,
-            marca_id INTEGER,
-            -- Nuevos campos de Enums y Cantidades
-            medida_p_id INTEGER NOT NULL,
-            cantidad_p REAL NOT NULL DEFAULT 0,
-            medida_s_id INTEGER,
-            cantidad_s REAL,
-            empaque_id INTEGER NOT NULL,
-            -- Categoría y subcategoría
-            categoria_id INTEGER NOT NULL DEFAULT 1,
-            subcategoria_id INTEGER NOT NULL DEFAULT 1,
-            FOREIGN KEY (marca_id) REFERENCES marcas(id) ON DELETE SET NULL
-        )",
-        [],
-    )?;
-    Ok(())
-}
-
-/// Obtener todos los productos básicos (mapeo directo a struct Producto)
-pub fn obtener_productos(conn: &Connection) -> Result<Vec<Producto>> {
+/// Obtener productos con marca para la UI
+pub fn obtener_productos_con_marca(conn: &Connection) -> Result<Vec<ProductoConMarca>> {
     let mut stmt = conn.prepare(
-        "SELECT id, nombre, precio_neto, precio_venta, stock, descripcion,
-                codigo, activo, marca_id, medida_p_id, cantidad_p, 
-                medida_s_id, cantidad_s, empaque_id, categoria_id, subcategoria_id
-         FROM productos ORDER BY nombre ASC",
+        "SELECT p.id, p.nombre, p.precio_neto, p.precio_venta, p.stock, 
+                p.descripcion, p.codigo, p.activo, p.marca_id, m.nombre as marca_nombre,
+                p.medida_p_id, p.cantidad_p, p.empaque_id, p.categoria_id, p.subcategoria_id, p.moneda,
+                COALESCE(p.impuesto_id, c.impuesto_id), i.porcentaje, p.unidad_venta
+         FROM productos p
+         LEFT JOIN marcas m ON p.marca_id = m.id
+         JOIN categorias c ON p.categoria_id = c.id
+         JOIN impuestos i ON i.id = COALESCE(p.impuesto_id, c.impuesto_id)
+         ORDER BY p.nombre ASC",
     )?;
 
-    let productos = stmt.query_map([], mapear_producto)?;
+    let productos = stmt.query_map([], mapear_producto_con_marca)?;
 
     let mut resultado = Vec::new();
     for p in productos {
@@ -48,36 +24,24 @@ pub fn obtener_productos(conn: &Connection) -> Result<Vec<Producto>> {
     Ok(resultado)
 }
 
-/// Obtener productos con marca para la UI
-pub fn obtener_productos_con_marca(conn: &Connection) -> Result<Vec<ProductoConMarca>> {
+/// Buscar productos activos por código exacto o por coincidencia parcial del nombre
+/// (usado por el punto de venta)
+pub fn buscar_productos(conn: &Connection, texto: &str) -> Result<Vec<ProductoConMarca>> {
     let mut stmt = conn.prepare(
         "SELECT p.id, p.nombre, p.precio_neto, p.precio_venta, p.stock, 
                 p.descripcion, p.codigo, p.activo, p.marca_id, m.nombre as marca_nombre,
-                p.medida_p_id, p.cantidad_p, p.empaque_id, p.categoria_id, p.subcategoria_id
+                p.medida_p_id, p.cantidad_p, p.empaque_id, p.categoria_id, p.subcategoria_id, p.moneda,
+                COALESCE(p.impuesto_id, c.impuesto_id), i.porcentaje, p.unidad_venta
          FROM productos p
          LEFT JOIN marcas m ON p.marca_id = m.id
-         ORDER BY p.nombre ASC",
+         JOIN categorias c ON p.categoria_id = c.id
+         JOIN impuestos i ON i.id = COALESCE(p.impuesto_id, c.impuesto_id)
+         WHERE p.activo = 1 AND (p.codigo = ?1 OR p.nombre LIKE '%' || ?1 || '%')
+         ORDER BY (p.codigo = ?1) DESC, p.nombre ASC
+         LIMIT 50",
     )?;
 
-    let productos = stmt.query_map([], |row| {
-        Ok(ProductoConMarca {
-            id: row.get(0)?,
-            nombre: row.get(1)?,
-            precio_neto: row.get(2)?,
-            precio_venta: row.get(3)?,
-            stock: row.get(4)?,
-            descripcion: row.get(5)?,
-            codigo: row.get(6)?,
-            activo: row.get::<_, i32>(7)? != 0,
-            marca_id: row.get(8)?,
-            marca_nombre: row.get(9)?,
-            medida_p_id: row.get(10)?,
-            cantidad_p: row.get(11)?,
-            empaque_id: row.get(12)?,
-            categoria_id: row.get(13)?,
-            subcategoria_id: row.get(14)?,
-        })
-    })?;
+    let productos = stmt.query_map(params![texto.trim()], mapear_producto_con_marca)?;
 
     let mut resultado = Vec::new();
     for p in productos {
@@ -86,19 +50,21 @@ pub fn obtener_productos_con_marca(conn: &Connection) -> Result<Vec<ProductoConM
     Ok(resultado)
 }
 
-/// Insertar un nuevo producto usando la estructura ProductoNuevo
+/// Insertar un nuevo producto usando la estructura ProductoNuevo.
+/// El producto nace con stock 0: `p.stock` se carga como movimiento "inicial"
+/// con `movimientos::registrar_movimiento` para que quede en el kardex.
 pub fn crear_producto(conn: &Connection, p: &ProductoNuevo) -> Result<i64> {
     conn.execute(
         "INSERT INTO productos (
             nombre, precio_neto, precio_venta, stock, descripcion, 
             codigo, activo, marca_id, medida_p_id, cantidad_p, 
-            medida_s_id, cantidad_s, empaque_id, categoria_id, subcategoria_id
-        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+            medida_s_id, cantidad_s, empaque_id, categoria_id, subcategoria_id, moneda, impuesto_id,
+            stock_minimo, punto_reorden, unidad_venta
+        ) VALUES (?1, ?2, ?3, 0, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19)",
         params![
             p.nombre,
             p.precio_neto,
             p.precio_venta,
-            p.stock,
             p.descripcion,
             p.codigo,
             if p.activo { 1 } else { 0 },
@@ -109,18 +75,103 @@ pub fn crear_producto(conn: &Connection, p: &ProductoNuevo) -> Result<i64> {
             p.cantidad_s,
             p.empaque_id,
             p.categoria_id,
-            p.subcategoria_id
+            p.subcategoria_id,
+            p.moneda,
+            p.impuesto_id,
+            p.stock_minimo,
+            p.punto_reorden,
+            p.unidad_venta
         ],
     )?;
     Ok(conn.last_insert_rowid())
 }
 
+/// Obtener un producto completo por su ID
+pub fn obtener_producto_por_id(conn: &Connection, id: i64) -> Result<Option<Producto>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, nombre, precio_neto, precio_venta, stock, descripcion,
+                codigo, activo, marca_id, medida_p_id, cantidad_p, 
+                medida_s_id, cantidad_s, empaque_id, categoria_id, subcategoria_id, moneda, impuesto_id,
+                stock_minimo, punto_reorden, unidad_venta
+         FROM productos WHERE id = ?1",
+    )?;
+    let mut rows = stmt.query(params![id])?;
+
+    if let Some(row) = rows.next()? {
+        Ok(Some(mapear_producto(row)?))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Actualizar los datos de un producto existente.
+/// `stock` no se toca aquí: los cambios de existencias pasan por
+/// `movimientos::registrar_movimiento`.
+pub fn actualizar_producto(conn: &Connection, p: &Producto) -> Result<bool> {
+    let filas = conn.execute(
+        "UPDATE productos SET
+            nombre = ?1, precio_neto = ?2, precio_venta = ?3, descripcion = ?4,
+            codigo = ?5, activo = ?6, marca_id = ?7, medida_p_id = ?8, cantidad_p = ?9,
+            medida_s_id = ?10, cantidad_s = ?11, empaque_id = ?12, categoria_id = ?13,
+            subcategoria_id = ?14, moneda = ?15, impuesto_id = ?16, stock_minimo = ?17,
+            punto_reorden = ?18, unidad_venta = ?19
+         WHERE id = ?20",
+        params![
+            p.nombre,
+            p.precio_neto,
+            p.precio_venta,
+            p.descripcion,
+            p.codigo,
+            if p.activo { 1 } else { 0 },
+            p.marca_id,
+            p.medida_p_id,
+            p.cantidad_p,
+            p.medida_s_id,
+            p.cantidad_s,
+            p.empaque_id,
+            p.categoria_id,
+            p.subcategoria_id,
+            p.moneda,
+            p.impuesto_id,
+            p.stock_minimo,
+            p.punto_reorden,
+            p.unidad_venta,
+            p.id
+        ],
+    )?;
+    Ok(filas > 0)
+}
+
 /// Eliminar producto por ID
 pub fn eliminar_producto(conn: &Connection, id: i64) -> Result<bool> {
     let filas = conn.execute("DELETE FROM productos WHERE id = ?1", params![id])?;
     Ok(filas > 0)
 }
 
+/// Registros que impiden borrar un producto, como (tabla, descripción para el usuario)
+const HISTORIAL_PRODUCTO: [(&str, &str); 4] = [
+    ("ventas_detalle", "ventas"),
+    ("ordenes_compra_detalle", "órdenes de compra"),
+    ("movimientos_stock", "movimientos de stock"),
+    ("lotes", "lotes"),
+];
+
+/// Descripción de los registros que hacen referencia al producto (vacío si no tiene historial)
+pub fn historial_producto(conn: &Connection, id: i64) -> Result<Vec<&'static str>> {
+    let mut historial = Vec::new();
+    for (tabla, descripcion) in HISTORIAL_PRODUCTO {
+        let existe: bool = conn.query_row(
+            &format!("SELECT EXISTS (SELECT 1 FROM {} WHERE producto_id = ?1)", tabla),
+            params![id],
+            |row| row.get(0),
+        )?;
+        if existe {
+            historial.push(descripcion);
+        }
+    }
+    Ok(historial)
+}
+
 /// Verificar si existe un producto con el código/SKU dado
 /// Retorna true si el SKU ya existe en la base de datos
 pub fn existe_sku(conn: &Connection, codigo: &str) -> Result<bool> {
@@ -137,6 +188,71 @@ pub fn existe_sku(conn: &Connection, codigo: &str) -> Result<bool> {
     Ok(count > 0)
 }
 
+/// Igual que `existe_sku`, pero ignora el producto indicado (útil al editar,
+/// para que el SKU propio del producto no cuente como duplicado)
+pub fn existe_sku_excluyendo(conn: &Connection, codigo: &str, excluir_id: i64) -> Result<bool> {
+    if codigo.trim().is_empty() {
+        return Ok(false);
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT COUNT(*) FROM productos WHERE codigo = ?1 AND id != ?2"
+    )?;
+
+    let count: i32 = stmt.query_row(params![codigo.trim(), excluir_id], |row| row.get(0))?;
+    Ok(count > 0)
+}
+
+/// Indica si el producto tiene cantidades registradas en su medida de venta (stock, movimientos,
+/// lotes, ventas u órdenes de compra); en ese caso ya no se le puede cambiar la medida de venta
+pub fn tiene_cantidades_registradas(conn: &Connection, id: i64) -> Result<bool> {
+    conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM productos WHERE id = ?1 AND stock != 0)
+             OR EXISTS (SELECT 1 FROM movimientos_stock WHERE producto_id = ?1)
+             OR EXISTS (SELECT 1 FROM lotes WHERE producto_id = ?1)
+             OR EXISTS (SELECT 1 FROM ventas_detalle WHERE producto_id = ?1)
+             OR EXISTS (SELECT 1 FROM ordenes_compra_detalle WHERE producto_id = ?1)",
+        params![id],
+        |row| row.get(0),
+    )
+}
+
+/// Productos activos con alerta de existencias: los que están en su punto de reorden o stock
+/// mínimo, o por debajo. Incluye la cantidad vendida (netas de devoluciones, según el kardex)
+/// en los últimos `dias` días para sugerir cuánto pedir. Primero los que están bajo el mínimo.
+pub fn obtener_stock_bajo(conn: &Connection, dias: i64) -> Result<Vec<StockBajo>> {
+    let mut stmt = conn.prepare(
+        "SELECT p.id, p.codigo, p.nombre, p.stock, p.stock_minimo, p.punto_reorden, p.unidad_venta,
+                COALESCE((SELECT -SUM(m.cantidad) FROM movimientos_stock m
+                          WHERE m.producto_id = p.id AND m.motivo IN ('venta', 'devolucion')
+                            AND m.fecha >= datetime('now', '-' || ?1 || ' days')), 0)
+         FROM productos p
+         WHERE p.activo = 1
+           AND MAX(p.stock_minimo, p.punto_reorden) > 0
+           AND p.stock <= MAX(p.stock_minimo, p.punto_reorden)
+         ORDER BY (p.stock <= p.stock_minimo) DESC, p.nombre ASC",
+    )?;
+
+    let productos = stmt.query_map(params![dias], |row| {
+        Ok(StockBajo {
+            id: row.get(0)?,
+            codigo: row.get(1)?,
+            nombre: row.get(2)?,
+            stock: row.get(3)?,
+            stock_minimo: row.get(4)?,
+            punto_reorden: row.get(5)?,
+            unidad_venta: row.get(6)?,
+            vendidas: row.get(7)?,
+        })
+    })?;
+
+    let mut resultado = Vec::new();
+    for p in productos {
+        resultado.push(p?);
+    }
+    Ok(resultado)
+}
+
 /// Mapeo limpio de filas SQL a la estructura Producto
 fn mapear_producto(row: &Row) -> Result<Producto> {
     Ok(Producto {
@@ -156,5 +272,71 @@ fn mapear_producto(row: &Row) -> Result<Producto> {
         empaque_id: row.get(13)?,
         categoria_id: row.get(14)?,
         subcategoria_id: row.get(15)?,
+        moneda: row.get(16)?,
+        impuesto_id: row.get(17)?,
+        stock_minimo: row.get(18)?,
+        punto_reorden: row.get(19)?,
+        unidad_venta: row.get(20)?,
+    })
+}
+
+/// Mapeo de filas (SELECT con LEFT JOIN a marcas) a la estructura ProductoConMarca
+fn mapear_producto_con_marca(row: &Row) -> Result<ProductoConMarca> {
+    Ok(ProductoConMarca {
+        id: row.get(0)?,
+        nombre: row.get(1)?,
+        precio_neto: row.get(2)?,
+        precio_venta: row.get(3)?,
+        stock: row.get(4)?,
+        descripcion: row.get(5)?,
+        codigo: row.get(6)?,
+        activo: row.get::<_, i32>(7)? != 0,
+        marca_id: row.get(8)?,
+        marca_nombre: row.get(9)?,
+        medida_p_id: row.get(10)?,
+        cantidad_p: row.get(11)?,
+        empaque_id: row.get(12)?,
+        categoria_id: row.get(13)?,
+        subcategoria_id: row.get(14)?,
+        moneda: row.get(15)?,
+        impuesto_id: row.get(16)?,
+        porcentaje_impuesto: row.get(17)?,
+        unidad_venta: row.get(18)?,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::pruebas;
+    use crate::models::medida::Medida;
+    use crate::models::MotivoMovimiento;
+
+    #[test]
+    fn un_producto_con_movimientos_tiene_cantidades_registradas() {
+        let conn = pruebas::base_en_memoria();
+        let vacio = pruebas::producto(&conn, "Queso", 5.0, 8.0, 0.0, Medida::Kilogramo);
+        assert!(!tiene_cantidades_registradas(&conn, vacio).unwrap());
+
+        // Aunque el stock vuelva a 0, el kardex quedó en kilos
+        crate::db::movimientos::registrar_movimiento(&conn, vacio, 2.5, MotivoMovimiento::Compra, None, None, None)
+            .unwrap();
+        crate::db::movimientos::registrar_movimiento(&conn, vacio, -2.5, MotivoMovimiento::Ajuste, None, None, None)
+            .unwrap();
+        assert_eq!(pruebas::stock(&conn, vacio), 0.0);
+        assert!(tiene_cantidades_registradas(&conn, vacio).unwrap());
+    }
+
+    #[test]
+    fn un_producto_con_kardex_no_se_elimina() {
+        let conn = pruebas::base_en_memoria();
+        let nuevo = pruebas::producto(&conn, "Sal", 0.5, 1.0, 0.0, Medida::Unidad);
+        let arroz = pruebas::producto(&conn, "Arroz", 1.0, 2.0, 10.0, Medida::Unidad);
+
+        assert_eq!(historial_producto(&conn, arroz).unwrap(), ["movimientos de stock"]);
+        assert!(eliminar_producto(&conn, arroz).is_err());
+        assert_eq!(pruebas::contar(&conn, "movimientos_stock"), 1);
+        assert!(historial_producto(&conn, nuevo).unwrap().is_empty());
+        assert!(eliminar_producto(&conn, nuevo).unwrap());
+    }
+}
@@ -0,0 +1,80 @@
+use crate::models::{Proveedor, ProveedorNuevo};
+use rusqlite::{params, Connection, Result};
+
+/// Obtener todos los proveedores ordenados por nombre
+pub fn obtener_proveedores(conn: &Connection) -> Result<Vec<Proveedor>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, nombre, rif, telefono, email, direccion FROM proveedores ORDER BY nombre ASC",
+    )?;
+
+    let iter = stmt.query_map([], |row| {
+        Ok(Proveedor {
+            id: row.get(0)?,
+            nombre: row.get(1)?,
+            rif: row.get(2)?,
+            telefono: row.get(3)?,
+            email: row.get(4)?,
+            direccion: row.get(5)?,
+        })
+    })?;
+
+    let mut resultado = Vec::new();
+    for proveedor in iter {
+        resultado.push(proveedor?);
+    }
+    Ok(resultado)
+}
+
+/// Crear un nuevo proveedor
+pub fn crear_proveedor(conn: &Connection, p: &ProveedorNuevo) -> Result<i64> {
+    conn.execute(
+        "INSERT INTO proveedores (nombre, rif, telefono, email, direccion) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![p.nombre, p.rif, p.telefono, p.email, p.direccion],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// Actualizar un proveedor existente
+pub fn actualizar_proveedor(conn: &Connection, p: &Proveedor) -> Result<bool> {
+    let filas = conn.execute(
+        "UPDATE proveedores SET nombre = ?1, rif = ?2, telefono = ?3, email = ?4, direccion = ?5
+         WHERE id = ?6",
+        params![p.nombre, p.rif, p.telefono, p.email, p.direccion, p.id],
+    )?;
+    Ok(filas > 0)
+}
+
+/// Cantidad de órdenes de compra emitidas al proveedor
+pub fn contar_ordenes(conn: &Connection, id: i64) -> Result<i64> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM ordenes_compra WHERE proveedor_id = ?1",
+        params![id],
+        |row| row.get(0),
+    )
+}
+
+/// Eliminar un proveedor. Falla (FOREIGN KEY) si tiene órdenes de compra.
+pub fn eliminar_proveedor(conn: &Connection, id: i64) -> Result<bool> {
+    let filas = conn.execute("DELETE FROM proveedores WHERE id = ?1", params![id])?;
+    Ok(filas > 0)
+}
+
+/// Verificar si ya existe otro proveedor con el mismo nombre
+pub fn existe_nombre(conn: &Connection, nombre: &str, excluir_id: Option<i64>) -> Result<bool> {
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM proveedores WHERE nombre = ?1 AND id != ?2",
+        params![nombre.trim(), excluir_id.unwrap_or(0)],
+        |row| row.get(0),
+    )?;
+    Ok(count > 0)
+}
+
+/// Verificar si ya existe otro proveedor con el mismo RIF
+pub fn existe_rif(conn: &Connection, rif: &str, excluir_id: Option<i64>) -> Result<bool> {
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM proveedores WHERE rif = ?1 AND id != ?2",
+        params![rif.trim(), excluir_id.unwrap_or(0)],
+        |row| row.get(0),
+    )?;
+    Ok(count > 0)
+}
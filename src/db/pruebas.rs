@@ -0,0 +1,79 @@
+//! Datos mínimos para las pruebas de la capa de base de datos
+
+use super::{caja, migraciones, movimientos};
+use crate::models::medida::Medida;
+use crate::models::{DetalleVenta, MetodoPago, MotivoMovimiento, PagoNuevo};
+use rusqlite::{params, Connection};
+
+/// Base vacía en memoria con el esquema actual y las claves foráneas activas
+pub fn base_en_memoria() -> Connection {
+    let conn = Connection::open_in_memory().unwrap();
+    migraciones::migrar(&conn).unwrap();
+    conn
+}
+
+/// Crea un cajero con su caja abierta (sin fondo inicial) y devuelve su ID
+pub fn cajero_con_caja(conn: &Connection, username: &str) -> i64 {
+    conn.execute(
+        "INSERT INTO usuarios (username, password_hash, rol) VALUES (?1, 'x', 'Vendedor')",
+        params![username],
+    )
+    .unwrap();
+    let usuario_id = conn.last_insert_rowid();
+    caja::abrir(conn, usuario_id, 0.0).unwrap();
+    usuario_id
+}
+
+/// Crea un producto con `stock` inicial al costo `precio_neto` y devuelve su ID
+pub fn producto(
+    conn: &Connection,
+    nombre: &str,
+    precio_neto: f64,
+    precio_venta: f64,
+    stock: f64,
+    unidad_venta: Medida,
+) -> i64 {
+    conn.execute(
+        "INSERT INTO productos (nombre, precio_neto, precio_venta, medida_p_id, empaque_id, unidad_venta)
+         VALUES (?1, ?2, ?3, 1, 1, ?4)",
+        params![nombre, precio_neto, precio_venta, unidad_venta as i32],
+    )
+    .unwrap();
+    let producto_id = conn.last_insert_rowid();
+    if stock > 0.0 {
+        let costo = Some(precio_neto);
+        movimientos::registrar_movimiento(conn, producto_id, stock, MotivoMovimiento::Inicial, None, None, costo).unwrap();
+    }
+    producto_id
+}
+
+/// Línea de venta exenta de impuesto
+pub fn linea(producto_id: i64, cantidad: f64, precio_unitario: f64) -> DetalleVenta {
+    let subtotal = (cantidad * precio_unitario * 100.0).round() / 100.0;
+    DetalleVenta {
+        producto_id,
+        cantidad,
+        precio_unitario,
+        subtotal,
+        impuesto_id: None,
+        porcentaje_impuesto: 0.0,
+        base: subtotal,
+        impuesto: 0.0,
+    }
+}
+
+pub fn pago(metodo: MetodoPago, monto: f64) -> PagoNuevo {
+    PagoNuevo { metodo, monto, referencia: None }
+}
+
+/// Existencias actuales del producto
+pub fn stock(conn: &Connection, producto_id: i64) -> f64 {
+    conn.query_row("SELECT stock FROM productos WHERE id = ?1", params![producto_id], |r| r.get(0))
+        .unwrap()
+}
+
+/// Cantidad de filas de una tabla
+pub fn contar(conn: &Connection, tabla: &str) -> i64 {
+    conn.query_row(&format!("SELECT COUNT(*) FROM {}", tabla), [], |r| r.get(0))
+        .unwrap()
+}
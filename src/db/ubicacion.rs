@@ -0,0 +1,173 @@
+use rusqlite::{Connection, OpenFlags, Result};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+/// Nombre del archivo de base de datos dentro del directorio de datos
+const NOMBRE_ARCHIVO: &str = "bodex.db";
+
+/// Variable de entorno que fija la ruta de la base de datos
+pub const VAR_ENTORNO: &str = "BODEX_DB";
+
+/// Valor especial (flag o variable) para usar una base en memoria
+pub const MEMORIA: &str = ":memory:";
+
+/// URI de la base compartida en memoria: todas las conexiones del proceso
+/// ven los mismos datos mientras exista al menos una abierta
+const URI_MEMORIA: &str = "file:bodex_memoria?mode=memory&cache=shared";
+
+/// Dónde vive la base de datos de la sesión actual
+#[derive(Debug, Clone, PartialEq)]
+pub enum UbicacionDb {
+    Archivo(PathBuf),
+    /// Base volátil para pruebas; se pierde al cerrar la aplicación
+    Memoria,
+}
+
+static UBICACION: OnceLock<Mutex<Option<UbicacionDb>>> = OnceLock::new();
+
+/// Conexión que mantiene viva la base en memoria
+static ANCLA_MEMORIA: OnceLock<Mutex<Option<Connection>>> = OnceLock::new();
+
+fn get_ubicacion() -> &'static Mutex<Option<UbicacionDb>> {
+    UBICACION.get_or_init(|| Mutex::new(None))
+}
+
+fn get_ancla() -> &'static Mutex<Option<Connection>> {
+    ANCLA_MEMORIA.get_or_init(|| Mutex::new(None))
+}
+
+/// Fija la ubicación usada por `obtener_conexion` (llamar antes de `init_db`)
+pub fn configurar(ubicacion: UbicacionDb) {
+    if let UbicacionDb::Archivo(ruta) = &ubicacion {
+        println!("Base de datos: {}", ruta.display());
+    } else {
+        println!("Base de datos: en memoria (los datos no se guardan)");
+    }
+    *get_ubicacion().lock().unwrap() = Some(ubicacion);
+    // Las conexiones libres apuntan a la ubicación anterior
+    super::conexion::vaciar();
+}
+
+/// Ubicación configurada; si no se configuró, la resuelve desde el entorno
+pub fn actual() -> UbicacionDb {
+    let mut ubicacion = get_ubicacion().lock().unwrap();
+    ubicacion
+        .get_or_insert_with(|| resolver(std::env::args().skip(1)))
+        .clone()
+}
+
+/// Resuelve la ubicación por prioridad:
+/// 1. Flag `--db <ruta>` o `--db=<ruta>`
+/// 2. Variable de entorno `BODEX_DB`
+/// 3. Directorio de datos de la plataforma
+pub fn resolver<I: IntoIterator<Item = String>>(args: I) -> UbicacionDb {
+    let valor = ruta_desde_args(args).or_else(|| {
+        std::env::var(VAR_ENTORNO)
+            .ok()
+            .filter(|v| !v.trim().is_empty())
+    });
+
+    match valor {
+        Some(v) if v == MEMORIA => UbicacionDb::Memoria,
+        Some(v) => UbicacionDb::Archivo(PathBuf::from(v)),
+        None => UbicacionDb::Archivo(directorio_datos().join(NOMBRE_ARCHIVO)),
+    }
+}
+
+/// Busca `--db <ruta>` o `--db=<ruta>` en los argumentos de la línea de comandos
+fn ruta_desde_args<I: IntoIterator<Item = String>>(args: I) -> Option<String> {
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == "--db" {
+            return args.next();
+        }
+        if let Some(ruta) = arg.strip_prefix("--db=") {
+            return Some(ruta.to_string());
+        }
+    }
+    None
+}
+
+/// Directorio de datos de la aplicación según la plataforma
+pub fn directorio_datos() -> PathBuf {
+    #[cfg(not(target_os = "android"))]
+    let env = |nombre: &str| std::env::var_os(nombre).map(PathBuf::from);
+
+    #[cfg(target_os = "android")]
+    let dir = Some(PathBuf::from("/data/data/com.jose.bodex/files"));
+
+    #[cfg(target_os = "windows")]
+    let dir = env("APPDATA").map(|d| d.join("Bodex"));
+
+    #[cfg(target_os = "macos")]
+    let dir = env("HOME").map(|d| d.join("Library/Application Support/Bodex"));
+
+    #[cfg(not(any(target_os = "android", target_os = "windows", target_os = "macos")))]
+    let dir = env("XDG_DATA_HOME")
+        .or_else(|| env("HOME").map(|d| d.join(".local/share")))
+        .map(|d| d.join("bodex"));
+
+    // Sin directorio conocido: se mantiene el comportamiento anterior (directorio actual)
+    dir.unwrap_or_else(|| PathBuf::from("."))
+}
+
+/// Abre una conexión en la ubicación indicada
+pub fn abrir(ubicacion: &UbicacionDb) -> Result<Connection> {
+    match ubicacion {
+        UbicacionDb::Archivo(ruta) => {
+            preparar_archivo(ruta);
+            Connection::open(ruta)
+        }
+        UbicacionDb::Memoria => {
+            let flags = OpenFlags::SQLITE_OPEN_READ_WRITE
+                | OpenFlags::SQLITE_OPEN_CREATE
+                | OpenFlags::SQLITE_OPEN_URI
+                | OpenFlags::SQLITE_OPEN_NO_MUTEX;
+
+            let mut ancla = get_ancla().lock().unwrap();
+            if ancla.is_none() {
+                *ancla = Some(Connection::open_with_flags(URI_MEMORIA, flags)?);
+            }
+            Connection::open_with_flags(URI_MEMORIA, flags)
+        }
+    }
+}
+
+/// Crea el directorio padre y, la primera vez, trae el `bodex.db` que
+/// versiones anteriores dejaban en el directorio de trabajo
+fn preparar_archivo(ruta: &Path) {
+    if ruta.exists() {
+        return;
+    }
+    if let Some(padre) = ruta.parent().filter(|p| !p.as_os_str().is_empty()) {
+        if let Err(e) = std::fs::create_dir_all(padre) {
+            eprintln!("No se pudo crear el directorio {}: {}", padre.display(), e);
+            return;
+        }
+    }
+
+    let heredado = Path::new(NOMBRE_ARCHIVO);
+    if heredado.exists() && heredado.canonicalize().ok() != ruta.canonicalize().ok() {
+        match copiar_base(heredado, ruta) {
+            Ok(()) => println!(
+                "Base de datos existente copiada de {} a {}",
+                heredado.display(),
+                ruta.display()
+            ),
+            Err(e) => eprintln!("No se pudo copiar la base de datos anterior: {}", e),
+        }
+    }
+}
+
+/// Copia una base SQLite cerrada. Con WAL, lo último escrito puede estar todavía en
+/// `<archivo>-wal`: antes de copiar se vuelca al archivo principal y se vacía el WAL.
+fn copiar_base(origen: &Path, destino: &Path) -> std::result::Result<(), Box<dyn std::error::Error>> {
+    let conn = Connection::open(origen)?;
+    let ocupada: i64 = conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |r| r.get(0))?;
+    if ocupada != 0 {
+        return Err(format!("{} está en uso por otro proceso", origen.display()).into());
+    }
+    drop(conn);
+    std::fs::copy(origen, destino)?;
+    Ok(())
+}
@@ -1,21 +1,17 @@
 use crate::models::{Rol, Usuario};
 use chrono::NaiveDateTime;
-use rusqlite::{params, Connection, Result};
+use rusqlite::{params, Connection, OptionalExtension, Result, Row};
 
-/// Crea la tabla de usuarios si no existe
-pub fn create_table(conn: &Connection) -> Result<()> {
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS usuarios (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            username TEXT NOT NULL UNIQUE,
-            password_hash TEXT NOT NULL,
-            rol TEXT NOT NULL CHECK(rol IN ('Admin', 'Operador', 'Vendedor')),
-            activo INTEGER NOT NULL DEFAULT 1,
-            ultimo_login DATETIME
-        )",
-        [],
-    )?;
-    Ok(())
+/// Genera el hash bcrypt de una contraseña
+fn hash_password(pass: &str) -> Result<String> {
+    bcrypt::hash(pass, bcrypt::DEFAULT_COST)
+        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))
+}
+
+/// Indica si el valor almacenado es un hash bcrypt ($2a$, $2b$, $2y$...)
+/// o una contraseña heredada en texto plano
+fn es_hash_bcrypt(stored: &str) -> bool {
+    stored.starts_with("$2")
 }
 
 /// Inserta un usuario administrador inicial si la tabla está vacía
@@ -24,66 +20,178 @@ pub fn seed_admin(conn: &Connection) -> Result<()> {
 
     if count == 0 {
         conn.execute(
-            "INSERT INTO usuarios (username, password_hash, rol, activo) 
-             VALUES (?1, ?2, ?3, ?4)",
-            params!["admin", "admin", "Admin", 1],
+            "INSERT INTO usuarios (username, password_hash, rol, activo, debe_cambiar_password) 
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params!["admin", hash_password("admin")?, "Admin", 1, 1],
         )?;
-        println!("Usuario administrador inicial creado: admin/admin");
+        println!("Usuario administrador inicial creado: admin/admin (se pedirá cambiar la contraseña)");
     }
     Ok(())
 }
 
-/// Valida las credenciales y devuelve el Usuario si es exitoso
+/// Columnas de `Usuario`; el hash de la contraseña solo se lee al validar credenciales
+const COLUMNAS_USUARIO: &str = "id, username, rol, activo, ultimo_login, debe_cambiar_password";
+
+fn usuario_desde_fila(row: &Row) -> Result<Usuario> {
+    Ok(Usuario {
+        id: row.get(0)?,
+        username: row.get(1)?,
+        rol: Rol::from(row.get::<_, String>(2)?),
+        activo: row.get::<_, i32>(3)? != 0,
+        ultimo_login: row.get::<_, Option<NaiveDateTime>>(4)?,
+        debe_cambiar_password: row.get::<_, i32>(5)? != 0,
+    })
+}
+
+/// Valida las credenciales y devuelve el Usuario si es exitoso.
+/// Las contraseñas heredadas en texto plano se migran a bcrypt tras un login correcto.
 pub fn validar_usuario(conn: &Connection, user: &str, pass: &str) -> Result<Option<Usuario>> {
-    let mut stmt = conn.prepare(
-        "SELECT id, username, password_hash, rol, activo, ultimo_login 
-         FROM usuarios 
-         WHERE username = ?1 AND activo = 1",
-    )?;
+    let credencial = conn
+        .query_row(
+            "SELECT id, password_hash FROM usuarios WHERE username = ?1 AND activo = 1",
+            params![user],
+            |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)),
+        )
+        .optional()?;
+    let Some((id, stored_hash)) = credencial else {
+        return Ok(None);
+    };
 
-    let mut rows = stmt.query(params![user])?;
-
-    if let Some(row) = rows.next()? {
-        let stored_hash: String = row.get(2)?;
-
-        // Verificación de contraseña (texto plano por ahora, como en tus pruebas)
-        if stored_hash == pass {
-            // Actualizamos la fecha del último login
-            let _ = conn.execute(
-                "UPDATE usuarios SET ultimo_login = CURRENT_TIMESTAMP WHERE id = ?1",
-                params![row.get::<_, i64>(0)?],
-            );
-
-            return Ok(Some(Usuario {
-                id: row.get(0)?,
-                username: row.get(1)?,
-                password_hash: stored_hash,
-                rol: Rol::from(row.get::<_, String>(3)?),
-                activo: row.get::<_, i32>(4)? != 0,
-                ultimo_login: row.get(5)?,
-            }));
-        }
+    let valida = if es_hash_bcrypt(&stored_hash) {
+        // Un hash corrupto se trata como credencial inválida
+        bcrypt::verify(pass, &stored_hash).unwrap_or(false)
+    } else if stored_hash == pass {
+        // Contraseña heredada en texto plano: actualizar a bcrypt de forma transparente
+        conn.execute(
+            "UPDATE usuarios SET password_hash = ?1 WHERE id = ?2",
+            params![hash_password(pass)?, id],
+        )?;
+        true
+    } else {
+        false
+    };
+    if !valida {
+        return Ok(None);
     }
 
-    Ok(None)
+    // El usuario se devuelve con el último login anterior a este
+    let usuario = conn.query_row(
+        &format!("SELECT {} FROM usuarios WHERE id = ?1", COLUMNAS_USUARIO),
+        params![id],
+        usuario_desde_fila,
+    )?;
+    // Actualizamos la fecha del último login
+    let _ = conn.execute(
+        "UPDATE usuarios SET ultimo_login = CURRENT_TIMESTAMP WHERE id = ?1",
+        params![id],
+    );
+    Ok(Some(usuario))
+}
+
+/// Todos los usuarios (activos e inactivos) ordenados por nombre de usuario
+pub fn obtener_usuarios(conn: &Connection) -> Result<Vec<Usuario>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM usuarios ORDER BY username COLLATE NOCASE ASC",
+        COLUMNAS_USUARIO
+    ))?;
+    let iter = stmt.query_map([], usuario_desde_fila)?;
+    iter.collect()
+}
+
+/// Verifica si ya existe un usuario con ese nombre (sin distinguir mayúsculas)
+pub fn existe_username(conn: &Connection, username: &str) -> Result<bool> {
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM usuarios WHERE username = ?1 COLLATE NOCASE",
+        params![username.trim()],
+        |r| r.get(0),
+    )?;
+    Ok(count > 0)
 }
 
-/// Crea un nuevo usuario en el sistema
+/// Cantidad de administradores activos sin contar al usuario indicado
+pub fn contar_otros_admins_activos(conn: &Connection, excluir_id: i64) -> Result<i64> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM usuarios WHERE rol = 'Admin' AND activo = 1 AND id != ?1",
+        params![excluir_id],
+        |r| r.get(0),
+    )
+}
+
+/// Crea un nuevo usuario en el sistema (la contraseña se guarda como hash bcrypt).
+/// La contraseña la asigna el administrador, así que se pedirá cambiarla en el primer inicio de sesión.
 pub fn crear_usuario(
     conn: &Connection,
     username: &str,
-    password_hash: &str,
+    password: &str,
     rol: Rol,
 ) -> Result<i64> {
     conn.execute(
-        "INSERT INTO usuarios (username, password_hash, rol) VALUES (?1, ?2, ?3)",
-        params![username, password_hash, rol.to_string()],
+        "INSERT INTO usuarios (username, password_hash, rol, debe_cambiar_password) VALUES (?1, ?2, ?3, 1)",
+        params![username, hash_password(password)?, rol.to_string()],
     )?;
     Ok(conn.last_insert_rowid())
 }
 
+/// Cambia el rol de un usuario
+pub fn cambiar_rol(conn: &Connection, id: i64, rol: Rol) -> Result<bool> {
+    let filas = conn.execute("UPDATE usuarios SET rol = ?1 WHERE id = ?2", params![rol.to_string(), id])?;
+    Ok(filas > 0)
+}
+
+/// Activa o desactiva un usuario (los inactivos no pueden iniciar sesión)
+pub fn cambiar_activo(conn: &Connection, id: i64, activo: bool) -> Result<bool> {
+    let filas = conn.execute(
+        "UPDATE usuarios SET activo = ?1 WHERE id = ?2",
+        params![if activo { 1 } else { 0 }, id],
+    )?;
+    Ok(filas > 0)
+}
+
+/// Asigna una contraseña provisional; el usuario deberá cambiarla al iniciar sesión
+pub fn restablecer_password(conn: &Connection, id: i64, nueva: &str) -> Result<bool> {
+    let filas = conn.execute(
+        "UPDATE usuarios SET password_hash = ?1, debe_cambiar_password = 1 WHERE id = ?2",
+        params![hash_password(nueva)?, id],
+    )?;
+    Ok(filas > 0)
+}
+
+/// Cambia la contraseña de un usuario y retira la obligación de cambiarla
+pub fn cambiar_password(conn: &Connection, id: i64, nueva: &str) -> Result<bool> {
+    let filas = conn.execute(
+        "UPDATE usuarios SET password_hash = ?1, debe_cambiar_password = 0 WHERE id = ?2",
+        params![hash_password(nueva)?, id],
+    )?;
+    Ok(filas > 0)
+}
+
 /// Elimina un usuario por ID
 pub fn eliminar_usuario(conn: &Connection, id: i64) -> Result<bool> {
     let filas = conn.execute("DELETE FROM usuarios WHERE id = ?1", params![id])?;
     Ok(filas > 0)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::pruebas;
+
+    #[test]
+    fn solo_cuentan_los_otros_administradores_activos() {
+        let conn = pruebas::base_en_memoria();
+        conn.execute_batch(
+            "INSERT INTO usuarios (id, username, password_hash, rol, activo) VALUES
+                 (1, 'admin', 'x', 'Admin', 1),
+                 (2, 'ana', 'x', 'Admin', 0),
+                 (3, 'luis', 'x', 'Vendedor', 1);",
+        )
+        .unwrap();
+        assert_eq!(contar_otros_admins_activos(&conn, 1).unwrap(), 0);
+        assert_eq!(contar_otros_admins_activos(&conn, 3).unwrap(), 1);
+
+        assert!(cambiar_activo(&conn, 2, true).unwrap());
+        assert_eq!(contar_otros_admins_activos(&conn, 1).unwrap(), 1);
+        assert!(existe_username(&conn, " ANA ").unwrap());
+        assert_eq!(obtener_usuarios(&conn).unwrap().iter().map(|u| u.id).collect::<Vec<_>>(), vec![1, 2, 3]);
+    }
+}
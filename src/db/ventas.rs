@@ -1,78 +1,279 @@
-use crate::models::{DetalleVenta, Venta};
-use rusqlite::{params, Connection, Result};
-
-/// Crea la tabla principal de ventas
-pub fn create_table(conn: &Connection) -> Result<()> {
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS ventas (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            fecha DATETIME DEFAULT CURRENT_TIMESTAMP,
-            total REAL NOT NULL,
-            usuario_id INTEGER,
-            cliente_nombre TEXT,
-            FOREIGN KEY (usuario_id) REFERENCES usuarios(id)
-        )",
-        [],
-    )?;
-    Ok(())
+use super::caja;
+use super::clientes;
+use super::credito;
+use super::lotes::{self, LoteError};
+use super::movimientos::{self, MovimientoError};
+use super::pagos;
+use crate::models::medida::formatear_cantidad;
+use crate::models::{
+    Cliente, DetalleVenta, DetalleVentaConProducto, EstadoVenta, FiltroVentas, MetodoPago, MotivoMovimiento, PagoNuevo, Venta,
+    VentaConDetalles,
+};
+use rusqlite::{params, Connection, OptionalExtension, Result, Row};
+use std::fmt;
+
+/// Errores de negocio al registrar una venta
+#[derive(Debug)]
+pub enum VentaError {
+    /// El producto no tiene unidades suficientes; la venta completa se revierte
+    StockInsuficiente {
+        producto_id: i64,
+        nombre: String,
+        disponible: f64,
+        solicitado: f64,
+    },
+    /// Cantidad no positiva o importe inválido en una línea, o cantidad con decimales
+    /// en un producto que se vende por unidades
+    CantidadInvalida(String),
+    /// El producto de la línea no existe en la base de datos
+    ProductoNoEncontrado(i64),
+    /// El cliente de la venta no existe en la base de datos
+    ClienteNoEncontrado(i64),
+    /// El cajero no tiene una caja abierta a la cual asociar la venta
+    SinCajaAbierta,
+    /// Lo que falta para la línea solo está en lotes vencidos
+    LoteVencido(String),
+    /// Los pagos no cubren el total de la venta
+    PagoInsuficiente { total: f64, pagado: f64 },
+    /// Sin pagos, montos no positivos o vuelto imposible de entregar
+    PagoInvalido(String),
+    /// La parte a crédito deja al cliente por encima de su límite
+    CreditoExcedido { limite: f64, saldo: f64, solicitado: f64 },
+    /// Error de SQLite
+    Db(rusqlite::Error),
 }
 
-/// Crea la tabla de detalles (productos vendidos en cada venta)
-pub fn create_detalle_table(conn: &Connection) -> Result<()> {
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS ventas_detalle (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            venta_id INTEGER NOT NULL,
-            producto_id INTEGER NOT NULL,
-            cantidad INTEGER NOT NULL,
-            precio_unitario REAL NOT NULL,
-            subtotal REAL NOT NULL,
-            FOREIGN KEY (venta_id) REFERENCES ventas(id) ON DELETE CASCADE,
-            FOREIGN KEY (producto_id) REFERENCES productos(id)
-        )",
-        [],
-    )?;
-    Ok(())
+impl fmt::Display for VentaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VentaError::StockInsuficiente { nombre, disponible, solicitado, .. } => write!(
+                f,
+                "Stock insuficiente para '{}': disponibles {}, solicitados {}",
+                nombre,
+                formatear_cantidad(*disponible),
+                formatear_cantidad(*solicitado)
+            ),
+            VentaError::CantidadInvalida(msg) => write!(f, "{}", msg),
+            VentaError::ProductoNoEncontrado(id) => write!(f, "El producto con ID {} no existe", id),
+            VentaError::ClienteNoEncontrado(id) => write!(f, "El cliente con ID {} no existe", id),
+            VentaError::SinCajaAbierta => write!(f, "No hay una caja abierta: abra la caja antes de vender"),
+            VentaError::LoteVencido(msg) => write!(f, "{}", msg),
+            VentaError::PagoInsuficiente { total, pagado } => write!(
+                f,
+                "Los pagos no cubren el total: total {:.2}, pagado {:.2} (faltan {:.2})",
+                total,
+                pagado,
+                total - pagado
+            ),
+            VentaError::PagoInvalido(msg) => write!(f, "{}", msg),
+            VentaError::CreditoExcedido { limite, saldo, solicitado } => write!(
+                f,
+                "El crédito de {:.2} excede el disponible del cliente: límite {:.2}, saldo {:.2} (disponible {:.2})",
+                solicitado,
+                limite,
+                saldo,
+                (limite - saldo).max(0.0)
+            ),
+            VentaError::Db(e) => write!(f, "Error de base de datos: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for VentaError {}
+
+impl From<rusqlite::Error> for VentaError {
+    fn from(e: rusqlite::Error) -> Self {
+        VentaError::Db(e)
+    }
+}
+
+impl From<MovimientoError> for VentaError {
+    fn from(e: MovimientoError) -> Self {
+        match e {
+            MovimientoError::StockInsuficiente { producto_id, nombre, disponible, solicitado } => {
+                VentaError::StockInsuficiente { producto_id, nombre, disponible, solicitado }
+            }
+            otro @ MovimientoError::CantidadFraccionaria { .. } => VentaError::CantidadInvalida(otro.to_string()),
+            MovimientoError::ProductoNoEncontrado(id) => VentaError::ProductoNoEncontrado(id),
+            MovimientoError::Db(e) => VentaError::Db(e),
+        }
+    }
 }
 
-/// Registra una venta completa y actualiza el stock usando una Transacción
+impl From<LoteError> for VentaError {
+    fn from(e: LoteError) -> Self {
+        match e {
+            LoteError::Db(e) => VentaError::Db(e),
+            otro => VentaError::LoteVencido(otro.to_string()),
+        }
+    }
+}
+
+/// Cambio a entregar en efectivo para cobrar `total` con `pagos`.
+/// Los pagos deben cubrir el total y el excedente solo puede salir de lo recibido en efectivo
+/// (una tarjeta o transferencia no se cobra de más).
+pub fn calcular_vuelto(total: f64, pagos: &[PagoNuevo]) -> std::result::Result<f64, VentaError> {
+    if pagos.is_empty() {
+        return Err(VentaError::PagoInvalido("Registre al menos un pago".to_string()));
+    }
+    if pagos.iter().any(|p| !p.monto.is_finite() || p.monto <= 0.0) {
+        return Err(VentaError::PagoInvalido(
+            "El monto de cada pago debe ser mayor a 0".to_string(),
+        ));
+    }
+
+    // Comparar en céntimos evita falsos faltantes por coma flotante
+    let centimos = |monto: f64| (monto * 100.0).round() as i64;
+    let pagado: f64 = pagos.iter().map(|p| p.monto).sum();
+    if centimos(pagado) < centimos(total) {
+        return Err(VentaError::PagoInsuficiente { total, pagado });
+    }
+
+    let vuelto = (centimos(pagado) - centimos(total)) as f64 / 100.0;
+    let efectivo: f64 = pagos
+        .iter()
+        .filter(|p| p.metodo == MetodoPago::Efectivo)
+        .map(|p| p.monto)
+        .sum();
+    if centimos(vuelto) > centimos(efectivo) {
+        return Err(VentaError::PagoInvalido(format!(
+            "El excedente de {:.2} solo puede devolverse de pagos en efectivo",
+            vuelto
+        )));
+    }
+    Ok(vuelto)
+}
+
+/// Registra una venta completa y actualiza el stock usando una Transacción.
+/// La venta queda asociada a la caja abierta del cajero (`VentaError::SinCajaAbierta` si no tiene)
+/// y los pagos deben cubrir el total (ver `calcular_vuelto`).
+/// `cliente_id` es el cliente registrado (`Cliente::CONSUMIDOR_FINAL` para ventas de mostrador);
+/// su nombre se copia en la venta para que el recibo no cambie si se edita el cliente.
+/// `monto_credito` es la parte del total que el cliente queda debiendo: los pagos solo deben
+/// cubrir el resto y el saldo del cliente no puede pasar su límite de crédito.
+/// `tasa_cambio` es la tasa con la que se cotizó la venta en bolívares: queda guardada para
+/// que el total en bolívares no cambie al registrar tasas nuevas.
+/// Si algún producto no tiene stock suficiente, la transacción se revierte y se
+/// devuelve `VentaError::StockInsuficiente` indicando el producto y las unidades disponibles.
 pub fn registrar_venta(
     conn: &mut Connection,
     usuario_id: i64,
-    cliente: &str,
+    cliente_id: i64,
     detalles: Vec<DetalleVenta>,
-) -> Result<i64> {
-    // Calculamos el total de la venta
+    pagos: &[PagoNuevo],
+    monto_credito: f64,
+    tasa_cambio: Option<f64>,
+) -> std::result::Result<i64, VentaError> {
+    // Una cantidad negativa sumaría stock en vez de descontarlo
+    if detalles.iter().any(|d| !d.cantidad.is_finite() || d.cantidad <= 0.0) {
+        return Err(VentaError::CantidadInvalida(
+            "La cantidad de cada línea debe ser mayor a 0".to_string(),
+        ));
+    }
+    if detalles.iter().any(|d| !d.subtotal.is_finite() || d.subtotal < 0.0) {
+        return Err(VentaError::CantidadInvalida(
+            "El subtotal de cada línea debe ser un monto válido".to_string(),
+        ));
+    }
+
+    // Calculamos el total de la venta y el cambio a entregar por lo que se cobra ahora
     let total_venta: f64 = detalles.iter().map(|d| d.subtotal).sum();
+    let centimos = |monto: f64| (monto * 100.0).round() as i64;
+    if !monto_credito.is_finite() || monto_credito < 0.0 || centimos(monto_credito) > centimos(total_venta) {
+        return Err(VentaError::PagoInvalido(
+            "El monto a crédito debe estar entre 0 y el total de la venta".to_string(),
+        ));
+    }
+    let por_cobrar = (centimos(total_venta) - centimos(monto_credito)) as f64 / 100.0;
+    let vuelto = if pagos.is_empty() && centimos(por_cobrar) == 0 {
+        0.0
+    } else {
+        calcular_vuelto(por_cobrar, pagos)?
+    };
 
     // Iniciamos la transacción: si algo falla, nada se guarda
     let tx = conn.transaction()?;
+    let sesion_id = caja::id_sesion_abierta(&tx, usuario_id)?.ok_or(VentaError::SinCajaAbierta)?;
+    let cliente = clientes::obtener_cliente(&tx, cliente_id)?.ok_or(VentaError::ClienteNoEncontrado(cliente_id))?;
+    let cliente_nombre = (cliente.id != Cliente::CONSUMIDOR_FINAL).then_some(cliente.nombre);
+    if centimos(monto_credito) > 0 {
+        let (limite, saldo) = credito::credito_disponible(&tx, cliente_id)?.ok_or_else(|| {
+            VentaError::PagoInvalido("Seleccione un cliente registrado para vender a crédito".to_string())
+        })?;
+        if centimos(saldo + monto_credito) > centimos(limite) {
+            return Err(VentaError::CreditoExcedido { limite, saldo, solicitado: monto_credito });
+        }
+    }
 
     // 1. Insertar en la tabla 'ventas'
     tx.execute(
-        "INSERT INTO ventas (total, usuario_id, cliente_nombre) VALUES (?1, ?2, ?3)",
-        params![total_venta, usuario_id, cliente],
+        "INSERT INTO ventas (
+            total, usuario_id, cliente_id, cliente_nombre, sesion_id, vuelto, tasa_cambio, monto_credito
+         ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            total_venta,
+            usuario_id,
+            cliente_id,
+            cliente_nombre,
+            sesion_id,
+            vuelto,
+            tasa_cambio,
+            monto_credito
+        ],
     )?;
 
     let venta_id = tx.last_insert_rowid();
+    let referencia = format!("Venta #{}", venta_id);
 
     // 2. Insertar detalles y descontar stock
     for item in detalles {
-        // Registrar detalle
+        // Registrar detalle con la medida en que se vende hoy el producto
         tx.execute(
-            "INSERT INTO ventas_detalle (venta_id, producto_id, cantidad, precio_unitario, subtotal) 
-             VALUES (?1, ?2, ?3, ?4, ?5)",
-            params![venta_id, item.producto_id, item.cantidad, item.precio_unitario, item.subtotal],
+            "INSERT INTO ventas_detalle (
+                venta_id, producto_id, cantidad, precio_unitario, subtotal,
+                impuesto_id, porcentaje_impuesto, base, impuesto, unidad_venta
+             ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9,
+                       (SELECT unidad_venta FROM productos WHERE id = ?2))",
+            params![
+                venta_id,
+                item.producto_id,
+                item.cantidad,
+                item.precio_unitario,
+                item.subtotal,
+                item.impuesto_id,
+                item.porcentaje_impuesto,
+                item.base,
+                item.impuesto
+            ],
         )?;
+        let detalle_id = tx.last_insert_rowid();
 
-        // Descontar stock del producto
-        tx.execute(
-            "UPDATE productos SET stock = stock - ?1 WHERE id = ?2 AND stock >= ?1",
-            params![item.cantidad, item.producto_id],
+        // Descontar stock dejando el movimiento en el kardex.
+        // Si no alcanza, se retorna sin commit y la transacción se revierte al soltarse `tx`.
+        movimientos::registrar_movimiento(
+            &tx,
+            item.producto_id,
+            -item.cantidad,
+            MotivoMovimiento::Venta,
+            Some(usuario_id),
+            Some(&referencia),
+            None,
         )?;
+        // Las unidades salen primero de los lotes que vencen antes (FEFO), nunca de los vencidos
+        lotes::consumir_venta(&tx, detalle_id, item.producto_id, item.cantidad)?;
+    }
 
-        // Verificación de seguridad: si el stock quedó negativo, SQLite lanzará un error
-        // o podemos verificar las filas afectadas.
+    // 3. Registrar los pagos
+    for pago in pagos {
+        tx.execute(
+            "INSERT INTO pagos (venta_id, metodo, monto, referencia) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                venta_id,
+                pago.metodo.as_str(),
+                pago.monto,
+                pago.referencia.as_deref().map(str::trim).filter(|r| !r.is_empty())
+            ],
+        )?;
     }
 
     // Confirmar todos los cambios
@@ -81,25 +282,280 @@ pub fn registrar_venta(
     Ok(venta_id)
 }
 
-/// Obtener el historial de ventas (Resumen)
-pub fn obtener_historial(conn: &Connection) -> Result<Vec<Venta>> {
-    let mut stmt = conn.prepare(
-        "SELECT id, fecha, total, usuario_id, cliente_nombre FROM ventas ORDER BY fecha DESC",
+/// Columnas de `Venta` para las consultas del historial (alias `v` = ventas, `u` = usuarios)
+const COLUMNAS_VENTA: &str = "v.id, v.fecha, v.total, u.username, v.cliente_nombre, v.estado,
+    COALESCE((SELECT SUM(dd.monto) FROM devoluciones_detalle dd
+              JOIN devoluciones d ON dd.devolucion_id = d.id
+              WHERE d.venta_id = v.id), 0),
+    v.vuelto, v.tasa_cambio,
+    COALESCE((SELECT SUM(d.impuesto) FROM ventas_detalle d WHERE d.venta_id = v.id), 0),
+    COALESCE((SELECT SUM(dd.cantidad * d.impuesto / d.cantidad) FROM devoluciones_detalle dd
+              JOIN ventas_detalle d ON dd.detalle_id = d.id
+              WHERE d.venta_id = v.id), 0),
+    v.cliente_id, (SELECT c.documento FROM clientes c WHERE c.id = v.cliente_id), v.monto_credito";
+
+fn venta_desde_fila(row: &Row) -> Result<Venta> {
+    Ok(Venta {
+        id: row.get(0)?,
+        fecha: row.get(1)?,
+        total: row.get(2)?,
+        usuario_nombre: row.get(3)?,
+        cliente_nombre: row.get(4)?,
+        estado: EstadoVenta::from(row.get::<_, String>(5)?),
+        monto_devuelto: row.get(6)?,
+        vuelto: row.get(7)?,
+        tasa_cambio: row.get(8)?,
+        impuesto: row.get(9)?,
+        impuesto_devuelto: row.get(10)?,
+        cliente_id: row.get(11)?,
+        cliente_documento: row.get(12)?,
+        monto_credito: row.get(13)?,
+    })
+}
+
+/// Obtener el historial de ventas (Resumen), de la más reciente a la más antigua
+pub fn obtener_historial(conn: &Connection, filtro: &FiltroVentas) -> Result<Vec<Venta>> {
+    // `fecha` se guarda como texto 'AAAA-MM-DD HH:MM:SS' (CURRENT_TIMESTAMP)
+    let formato = |f: &chrono::NaiveDateTime| f.format("%Y-%m-%d %H:%M:%S").to_string();
+    let cliente = filtro
+        .cliente
+        .as_deref()
+        .map(str::trim)
+        .filter(|c| !c.is_empty())
+        .map(|c| format!("%{}%", c));
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM ventas v
+         LEFT JOIN usuarios u ON v.usuario_id = u.id
+         WHERE (?1 IS NULL OR v.fecha >= ?1)
+           AND (?2 IS NULL OR v.fecha <= ?2)
+           AND (?3 IS NULL OR v.usuario_id = ?3)
+           AND (?4 IS NULL OR v.cliente_nombre LIKE ?4
+                OR (SELECT c.documento FROM clientes c WHERE c.id = v.cliente_id) LIKE ?4)
+           AND (?5 IS NULL OR v.cliente_id = ?5)
+         ORDER BY v.fecha DESC, v.id DESC",
+        COLUMNAS_VENTA
+    ))?;
+
+    let ventas_iter = stmt.query_map(
+        params![
+            filtro.desde.as_ref().map(formato),
+            filtro.hasta.as_ref().map(formato),
+            filtro.usuario_id,
+            cliente,
+            filtro.cliente_id
+        ],
+        venta_desde_fila,
     )?;
 
-    let ventas_iter = stmt.query_map([], |row| {
-        Ok(Venta {
+    let mut resultado = Vec::new();
+    for v in ventas_iter {
+        resultado.push(v?);
+    }
+    Ok(resultado)
+}
+
+/// Una venta con sus líneas (con el nombre de cada producto) y sus pagos
+pub fn obtener_venta_con_detalles(conn: &Connection, venta_id: i64) -> Result<Option<VentaConDetalles>> {
+    let venta = conn
+        .query_row(
+            &format!(
+                "SELECT {} FROM ventas v LEFT JOIN usuarios u ON v.usuario_id = u.id WHERE v.id = ?1",
+                COLUMNAS_VENTA
+            ),
+            params![venta_id],
+            venta_desde_fila,
+        )
+        .optional()?;
+    let Some(venta) = venta else {
+        return Ok(None);
+    };
+
+    let mut stmt = conn.prepare(
+        "SELECT d.id, d.producto_id, p.nombre, d.cantidad, d.precio_unitario, d.subtotal,
+                d.porcentaje_impuesto, d.base, d.impuesto,
+                COALESCE((SELECT ROUND(SUM(dd.cantidad), 3) FROM devoluciones_detalle dd WHERE dd.detalle_id = d.id), 0),
+                d.unidad_venta
+         FROM ventas_detalle d
+         JOIN productos p ON d.producto_id = p.id
+         WHERE d.venta_id = ?1
+         ORDER BY d.id ASC",
+    )?;
+    let detalles_iter = stmt.query_map(params![venta_id], |row| {
+        Ok(DetalleVentaConProducto {
             id: row.get(0)?,
-            fecha: row.get(1)?,
-            total: row.get(2)?,
-            usuario_id: row.get(3)?,
-            cliente_nombre: row.get(4)?,
+            producto_id: row.get(1)?,
+            nombre_producto: row.get(2)?,
+            cantidad: row.get(3)?,
+            precio_unitario: row.get(4)?,
+            subtotal: row.get(5)?,
+            porcentaje_impuesto: row.get(6)?,
+            base: row.get(7)?,
+            impuesto: row.get(8)?,
+            cantidad_devuelta: row.get(9)?,
+            unidad_venta: row.get(10)?,
         })
     })?;
 
+    let mut detalles = Vec::new();
+    for d in detalles_iter {
+        detalles.push(d?);
+    }
+    let pagos = pagos::obtener_por_venta(conn, venta_id)?;
+    Ok(Some(VentaConDetalles { venta, detalles, pagos }))
+}
+
+/// Usuarios que han registrado ventas (id, username), para filtrar el historial por cajero
+pub fn obtener_cajeros(conn: &Connection) -> Result<Vec<(i64, String)>> {
+    let mut stmt = conn.prepare(
+        "SELECT DISTINCT u.id, u.username FROM ventas v
+         JOIN usuarios u ON v.usuario_id = u.id
+         ORDER BY u.username ASC",
+    )?;
+    let iter = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+
     let mut resultado = Vec::new();
-    for v in ventas_iter {
-        resultado.push(v?);
+    for cajero in iter {
+        resultado.push(cajero?);
     }
     Ok(resultado)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::pruebas::{self, linea, pago};
+    use crate::models::medida::Medida;
+
+    #[test]
+    fn una_linea_sin_stock_revierte_toda_la_venta() {
+        let mut conn = pruebas::base_en_memoria();
+        let cajero = pruebas::cajero_con_caja(&conn, "ana");
+        let arroz = pruebas::producto(&conn, "Arroz", 1.0, 2.0, 10.0, Medida::Unidad);
+        let cafe = pruebas::producto(&conn, "Café", 3.0, 5.0, 1.0, Medida::Unidad);
+        let movimientos_previos = pruebas::contar(&conn, "movimientos_stock");
+
+        let resultado = registrar_venta(
+            &mut conn,
+            cajero,
+            Cliente::CONSUMIDOR_FINAL,
+            vec![linea(arroz, 3.0, 2.0), linea(cafe, 2.0, 5.0)],
+            &[pago(MetodoPago::Efectivo, 16.0)],
+            0.0,
+            None,
+        );
+
+        assert!(matches!(
+            resultado,
+            Err(VentaError::StockInsuficiente { producto_id, .. }) if producto_id == cafe
+        ));
+        assert_eq!(pruebas::contar(&conn, "ventas"), 0);
+        assert_eq!(pruebas::contar(&conn, "ventas_detalle"), 0);
+        assert_eq!(pruebas::contar(&conn, "pagos"), 0);
+        assert_eq!(pruebas::contar(&conn, "movimientos_stock"), movimientos_previos);
+        assert_eq!(pruebas::stock(&conn, arroz), 10.0);
+        assert_eq!(pruebas::stock(&conn, cafe), 1.0);
+    }
+
+    #[test]
+    fn registra_la_venta_y_descuenta_el_stock() {
+        let mut conn = pruebas::base_en_memoria();
+        let cajero = pruebas::cajero_con_caja(&conn, "ana");
+        let queso = pruebas::producto(&conn, "Queso", 6.0, 12.99, 2.5, Medida::Kilogramo);
+
+        let venta_id = registrar_venta(
+            &mut conn,
+            cajero,
+            Cliente::CONSUMIDOR_FINAL,
+            vec![linea(queso, 0.755, 12.99)],
+            &[pago(MetodoPago::Efectivo, 10.0)],
+            0.0,
+            None,
+        )
+        .unwrap();
+
+        let venta = obtener_venta_con_detalles(&conn, venta_id).unwrap().unwrap();
+        assert_eq!(venta.venta.total, 9.81);
+        assert_eq!(venta.venta.vuelto, 0.19);
+        assert_eq!(pruebas::stock(&conn, queso), 1.745);
+
+        // La línea conserva la medida de la venta aunque el producto pase a venderse por gramo
+        conn.execute("UPDATE productos SET unidad_venta = ?1 WHERE id = ?2", params![Medida::Gramo as i32, queso])
+            .unwrap();
+        let venta = obtener_venta_con_detalles(&conn, venta_id).unwrap().unwrap();
+        assert_eq!(venta.detalles[0].unidad_venta, Medida::Kilogramo as i32);
+    }
+
+    #[test]
+    fn una_cantidad_negativa_no_registra_la_venta() {
+        let mut conn = pruebas::base_en_memoria();
+        let cajero = pruebas::cajero_con_caja(&conn, "ana");
+        let arroz = pruebas::producto(&conn, "Arroz", 1.0, 2.0, 10.0, Medida::Unidad);
+
+        let resultado = registrar_venta(
+            &mut conn,
+            cajero,
+            Cliente::CONSUMIDOR_FINAL,
+            vec![linea(arroz, -3.0, 2.0)],
+            &[pago(MetodoPago::Efectivo, 1.0)],
+            0.0,
+            None,
+        );
+
+        assert!(matches!(resultado, Err(VentaError::CantidadInvalida(_))));
+        assert_eq!(pruebas::contar(&conn, "ventas"), 0);
+        assert_eq!(pruebas::stock(&conn, arroz), 10.0);
+    }
+
+    #[test]
+    fn el_vuelto_solo_sale_del_efectivo() {
+        let efectivo = |monto| pago(MetodoPago::Efectivo, monto);
+        let tarjeta = |monto| pago(MetodoPago::Tarjeta, monto);
+
+        assert_eq!(calcular_vuelto(9.81, &[efectivo(10.0)]).unwrap(), 0.19);
+        assert_eq!(calcular_vuelto(0.3, &[efectivo(0.1), efectivo(0.2)]).unwrap(), 0.0);
+        assert_eq!(calcular_vuelto(15.0, &[tarjeta(10.0), efectivo(20.0)]).unwrap(), 15.0);
+        assert!(matches!(calcular_vuelto(15.0, &[tarjeta(16.0)]), Err(VentaError::PagoInvalido(_))));
+        assert!(matches!(
+            calcular_vuelto(15.0, &[tarjeta(5.0), efectivo(9.99)]),
+            Err(VentaError::PagoInsuficiente { .. })
+        ));
+        assert!(matches!(calcular_vuelto(15.0, &[]), Err(VentaError::PagoInvalido(_))));
+        assert!(matches!(calcular_vuelto(1.0, &[efectivo(f64::NAN)]), Err(VentaError::PagoInvalido(_))));
+    }
+
+    #[test]
+    fn pagos_que_no_cubren_el_total_no_registran_nada() {
+        let mut conn = pruebas::base_en_memoria();
+        let cajero = pruebas::cajero_con_caja(&conn, "ana");
+        let arroz = pruebas::producto(&conn, "Arroz", 1.0, 2.0, 10.0, Medida::Unidad);
+
+        let resultado = registrar_venta(
+            &mut conn,
+            cajero,
+            Cliente::CONSUMIDOR_FINAL,
+            vec![linea(arroz, 3.0, 2.0)],
+            &[pago(MetodoPago::Efectivo, 2.0), pago(MetodoPago::PagoMovil, 3.99)],
+            0.0,
+            None,
+        );
+
+        assert!(matches!(resultado, Err(VentaError::PagoInsuficiente { .. })));
+        assert_eq!(pruebas::contar(&conn, "ventas"), 0);
+        assert_eq!(pruebas::stock(&conn, arroz), 10.0);
+
+        let venta_id = registrar_venta(
+            &mut conn,
+            cajero,
+            Cliente::CONSUMIDOR_FINAL,
+            vec![linea(arroz, 3.0, 2.0)],
+            &[pago(MetodoPago::PagoMovil, 4.0), pago(MetodoPago::Efectivo, 5.0)],
+            0.0,
+            None,
+        )
+        .unwrap();
+        let venta = obtener_venta_con_detalles(&conn, venta_id).unwrap().unwrap();
+        assert_eq!(venta.pagos.len(), 2);
+        assert_eq!(venta.venta.vuelto, 3.0);
+    }
+}
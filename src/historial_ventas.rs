@@ -0,0 +1,228 @@
+use crate::db;
+use crate::models::impuesto::etiqueta_porcentaje;
+use crate::models::medida;
+use crate::models::{DetalleVentaConProducto, FiltroVentas, LineaDevolucion, Medida, Venta, VentaConDetalles};
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use slint::{ModelRc, SharedString, StandardListViewItem, VecModel};
+use std::rc::Rc;
+use std::sync::{Mutex, OnceLock};
+
+/// Primera opción del selector de cajero (sin filtrar)
+const TODOS: &str = "Todos";
+
+/// Ventas en el orden mostrado en la tabla del historial
+static VENTAS: OnceLock<Mutex<Vec<Venta>>> = OnceLock::new();
+
+/// Cajeros (id, username) en el orden del selector, sin la opción "Todos"
+static CAJEROS: OnceLock<Mutex<Vec<(i64, String)>>> = OnceLock::new();
+
+/// Venta abierta en el detalle (destino de devoluciones y anulaciones)
+static SELECCIONADA: OnceLock<Mutex<Option<VentaConDetalles>>> = OnceLock::new();
+
+fn get_ventas() -> &'static Mutex<Vec<Venta>> {
+    VENTAS.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+fn get_cajeros() -> &'static Mutex<Vec<(i64, String)>> {
+    CAJEROS.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+fn get_seleccionada() -> &'static Mutex<Option<VentaConDetalles>> {
+    SELECCIONADA.get_or_init(|| Mutex::new(None))
+}
+
+/// Interpreta una fecha dd/mm/aaaa del filtro; vacía = sin límite.
+/// `hora` fija el inicio (00:00:00) o el final (23:59:59) del día local, que se pasa a UTC
+/// como las fechas guardadas.
+fn parse_fecha(texto: &str, hora: NaiveTime) -> Result<Option<NaiveDateTime>, String> {
+    let texto = texto.trim();
+    if texto.is_empty() {
+        return Ok(None);
+    }
+    NaiveDate::parse_from_str(texto, "%d/%m/%Y")
+        .map(|fecha| Some(db::local_a_utc(fecha.and_time(hora))))
+        .map_err(|_| format!("La fecha '{}' debe tener el formato dd/mm/aaaa", texto))
+}
+
+/// Arma el filtro del historial a partir de los campos del formulario
+pub fn filtro(desde: &str, hasta: &str, cajero_index: i32, cliente: &str) -> Result<FiltroVentas, String> {
+    let desde = parse_fecha(desde, NaiveTime::MIN)?;
+    let hasta = parse_fecha(hasta, NaiveTime::from_hms_opt(23, 59, 59).unwrap_or(NaiveTime::MIN))?;
+    if let (Some(d), Some(h)) = (desde, hasta) {
+        if d > h {
+            return Err("La fecha inicial es posterior a la final".to_string());
+        }
+    }
+
+    Ok(FiltroVentas {
+        desde,
+        hasta,
+        usuario_id: id_cajero(cajero_index),
+        cliente: Some(cliente.trim().to_string()).filter(|c| !c.is_empty()),
+        cliente_id: None,
+    })
+}
+
+/// Lee los cajeros con ventas y actualiza la caché (Send-safe)
+pub fn cargar_cajeros() -> Result<Vec<(i64, String)>, Box<dyn std::error::Error>> {
+    let conn = db::obtener_conexion()?;
+    let cajeros = db::ventas::obtener_cajeros(&conn)?;
+    *get_cajeros().lock().unwrap() = cajeros.clone();
+    Ok(cajeros)
+}
+
+/// Nombres para el selector de cajero: "Todos" y luego cada usuario con ventas
+pub fn cajeros_para_select(cajeros: &[(i64, String)]) -> ModelRc<SharedString> {
+    let nombres: Vec<SharedString> = std::iter::once(SharedString::from(TODOS))
+        .chain(cajeros.iter().map(|(_, nombre)| SharedString::from(nombre.as_str())))
+        .collect();
+    ModelRc::from(Rc::new(VecModel::from(nombres)))
+}
+
+/// Índice del selector -> usuario_id (None para "Todos" o índice inválido)
+fn id_cajero(index: i32) -> Option<i64> {
+    if index <= 0 {
+        return None;
+    }
+    get_cajeros().lock().unwrap().get(index as usize - 1).map(|(id, _)| *id)
+}
+
+/// Busca las ventas que cumplen el filtro y actualiza la caché (Send-safe)
+pub fn buscar(filtro: &FiltroVentas) -> Result<Vec<Venta>, Box<dyn std::error::Error>> {
+    let conn = db::obtener_conexion()?;
+    let ventas = db::ventas::obtener_historial(&conn, filtro)?;
+    *get_ventas().lock().unwrap() = ventas.clone();
+    Ok(ventas)
+}
+
+/// Venta de la fila indicada con sus líneas; queda seleccionada para devoluciones
+pub fn detalle(index: i32) -> Result<Option<VentaConDetalles>, Box<dyn std::error::Error>> {
+    let Some(venta_id) = get_ventas().lock().unwrap().get(index as usize).map(|v| v.id) else {
+        return Ok(None);
+    };
+    recargar_venta(venta_id)
+}
+
+/// Relee una venta (tras una devolución) y la deja seleccionada
+pub fn recargar_venta(venta_id: i64) -> Result<Option<VentaConDetalles>, Box<dyn std::error::Error>> {
+    let conn = db::obtener_conexion()?;
+    let venta = db::ventas::obtener_venta_con_detalles(&conn, venta_id)?;
+    *get_seleccionada().lock().unwrap() = venta.clone();
+    Ok(venta)
+}
+
+/// Quita la venta seleccionada (p. ej. al repetir la búsqueda)
+pub fn limpiar_seleccion() {
+    *get_seleccionada().lock().unwrap() = None;
+}
+
+/// Id de la venta seleccionada
+pub fn venta_seleccionada() -> Option<i64> {
+    get_seleccionada().lock().unwrap().as_ref().map(|v| v.venta.id)
+}
+
+/// Devuelve unidades de una línea (índice en la tabla de detalle) de la venta
+/// seleccionada. Sin cantidad se devuelve todo lo que queda de la línea.
+pub fn devolver_linea(
+    index: i32,
+    cantidad: &str,
+    motivo: &str,
+    usuario_id: i64,
+) -> Result<i64, Box<dyn std::error::Error>> {
+    let (venta_id, detalle) = {
+        let seleccionada = get_seleccionada().lock().unwrap();
+        let venta = seleccionada.as_ref().ok_or("Seleccione una venta del historial")?;
+        let detalle = venta
+            .detalles
+            .get(index as usize)
+            .cloned()
+            .ok_or("Seleccione un producto de la venta")?;
+        (venta.venta.id, detalle)
+    };
+
+    let cantidad = if cantidad.trim().is_empty() {
+        detalle.devolvible()
+    } else {
+        medida::parse_cantidad(cantidad, Medida::from_i32(detalle.unidad_venta))?
+    };
+    if cantidad <= 0.0 {
+        return Err("La cantidad a devolver debe ser mayor a 0".into());
+    }
+
+    let mut conn = db::obtener_conexion()?;
+    let linea = LineaDevolucion {
+        detalle_id: detalle.id,
+        cantidad,
+    };
+    Ok(db::devoluciones::registrar_devolucion(&mut conn, venta_id, usuario_id, motivo, &[linea])?)
+}
+
+/// Anula la venta seleccionada devolviendo todo lo pendiente
+pub fn anular(motivo: &str, usuario_id: i64) -> Result<i64, Box<dyn std::error::Error>> {
+    let venta_id = venta_seleccionada().ok_or("Seleccione una venta del historial")?;
+    let mut conn = db::obtener_conexion()?;
+    Ok(db::devoluciones::anular_venta(&mut conn, venta_id, usuario_id, motivo)?)
+}
+
+/// Suma de los totales de las ventas, descontando devoluciones y anulaciones
+pub fn total(ventas: &[Venta]) -> f64 {
+    ventas.iter().map(|v| v.total_neto()).sum()
+}
+
+/// Base imponible e IVA del total neto (descontando lo devuelto)
+pub fn base_e_impuesto(ventas: &[Venta]) -> (f64, f64) {
+    let impuesto: f64 = ventas.iter().map(|v| v.impuesto_neto()).sum();
+    (total(ventas) - impuesto, impuesto)
+}
+
+/// Convierte las ventas a filas de tabla (N°, Fecha, Cajero, Cliente, Estado, IVA, Total, Total Bs, Devuelto).
+/// El total en bolívares usa la tasa guardada con cada venta.
+pub fn ventas_to_model_rows(ventas: &[Venta]) -> ModelRc<ModelRc<StandardListViewItem>> {
+    let rows: Vec<ModelRc<StandardListViewItem>> = ventas
+        .iter()
+        .map(|v| {
+            let row_data = vec![
+                StandardListViewItem::from(SharedString::from(v.id.to_string())),
+                StandardListViewItem::from(SharedString::from(v.fecha.format("%d/%m/%Y %H:%M").to_string())),
+                StandardListViewItem::from(SharedString::from(v.usuario_nombre.clone().unwrap_or_else(|| "-".into()))),
+                StandardListViewItem::from(SharedString::from(v.cliente_nombre.clone().unwrap_or_default())),
+                StandardListViewItem::from(SharedString::from(v.estado.etiqueta())),
+                StandardListViewItem::from(SharedString::from(format!("{:.2}", v.impuesto))),
+                StandardListViewItem::from(SharedString::from(format!("{:.2}", v.total))),
+                StandardListViewItem::from(SharedString::from(
+                    v.total_local().map(|t| format!("{:.2}", t)).unwrap_or_else(|| "-".into()),
+                )),
+                StandardListViewItem::from(SharedString::from(format!("{:.2}", v.monto_devuelto))),
+            ];
+            ModelRc::from(Rc::new(VecModel::from(row_data)))
+        })
+        .collect();
+
+    ModelRc::from(Rc::new(VecModel::from(rows)))
+}
+
+/// Convierte las líneas de una venta a filas de tabla (Producto, Cant., Precio, Base, IVA, Subtotal, Devuelto)
+pub fn detalles_to_model_rows(detalles: &[DetalleVentaConProducto]) -> ModelRc<ModelRc<StandardListViewItem>> {
+    let rows: Vec<ModelRc<StandardListViewItem>> = detalles
+        .iter()
+        .map(|d| {
+            let medida = Medida::from_i32(d.unidad_venta);
+            let row_data = vec![
+                StandardListViewItem::from(SharedString::from(d.nombre_producto.as_str())),
+                StandardListViewItem::from(SharedString::from(medida.formatear(d.cantidad))),
+                StandardListViewItem::from(SharedString::from(format!("{:.2}", d.precio_unitario))),
+                StandardListViewItem::from(SharedString::from(format!("{:.2}", d.base))),
+                StandardListViewItem::from(SharedString::from(format!(
+                    "{:.2} ({})",
+                    d.impuesto,
+                    etiqueta_porcentaje(d.porcentaje_impuesto)
+                ))),
+                StandardListViewItem::from(SharedString::from(format!("{:.2}", d.subtotal))),
+                StandardListViewItem::from(SharedString::from(medida.formatear(d.cantidad_devuelta))),
+            ];
+            ModelRc::from(Rc::new(VecModel::from(row_data)))
+        })
+        .collect();
+
+    ModelRc::from(Rc::new(VecModel::from(rows)))
+}
@@ -0,0 +1,92 @@
+use crate::db;
+use crate::models::{Impuesto, ModoPrecios};
+use slint::{ModelRc, SharedString, VecModel};
+use std::rc::Rc;
+use std::sync::{Mutex, OnceLock};
+
+/// Opción del selector de IVA del producto que hereda la tasa de su categoría (NULL)
+const SEGUN_CATEGORIA: &str = "Según categoría";
+
+/// Tasas registradas, en el orden de los selectores
+static IMPUESTOS: OnceLock<Mutex<Vec<Impuesto>>> = OnceLock::new();
+
+fn get_impuestos() -> &'static Mutex<Vec<Impuesto>> {
+    IMPUESTOS.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+/// Carga las tasas de la base de datos y actualiza la caché de los selectores
+pub fn cargar_impuestos() -> Result<Vec<Impuesto>, Box<dyn std::error::Error>> {
+    let conn = db::obtener_conexion()?;
+    let impuestos = db::impuestos::obtener_impuestos(&conn)?;
+    *get_impuestos().lock().unwrap() = impuestos.clone();
+    Ok(impuestos)
+}
+
+/// Nombres para el selector de IVA de la categoría ("IVA General (16%)", ...)
+pub fn impuestos_para_select() -> ModelRc<SharedString> {
+    let nombres: Vec<SharedString> = get_impuestos()
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|i| SharedString::from(i.etiqueta()))
+        .collect();
+    ModelRc::from(Rc::new(VecModel::from(nombres)))
+}
+
+/// Índice del selector de la categoría -> impuesto_id (la tasa general si no es válido)
+pub fn id_impuesto(index: i32) -> i64 {
+    get_impuestos()
+        .lock()
+        .unwrap()
+        .get(index.max(0) as usize)
+        .map(|i| i.id)
+        .unwrap_or(Impuesto::GENERAL)
+}
+
+/// impuesto_id -> índice del selector de la categoría (0 si no se encuentra)
+pub fn indice_impuesto(impuesto_id: i64) -> i32 {
+    get_impuestos()
+        .lock()
+        .unwrap()
+        .iter()
+        .position(|i| i.id == impuesto_id)
+        .map(|i| i as i32)
+        .unwrap_or(0)
+}
+
+/// Nombres para el selector de IVA del producto ("Según categoría" primero)
+pub fn impuestos_producto_para_select() -> ModelRc<SharedString> {
+    let nombres: Vec<SharedString> = std::iter::once(SharedString::from(SEGUN_CATEGORIA))
+        .chain(get_impuestos().lock().unwrap().iter().map(|i| SharedString::from(i.etiqueta())))
+        .collect();
+    ModelRc::from(Rc::new(VecModel::from(nombres)))
+}
+
+/// Índice del selector del producto -> impuesto_id (None = según categoría)
+pub fn id_impuesto_producto(index: i32) -> Option<i64> {
+    if index <= 0 {
+        return None;
+    }
+    get_impuestos().lock().unwrap().get(index as usize - 1).map(|i| i.id)
+}
+
+/// impuesto_id del producto -> índice del selector (0 = según categoría)
+pub fn indice_impuesto_producto(impuesto_id: Option<i64>) -> i32 {
+    impuesto_id
+        .and_then(|id| get_impuestos().lock().unwrap().iter().position(|i| i.id == id))
+        .map(|i| i as i32 + 1)
+        .unwrap_or(0)
+}
+
+/// Modo de los precios de venta configurado (Send-safe)
+pub fn modo_precios() -> Result<ModoPrecios, Box<dyn std::error::Error>> {
+    let conn = db::obtener_conexion()?;
+    Ok(db::impuestos::obtener_modo_precios(&conn)?)
+}
+
+/// Guarda el modo de los precios de venta (Send-safe)
+pub fn guardar_modo_precios(modo: ModoPrecios) -> Result<(), Box<dyn std::error::Error>> {
+    let conn = db::obtener_conexion()?;
+    db::impuestos::guardar_modo_precios(&conn, modo)?;
+    Ok(())
+}
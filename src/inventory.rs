@@ -1,5 +1,9 @@
 use crate::db;
-use crate::models::{Producto as DbProducto, ProductoNuevo};
+use crate::models::medida::{formatear_cantidad, redondear_cantidad};
+use crate::models::producto::DIAS_VENTAS_RECIENTES;
+use crate::models::{Medida, Moneda, MotivoMovimiento, Producto as DbProducto, ProductoNuevo, StockBajo};
+use crate::session;
+use crate::tasas;
 use regex::Regex;
 use slint::{ModelRc, SharedString, StandardListViewItem, VecModel};
 use std::rc::Rc;
@@ -10,8 +14,8 @@ use std::sync::{Mutex, OnceLock};
 pub struct ProductInfo {
     pub id: i64,
     pub nombre: String,
-    pub precio_venta: f64,
-    pub stock: i64,
+    pub stock: f64,
+    pub unidad_venta: i32,
 }
 
 /// Caché global segura para hilos
@@ -22,8 +26,15 @@ fn get_cache() -> &'static Mutex<Vec<ProductInfo>> {
     LOADED_PRODUCTS.get_or_init(|| Mutex::new(Vec::new()))
 }
 
-/// Helper para parsear SharedString de Slint a tipos numéricos de Rust
-fn parse_num<T: std::str::FromStr>(val: &SharedString, default: T) -> T {
+/// Producto abierto en el formulario de edición y el stock que se le mostró (id, stock)
+static STOCK_EN_EDICION: OnceLock<Mutex<Option<(i64, f64)>>> = OnceLock::new();
+
+fn get_stock_en_edicion() -> &'static Mutex<Option<(i64, f64)>> {
+    STOCK_EN_EDICION.get_or_init(|| Mutex::new(None))
+}
+
+/// Helper para parsear los textos del formulario a tipos numéricos de Rust
+fn parse_num<T: std::str::FromStr>(val: &str, default: T) -> T {
     if val.is_empty() {
         default
     } else {
@@ -42,9 +53,41 @@ pub struct ProductRowData {
     pub activo: bool,
 }
 
+/// Campos del formulario de producto tal como llegan de la UI (Send-safe).
+/// Los selectores ya vienen traducidos a IDs; los vacíos toman el valor por defecto al guardar.
+#[derive(Debug, Clone, Default)]
+pub struct ProductoForm {
+    pub nombre: String,
+    pub precio_neto: String,
+    pub precio_venta: String,
+    pub stock: String,
+    pub descripcion: String,
+    pub codigo: String,
+    pub activo: bool,
+    pub marca_id: String,
+    // Campos de Enums y Medidas
+    pub medida_p_id: String,
+    pub cantidad_p: String,
+    pub medida_s_id: String,
+    pub cantidad_s: String,
+    pub empaque_id: String,
+    // Categoría y subcategoría
+    pub categoria_id: String,
+    pub subcategoria_id: String,
+    // Moneda del precio de venta (vacía = moneda base)
+    pub moneda: String,
+    // Tasa de IVA propia (vacía = la de su categoría)
+    pub impuesto_id: String,
+    // Niveles de alerta de existencias (vacíos = 0, sin alerta)
+    pub stock_minimo: String,
+    pub punto_reorden: String,
+    // Medida en que se vende y se cuenta el stock (vacía = por unidad)
+    pub unidad_venta: String,
+}
+
 /// Obtiene los productos de la DB como datos crudos (Send-safe) para usar en hilos secundarios
 pub fn get_inventory_rows_raw() -> Result<Vec<ProductRowData>, Box<dyn std::error::Error>> {
-    let conn = db::open_connection()?;
+    let conn = db::obtener_conexion()?;
     let productos = db::productos::obtener_productos_con_marca(&conn)?;
 
     // Actualizar caché interno
@@ -53,8 +96,8 @@ pub fn get_inventory_rows_raw() -> Result<Vec<ProductRowData>, Box<dyn std::erro
         .map(|p| ProductInfo {
             id: p.id,
             nombre: p.nombre.clone(),
-            precio_venta: p.precio_venta,
-            stock: p.stock as i64,
+            stock: p.stock,
+            unidad_venta: p.unidad_venta,
         })
         .collect();
 
@@ -69,8 +112,8 @@ pub fn get_inventory_rows_raw() -> Result<Vec<ProductRowData>, Box<dyn std::erro
         .map(|p| ProductRowData {
             codigo: p.codigo.clone().unwrap_or_else(|| "S/C".into()),
             nombre: p.nombre,
-            precio_venta: format!("{:.2}", p.precio_venta),
-            stock: p.stock.to_string(),
+            precio_venta: tasas::precio_con_moneda(p.precio_venta, &p.moneda),
+            stock: Medida::from_i32(p.unidad_venta).formatear(p.stock),
             marca_nombre: p.marca_nombre.unwrap_or_else(|| "Sin Marca".into()),
             activo: p.activo,
         })
@@ -99,46 +142,6 @@ pub fn raw_to_model_rows(rows: Vec<ProductRowData>) -> ModelRc<ModelRc<StandardL
     ModelRc::from(Rc::new(VecModel::from(model_rows)))
 }
 
-/// Obtiene los productos de la DB y los formatea para la tabla de Slint
-pub fn get_inventory_rows() -> Result<ModelRc<ModelRc<StandardListViewItem>>, Box<dyn std::error::Error>> {
-    let conn = db::open_connection()?;
-    let productos = db::productos::obtener_productos_con_marca(&conn)?;
-
-    // 1. Actualizar caché interno
-    let product_infos: Vec<ProductInfo> = productos
-        .iter()
-        .map(|p| ProductInfo {
-            id: p.id,
-            nombre: p.nombre.clone(),
-            precio_venta: p.precio_venta,
-            stock: p.stock as i64,
-        })
-        .collect();
-
-    {
-        let mut cache = get_cache().lock().unwrap();
-        *cache = product_infos;
-    }
-
-    // 2. Formatear filas para la UI (Código, Nombre, Precio, Stock, Marca, Estado)
-    let rows: Vec<ModelRc<StandardListViewItem>> = productos
-        .into_iter()
-        .map(|p| {
-            let row_data = vec![
-                StandardListViewItem::from(SharedString::from(p.codigo.clone().unwrap_or_else(|| "S/C".into()))),
-                StandardListViewItem::from(SharedString::from(p.nombre)),
-                StandardListViewItem::from(SharedString::from(format!("{:.2}", p.precio_venta))),
-                StandardListViewItem::from(SharedString::from(p.stock.to_string())),
-                StandardListViewItem::from(SharedString::from(p.marca_nombre.unwrap_or_else(|| "Sin Marca".into()))),
-                StandardListViewItem::from(SharedString::from(if p.activo { "true" } else { "false" })),
-            ];
-            ModelRc::from(Rc::new(VecModel::from(row_data)))
-        })
-        .collect();
-
-    Ok(ModelRc::from(Rc::new(VecModel::from(rows))))
-}
-
 /// Recupera la información de un producto por su índice en la tabla
 pub fn get_product_by_index(index: i32) -> Option<ProductInfo> {
     let cache = get_cache().lock().unwrap();
@@ -148,58 +151,88 @@ pub fn get_product_by_index(index: i32) -> Option<ProductInfo> {
 /// Elimina un producto usando el índice de la UI
 pub fn delete_product_by_index(index: i32) -> Result<bool, Box<dyn std::error::Error>> {
     if let Some(product) = get_product_by_index(index) {
-        let conn = db::open_connection()?;
-        let deleted = db::productos::eliminar_producto(&conn, product.id)?;
-        Ok(deleted)
+        let conn = db::obtener_conexion()?;
+        match db::productos::eliminar_producto(&conn, product.id) {
+            Ok(deleted) => Ok(deleted),
+            // Las claves foráneas ahora se aplican en todas las conexiones
+            Err(rusqlite::Error::SqliteFailure(e, _))
+                if e.code == rusqlite::ErrorCode::ConstraintViolation =>
+            {
+                let historial = db::productos::historial_producto(&conn, product.id)?;
+                Err(format!(
+                    "No se puede eliminar '{}': tiene registros de {}. Márquelo como inactivo.",
+                    product.nombre,
+                    if historial.is_empty() { "otras tablas".to_string() } else { historial.join(", ") }
+                )
+                .into())
+            }
+            Err(e) => Err(e.into()),
+        }
     } else {
         Ok(false)
     }
 }
 
 /// Agrega un nuevo producto con la lógica de Enums (IDs) y Doble Medida
-pub fn add_product(
-    nombre: SharedString,
-    precio_neto: SharedString,
-    precio_venta: SharedString,
-    stock: SharedString,
-    descripcion: SharedString,
-    codigo: SharedString,
-    activo_str: SharedString,
-    marca_id: SharedString,
-    // Nuevos campos de Enums y Medidas
-    medida_p_id: SharedString,
-    cantidad_p: SharedString,
-    medida_s_id: SharedString,
-    cantidad_s: SharedString,
-    empaque_id: SharedString,
-    // Categoría y subcategoría
-    categoria_id: SharedString,
-    subcategoria_id: SharedString,
-) -> Result<i64, Box<dyn std::error::Error>> {
-    let conn = db::open_connection()?;
-    let activo = activo_str == "true";
+pub fn add_product(form: &ProductoForm) -> Result<i64, Box<dyn std::error::Error>> {
+    let mut conn = db::obtener_conexion()?;
 
     let p_nuevo = ProductoNuevo {
-        nombre: nombre.into(),
-        precio_neto: parse_num(&precio_neto, 0.0),
-        precio_venta: parse_num(&precio_venta, 0.0),
-        stock: parse_num(&stock, 0),
-        descripcion: (!descripcion.is_empty()).then(|| descripcion.into()),
-        codigo: (!codigo.is_empty()).then(|| codigo.into()),
-        activo,
-        marca_id: (!marca_id.is_empty()).then(|| marca_id.parse().ok()).flatten(),
+        nombre: form.nombre.clone(),
+        precio_neto: parse_num(&form.precio_neto, 0.0),
+        precio_venta: parse_num(&form.precio_venta, 0.0),
+        stock: redondear_cantidad(parse_num(&form.stock, 0.0)),
+        descripcion: (!form.descripcion.is_empty()).then(|| form.descripcion.clone()),
+        codigo: (!form.codigo.is_empty()).then(|| form.codigo.clone()),
+        activo: form.activo,
+        marca_id: (!form.marca_id.is_empty()).then(|| form.marca_id.parse().ok()).flatten(),
         // Mapeo de Enums y Medidas
-        medida_p_id: parse_num(&medida_p_id, 1), // Default a 1 (Unidad)
-        cantidad_p: parse_num(&cantidad_p, 0.0),
-        medida_s_id: (!medida_s_id.is_empty()).then(|| medida_s_id.parse().ok()).flatten(),
-        cantidad_s: (!cantidad_s.is_empty()).then(|| cantidad_s.parse().ok()).flatten(),
-        empaque_id: parse_num(&empaque_id, 1),   // Default a 1 (Individual)
+        medida_p_id: parse_num(&form.medida_p_id, 1), // Default a 1 (Unidad)
+        cantidad_p: parse_num(&form.cantidad_p, 0.0),
+        medida_s_id: (!form.medida_s_id.is_empty()).then(|| form.medida_s_id.parse().ok()).flatten(),
+        cantidad_s: (!form.cantidad_s.is_empty()).then(|| form.cantidad_s.parse().ok()).flatten(),
+        empaque_id: parse_num(&form.empaque_id, 1),   // Default a 1 (Individual)
         // Categoría y subcategoría
-        categoria_id: parse_num(&categoria_id, 1),
-        subcategoria_id: parse_num(&subcategoria_id, 1),
+        categoria_id: parse_num(&form.categoria_id, 1),
+        subcategoria_id: (!form.subcategoria_id.is_empty()).then(|| form.subcategoria_id.parse().ok()).flatten(),
+        moneda: moneda_o_base(&form.moneda),
+        impuesto_id: form.impuesto_id.parse().ok(),
+        stock_minimo: redondear_cantidad(parse_num(&form.stock_minimo, 0.0)),
+        punto_reorden: redondear_cantidad(parse_num(&form.punto_reorden, 0.0)),
+        unidad_venta: parse_num(&form.unidad_venta, Medida::Unidad as i32),
     };
 
-    Ok(db::productos::crear_producto(&conn, &p_nuevo)?)
+    // El alta y su stock inicial se guardan juntos (o ninguno)
+    let tx = conn.transaction()?;
+    let id = db::productos::crear_producto(&tx, &p_nuevo)?;
+    if p_nuevo.stock != 0.0 {
+        db::movimientos::registrar_movimiento(
+            &tx,
+            id,
+            p_nuevo.stock,
+            MotivoMovimiento::Inicial,
+            usuario_actual_id(),
+            None,
+            // El stock inicial entra al precio neto del formulario
+            Some(p_nuevo.precio_neto),
+        )?;
+    }
+    tx.commit()?;
+    Ok(id)
+}
+
+/// Código de moneda del formulario; vacío = moneda base
+fn moneda_o_base(moneda: &str) -> String {
+    if moneda.trim().is_empty() {
+        Moneda::BASE.to_string()
+    } else {
+        moneda.trim().to_string()
+    }
+}
+
+/// Id del usuario en sesión, para firmar los movimientos de stock
+fn usuario_actual_id() -> Option<i64> {
+    session::usuario_actual().map(|u| u.id)
 }
 
 /// Resultado de validación de SKU
@@ -263,18 +296,23 @@ pub fn validar_formato_sku(sku: &str) -> SkuValidationResult {
     }
 }
 
-/// Verifica si un SKU ya existe en la base de datos
-pub fn sku_existe(sku: &str) -> Result<bool, Box<dyn std::error::Error>> {
+/// Verifica si un SKU ya existe en la base de datos.
+/// Si se indica `excluir_id`, el producto con ese ID no cuenta como duplicado (modo edición).
+pub fn sku_existe(sku: &str, excluir_id: Option<i64>) -> Result<bool, Box<dyn std::error::Error>> {
     if sku.trim().is_empty() {
         return Ok(false);
     }
     
-    let conn = db::open_connection()?;
-    Ok(db::productos::existe_sku(&conn, sku)?)
+    let conn = db::obtener_conexion()?;
+    match excluir_id {
+        Some(id) => Ok(db::productos::existe_sku_excluyendo(&conn, sku, id)?),
+        None => Ok(db::productos::existe_sku(&conn, sku)?),
+    }
 }
 
 /// Validación completa de SKU: formato + unicidad
-pub fn validar_sku_completo(sku: &str) -> SkuValidationResult {
+/// En edición se pasa el ID del producto para excluir su propio SKU de la verificación
+pub fn validar_sku_completo(sku: &str, excluir_id: Option<i64>) -> SkuValidationResult {
     // 1. Validar formato
     let resultado_formato = validar_formato_sku(sku);
     if !resultado_formato.es_valido {
@@ -283,7 +321,7 @@ pub fn validar_sku_completo(sku: &str) -> SkuValidationResult {
     
     // 2. Validar unicidad en BD
     if !sku.trim().is_empty() {
-        match sku_existe(sku) {
+        match sku_existe(sku, excluir_id) {
             Ok(existe) => {
                 if existe {
                     return SkuValidationResult {
@@ -307,48 +345,209 @@ pub fn validar_sku_completo(sku: &str) -> SkuValidationResult {
     }
 }
 
+/// Validación financiera de costo y precio de venta (compartida entre alta y edición)
+/// El costo está en la moneda base; un precio en otra moneda se compara convertido a la tasa del día.
+/// Retorna el mensaje de error para la UI si los valores no son aceptables
+pub fn validar_precios(p_neto: &str, p_venta: &str, moneda: &str) -> Result<(), String> {
+    // Parseo seguro de valores numéricos
+    let neto: f64 = p_neto.trim().parse().unwrap_or(-1.0);
+    let mut venta: f64 = p_venta.trim().parse().unwrap_or(-1.0);
+
+    if moneda != Moneda::BASE && venta > 0.0 {
+        let tasa = tasas::tasa_del_dia(moneda)
+            .map_err(|e| format!("Error al leer la tasa de cambio: {}", e))?
+            .ok_or_else(|| format!("Registre la tasa de cambio de {} antes de fijar precios en esa moneda", moneda))?;
+        venta /= tasa;
+    }
+
+    // Validar que los precios sean positivos
+    if neto <= 0.0 {
+        return Err("El costo neto debe ser un número positivo mayor a 0".to_string());
+    }
+
+    if venta <= 0.0 {
+        return Err("El precio de venta debe ser un número positivo mayor a 0".to_string());
+    }
+
+    // Validar que no haya pérdida (precio de venta >= costo)
+    if venta < neto {
+        return Err(format!(
+            "¡Pérdida detectada! El precio de venta (${:.2}) es menor al costo (${:.2})",
+            venta, neto
+        ));
+    }
+
+    // Advertencia si el margen es muy bajo (menos del 10%)
+    let margen_porcentaje = ((venta - neto) / neto) * 100.0;
+    if margen_porcentaje < 10.0 && margen_porcentaje > 0.0 {
+        println!("⚠ Advertencia: Margen de ganancia bajo ({:.1}%)", margen_porcentaje);
+        // No bloqueamos el guardado, solo mostramos advertencia en consola
+    }
+
+    Ok(())
+}
+
+/// Validación del stock, el stock mínimo y el punto de reorden (compartida entre alta y edición).
+/// Se expresan en la medida de venta y solo admiten decimales si esta es fraccionable.
+/// Vacíos valen 0 (sin alerta); si se indica un punto de reorden no puede quedar bajo el mínimo.
+pub fn validar_niveles_stock(
+    stock: &str,
+    stock_minimo: &str,
+    punto_reorden: &str,
+    unidad_venta: Medida,
+) -> Result<(), String> {
+    let nivel = |texto: &str, campo: &str| -> Result<f64, String> {
+        let texto = texto.trim();
+        if texto.is_empty() {
+            return Ok(0.0);
+        }
+        match texto.parse::<f64>().map(redondear_cantidad) {
+            Ok(n) if n.is_finite() && n >= 0.0 && (unidad_venta.fraccionable() || n.fract() == 0.0) => Ok(n),
+            Ok(n) if n.is_finite() && n >= 0.0 => Err(format!(
+                "El {} debe ser entero: el producto se vende por unidades",
+                campo
+            )),
+            _ => Err(format!("El {} debe ser un número no negativo", campo)),
+        }
+    };
+    nivel(stock, "stock")?;
+    let minimo = nivel(stock_minimo, "stock mínimo")?;
+    let reorden = nivel(punto_reorden, "punto de reorden")?;
+    if reorden > 0.0 && reorden < minimo {
+        return Err(format!(
+            "El punto de reorden ({}) no puede ser menor que el stock mínimo ({})",
+            formatear_cantidad(reorden),
+            formatear_cantidad(minimo)
+        ));
+    }
+    Ok(())
+}
+
+/// Productos con alerta de existencias y sus ventas recientes, para el dashboard (Send-safe)
+pub fn stock_bajo() -> Result<Vec<StockBajo>, Box<dyn std::error::Error>> {
+    let conn = db::obtener_conexion()?;
+    Ok(db::productos::obtener_stock_bajo(&conn, DIAS_VENTAS_RECIENTES)?)
+}
+
+/// Convierte las alertas de existencias a filas de tabla
+/// (Código, Producto, Stock, Mínimo, Reorden, Vendidas, Sugerido, Estado)
+pub fn stock_bajo_to_model_rows(productos: &[StockBajo]) -> ModelRc<ModelRc<StandardListViewItem>> {
+    let rows: Vec<ModelRc<StandardListViewItem>> = productos
+        .iter()
+        .map(|p| {
+            let medida = Medida::from_i32(p.unidad_venta);
+            let row_data = vec![
+                StandardListViewItem::from(SharedString::from(p.codigo.clone().unwrap_or_else(|| "S/C".into()))),
+                StandardListViewItem::from(SharedString::from(p.nombre.as_str())),
+                StandardListViewItem::from(SharedString::from(medida.formatear(p.stock))),
+                StandardListViewItem::from(SharedString::from(medida.formatear(p.stock_minimo))),
+                StandardListViewItem::from(SharedString::from(medida.formatear(p.punto_reorden))),
+                StandardListViewItem::from(SharedString::from(medida.formatear(p.vendidas))),
+                StandardListViewItem::from(SharedString::from(medida.formatear(p.cantidad_sugerida()))),
+                StandardListViewItem::from(SharedString::from(if p.bajo_minimo() { "BAJO MÍNIMO" } else { "REORDENAR" })),
+            ];
+            ModelRc::from(Rc::new(VecModel::from(row_data)))
+        })
+        .collect();
+
+    ModelRc::from(Rc::new(VecModel::from(rows)))
+}
+
+/// Carga el producto completo (todas las columnas) a partir del índice de la tabla
+/// y recuerda el stock que se muestra en el formulario
+pub fn get_product_for_edit(index: i32) -> Result<Option<DbProducto>, Box<dyn std::error::Error>> {
+    if let Some(product) = get_product_by_index(index) {
+        let conn = db::obtener_conexion()?;
+        let producto = db::productos::obtener_producto_por_id(&conn, product.id)?;
+        *get_stock_en_edicion().lock().unwrap() = producto.as_ref().map(|p| (p.id, p.stock));
+        Ok(producto)
+    } else {
+        Ok(None)
+    }
+}
+
+/// Indica si los precios del formulario (o la moneda del precio) difieren de los guardados
+/// para el producto (si no se puede leer el producto se asume que sí cambiaron)
+pub fn precios_modificados(id: i64, p_neto: &str, p_venta: &str, moneda: &str) -> bool {
+    let neto: f64 = p_neto.trim().parse().unwrap_or(0.0);
+    let venta: f64 = p_venta.trim().parse().unwrap_or(0.0);
+
+    let actual = db::obtener_conexion()
+        .ok()
+        .and_then(|conn| db::productos::obtener_producto_por_id(&conn, id).ok().flatten());
+
+    match actual {
+        Some(p) => {
+            (p.precio_neto - neto).abs() > 0.001 || (p.precio_venta - venta).abs() > 0.001 || p.moneda != moneda
+        }
+        None => true,
+    }
+}
+
 /// Actualiza un producto existente
-pub fn update_product(
-    id: i64,
-    nombre: SharedString,
-    precio_neto: SharedString,
-    precio_venta: SharedString,
-    stock: SharedString,
-    descripcion: SharedString,
-    codigo: SharedString,
-    activo: bool,
-    marca_id: SharedString,
-    // Nuevos campos de Enums y Medidas
-    medida_p_id: SharedString,
-    cantidad_p: SharedString,
-    medida_s_id: SharedString,
-    cantidad_s: SharedString,
-    empaque_id: SharedString,
-    // Categoría y subcategoría
-    categoria_id: SharedString,
-    subcategoria_id: SharedString,
-) -> Result<bool, Box<dyn std::error::Error>> {
-    let conn = db::open_connection()?;
+pub fn update_product(id: i64, form: &ProductoForm) -> Result<bool, Box<dyn std::error::Error>> {
+    let mut conn = db::obtener_conexion()?;
 
-    let _p_editado = DbProducto {
+    let p_editado = DbProducto {
         id,
-        nombre: nombre.into(),
-        precio_neto: parse_num(&precio_neto, 0.0),
-        precio_venta: parse_num(&precio_venta, 0.0),
-        stock: parse_num(&stock, 0),
-        descripcion: (!descripcion.is_empty()).then(|| descripcion.into()),
-        codigo: (!codigo.is_empty()).then(|| codigo.into()),
-        activo,
-        marca_id: (!marca_id.is_empty()).then(|| marca_id.parse().ok()).flatten(),
-        medida_p_id: parse_num(&medida_p_id, 1),
-        cantidad_p: parse_num(&cantidad_p, 0.0),
-        medida_s_id: (!medida_s_id.is_empty()).then(|| medida_s_id.parse().ok()).flatten(),
-        cantidad_s: (!cantidad_s.is_empty()).then(|| cantidad_s.parse().ok()).flatten(),
-        empaque_id: parse_num(&empaque_id, 1),
-        categoria_id: parse_num(&categoria_id, 1),
-        subcategoria_id: parse_num(&subcategoria_id, 1),
+        nombre: form.nombre.clone(),
+        precio_neto: parse_num(&form.precio_neto, 0.0),
+        precio_venta: parse_num(&form.precio_venta, 0.0),
+        stock: redondear_cantidad(parse_num(&form.stock, 0.0)),
+        descripcion: (!form.descripcion.is_empty()).then(|| form.descripcion.clone()),
+        codigo: (!form.codigo.is_empty()).then(|| form.codigo.clone()),
+        activo: form.activo,
+        marca_id: (!form.marca_id.is_empty()).then(|| form.marca_id.parse().ok()).flatten(),
+        medida_p_id: parse_num(&form.medida_p_id, 1),
+        cantidad_p: parse_num(&form.cantidad_p, 0.0),
+        medida_s_id: (!form.medida_s_id.is_empty()).then(|| form.medida_s_id.parse().ok()).flatten(),
+        cantidad_s: (!form.cantidad_s.is_empty()).then(|| form.cantidad_s.parse().ok()).flatten(),
+        empaque_id: parse_num(&form.empaque_id, 1),
+        categoria_id: parse_num(&form.categoria_id, 1),
+        subcategoria_id: (!form.subcategoria_id.is_empty()).then(|| form.subcategoria_id.parse().ok()).flatten(),
+        moneda: moneda_o_base(&form.moneda),
+        impuesto_id: form.impuesto_id.parse().ok(),
+        stock_minimo: redondear_cantidad(parse_num(&form.stock_minimo, 0.0)),
+        punto_reorden: redondear_cantidad(parse_num(&form.punto_reorden, 0.0)),
+        unidad_venta: parse_num(&form.unidad_venta, Medida::Unidad as i32),
     };
 
-    // Aquí llamarías a db::productos::actualizar_producto(&conn, &_p_editado)
+    let tx = conn.transaction()?;
+    let Some(actual) = db::productos::obtener_producto_por_id(&tx, id)? else {
+        return Ok(false);
+    };
+    // Las cantidades guardadas están en la medida anterior: cambiarla las reinterpretaría sin convertir
+    if p_editado.unidad_venta != actual.unidad_venta && db::productos::tiene_cantidades_registradas(&tx, id)? {
+        return Err(format!(
+            "No se puede cambiar la unidad de venta de '{}': ya tiene stock, lotes o movimientos en {}",
+            actual.nombre,
+            Medida::from_i32(actual.unidad_venta).info().0.to_lowercase()
+        )
+        .into());
+    }
+    db::productos::actualizar_producto(&tx, &p_editado)?;
+
+    // Un stock distinto al que se cargó en el formulario se registra como ajuste en el kardex;
+    // así las ventas o compras hechas mientras se editaba no se deshacen al guardar
+    let cargado = match *get_stock_en_edicion().lock().unwrap() {
+        Some((editado_id, stock)) if editado_id == id => stock,
+        _ => actual.stock,
+    };
+    let diferencia = redondear_cantidad(p_editado.stock - cargado);
+    if diferencia != 0.0 {
+        db::movimientos::registrar_movimiento(
+            &tx,
+            id,
+            diferencia,
+            MotivoMovimiento::Ajuste,
+            usuario_actual_id(),
+            Some("Edición de producto"),
+            None,
+        )?;
+        // Si el ajuste dejó menos stock que lo asignado a lotes, salen de los que vencen antes
+        db::lotes::ajustar_a_stock(&tx, id)?;
+    }
+    tx.commit()?;
+    *get_stock_en_edicion().lock().unwrap() = None;
     Ok(true)
 }
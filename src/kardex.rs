@@ -0,0 +1,52 @@
+use crate::db;
+use crate::inventory::{self, ProductInfo};
+use crate::models::medida::formatear_cantidad;
+use crate::models::MovimientoStock;
+use slint::{ModelRc, SharedString, StandardListViewItem, VecModel};
+use std::rc::Rc;
+
+/// Producto con sus movimientos de stock, del más antiguo al más reciente
+#[derive(Debug, Clone)]
+pub struct KardexProducto {
+    pub producto: ProductInfo,
+    pub movimientos: Vec<MovimientoStock>,
+}
+
+/// Producto de la fila indicada en el inventario junto con su kardex
+pub fn cargar(index: i32) -> Result<Option<KardexProducto>, Box<dyn std::error::Error>> {
+    let Some(producto) = inventory::get_product_by_index(index) else {
+        return Ok(None);
+    };
+
+    let conn = db::obtener_conexion()?;
+    let movimientos = db::movimientos::obtener_kardex(&conn, producto.id)?;
+    Ok(Some(KardexProducto { producto, movimientos }))
+}
+
+/// Convierte el kardex a filas de tabla (Fecha, Motivo, Entrada, Salida, Saldo, Costo, Costo prom., Usuario, Referencia)
+pub fn to_model_rows(movimientos: &[MovimientoStock]) -> ModelRc<ModelRc<StandardListViewItem>> {
+    let rows: Vec<ModelRc<StandardListViewItem>> = movimientos
+        .iter()
+        .map(|m| {
+            let (entrada, salida) = if m.cantidad >= 0.0 {
+                (formatear_cantidad(m.cantidad), String::new())
+            } else {
+                (String::new(), formatear_cantidad(-m.cantidad))
+            };
+            let row_data = vec![
+                StandardListViewItem::from(SharedString::from(m.fecha.format("%d/%m/%Y %H:%M").to_string())),
+                StandardListViewItem::from(SharedString::from(m.motivo.etiqueta())),
+                StandardListViewItem::from(SharedString::from(entrada)),
+                StandardListViewItem::from(SharedString::from(salida)),
+                StandardListViewItem::from(SharedString::from(formatear_cantidad(m.stock_resultante))),
+                StandardListViewItem::from(SharedString::from(format!("{:.2}", m.costo_unitario))),
+                StandardListViewItem::from(SharedString::from(format!("{:.2}", m.costo_promedio))),
+                StandardListViewItem::from(SharedString::from(m.usuario_nombre.clone().unwrap_or_else(|| "-".into()))),
+                StandardListViewItem::from(SharedString::from(m.referencia.clone().unwrap_or_default())),
+            ];
+            ModelRc::from(Rc::new(VecModel::from(row_data)))
+        })
+        .collect();
+
+    ModelRc::from(Rc::new(VecModel::from(rows)))
+}
@@ -1,8 +1,25 @@
 // 1. Declaración de módulos internos
+mod caja;
+mod categorias;
+mod clientes;
+mod compras;
+mod credito;
 mod db;
+mod historial_ventas;
+mod impuestos;
 mod inventory;
+mod kardex;
+mod lotes;
+mod marcas;
 mod models;
+mod pos;
+mod proveedores;
+mod recibo;
+mod session;
+mod tasas;
 mod ui_handlers;
+mod usuarios;
+mod valoracion;
 
 // 2. Importaciones de Slint y estándares
 slint::include_modules!();
@@ -24,7 +41,10 @@ async fn main() {
         || cfg!(target_os = "macos");
 
     // 3. Inicialización de la persistencia (Base de Datos)
-    let conn = match db::open_connection() {
+    // Ubicación: --db <ruta> | BODEX_DB | directorio de datos de la plataforma (":memory:" para pruebas)
+    db::ubicacion::configurar(db::ubicacion::resolver(std::env::args().skip(1)));
+
+    let conn = match db::obtener_conexion() {
         Ok(c) => c,
         Err(e) => {
             eprintln!("Error crítico: No se pudo abrir la base de datos: {}", e);
@@ -36,7 +56,7 @@ async fn main() {
         eprintln!("Error crítico: No se pudo inicializar las tablas: {}", e);
         return;
     }
-    // Cerramos la conexión inicial; los handlers abrirán las suyas propias
+    // Devolvemos la conexión inicial al pool; los handlers piden las suyas con db::obtener_conexion()
     drop(conn);
 
     if has_display {
@@ -0,0 +1,139 @@
+use crate::db;
+use crate::inventory::{self, ProductInfo};
+use crate::models::medida::{self, formatear_cantidad};
+use crate::models::{Lote, LoteNuevo, LotePorVencer, Medida};
+use crate::tasas;
+use chrono::NaiveDate;
+use slint::{ModelRc, SharedString, StandardListViewItem, VecModel};
+use std::rc::Rc;
+
+/// Días del reporte de vencimientos si no se indican otros
+pub const DIAS_POR_VENCER: i64 = 30;
+
+/// Interpreta el lote de una entrada de mercancía. Sin número no hay lote;
+/// sin vencimiento el lote no vence.
+pub fn parse_lote(numero: &str, vencimiento: &str) -> Result<Option<LoteNuevo>, String> {
+    let numero = numero.trim();
+    if numero.is_empty() {
+        return Ok(None);
+    }
+    let vencimiento = vencimiento.trim();
+    let vencimiento = if vencimiento.is_empty() {
+        None
+    } else {
+        Some(
+            NaiveDate::parse_from_str(vencimiento, "%d/%m/%Y")
+                .map_err(|_| "El vencimiento debe tener el formato dd/mm/aaaa".to_string())?,
+        )
+    };
+    Ok(Some(LoteNuevo {
+        numero: numero.to_string(),
+        vencimiento,
+    }))
+}
+
+/// Producto con sus lotes y las unidades que no están en ningún lote
+#[derive(Debug, Clone)]
+pub struct LotesProducto {
+    pub producto: ProductInfo,
+    pub lotes: Vec<Lote>,
+    pub sin_lote: f64,
+}
+
+/// Producto de la fila indicada en el inventario con sus lotes y las unidades sin lote (Send-safe)
+pub fn cargar(index: i32) -> Result<Option<LotesProducto>, Box<dyn std::error::Error>> {
+    let Some(producto) = inventory::get_product_by_index(index) else {
+        return Ok(None);
+    };
+    let (lotes, sin_lote) = cargar_lotes(producto.id)?;
+    Ok(Some(LotesProducto { producto, lotes, sin_lote }))
+}
+
+/// Lotes del producto y sus unidades sin lote (Send-safe)
+pub fn cargar_lotes(producto_id: i64) -> Result<(Vec<Lote>, f64), Box<dyn std::error::Error>> {
+    let conn = db::obtener_conexion()?;
+    let lotes = db::lotes::obtener_lotes(&conn, producto_id)?;
+    let sin_lote = db::lotes::stock_sin_lote(&conn, producto_id)?;
+    Ok((lotes, sin_lote))
+}
+
+/// Asigna a un lote unidades del stock sin lote del producto (en su medida de venta)
+pub fn asignar(producto_id: i64, numero: &str, vencimiento: &str, cantidad: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let lote = parse_lote(numero, vencimiento)?.ok_or("El número de lote es requerido")?;
+
+    let mut conn = db::obtener_conexion()?;
+    let producto = db::productos::obtener_producto_por_id(&conn, producto_id)?.ok_or("El producto ya no existe")?;
+    let cantidad = medida::parse_cantidad(cantidad, Medida::from_i32(producto.unidad_venta))?;
+    db::lotes::asignar_stock(&mut conn, producto_id, &lote, cantidad)?;
+    Ok(())
+}
+
+/// Lotes con unidades que vencen dentro de los próximos `dias` (vacío = 30), incluidos los
+/// ya vencidos (Send-safe)
+pub fn por_vencer(dias: &str) -> Result<Vec<LotePorVencer>, Box<dyn std::error::Error>> {
+    let dias: i64 = if dias.trim().is_empty() {
+        DIAS_POR_VENCER
+    } else {
+        dias.trim().parse().map_err(|_| "Los días deben ser un número entero")?
+    };
+    if dias < 0 {
+        return Err("Los días no pueden ser negativos".into());
+    }
+
+    let conn = db::obtener_conexion()?;
+    Ok(db::lotes::por_vencer(&conn, tasas::hoy(), dias)?)
+}
+
+/// Vencimiento y días restantes como se muestran en las tablas
+fn vencimiento_y_dias(lote: &Lote, hoy: NaiveDate) -> (String, String) {
+    match (lote.vencimiento, lote.dias_para_vencer(hoy)) {
+        (Some(v), Some(dias)) => (
+            v.format("%d/%m/%Y").to_string(),
+            if dias < 0 { format!("VENCIDO ({})", -dias) } else { dias.to_string() },
+        ),
+        _ => ("No vence".to_string(), "-".to_string()),
+    }
+}
+
+/// Convierte los lotes del producto a filas de tabla (Lote, Vence, Días, Cantidad, Ingreso)
+pub fn lotes_to_model_rows(lotes: &[Lote]) -> ModelRc<ModelRc<StandardListViewItem>> {
+    let hoy = tasas::hoy();
+    let rows: Vec<ModelRc<StandardListViewItem>> = lotes
+        .iter()
+        .map(|l| {
+            let (vence, dias) = vencimiento_y_dias(l, hoy);
+            let row_data = vec![
+                StandardListViewItem::from(SharedString::from(l.numero.as_str())),
+                StandardListViewItem::from(SharedString::from(vence)),
+                StandardListViewItem::from(SharedString::from(dias)),
+                StandardListViewItem::from(SharedString::from(formatear_cantidad(l.cantidad))),
+                StandardListViewItem::from(SharedString::from(l.ingreso.format("%d/%m/%Y").to_string())),
+            ];
+            ModelRc::from(Rc::new(VecModel::from(row_data)))
+        })
+        .collect();
+
+    ModelRc::from(Rc::new(VecModel::from(rows)))
+}
+
+/// Convierte el reporte de vencimientos a filas de tabla (Código, Producto, Lote, Vence, Días, Cantidad)
+pub fn por_vencer_to_model_rows(lotes: &[LotePorVencer]) -> ModelRc<ModelRc<StandardListViewItem>> {
+    let hoy = tasas::hoy();
+    let rows: Vec<ModelRc<StandardListViewItem>> = lotes
+        .iter()
+        .map(|l| {
+            let (vence, dias) = vencimiento_y_dias(&l.lote, hoy);
+            let row_data = vec![
+                StandardListViewItem::from(SharedString::from(l.producto_codigo.clone().unwrap_or_else(|| "S/C".into()))),
+                StandardListViewItem::from(SharedString::from(l.producto_nombre.as_str())),
+                StandardListViewItem::from(SharedString::from(l.lote.numero.as_str())),
+                StandardListViewItem::from(SharedString::from(vence)),
+                StandardListViewItem::from(SharedString::from(dias)),
+                StandardListViewItem::from(SharedString::from(formatear_cantidad(l.lote.cantidad))),
+            ];
+            ModelRc::from(Rc::new(VecModel::from(row_data)))
+        })
+        .collect();
+
+    ModelRc::from(Rc::new(VecModel::from(rows)))
+}
@@ -0,0 +1,158 @@
+use crate::db;
+use crate::models::{Marca, MarcaConProductos, MarcaNueva};
+use slint::{ModelRc, SharedString, StandardListViewItem, VecModel};
+use std::rc::Rc;
+use std::sync::{Mutex, OnceLock};
+
+/// Opción del selector de marca para productos sin marca (marca_id = NULL)
+const SIN_MARCA: &str = "Sin marca";
+
+/// Caché de marcas en el orden mostrado (tabla de gestión y selectores)
+static MARCAS: OnceLock<Mutex<Vec<MarcaConProductos>>> = OnceLock::new();
+
+fn get_cache() -> &'static Mutex<Vec<MarcaConProductos>> {
+    MARCAS.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+/// Lee las marcas con su conteo de productos y actualiza la caché (Send-safe)
+pub fn cargar() -> Result<Vec<MarcaConProductos>, Box<dyn std::error::Error>> {
+    let conn = db::obtener_conexion()?;
+    let marcas = db::marcas::obtener_marcas_con_conteo(&conn)?;
+    *get_cache().lock().unwrap() = marcas.clone();
+    Ok(marcas)
+}
+
+/// Marca de la fila indicada en la tabla de gestión
+pub fn get_marca_by_index(index: i32) -> Option<MarcaConProductos> {
+    get_cache().lock().unwrap().get(index as usize).cloned()
+}
+
+/// Nombres para el selector de marca del formulario de producto.
+/// La primera opción es "Sin marca"; el resto sigue el orden de la caché.
+pub fn nombres_para_select(marcas: &[MarcaConProductos]) -> ModelRc<SharedString> {
+    let nombres: Vec<SharedString> = std::iter::once(SharedString::from(SIN_MARCA))
+        .chain(marcas.iter().map(|m| SharedString::from(m.nombre.as_str())))
+        .collect();
+    ModelRc::from(Rc::new(VecModel::from(nombres)))
+}
+
+/// Índice del selector -> marca_id (None para "Sin marca" o índice inválido)
+pub fn id_para_select(index: i32) -> Option<i64> {
+    if index <= 0 {
+        return None;
+    }
+    get_cache()
+        .lock()
+        .unwrap()
+        .get(index as usize - 1)
+        .map(|m| m.id)
+}
+
+/// marca_id -> índice del selector (0 = "Sin marca")
+pub fn indice_para_select(marca_id: Option<i64>) -> i32 {
+    marca_id
+        .and_then(|id| get_cache().lock().unwrap().iter().position(|m| m.id == id))
+        .map(|i| i as i32 + 1)
+        .unwrap_or(0)
+}
+
+/// Valida nombre y RIF antes de guardar (requeridos/únicos)
+pub fn validar(nombre: &str, rif: &str, excluir_id: Option<i64>) -> Result<(), String> {
+    if nombre.trim().is_empty() {
+        return Err("El nombre de la marca es requerido".to_string());
+    }
+
+    let conn = db::obtener_conexion().map_err(|e| e.to_string())?;
+    if db::marcas::existe_nombre(&conn, nombre, excluir_id).map_err(|e| e.to_string())? {
+        return Err(format!("Ya existe una marca llamada '{}'", nombre.trim()));
+    }
+    if !rif.trim().is_empty()
+        && db::marcas::existe_rif(&conn, rif, excluir_id).map_err(|e| e.to_string())?
+    {
+        return Err(format!("El RIF '{}' ya está registrado en otra marca", rif.trim()));
+    }
+    Ok(())
+}
+
+/// Convierte un texto vacío en None
+fn opcional(valor: &str) -> Option<String> {
+    let valor = valor.trim();
+    (!valor.is_empty()).then(|| valor.to_string())
+}
+
+/// Crea (id = None) o actualiza una marca. Devuelve el id guardado.
+pub fn guardar(
+    id: Option<i64>,
+    nombre: &str,
+    rif: &str,
+    descripcion: &str,
+    logo: &str,
+) -> Result<i64, Box<dyn std::error::Error>> {
+    validar(nombre, rif, id)?;
+    let conn = db::obtener_conexion()?;
+
+    match id {
+        Some(id) => {
+            let marca = Marca {
+                id,
+                nombre: nombre.trim().to_string(),
+                descripcion: opcional(descripcion),
+                logo: opcional(logo),
+                rif: opcional(rif),
+            };
+            if !db::marcas::actualizar_marca(&conn, &marca)? {
+                return Err("La marca ya no existe".into());
+            }
+            Ok(id)
+        }
+        None => {
+            let marca = MarcaNueva {
+                nombre: nombre.trim().to_string(),
+                descripcion: opcional(descripcion),
+                logo: opcional(logo),
+                rif: opcional(rif),
+            };
+            Ok(db::marcas::crear_marca(&conn, &marca)?)
+        }
+    }
+}
+
+/// Texto de confirmación antes de eliminar: advierte cuántos productos quedarán sin marca
+pub fn advertencia_eliminar(marca: &MarcaConProductos) -> Result<String, Box<dyn std::error::Error>> {
+    let conn = db::obtener_conexion()?;
+    let total = db::marcas::contar_productos(&conn, marca.id)?;
+
+    Ok(if total == 0 {
+        format!("¿Eliminar la marca '{}'? No tiene productos asociados.", marca.nombre)
+    } else {
+        format!(
+            "¿Eliminar la marca '{}'? {} producto(s) quedarán sin marca (marca_id = NULL).",
+            marca.nombre, total
+        )
+    })
+}
+
+/// Elimina la marca (los productos asociados pasan a marca_id = NULL)
+pub fn eliminar(id: i64) -> Result<bool, Box<dyn std::error::Error>> {
+    let conn = db::obtener_conexion()?;
+    Ok(db::marcas::eliminar_marca(&conn, id)?)
+}
+
+/// Convierte las marcas a filas de tabla (Nombre, RIF, Descripción, Logo, Productos)
+pub fn to_model_rows(marcas: &[MarcaConProductos]) -> ModelRc<ModelRc<StandardListViewItem>> {
+    let rows: Vec<ModelRc<StandardListViewItem>> = marcas
+        .iter()
+        .map(|m| {
+            let row_data = vec![
+                StandardListViewItem::from(SharedString::from(m.nombre.as_str())),
+                StandardListViewItem::from(SharedString::from(m.rif.clone().unwrap_or_else(|| "-".into()))),
+                StandardListViewItem::from(SharedString::from(m.descripcion.clone().unwrap_or_default())),
+                StandardListViewItem::from(SharedString::from(m.logo.clone().unwrap_or_default())),
+                StandardListViewItem::from(SharedString::from(m.total_productos.to_string())),
+            ];
+            ModelRc::from(Rc::new(VecModel::from(row_data)))
+        })
+        .collect();
+
+    ModelRc::from(Rc::new(VecModel::from(rows)))
+}
@@ -0,0 +1,59 @@
+use super::MetodoPago;
+use chrono::NaiveDateTime;
+
+/// Estado de una sesión de caja. Una sesión cerrada queda bloqueada.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EstadoCaja {
+    Abierta,
+    Cerrada,
+}
+
+impl From<String> for EstadoCaja {
+    fn from(s: String) -> Self {
+        match s.as_str() {
+            "cerrada" => Self::Cerrada,
+            _ => Self::Abierta,
+        }
+    }
+}
+
+/// Sesión de caja (apertura con fondo inicial, ventas asociadas y cierre)
+#[derive(Debug, Clone)]
+pub struct SesionCaja {
+    pub id: i64,
+    pub usuario_nombre: Option<String>, // username del cajero (JOIN con usuarios)
+    pub apertura: NaiveDateTime,
+    pub cierre: Option<NaiveDateTime>,
+    pub fondo_inicial: f64,
+    pub estado: EstadoCaja,
+    pub notas: Option<String>,
+    pub ventas: i64,         // Ventas registradas en la sesión
+    pub total_esperado: f64, // Suma del arqueo (0 mientras está abierta)
+    pub total_contado: f64,
+}
+
+impl SesionCaja {
+    /// Sobrante (positivo) o faltante (negativo) del cierre
+    pub fn diferencia(&self) -> f64 {
+        redondear(self.total_contado - self.total_esperado)
+    }
+}
+
+/// Arqueo de un método de pago: lo que debería haber en caja frente a lo contado
+#[derive(Debug, Clone)]
+pub struct ConteoCaja {
+    pub metodo: MetodoPago,
+    pub esperado: f64,
+    pub contado: f64,
+}
+
+impl ConteoCaja {
+    pub fn diferencia(&self) -> f64 {
+        redondear(self.contado - self.esperado)
+    }
+}
+
+/// Redondea a céntimos (sin "-0.00" por errores de coma flotante)
+fn redondear(monto: f64) -> f64 {
+    (monto * 100.0).round() / 100.0 + 0.0
+}
@@ -0,0 +1,25 @@
+#[derive(Debug, Clone)]
+pub struct Categoria {
+    pub id: i64,
+    pub nombre: String,
+    pub descripcion: Option<String>,
+    pub impuesto_id: i64, // Tasa de IVA de sus productos
+}
+
+/// Categoría con totales para la vista de gestión
+#[derive(Debug, Clone)]
+pub struct CategoriaConConteo {
+    pub id: i64,
+    pub nombre: String,
+    pub descripcion: Option<String>,
+    pub total_subcategorias: i64,
+    pub total_productos: i64,
+    pub impuesto_id: i64,
+    pub porcentaje_impuesto: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Subcategoria {
+    pub id: i64,
+    pub nombre: String,
+}
@@ -0,0 +1,35 @@
+/// Cliente registrado. Las ventas lo referencian por `ventas.cliente_id`.
+#[derive(Debug, Clone)]
+pub struct Cliente {
+    pub id: i64,
+    pub documento: Option<String>, // Cédula o RIF; solo el consumidor final no lo tiene
+    pub nombre: String,
+    pub direccion: Option<String>,
+    pub telefono: Option<String>,
+    pub email: Option<String>,
+    pub limite_credito: f64, // Máximo que puede adeudar (0 = no compra a crédito)
+    pub saldo: f64,          // Lo que adeuda hoy (calculado, no se guarda)
+}
+
+impl Cliente {
+    /// Cliente de mostrador: el que recibe las ventas sin cliente identificado
+    pub const CONSUMIDOR_FINAL: i64 = 1;
+
+    /// Texto para el selector del punto de venta: "V-12345678 · José Pérez"
+    pub fn etiqueta(&self) -> String {
+        match self.documento.as_deref() {
+            Some(documento) => format!("{} · {}", documento, self.nombre),
+            None => self.nombre.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ClienteNuevo {
+    pub documento: Option<String>,
+    pub nombre: String,
+    pub direccion: Option<String>,
+    pub telefono: Option<String>,
+    pub email: Option<String>,
+    pub limite_credito: f64,
+}
@@ -0,0 +1,96 @@
+use super::medida::redondear_cantidad;
+use super::LoteNuevo;
+use chrono::NaiveDateTime;
+
+/// Estado de una orden de compra (se guarda como texto en `ordenes_compra.estado`)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EstadoOrden {
+    Pendiente,
+    Parcial,
+    Recibida,
+    Cancelada,
+}
+
+impl EstadoOrden {
+    /// Valor almacenado en la base de datos
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pendiente => "pendiente",
+            Self::Parcial => "parcial",
+            Self::Recibida => "recibida",
+            Self::Cancelada => "cancelada",
+        }
+    }
+
+    /// Texto para mostrar en la UI
+    pub fn etiqueta(&self) -> &'static str {
+        match self {
+            Self::Pendiente => "Pendiente",
+            Self::Parcial => "Recibida parcialmente",
+            Self::Recibida => "Recibida",
+            Self::Cancelada => "Cancelada",
+        }
+    }
+
+    /// Solo las órdenes pendientes o parciales admiten recepciones
+    pub fn abierta(&self) -> bool {
+        matches!(self, Self::Pendiente | Self::Parcial)
+    }
+}
+
+impl From<String> for EstadoOrden {
+    fn from(s: String) -> Self {
+        match s.as_str() {
+            "parcial" => Self::Parcial,
+            "recibida" => Self::Recibida,
+            "cancelada" => Self::Cancelada,
+            _ => Self::Pendiente,
+        }
+    }
+}
+
+/// Cabecera de una orden de compra con el nombre del proveedor y su total
+#[derive(Debug, Clone)]
+pub struct OrdenCompra {
+    pub id: i64,
+    pub proveedor_nombre: String,
+    pub fecha: NaiveDateTime,
+    pub estado: EstadoOrden,
+    pub total: f64, // Suma de cantidad_pedida * costo_unitario
+}
+
+/// Línea de una orden de compra
+#[derive(Debug, Clone)]
+pub struct LineaOrdenCompra {
+    pub id: i64,
+    pub producto_id: i64,
+    pub producto_nombre: String,
+    pub cantidad_pedida: f64,
+    pub cantidad_recibida: f64,
+    pub costo_unitario: f64, // Costo pactado en la orden
+    pub unidad_venta: i32,   // Medida en que se cuenta el producto
+}
+
+impl LineaOrdenCompra {
+    /// Unidades que faltan por recibir
+    pub fn pendiente(&self) -> f64 {
+        redondear_cantidad((self.cantidad_pedida - self.cantidad_recibida).max(0.0))
+    }
+}
+
+/// Línea para crear una orden de compra
+#[derive(Debug, Clone)]
+pub struct LineaOrdenNueva {
+    pub producto_id: i64,
+    pub cantidad: f64,
+    pub costo_unitario: f64,
+}
+
+/// Cantidad recibida de una línea, su costo real y el lote con el que entra (si se controla)
+#[derive(Debug, Clone)]
+pub struct LineaRecepcion {
+    pub linea_id: i64,
+    pub cantidad: f64,
+    pub costo_unitario: f64,
+    pub lote: Option<LoteNuevo>,
+}
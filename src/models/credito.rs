@@ -0,0 +1,102 @@
+use chrono::{NaiveDate, NaiveDateTime};
+
+/// Parte a crédito de una venta que sigue sin pagar (descontando lo devuelto a la cuenta)
+#[derive(Debug, Clone)]
+pub struct CargoCredito {
+    pub venta_id: i64,
+    pub fecha: NaiveDateTime,
+    pub monto: f64,
+}
+
+/// Renglón del estado de cuenta de un cliente, con el saldo acumulado tras el movimiento
+#[derive(Debug, Clone)]
+pub struct MovimientoCuenta {
+    pub fecha: NaiveDateTime,
+    pub concepto: String,
+    pub cargo: f64,
+    pub abono: f64,
+    pub saldo: f64,
+}
+
+/// Tramos del reporte de antigüedad, en días desde la venta
+pub const TRAMOS_ANTIGUEDAD: [&str; 4] = ["0-30", "31-60", "61-90", "90+"];
+
+/// Saldo de un cliente repartido por antigüedad (ver `TRAMOS_ANTIGUEDAD`)
+#[derive(Debug, Clone)]
+pub struct SaldoPorAntiguedad {
+    pub cliente_id: i64,
+    pub documento: Option<String>,
+    pub nombre: String,
+    pub limite_credito: f64,
+    pub tramos: [f64; 4],
+}
+
+impl SaldoPorAntiguedad {
+    /// Saldo total del cliente
+    pub fn total(&self) -> f64 {
+        self.tramos.iter().sum()
+    }
+}
+
+/// Reparte lo pendiente por antigüedad al día `hoy`. Los abonos se aplican primero a las
+/// ventas más antiguas, así que lo que queda pendiente es lo más reciente.
+pub fn repartir_por_antiguedad(cargos: &[CargoCredito], abonado: f64, hoy: NaiveDate) -> [f64; 4] {
+    let centimos = |monto: f64| (monto * 100.0).round() as i64;
+    let mut cargos: Vec<&CargoCredito> = cargos.iter().collect();
+    cargos.sort_by_key(|c| (c.fecha, c.venta_id));
+
+    let mut por_aplicar = centimos(abonado);
+    let mut tramos = [0_i64; 4];
+    for cargo in cargos {
+        let monto = centimos(cargo.monto);
+        let aplicado = por_aplicar.clamp(0, monto);
+        por_aplicar -= aplicado;
+
+        let dias = (hoy - cargo.fecha.date()).num_days();
+        let tramo = match dias {
+            ..=30 => 0,
+            31..=60 => 1,
+            61..=90 => 2,
+            _ => 3,
+        };
+        tramos[tramo] += monto - aplicado;
+    }
+    tramos.map(|t| t as f64 / 100.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cargo(venta_id: i64, fecha: NaiveDate, monto: f64) -> CargoCredito {
+        CargoCredito {
+            venta_id,
+            fecha: fecha.and_hms_opt(10, 0, 0).unwrap(),
+            monto,
+        }
+    }
+
+    #[test]
+    fn reparte_por_dias_desde_la_venta() {
+        let hoy = NaiveDate::from_ymd_opt(2025, 6, 30).unwrap();
+        let cargos = [
+            cargo(1, NaiveDate::from_ymd_opt(2025, 6, 30).unwrap(), 10.0),
+            cargo(2, NaiveDate::from_ymd_opt(2025, 5, 31).unwrap(), 20.0), // 30 días
+            cargo(3, NaiveDate::from_ymd_opt(2025, 5, 30).unwrap(), 30.0), // 31 días
+            cargo(4, NaiveDate::from_ymd_opt(2025, 4, 1).unwrap(), 40.0),  // 90 días
+            cargo(5, NaiveDate::from_ymd_opt(2024, 12, 1).unwrap(), 50.0),
+        ];
+        assert_eq!(repartir_por_antiguedad(&cargos, 0.0, hoy), [30.0, 30.0, 40.0, 50.0]);
+    }
+
+    #[test]
+    fn los_abonos_pagan_primero_lo_mas_antiguo() {
+        let hoy = NaiveDate::from_ymd_opt(2025, 6, 30).unwrap();
+        let cargos = [
+            cargo(2, NaiveDate::from_ymd_opt(2025, 6, 20).unwrap(), 25.5),
+            cargo(1, NaiveDate::from_ymd_opt(2025, 3, 1).unwrap(), 40.0),
+        ];
+        assert_eq!(repartir_por_antiguedad(&cargos, 50.25, hoy), [15.25, 0.0, 0.0, 0.0]);
+        assert_eq!(repartir_por_antiguedad(&cargos, 100.0, hoy), [0.0; 4]);
+    }
+}
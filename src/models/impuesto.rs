@@ -0,0 +1,124 @@
+/// Tasa de impuesto (IVA) registrada en `impuestos`.
+/// Cada categoría tiene una y el producto puede reemplazarla por otra.
+#[derive(Debug, Clone)]
+pub struct Impuesto {
+    pub id: i64,
+    pub nombre: String,
+    pub porcentaje: f64, // 16.0 = 16 %; 0 = exento
+}
+
+impl Impuesto {
+    /// Tasa que reciben las categorías nuevas y las existentes al migrar
+    pub const GENERAL: i64 = 1;
+
+    /// Texto para los selectores: "IVA General (16%)"
+    pub fn etiqueta(&self) -> String {
+        format!("{} ({})", self.nombre, etiqueta_porcentaje(self.porcentaje))
+    }
+}
+
+/// "16%", "8%" o "Exento"
+pub fn etiqueta_porcentaje(porcentaje: f64) -> String {
+    if porcentaje == 0.0 {
+        "Exento".to_string()
+    } else {
+        format!("{}%", porcentaje)
+    }
+}
+
+/// Cómo se interpreta `precio_venta` (se guarda en `configuracion`)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ModoPrecios {
+    /// El precio de venta ya trae el IVA: la base se despeja del total
+    IvaIncluido,
+    /// El precio de venta es la base: el IVA se suma al cobrar
+    MasIva,
+}
+
+impl ModoPrecios {
+    /// Todos los modos, en el orden del selector
+    pub const TODOS: [ModoPrecios; 2] = [Self::IvaIncluido, Self::MasIva];
+
+    /// Valor almacenado en la configuración
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::IvaIncluido => "incluido",
+            Self::MasIva => "mas_iva",
+        }
+    }
+
+    /// Texto para mostrar en la UI
+    pub fn etiqueta(&self) -> &'static str {
+        match self {
+            Self::IvaIncluido => "IVA incluido",
+            Self::MasIva => "Precios + IVA",
+        }
+    }
+}
+
+impl From<String> for ModoPrecios {
+    fn from(s: String) -> Self {
+        match s.as_str() {
+            "mas_iva" => Self::MasIva,
+            _ => Self::IvaIncluido,
+        }
+    }
+}
+
+/// Base, impuesto y total de una línea de venta, en céntimos exactos (base + impuesto = total)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DesgloseLinea {
+    pub base: f64,
+    pub impuesto: f64,
+    pub total: f64,
+}
+
+/// Desglosa `cantidad` (unidades o fracción de la medida de venta) a `precio_unitario` con el porcentaje de IVA indicado
+pub fn desglosar(precio_unitario: f64, cantidad: f64, porcentaje: f64, modo: ModoPrecios) -> DesgloseLinea {
+    let centimos = |monto: f64| (monto * 100.0).round() as i64;
+    let importe = centimos(precio_unitario * cantidad);
+    let (base, impuesto) = match modo {
+        ModoPrecios::IvaIncluido => {
+            let base = (importe as f64 / (1.0 + porcentaje / 100.0)).round() as i64;
+            (base, importe - base)
+        }
+        ModoPrecios::MasIva => (importe, (importe as f64 * porcentaje / 100.0).round() as i64),
+    };
+    DesgloseLinea {
+        base: base as f64 / 100.0,
+        impuesto: impuesto as f64 / 100.0,
+        total: (base + impuesto) as f64 / 100.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn iva_incluido_despeja_la_base() {
+        let d = desglosar(11.6, 1.0, 16.0, ModoPrecios::IvaIncluido);
+        assert_eq!(d, DesgloseLinea { base: 10.0, impuesto: 1.6, total: 11.6 });
+    }
+
+    #[test]
+    fn mas_iva_suma_el_impuesto() {
+        let d = desglosar(2.5, 3.0, 8.0, ModoPrecios::MasIva);
+        assert_eq!(d, DesgloseLinea { base: 7.5, impuesto: 0.6, total: 8.1 });
+    }
+
+    #[test]
+    fn exento_no_cambia_el_precio() {
+        for modo in [ModoPrecios::IvaIncluido, ModoPrecios::MasIva] {
+            let d = desglosar(1.99, 2.0, 0.0, modo);
+            assert_eq!(d, DesgloseLinea { base: 3.98, impuesto: 0.0, total: 3.98 });
+        }
+    }
+
+    #[test]
+    fn cantidad_por_peso_redondea_el_importe() {
+        // 0.755 kg a 12.99 el kilo = 9.80745 -> 9.81
+        let d = desglosar(12.99, 0.755, 0.0, ModoPrecios::IvaIncluido);
+        assert_eq!(d.total, 9.81);
+    }
+}
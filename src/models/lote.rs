@@ -0,0 +1,57 @@
+use chrono::{NaiveDate, NaiveDateTime};
+
+/// Lote de un producto. La suma de los lotes nunca supera `productos.stock`: lo que sobra es
+/// stock sin lote (el anterior al control por lotes o el que se recibió sin número de lote).
+#[derive(Debug, Clone)]
+pub struct Lote {
+    pub numero: String,
+    pub vencimiento: Option<NaiveDate>, // None = no vence
+    pub cantidad: f64,                  // Unidades que quedan del lote
+    pub ingreso: NaiveDateTime,
+}
+
+impl Lote {
+    /// Días que faltan para el vencimiento (negativos si ya venció; None si no vence)
+    pub fn dias_para_vencer(&self, hoy: NaiveDate) -> Option<i64> {
+        self.vencimiento.map(|v| (v - hoy).num_days())
+    }
+}
+
+/// Lote con los datos del producto, para el reporte de vencimientos
+#[derive(Debug, Clone)]
+pub struct LotePorVencer {
+    pub lote: Lote,
+    pub producto_codigo: Option<String>,
+    pub producto_nombre: String,
+}
+
+/// Lote con el que entra mercancía (al recibir una compra o al asignar stock sin lote)
+#[derive(Debug, Clone)]
+pub struct LoteNuevo {
+    pub numero: String,
+    pub vencimiento: Option<NaiveDate>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lote(vencimiento: Option<NaiveDate>) -> Lote {
+        Lote {
+            numero: "L-001".to_string(),
+            vencimiento,
+            cantidad: 10.0,
+            ingreso: NaiveDate::from_ymd_opt(2026, 1, 1).unwrap().and_hms_opt(0, 0, 0).unwrap(),
+        }
+    }
+
+    #[test]
+    fn cuenta_los_dias_hasta_el_vencimiento() {
+        let hoy = NaiveDate::from_ymd_opt(2026, 3, 10).unwrap();
+        assert_eq!(lote(NaiveDate::from_ymd_opt(2026, 3, 25)).dias_para_vencer(hoy), Some(15));
+        assert_eq!(lote(Some(hoy)).dias_para_vencer(hoy), Some(0));
+        // Ya vencido: días negativos
+        assert_eq!(lote(NaiveDate::from_ymd_opt(2026, 3, 8)).dias_para_vencer(hoy), Some(-2));
+        assert_eq!(lote(None).dias_para_vencer(hoy), None);
+    }
+}
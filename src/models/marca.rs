@@ -15,3 +15,14 @@ pub struct MarcaNueva {
     pub rif: Option<String>,
 }
 
+
+/// Marca con la cantidad de productos que la referencian (vista de gestión de marcas)
+#[derive(Debug, Clone)]
+pub struct MarcaConProductos {
+    pub id: i64,
+    pub nombre: String,
+    pub descripcion: Option<String>,
+    pub logo: Option<String>,
+    pub rif: Option<String>,
+    pub total_productos: i64,
+}
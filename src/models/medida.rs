@@ -8,6 +8,16 @@ pub enum Medida {
     Mililitro = 5,
 }
 
+/// Decimales con los que se guardan las cantidades (gramos y mililitros en kg y litros)
+pub const DECIMALES_CANTIDAD: i32 = 3;
+
+/// Tabla de conversiones entre medidas compatibles: (desde, hacia, factor).
+/// `cantidad_en_hacia = cantidad_en_desde * factor`; la conversión inversa divide.
+const CONVERSIONES: &[(Medida, Medida, f64)] = &[
+    (Medida::Kilogramo, Medida::Gramo, 1000.0),
+    (Medida::Litro, Medida::Mililitro, 1000.0),
+];
+
 impl Medida {
     pub fn from_i32(id: i32) -> Self {
         match id {
@@ -28,13 +38,133 @@ impl Medida {
             Self::Mililitro => ("Mililitro", "ml"),
         }
     }
-    pub fn todos_los_nombres() -> Vec<String> {
-        vec![
-            Self::Unidad.info().0.to_string(),
-            Self::Kilogramo.info().0.to_string(),
-            Self::Gramo.info().0.to_string(),
-            Self::Litro.info().0.to_string(),
-            Self::Mililitro.info().0.to_string(),
-        ]
+
+    /// Medida a partir de su abreviatura ("kg", "g", "l"/"lt", "ml", "un"), sin distinguir mayúsculas
+    pub fn desde_abreviatura(texto: &str) -> Option<Self> {
+        match texto.trim().to_lowercase().as_str() {
+            "un" | "u" | "und" => Some(Self::Unidad),
+            "kg" | "kgs" => Some(Self::Kilogramo),
+            "g" | "gr" | "grs" => Some(Self::Gramo),
+            "l" | "lt" | "lts" => Some(Self::Litro),
+            "ml" => Some(Self::Mililitro),
+            _ => None,
+        }
+    }
+
+    /// Si se puede vender o contar por fracciones (todo menos la unidad)
+    pub fn fraccionable(&self) -> bool {
+        *self != Self::Unidad
+    }
+
+    /// Factor para pasar una cantidad de esta medida a `hacia`, según la tabla de conversiones.
+    /// None si las medidas no son compatibles (p. ej. kilogramos a litros).
+    pub fn factor_a(&self, hacia: Medida) -> Option<f64> {
+        if *self == hacia {
+            return Some(1.0);
+        }
+        CONVERSIONES.iter().find_map(|&(desde, a, factor)| {
+            if desde == *self && a == hacia {
+                Some(factor)
+            } else if desde == hacia && a == *self {
+                Some(1.0 / factor)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Convierte `cantidad` de esta medida a `hacia`, redondeada a `DECIMALES_CANTIDAD`
+    pub fn convertir(&self, cantidad: f64, hacia: Medida) -> Option<f64> {
+        self.factor_a(hacia).map(|factor| redondear_cantidad(cantidad * factor))
+    }
+
+    /// Cantidad con su abreviatura ("3", "0.75 kg", "250 ml"); la unidad no lleva abreviatura
+    pub fn formatear(&self, cantidad: f64) -> String {
+        match self {
+            Self::Unidad => formatear_cantidad(cantidad),
+            otra => format!("{} {}", formatear_cantidad(cantidad), otra.info().1),
+        }
+    }
+}
+
+/// Redondea una cantidad a `DECIMALES_CANTIDAD` (evita arrastrar errores de coma flotante en el stock)
+pub fn redondear_cantidad(cantidad: f64) -> f64 {
+    let escala = 10f64.powi(DECIMALES_CANTIDAD);
+    (cantidad * escala).round() / escala
+}
+
+/// Cantidad sin ceros de más: "3", "0.75", "1.125"
+pub fn formatear_cantidad(cantidad: f64) -> String {
+    let texto = format!("{:.*}", DECIMALES_CANTIDAD as usize, redondear_cantidad(cantidad));
+    let texto = texto.trim_end_matches('0').trim_end_matches('.');
+    if texto == "-0" {
+        "0".to_string()
+    } else {
+        texto.to_string()
+    }
+}
+
+/// Interpreta una cantidad escrita en la medida `base` o en una compatible ("0.75", "750 g",
+/// "1,5kg") y la devuelve en `base`. Si `base` es la unidad, la cantidad debe ser entera.
+pub fn parse_cantidad(texto: &str, base: Medida) -> Result<f64, String> {
+    let texto = texto.trim().replace(',', ".");
+    let corte = texto
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-'))
+        .unwrap_or(texto.len());
+    let (numero, abreviatura) = texto.split_at(corte);
+    let cantidad: f64 = numero
+        .trim()
+        .parse()
+        .map_err(|_| "La cantidad debe ser un número válido".to_string())?;
+
+    let cantidad = if abreviatura.trim().is_empty() {
+        redondear_cantidad(cantidad)
+    } else {
+        let medida = Medida::desde_abreviatura(abreviatura)
+            .ok_or_else(|| format!("'{}' no es una medida conocida", abreviatura.trim()))?;
+        medida.convertir(cantidad, base).ok_or_else(|| {
+            format!("No se puede convertir de {} a {}", medida.info().0, base.info().0)
+        })?
+    };
+
+    if !cantidad.is_finite() || cantidad <= 0.0 {
+        return Err("La cantidad debe ser mayor a 0".to_string());
+    }
+    if !base.fraccionable() && cantidad.fract() != 0.0 {
+        return Err("Este producto se vende por unidades: la cantidad debe ser entera".to_string());
+    }
+    Ok(cantidad)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn convierte_entre_medidas_compatibles() {
+        assert_eq!(Medida::Kilogramo.convertir(0.75, Medida::Gramo), Some(750.0));
+        assert_eq!(Medida::Mililitro.convertir(250.0, Medida::Litro), Some(0.25));
+        assert_eq!(Medida::Gramo.convertir(1.0, Medida::Gramo), Some(1.0));
+        assert_eq!(Medida::Kilogramo.convertir(1.0, Medida::Litro), None);
+        assert_eq!(Medida::Unidad.convertir(1.0, Medida::Gramo), None);
+    }
+
+    #[test]
+    fn interpreta_cantidades_con_medida() {
+        assert_eq!(parse_cantidad("0.75", Medida::Kilogramo), Ok(0.75));
+        assert_eq!(parse_cantidad("750 g", Medida::Kilogramo), Ok(0.75));
+        assert_eq!(parse_cantidad("1,5kg", Medida::Gramo), Ok(1500.0));
+        assert_eq!(parse_cantidad("3", Medida::Unidad), Ok(3.0));
+        assert!(parse_cantidad("1.5", Medida::Unidad).is_err());
+        assert!(parse_cantidad("2 l", Medida::Kilogramo).is_err());
+        assert!(parse_cantidad("0", Medida::Kilogramo).is_err());
+    }
+
+    #[test]
+    fn formatea_sin_ceros_de_mas() {
+        assert_eq!(formatear_cantidad(3.0), "3");
+        assert_eq!(formatear_cantidad(0.1 + 0.2), "0.3");
+        assert_eq!(Medida::Kilogramo.formatear(1.25), "1.25 kg");
+        assert_eq!(Medida::Unidad.formatear(2.0), "2");
     }
 }
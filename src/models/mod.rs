@@ -1,16 +1,46 @@
+pub mod caja;
+pub mod categoria;
+pub mod cliente;
+pub mod compra;
+pub mod credito;
+pub mod impuesto;
+pub mod lote;
 pub mod marca;
 pub mod medida;
+pub mod moneda;
+pub mod movimiento;
+pub mod pago;
+pub mod permiso;
 pub mod producto;
+pub mod proveedor;
+pub mod recibo;
 pub mod rol;
 pub mod tipo_empaque;
 pub mod usuario;
+pub mod valoracion;
 pub mod venta;
 
 // Re-exportaciones para mayor comodidad
-pub use marca::{Marca, MarcaNueva};
+pub use caja::{ConteoCaja, EstadoCaja, SesionCaja};
+pub use categoria::{Categoria, CategoriaConConteo, Subcategoria};
+pub use cliente::{Cliente, ClienteNuevo};
+pub use compra::{EstadoOrden, LineaOrdenCompra, LineaOrdenNueva, LineaRecepcion, OrdenCompra};
+pub use credito::{CargoCredito, MovimientoCuenta, SaldoPorAntiguedad};
+pub use impuesto::{DesgloseLinea, Impuesto, ModoPrecios};
+pub use lote::{Lote, LoteNuevo, LotePorVencer};
+pub use marca::{Marca, MarcaConProductos, MarcaNueva};
 pub use medida::Medida;
-pub use producto::{Producto, ProductoConMarca, ProductoNuevo};
+pub use moneda::{Moneda, TasaCambio};
+pub use movimiento::{MotivoMovimiento, MovimientoStock};
+pub use pago::{MetodoPago, Pago, PagoNuevo};
+pub use permiso::Permiso;
+pub use producto::{Producto, ProductoConMarca, ProductoNuevo, StockBajo};
+pub use proveedor::{Proveedor, ProveedorNuevo};
+pub use recibo::{AnchoPapel, ConfiguracionRecibo, DatosTienda};
 pub use rol::Rol;
 pub use tipo_empaque::TipoEmpaque;
 pub use usuario::Usuario;
-pub use venta::{DetalleVenta, DetalleVentaConProducto, Venta, VentaConDetalles};
+pub use valoracion::{ValoracionGrupo, ValoracionProducto};
+pub use venta::{
+    DetalleVenta, DetalleVentaConProducto, EstadoVenta, FiltroVentas, LineaDevolucion, Venta, VentaConDetalles,
+};
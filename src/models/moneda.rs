@@ -0,0 +1,28 @@
+use chrono::{NaiveDate, NaiveDateTime};
+
+/// Moneda registrada en `monedas`. Costos, ventas y pagos se llevan en la moneda base;
+/// las demás solo sirven para fijar precios y mostrar equivalencias.
+#[derive(Debug, Clone)]
+pub struct Moneda {
+    pub codigo: String, // ISO 4217: "USD", "VES"
+    pub nombre: String,
+    pub simbolo: String,
+    pub es_base: bool,
+}
+
+impl Moneda {
+    /// Moneda en la que se registran las ventas, los pagos y los costos
+    pub const BASE: &'static str = "USD";
+    /// Moneda en la que se cobra en el país; cada venta guarda su tasa respecto a la base
+    pub const LOCAL: &'static str = "VES";
+}
+
+/// Tasa de cambio de una moneda para un día: unidades de `moneda` por una unidad de la moneda base
+#[derive(Debug, Clone)]
+pub struct TasaCambio {
+    pub moneda: String,
+    pub fecha: NaiveDate,
+    pub tasa: f64,
+    pub usuario_nombre: Option<String>, // Quién la registró (JOIN con usuarios)
+    pub registrada: NaiveDateTime,
+}
@@ -0,0 +1,60 @@
+use chrono::NaiveDateTime;
+
+/// Origen de un cambio de stock (se guarda como texto en `movimientos_stock.motivo`)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MotivoMovimiento {
+    Inicial,
+    Compra,
+    Venta,
+    Devolucion,
+    Ajuste,
+}
+
+impl MotivoMovimiento {
+    /// Valor almacenado en la base de datos
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Inicial => "inicial",
+            Self::Compra => "compra",
+            Self::Venta => "venta",
+            Self::Devolucion => "devolucion",
+            Self::Ajuste => "ajuste",
+        }
+    }
+
+    /// Texto para mostrar en el kardex
+    pub fn etiqueta(&self) -> &'static str {
+        match self {
+            Self::Inicial => "Inventario inicial",
+            Self::Compra => "Compra",
+            Self::Venta => "Venta",
+            Self::Devolucion => "Devolución",
+            Self::Ajuste => "Ajuste",
+        }
+    }
+}
+
+impl From<String> for MotivoMovimiento {
+    fn from(s: String) -> Self {
+        match s.as_str() {
+            "inicial" => Self::Inicial,
+            "compra" => Self::Compra,
+            "venta" => Self::Venta,
+            "devolucion" => Self::Devolucion,
+            _ => Self::Ajuste,
+        }
+    }
+}
+
+/// Una línea del kardex: cambio de stock de un producto
+#[derive(Debug, Clone)]
+pub struct MovimientoStock {
+    pub cantidad: f64,         // Positivo = entrada, negativo = salida
+    pub stock_resultante: f64, // Saldo del producto después del movimiento
+    pub motivo: MotivoMovimiento,
+    pub usuario_nombre: Option<String>, // username (JOIN con usuarios)
+    pub fecha: NaiveDateTime,
+    pub referencia: Option<String>, // Documento de origen, p. ej. "Venta #12"
+    pub costo_unitario: f64,        // Costo de las unidades movidas
+    pub costo_promedio: f64,        // Costo promedio ponderado después del movimiento
+}
@@ -0,0 +1,65 @@
+/// Medio con el que se cobra una venta (se guarda como texto)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MetodoPago {
+    Efectivo,
+    Tarjeta,
+    Transferencia,
+    PagoMovil,
+}
+
+impl MetodoPago {
+    /// Todos los métodos, en el orden en que se muestran
+    pub const TODOS: [MetodoPago; 4] = [
+        Self::Efectivo,
+        Self::Tarjeta,
+        Self::Transferencia,
+        Self::PagoMovil,
+    ];
+
+    /// Valor almacenado en la base de datos
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Efectivo => "efectivo",
+            Self::Tarjeta => "tarjeta",
+            Self::Transferencia => "transferencia",
+            Self::PagoMovil => "pago_movil",
+        }
+    }
+
+    /// Texto para mostrar en la UI
+    pub fn etiqueta(&self) -> &'static str {
+        match self {
+            Self::Efectivo => "Efectivo",
+            Self::Tarjeta => "Tarjeta",
+            Self::Transferencia => "Transferencia",
+            Self::PagoMovil => "Pago móvil",
+        }
+    }
+}
+
+impl From<String> for MetodoPago {
+    fn from(s: String) -> Self {
+        match s.as_str() {
+            "tarjeta" => Self::Tarjeta,
+            "transferencia" => Self::Transferencia,
+            "pago_movil" => Self::PagoMovil,
+            _ => Self::Efectivo,
+        }
+    }
+}
+
+/// Pago registrado de una venta
+#[derive(Debug, Clone)]
+pub struct Pago {
+    pub metodo: MetodoPago,
+    pub monto: f64,                 // Monto recibido (en efectivo puede incluir el vuelto)
+    pub referencia: Option<String>, // N° de aprobación, transferencia o pago móvil
+}
+
+/// Pago a registrar junto con la venta
+#[derive(Debug, Clone)]
+pub struct PagoNuevo {
+    pub metodo: MetodoPago,
+    pub monto: f64,
+    pub referencia: Option<String>,
+}
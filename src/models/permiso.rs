@@ -0,0 +1,53 @@
+/// Acciones protegidas de la aplicación. La matriz rol → permiso está en `Rol::puede`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Permiso {
+    VerInventario,
+    CrearProducto,
+    EditarProducto,
+    EditarPrecio,
+    EliminarProducto,
+    VerKardex,
+    VerValoracion,
+    RegistrarVenta,
+    OperarCaja,
+    VerCierresCaja,
+    VerHistorialVentas,
+    RegistrarDevolucion,
+    AnularVenta,
+    GestionarMarcas,
+    GestionarCategorias,
+    GestionarCompras,
+    GestionarClientes,
+    AsignarCredito,
+    GestionarUsuarios,
+    ConfigurarTienda,
+    GestionarTasas,
+}
+
+impl Permiso {
+    pub fn descripcion(&self) -> &'static str {
+        match self {
+            Self::VerInventario => "ver el inventario",
+            Self::CrearProducto => "crear productos",
+            Self::EditarProducto => "editar productos",
+            Self::EditarPrecio => "modificar precios",
+            Self::EliminarProducto => "eliminar productos",
+            Self::VerKardex => "ver el kardex de productos",
+            Self::VerValoracion => "ver la valoración del inventario",
+            Self::RegistrarVenta => "registrar ventas",
+            Self::OperarCaja => "abrir y cerrar la caja",
+            Self::VerCierresCaja => "consultar los cierres de caja",
+            Self::VerHistorialVentas => "consultar el historial de ventas",
+            Self::RegistrarDevolucion => "registrar devoluciones",
+            Self::AnularVenta => "anular ventas",
+            Self::GestionarMarcas => "gestionar marcas",
+            Self::GestionarCategorias => "gestionar categorías",
+            Self::GestionarCompras => "gestionar proveedores y compras",
+            Self::GestionarClientes => "gestionar clientes",
+            Self::AsignarCredito => "asignar límites de crédito a los clientes",
+            Self::GestionarUsuarios => "gestionar usuarios",
+            Self::ConfigurarTienda => "configurar los datos de la tienda y la impresora",
+            Self::GestionarTasas => "registrar las tasas de cambio",
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use chrono::NaiveDateTime;
+use super::medida::redondear_cantidad;
 
 #[derive(Debug, Clone)]
 pub struct Producto {
@@ -6,7 +6,7 @@ pub struct Producto {
     pub nombre: String,
     pub precio_neto: f64,
     pub precio_venta: f64,
-    pub stock: i64,
+    pub stock: f64, // En la medida de venta
     pub descripcion: Option<String>,
     pub codigo: Option<String>,
     pub activo: bool,
@@ -19,7 +19,12 @@ pub struct Producto {
     pub empaque_id: i32, // ID del Enum TipoEmpaque
     // Categoría y subcategoría
     pub categoria_id: i32,
-    pub subcategoria_id: i32,
+    pub subcategoria_id: Option<i32>, // NULL = sin subcategoría
+    pub moneda: String,               // Moneda de precio_venta (el costo va en la moneda base)
+    pub impuesto_id: Option<i64>,     // Tasa de IVA propia (NULL = la de su categoría)
+    pub stock_minimo: f64,            // Existencia mínima (0 = sin alerta)
+    pub punto_reorden: f64,           // Con esta existencia o menos hay que pedir (0 = sin alerta)
+    pub unidad_venta: i32,            // ID del Enum Medida en que se cuentan el stock y el precio de venta
 }
 
 #[derive(Debug, Clone)]
@@ -27,7 +32,7 @@ pub struct ProductoNuevo {
     pub nombre: String,
     pub precio_neto: f64,
     pub precio_venta: f64,
-    pub stock: i64,
+    pub stock: f64, // Stock inicial (se registra como movimiento "inicial")
     pub descripcion: Option<String>,
     pub codigo: Option<String>,
     pub activo: bool,
@@ -39,7 +44,12 @@ pub struct ProductoNuevo {
     pub empaque_id: i32,
     // Categoría y subcategoría
     pub categoria_id: i32,
-    pub subcategoria_id: i32,
+    pub subcategoria_id: Option<i32>, // NULL = sin subcategoría
+    pub moneda: String,               // Moneda de precio_venta (el costo va en la moneda base)
+    pub impuesto_id: Option<i64>,     // Tasa de IVA propia (NULL = la de su categoría)
+    pub stock_minimo: f64,            // Existencia mínima (0 = sin alerta)
+    pub punto_reorden: f64,           // Con esta existencia o menos hay que pedir (0 = sin alerta)
+    pub unidad_venta: i32,            // ID del Enum Medida en que se cuentan el stock y el precio de venta
 }
 
 #[derive(Debug, Clone)]
@@ -48,7 +58,7 @@ pub struct ProductoConMarca {
     pub nombre: String,
     pub precio_neto: f64,
     pub precio_venta: f64,
-    pub stock: i64,
+    pub stock: f64, // En la medida de venta
     pub descripcion: Option<String>,
     pub codigo: Option<String>,
     pub activo: bool,
@@ -60,5 +70,74 @@ pub struct ProductoConMarca {
     pub empaque_id: i32,
     // Categoría y subcategoría
     pub categoria_id: i32,
-    pub subcategoria_id: i32,
+    pub subcategoria_id: Option<i32>, // NULL = sin subcategoría
+    pub moneda: String,               // Moneda de precio_venta (el costo va en la moneda base)
+    pub impuesto_id: i64,             // Tasa de IVA efectiva (la propia o la de su categoría)
+    pub porcentaje_impuesto: f64,
+    pub unidad_venta: i32,            // ID del Enum Medida en que se cuentan el stock y el precio de venta
+}
+
+/// Días de ventas que se toman para sugerir cuánto pedir
+pub const DIAS_VENTAS_RECIENTES: i64 = 30;
+
+/// Producto activo con la existencia en su punto de reorden o stock mínimo, o por debajo
+#[derive(Debug, Clone)]
+pub struct StockBajo {
+    pub id: i64,
+    pub codigo: Option<String>,
+    pub nombre: String,
+    pub stock: f64, // En la medida de venta
+    pub stock_minimo: f64,
+    pub punto_reorden: f64,
+    pub unidad_venta: i32,
+    pub vendidas: f64, // Unidades vendidas (menos las devueltas) en los últimos `DIAS_VENTAS_RECIENTES` días
+}
+
+impl StockBajo {
+    /// Si ya está en el stock mínimo o por debajo (y no solo en el punto de reorden)
+    pub fn bajo_minimo(&self) -> bool {
+        self.stock <= self.stock_minimo
+    }
+
+    /// Unidades a pedir: las que faltan para volver al punto de reorden (o al mínimo, si es mayor)
+    /// más lo que se vendió en los últimos `DIAS_VENTAS_RECIENTES` días, que es lo que se espera
+    /// vender hasta la próxima revisión
+    pub fn cantidad_sugerida(&self) -> f64 {
+        let nivel = self.stock_minimo.max(self.punto_reorden);
+        redondear_cantidad((nivel + self.vendidas.max(0.0) - self.stock.max(0.0)).max(0.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stock_bajo(stock: f64, stock_minimo: f64, punto_reorden: f64, vendidas: f64) -> StockBajo {
+        StockBajo {
+            id: 1,
+            codigo: None,
+            nombre: "Harina".to_string(),
+            stock,
+            stock_minimo,
+            punto_reorden,
+            unidad_venta: 1,
+            vendidas,
+        }
+    }
+
+    #[test]
+    fn sugiere_reponer_el_nivel_y_las_ventas_recientes() {
+        // Faltan 6 para el punto de reorden y se vendieron 45 en el período
+        assert_eq!(stock_bajo(4.0, 5.0, 10.0, 45.0).cantidad_sugerida(), 51.0);
+        // Sin ventas recientes basta con volver al nivel de alerta
+        assert_eq!(stock_bajo(2.0, 5.0, 0.0, 0.0).cantidad_sugerida(), 3.0);
+        // Un stock negativo heredado no infla la sugerencia
+        assert_eq!(stock_bajo(-3.0, 5.0, 0.0, 0.0).cantidad_sugerida(), 5.0);
+    }
+
+    #[test]
+    fn distingue_bajo_minimo_de_punto_de_reorden() {
+        assert!(stock_bajo(5.0, 5.0, 10.0, 0.0).bajo_minimo());
+        assert!(!stock_bajo(8.0, 5.0, 10.0, 0.0).bajo_minimo());
+    }
 }
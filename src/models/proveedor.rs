@@ -0,0 +1,18 @@
+#[derive(Debug, Clone)]
+pub struct Proveedor {
+    pub id: i64,
+    pub nombre: String,
+    pub rif: Option<String>,
+    pub telefono: Option<String>,
+    pub email: Option<String>,
+    pub direccion: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ProveedorNuevo {
+    pub nombre: String,
+    pub rif: Option<String>,
+    pub telefono: Option<String>,
+    pub email: Option<String>,
+    pub direccion: Option<String>,
+}
@@ -0,0 +1,61 @@
+/// Datos de la tienda para el encabezado y el pie del recibo
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DatosTienda {
+    pub nombre: String,
+    pub rif: String,
+    pub direccion: String,
+    pub telefono: String,
+    pub pie: String, // Mensaje final, p. ej. "¡Gracias por su compra!"
+}
+
+/// Ancho del rollo de la impresora térmica
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AnchoPapel {
+    Mm58,
+    Mm80,
+}
+
+impl AnchoPapel {
+    /// Valor almacenado en la configuración
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Mm58 => "58",
+            Self::Mm80 => "80",
+        }
+    }
+
+    /// Caracteres por línea con la fuente A de la impresora
+    pub fn columnas(&self) -> usize {
+        match self {
+            Self::Mm58 => 32,
+            Self::Mm80 => 48,
+        }
+    }
+
+    /// Ancho del papel en puntos PDF (1 mm = 72 / 25.4 pt)
+    pub fn puntos(&self) -> f64 {
+        let mm = match self {
+            Self::Mm58 => 58.0,
+            Self::Mm80 => 80.0,
+        };
+        mm * 72.0 / 25.4
+    }
+}
+
+impl From<String> for AnchoPapel {
+    fn from(s: String) -> Self {
+        match s.as_str() {
+            "58" => Self::Mm58,
+            _ => Self::Mm80,
+        }
+    }
+}
+
+/// Configuración de recibos guardada en la tabla `configuracion`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfiguracionRecibo {
+    pub tienda: DatosTienda,
+    pub ancho: AnchoPapel,
+    pub impresora: Option<String>,   // Ruta del dispositivo o archivo destino del ESC/POS
+    pub carpeta_pdf: Option<String>, // Carpeta de los PDF (None = <datos>/recibos)
+}
@@ -1,3 +1,6 @@
+use crate::models::Permiso;
+use std::fmt;
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Rol {
     Admin,
@@ -15,12 +18,53 @@ impl From<String> for Rol {
     }
 }
 
-impl ToString for Rol {
-    fn to_string(&self) -> String {
+impl fmt::Display for Rol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let nombre = match self {
+            Rol::Admin => "Admin",
+            Rol::Operador => "Operador",
+            Rol::Vendedor => "Vendedor",
+        };
+        f.write_str(nombre)
+    }
+}
+
+impl Rol {
+    /// Roles en el orden del selector de la gestión de usuarios
+    pub const TODOS: [Rol; 3] = [Rol::Admin, Rol::Operador, Rol::Vendedor];
+
+    /// Matriz de permisos por rol
+    pub fn puede(&self, permiso: Permiso) -> bool {
         match self {
-            Rol::Admin => "Admin".to_string(),
-            Rol::Operador => "Operador".to_string(),
-            Rol::Vendedor => "Vendedor".to_string(),
+            // El administrador puede hacerlo todo
+            Rol::Admin => true,
+            // El operador gestiona el catálogo, los precios (y sus tasas de cambio), las compras y el stock,
+            // pero no vende ni administra usuarios
+            Rol::Operador => matches!(
+                permiso,
+                Permiso::VerInventario
+                    | Permiso::CrearProducto
+                    | Permiso::EditarProducto
+                    | Permiso::EditarPrecio
+                    | Permiso::EliminarProducto
+                    | Permiso::VerKardex
+                    | Permiso::VerValoracion
+                    | Permiso::GestionarMarcas
+                    | Permiso::GestionarCategorias
+                    | Permiso::GestionarCompras
+                    | Permiso::GestionarTasas
+            ),
+            // El vendedor consulta el inventario, abre y cierra su caja, vende, registra a sus clientes,
+            // revisa las ventas y recibe devoluciones; anular ventas y revisar los cierres queda reservado al administrador
+            Rol::Vendedor => matches!(
+                permiso,
+                Permiso::VerInventario
+                    | Permiso::RegistrarVenta
+                    | Permiso::GestionarClientes
+                    | Permiso::OperarCaja
+                    | Permiso::VerHistorialVentas
+                    | Permiso::RegistrarDevolucion
+            ),
         }
     }
 }
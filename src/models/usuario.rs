@@ -5,8 +5,8 @@ use chrono::NaiveDateTime; // Importamos el re-export que hicimos en mod.rs
 pub struct Usuario {
     pub id: i64,
     pub username: String,
-    pub password_hash: String,
     pub rol: Rol,
     pub activo: bool,
     pub ultimo_login: Option<NaiveDateTime>,
+    pub debe_cambiar_password: bool, // Forzar cambio en el próximo inicio de sesión
 }
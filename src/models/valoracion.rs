@@ -0,0 +1,26 @@
+/// Existencias de un producto a una fecha, valoradas a su costo promedio ponderado
+#[derive(Debug, Clone)]
+pub struct ValoracionProducto {
+    pub codigo: Option<String>,
+    pub nombre: String,
+    pub marca: String,     // "Sin Marca" si no tiene
+    pub categoria: String,
+    pub stock: f64,        // Saldo del kardex a la fecha
+    pub costo_promedio: f64,
+}
+
+impl ValoracionProducto {
+    /// Stock × costo promedio
+    pub fn valor(&self) -> f64 {
+        self.stock * self.costo_promedio
+    }
+}
+
+/// Totales de la valoración agrupados por marca o por categoría
+#[derive(Debug, Clone)]
+pub struct ValoracionGrupo {
+    pub nombre: String,
+    pub productos: i64,
+    pub unidades: f64,
+    pub valor: f64,
+}
@@ -1,37 +1,175 @@
+use super::medida::redondear_cantidad;
+use super::Pago;
 use chrono::NaiveDateTime;
 
+/// Estado de una venta (se guarda como texto en `ventas.estado`).
+/// La venta original no se modifica: las devoluciones quedan en `devoluciones`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EstadoVenta {
+    Completada,
+    DevolucionParcial,
+    Devuelta,
+    Anulada,
+}
+
+impl EstadoVenta {
+    /// Valor almacenado en la base de datos
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Completada => "completada",
+            Self::DevolucionParcial => "devolucion_parcial",
+            Self::Devuelta => "devuelta",
+            Self::Anulada => "anulada",
+        }
+    }
+
+    /// Texto para mostrar en la UI
+    pub fn etiqueta(&self) -> &'static str {
+        match self {
+            Self::Completada => "Completada",
+            Self::DevolucionParcial => "Devolución parcial",
+            Self::Devuelta => "Devuelta",
+            Self::Anulada => "Anulada",
+        }
+    }
+
+    /// Solo las ventas con unidades sin devolver admiten devoluciones o anulación
+    pub fn admite_devolucion(&self) -> bool {
+        matches!(self, Self::Completada | Self::DevolucionParcial)
+    }
+}
+
+impl From<String> for EstadoVenta {
+    fn from(s: String) -> Self {
+        match s.as_str() {
+            "devolucion_parcial" => Self::DevolucionParcial,
+            "devuelta" => Self::Devuelta,
+            "anulada" => Self::Anulada,
+            _ => Self::Completada,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Venta {
     pub id: i64,
     pub fecha: NaiveDateTime,
     pub total: f64,
-    pub usuario_id: Option<i64>,        // Para saber quién vendió
-    pub cliente_nombre: Option<String>, // Para saber a quién se vendió
+    pub usuario_nombre: Option<String>,    // username del cajero (JOIN con usuarios)
+    pub cliente_id: Option<i64>,           // None en ventas anteriores con nombre libre
+    pub cliente_nombre: Option<String>,    // Nombre del cliente al momento de la venta
+    pub cliente_documento: Option<String>, // Cédula o RIF del cliente (JOIN con clientes)
+    pub estado: EstadoVenta,
+    pub monto_devuelto: f64,      // Suma de las devoluciones y anulaciones
+    pub vuelto: f64,              // Cambio entregado en efectivo
+    pub tasa_cambio: Option<f64>, // Bolívares por dólar usados en la venta
+    pub impuesto: f64,            // IVA incluido en el total
+    pub impuesto_devuelto: f64,   // Parte del IVA que corresponde a lo devuelto
+    pub monto_credito: f64,       // Parte del total que quedó en la cuenta del cliente
+}
+
+impl Venta {
+    /// Total descontando lo devuelto
+    pub fn total_neto(&self) -> f64 {
+        self.total - self.monto_devuelto
+    }
+
+    /// IVA descontando el de lo devuelto
+    pub fn impuesto_neto(&self) -> f64 {
+        self.impuesto - self.impuesto_devuelto
+    }
+
+    /// Total en bolívares a la tasa guardada con la venta (None si la venta no tiene tasa)
+    pub fn total_local(&self) -> Option<f64> {
+        self.tasa_cambio.map(|tasa| self.total * tasa)
+    }
 }
 
+/// Criterios del historial de ventas (None = sin filtrar por ese campo)
+#[derive(Debug, Clone, Default)]
+pub struct FiltroVentas {
+    pub desde: Option<NaiveDateTime>,
+    pub hasta: Option<NaiveDateTime>,
+    pub usuario_id: Option<i64>,
+    pub cliente: Option<String>, // Coincidencia parcial en nombre o documento, sin distinguir mayúsculas
+    pub cliente_id: Option<i64>,
+}
+
+/// Línea de venta. `subtotal` es el total de la línea (base + impuesto).
 #[derive(Debug, Clone)]
 pub struct DetalleVenta {
-    pub id: i64,
-    pub venta_id: i64,
     pub producto_id: i64,
-    pub cantidad: i64,
+    pub cantidad: f64,
     pub precio_unitario: f64,
     pub subtotal: f64,
+    pub impuesto_id: Option<i64>,
+    pub porcentaje_impuesto: f64, // Porcentaje aplicado al vender (no cambia si la tasa cambia)
+    pub base: f64,
+    pub impuesto: f64,
 }
 
 #[derive(Debug, Clone)]
 pub struct DetalleVentaConProducto {
     pub id: i64,
-    pub venta_id: i64,
     pub producto_id: i64,
     pub nombre_producto: String,
-    pub cantidad: i64,
+    pub cantidad: f64,
     pub precio_unitario: f64,
     pub subtotal: f64,
+    pub porcentaje_impuesto: f64,
+    pub base: f64,
+    pub impuesto: f64,
+    pub cantidad_devuelta: f64, // Unidades ya devueltas de esta línea
+    pub unidad_venta: i32,      // ID del Enum Medida en que se vendió la línea
+}
+
+impl DetalleVentaConProducto {
+    /// Unidades que aún pueden devolverse
+    pub fn devolvible(&self) -> f64 {
+        redondear_cantidad((self.cantidad - self.cantidad_devuelta).max(0.0))
+    }
+}
+
+/// Unidades de una línea de venta (`ventas_detalle.id`) que el cliente devuelve
+#[derive(Debug, Clone)]
+pub struct LineaDevolucion {
+    pub detalle_id: i64,
+    pub cantidad: f64,
+}
+
+/// Base e IVA de una venta agrupados por porcentaje
+#[derive(Debug, Clone, PartialEq)]
+pub struct DesgloseImpuesto {
+    pub porcentaje: f64,
+    pub base: f64,
+    pub impuesto: f64,
 }
 
 #[derive(Debug, Clone)]
 pub struct VentaConDetalles {
     pub venta: Venta,
     pub detalles: Vec<DetalleVentaConProducto>,
+    pub pagos: Vec<Pago>,
+}
+
+impl VentaConDetalles {
+    /// Base e IVA de las líneas por porcentaje, del mayor al menor (exento al final)
+    pub fn desglose_impuestos(&self) -> Vec<DesgloseImpuesto> {
+        let mut grupos: Vec<DesgloseImpuesto> = Vec::new();
+        for d in &self.detalles {
+            match grupos.iter_mut().find(|g| g.porcentaje == d.porcentaje_impuesto) {
+                Some(g) => {
+                    g.base += d.base;
+                    g.impuesto += d.impuesto;
+                }
+                None => grupos.push(DesgloseImpuesto {
+                    porcentaje: d.porcentaje_impuesto,
+                    base: d.base,
+                    impuesto: d.impuesto,
+                }),
+            }
+        }
+        grupos.sort_by(|a, b| b.porcentaje.total_cmp(&a.porcentaje));
+        grupos
+    }
 }
@@ -0,0 +1,429 @@
+use crate::db;
+use crate::db::ventas::VentaError;
+use crate::impuestos;
+use crate::models::impuesto::{desglosar, etiqueta_porcentaje};
+use crate::models::medida::{self, redondear_cantidad};
+use crate::models::{DesgloseLinea, DetalleVenta, Medida, MetodoPago, ModoPrecios, Moneda, PagoNuevo, ProductoConMarca};
+use crate::tasas;
+use slint::{ModelRc, SharedString, StandardListViewItem, VecModel};
+use std::rc::Rc;
+use std::sync::{Mutex, OnceLock};
+
+/// Línea del carrito del punto de venta
+#[derive(Debug, Clone)]
+pub struct ItemCarrito {
+    pub producto_id: i64,
+    pub codigo: String,
+    pub nombre: String,
+    pub precio_unitario: f64, // En la moneda base y por unidad de `medida` (precio por kg, por litro...)
+    pub cantidad: f64,        // En `medida`: piezas, o el peso/volumen de la línea
+    pub stock: f64,
+    pub medida: Medida,
+    pub impuesto_id: i64,
+    pub porcentaje_impuesto: f64,
+}
+
+impl ItemCarrito {
+    /// Base, IVA y total de la línea según el modo de precios de la venta en curso
+    pub fn desglose(&self) -> DesgloseLinea {
+        desglosar(self.precio_unitario, self.cantidad, self.porcentaje_impuesto, modo_precios())
+    }
+
+    /// Total de la línea con IVA
+    pub fn subtotal(&self) -> f64 {
+        self.desglose().total
+    }
+
+    /// Precio unitario como se muestra: "12.99" o, por peso o volumen, "12.99/kg"
+    pub fn precio_etiqueta(&self) -> String {
+        if self.medida.fraccionable() {
+            format!("{:.2}/{}", self.precio_unitario, self.medida.info().1)
+        } else {
+            format!("{:.2}", self.precio_unitario)
+        }
+    }
+}
+
+/// Carrito de la venta en curso
+static CARRITO: OnceLock<Mutex<Vec<ItemCarrito>>> = OnceLock::new();
+
+/// Últimos resultados de búsqueda (para resolver el índice seleccionado en la tabla)
+static RESULTADOS: OnceLock<Mutex<Vec<ProductoConMarca>>> = OnceLock::new();
+
+/// Pagos registrados para la venta en curso
+static PAGOS: OnceLock<Mutex<Vec<PagoNuevo>>> = OnceLock::new();
+
+/// Parte del total que queda en la cuenta del cliente (venta a crédito)
+static CREDITO: OnceLock<Mutex<f64>> = OnceLock::new();
+
+/// Bolívares por dólar con los que se cotiza la venta en curso (None = no hay tasa registrada)
+static TASA: OnceLock<Mutex<Option<f64>>> = OnceLock::new();
+
+/// Si los precios de la venta en curso incluyen el IVA o se les suma
+static MODO_PRECIOS: OnceLock<Mutex<ModoPrecios>> = OnceLock::new();
+
+fn get_carrito() -> &'static Mutex<Vec<ItemCarrito>> {
+    CARRITO.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+fn get_resultados() -> &'static Mutex<Vec<ProductoConMarca>> {
+    RESULTADOS.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+fn get_pagos() -> &'static Mutex<Vec<PagoNuevo>> {
+    PAGOS.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+fn get_credito() -> &'static Mutex<f64> {
+    CREDITO.get_or_init(|| Mutex::new(0.0))
+}
+
+fn get_tasa() -> &'static Mutex<Option<f64>> {
+    TASA.get_or_init(|| Mutex::new(None))
+}
+
+fn get_modo_precios() -> &'static Mutex<ModoPrecios> {
+    MODO_PRECIOS.get_or_init(|| Mutex::new(ModoPrecios::IvaIncluido))
+}
+
+/// Modo de precios con el que se cobra la venta en curso
+pub fn modo_precios() -> ModoPrecios {
+    *get_modo_precios().lock().unwrap()
+}
+
+/// Tasa con la que se cotiza la venta en curso
+pub fn tasa() -> Option<f64> {
+    *get_tasa().lock().unwrap()
+}
+
+/// Precio de venta del producto en la moneda base, redondeado a céntimos
+fn precio_en_base(producto: &ProductoConMarca) -> Result<f64, String> {
+    let tasa = match producto.moneda.as_str() {
+        Moneda::BASE => return Ok(producto.precio_venta),
+        Moneda::LOCAL => tasa(),
+        otra => tasas::tasa_del_dia(otra).map_err(|e| format!("Error al leer la tasa de cambio: {}", e))?,
+    };
+    let tasa = tasa.ok_or_else(|| {
+        format!(
+            "'{}' tiene precio en {} y no hay tasa de cambio registrada",
+            producto.nombre, producto.moneda
+        )
+    })?;
+    Ok((producto.precio_venta / tasa * 100.0).round() / 100.0)
+}
+
+/// Busca productos por SKU o nombre y guarda los resultados en caché
+pub fn buscar(texto: &str) -> Result<Vec<ProductoConMarca>, Box<dyn std::error::Error>> {
+    let resultados = if texto.trim().is_empty() {
+        Vec::new()
+    } else {
+        let conn = db::obtener_conexion()?;
+        db::productos::buscar_productos(&conn, texto)?
+    };
+
+    *get_resultados().lock().unwrap() = resultados.clone();
+    Ok(resultados)
+}
+
+/// Si el texto coincide exactamente con el SKU de un resultado, devuelve su índice
+/// (permite agregar directamente al escanear o teclear un código)
+pub fn indice_por_sku(texto: &str) -> Option<i32> {
+    let resultados = get_resultados().lock().unwrap();
+    resultados
+        .iter()
+        .position(|p| p.codigo.as_deref() == Some(texto.trim()))
+        .map(|i| i as i32)
+}
+
+/// Agrega una unidad del producto (índice en los resultados de búsqueda) al carrito.
+/// Los productos por peso o volumen entran con una unidad de su medida (1 kg, 1 l) hasta
+/// que se indique lo pesado.
+pub fn agregar_resultado(index: i32) -> Result<(), String> {
+    let producto = get_resultados()
+        .lock()
+        .unwrap()
+        .get(index as usize)
+        .cloned()
+        .ok_or_else(|| "Seleccione un producto de la búsqueda".to_string())?;
+
+    let mut carrito = get_carrito().lock().unwrap();
+    // El modo de precios se fija con el primer producto, igual que la tasa
+    if carrito.is_empty() {
+        *get_modo_precios().lock().unwrap() =
+            impuestos::modo_precios().map_err(|e| format!("Error al leer el modo de precios: {}", e))?;
+    }
+    // La tasa se fija con el primer producto de la venta; mientras no haya una, se vuelve a consultar
+    if carrito.is_empty() || tasa().is_none() {
+        *get_tasa().lock().unwrap() = tasas::tasa_del_dia(Moneda::LOCAL)
+            .map_err(|e| format!("Error al leer la tasa de cambio: {}", e))?;
+    }
+
+    if let Some(item) = carrito.iter_mut().find(|i| i.producto_id == producto.id) {
+        item.cantidad = redondear_cantidad(item.cantidad + 1.0);
+    } else {
+        let precio_unitario = precio_en_base(&producto)?;
+        carrito.push(ItemCarrito {
+            producto_id: producto.id,
+            codigo: producto.codigo.unwrap_or_else(|| "S/C".into()),
+            nombre: producto.nombre,
+            precio_unitario,
+            cantidad: 1.0,
+            stock: producto.stock,
+            medida: Medida::from_i32(producto.unidad_venta),
+            impuesto_id: producto.impuesto_id,
+            porcentaje_impuesto: producto.porcentaje_impuesto,
+        });
+    }
+    Ok(())
+}
+
+/// Cambia la cantidad de una línea del carrito. En productos por peso o volumen es lo
+/// pesado, en su medida o en una convertible ("0.75" o "750 g" para un producto por kg)
+pub fn cambiar_cantidad(index: i32, cantidad: &str) -> Result<(), String> {
+    let mut carrito = get_carrito().lock().unwrap();
+    let item = carrito
+        .get_mut(index as usize)
+        .ok_or_else(|| "Seleccione una línea del carrito".to_string())?;
+    item.cantidad = medida::parse_cantidad(cantidad, item.medida)?;
+    Ok(())
+}
+
+/// Quita una línea del carrito
+pub fn quitar(index: i32) {
+    let mut carrito = get_carrito().lock().unwrap();
+    if (index as usize) < carrito.len() {
+        carrito.remove(index as usize);
+    }
+}
+
+/// Vacía el carrito, los pagos, los resultados de búsqueda y la tasa de la venta
+/// (el modo de precios se vuelve a leer con el primer producto de la próxima)
+pub fn limpiar() {
+    get_carrito().lock().unwrap().clear();
+    get_pagos().lock().unwrap().clear();
+    *get_credito().lock().unwrap() = 0.0;
+    get_resultados().lock().unwrap().clear();
+    *get_tasa().lock().unwrap() = None;
+}
+
+/// Total acumulado del carrito (con IVA)
+pub fn total() -> f64 {
+    get_carrito().lock().unwrap().iter().map(|i| i.subtotal()).sum()
+}
+
+/// Base imponible e IVA del carrito
+pub fn base_e_impuesto() -> (f64, f64) {
+    get_carrito()
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|i| i.desglose())
+        .fold((0.0, 0.0), |(base, impuesto), d| (base + d.base, impuesto + d.impuesto))
+}
+
+/// Total del carrito en bolívares a la tasa de la venta en curso
+pub fn total_local() -> Option<f64> {
+    tasa().map(|tasa| total() * tasa)
+}
+
+/// Copia del contenido actual del carrito (Send-safe)
+pub fn items() -> Vec<ItemCarrito> {
+    get_carrito().lock().unwrap().clone()
+}
+
+// ==========================================
+// PAGOS
+// ==========================================
+
+/// Agrega un pago con el método indicado (índice en `MetodoPago::TODOS`).
+/// Monto vacío = lo que falta por cobrar.
+pub fn agregar_pago(metodo_index: i32, monto: &str, referencia: &str) -> Result<(), String> {
+    let metodo = MetodoPago::TODOS
+        .get(metodo_index.max(0) as usize)
+        .copied()
+        .ok_or_else(|| "Seleccione un método de pago".to_string())?;
+
+    let monto = if monto.trim().is_empty() {
+        let restante = restante();
+        if restante <= 0.0 {
+            return Err("El total ya está cubierto".to_string());
+        }
+        restante
+    } else {
+        monto
+            .trim()
+            .parse()
+            .map_err(|_| "El monto debe ser un número válido".to_string())?
+    };
+    if monto.is_nan() || monto <= 0.0 {
+        return Err("El monto debe ser mayor a 0".to_string());
+    }
+
+    get_pagos().lock().unwrap().push(PagoNuevo {
+        metodo,
+        monto,
+        referencia: Some(referencia.trim().to_string()).filter(|r| !r.is_empty()),
+    });
+    Ok(())
+}
+
+/// Quita un pago de la venta en curso
+pub fn quitar_pago(index: i32) {
+    let mut pagos = get_pagos().lock().unwrap();
+    if (index as usize) < pagos.len() {
+        pagos.remove(index as usize);
+    }
+}
+
+/// Copia de los pagos registrados (Send-safe)
+pub fn pagos() -> Vec<PagoNuevo> {
+    get_pagos().lock().unwrap().clone()
+}
+
+/// Suma de los pagos registrados
+pub fn pagado() -> f64 {
+    get_pagos().lock().unwrap().iter().map(|p| p.monto).sum()
+}
+
+/// Parte a crédito de la venta en curso (nunca más que el total, aunque se quiten productos)
+pub fn credito() -> f64 {
+    get_credito().lock().unwrap().min(total())
+}
+
+/// Deja a crédito lo que falta por cobrar o, si ya hay una parte a crédito, la quita
+pub fn alternar_credito() -> Result<(), String> {
+    if credito() > 0.0 {
+        *get_credito().lock().unwrap() = 0.0;
+        return Ok(());
+    }
+    let restante = restante();
+    if restante <= 0.0 {
+        return Err("No queda monto por cobrar para dejar a crédito".to_string());
+    }
+    *get_credito().lock().unwrap() = restante;
+    Ok(())
+}
+
+/// Lo que se cobra en el momento: el total menos la parte a crédito
+fn por_cobrar() -> f64 {
+    ((total() - credito()) * 100.0).round() / 100.0
+}
+
+/// Lo que falta por cobrar
+pub fn restante() -> f64 {
+    ((por_cobrar() - pagado()) * 100.0).round().max(0.0) / 100.0
+}
+
+/// Cambio a entregar si se cobrara ahora (0 si los pagos no alcanzan o no son válidos)
+pub fn vuelto() -> f64 {
+    db::ventas::calcular_vuelto(por_cobrar(), &pagos()).unwrap_or(0.0)
+}
+
+/// Nombres de los métodos de pago para el selector
+pub fn metodos_para_select() -> ModelRc<SharedString> {
+    let nombres: Vec<SharedString> = MetodoPago::TODOS.iter().map(|m| SharedString::from(m.etiqueta())).collect();
+    ModelRc::from(Rc::new(VecModel::from(nombres)))
+}
+
+/// Convierte los pagos a filas de tabla (Método, Monto, Referencia)
+pub fn pagos_to_model_rows(pagos: &[PagoNuevo]) -> ModelRc<ModelRc<StandardListViewItem>> {
+    let rows: Vec<ModelRc<StandardListViewItem>> = pagos
+        .iter()
+        .map(|p| {
+            let row_data = vec![
+                StandardListViewItem::from(SharedString::from(p.metodo.etiqueta())),
+                StandardListViewItem::from(SharedString::from(format!("{:.2}", p.monto))),
+                StandardListViewItem::from(SharedString::from(p.referencia.clone().unwrap_or_default())),
+            ];
+            ModelRc::from(Rc::new(VecModel::from(row_data)))
+        })
+        .collect();
+
+    ModelRc::from(Rc::new(VecModel::from(rows)))
+}
+
+/// Registra la venta del carrito actual con sus pagos a nombre del usuario indicado
+pub fn confirmar_venta(usuario_id: i64, cliente_id: i64) -> Result<i64, Box<dyn std::error::Error>> {
+    let items = items();
+    if items.is_empty() {
+        return Err("El carrito está vacío".into());
+    }
+
+    let detalles: Vec<DetalleVenta> = items
+        .iter()
+        .map(|i| {
+            let desglose = i.desglose();
+            DetalleVenta {
+                producto_id: i.producto_id,
+                cantidad: i.cantidad,
+                precio_unitario: i.precio_unitario,
+                subtotal: desglose.total,
+                impuesto_id: Some(i.impuesto_id),
+                porcentaje_impuesto: i.porcentaje_impuesto,
+                base: desglose.base,
+                impuesto: desglose.impuesto,
+            }
+        })
+        .collect();
+
+    let mut conn = db::obtener_conexion()?;
+    match db::ventas::registrar_venta(&mut conn, usuario_id, cliente_id, detalles, &pagos(), credito(), tasa()) {
+        Ok(venta_id) => Ok(venta_id),
+        Err(VentaError::StockInsuficiente { producto_id, nombre, disponible, solicitado }) => {
+            // Actualizar el stock conocido de la línea e indicar al cajero cuál falló
+            let mut carrito = get_carrito().lock().unwrap();
+            let linea = carrito.iter().position(|i| i.producto_id == producto_id);
+            let medida = linea.map(|i| carrito[i].medida).unwrap_or(Medida::Unidad);
+            if let Some(i) = linea {
+                carrito[i].stock = disponible;
+            }
+            Err(format!(
+                "Línea {}: stock insuficiente para '{}' (disponibles {}, solicitados {})",
+                linea.map(|i| i + 1).unwrap_or(0),
+                nombre,
+                medida.formatear(disponible),
+                medida.formatear(solicitado)
+            )
+            .into())
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Convierte los resultados de búsqueda a filas de tabla (Código, Nombre, Precio, Stock)
+pub fn resultados_to_model_rows(resultados: &[ProductoConMarca]) -> ModelRc<ModelRc<StandardListViewItem>> {
+    let rows: Vec<ModelRc<StandardListViewItem>> = resultados
+        .iter()
+        .map(|p| {
+            let row_data = vec![
+                StandardListViewItem::from(SharedString::from(p.codigo.clone().unwrap_or_else(|| "S/C".into()))),
+                StandardListViewItem::from(SharedString::from(p.nombre.as_str())),
+                StandardListViewItem::from(SharedString::from(tasas::precio_con_moneda(p.precio_venta, &p.moneda))),
+                StandardListViewItem::from(SharedString::from(Medida::from_i32(p.unidad_venta).formatear(p.stock))),
+            ];
+            ModelRc::from(Rc::new(VecModel::from(row_data)))
+        })
+        .collect();
+
+    ModelRc::from(Rc::new(VecModel::from(rows)))
+}
+
+/// Convierte el carrito a filas de tabla (Código, Producto, Cant., P. Unit, IVA, Subtotal)
+pub fn carrito_to_model_rows(items: &[ItemCarrito]) -> ModelRc<ModelRc<StandardListViewItem>> {
+    let rows: Vec<ModelRc<StandardListViewItem>> = items
+        .iter()
+        .map(|i| {
+            let row_data = vec![
+                StandardListViewItem::from(SharedString::from(i.codigo.as_str())),
+                StandardListViewItem::from(SharedString::from(i.nombre.as_str())),
+                StandardListViewItem::from(SharedString::from(i.medida.formatear(i.cantidad))),
+                StandardListViewItem::from(SharedString::from(i.precio_etiqueta())),
+                StandardListViewItem::from(SharedString::from(etiqueta_porcentaje(i.porcentaje_impuesto))),
+                StandardListViewItem::from(SharedString::from(format!("{:.2}", i.subtotal()))),
+            ];
+            ModelRc::from(Rc::new(VecModel::from(row_data)))
+        })
+        .collect();
+
+    ModelRc::from(Rc::new(VecModel::from(rows)))
+}
@@ -0,0 +1,145 @@
+use crate::db;
+use crate::models::{Proveedor, ProveedorNuevo};
+use slint::{ModelRc, SharedString, StandardListViewItem, VecModel};
+use std::rc::Rc;
+use std::sync::{Mutex, OnceLock};
+
+/// Caché de proveedores en el orden mostrado (tabla de gestión y selector de la orden de compra)
+static PROVEEDORES: OnceLock<Mutex<Vec<Proveedor>>> = OnceLock::new();
+
+fn get_cache() -> &'static Mutex<Vec<Proveedor>> {
+    PROVEEDORES.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+/// Convierte un texto vacío en None
+fn opcional(valor: &str) -> Option<String> {
+    let valor = valor.trim();
+    (!valor.is_empty()).then(|| valor.to_string())
+}
+
+/// Lee los proveedores y actualiza la caché (Send-safe)
+pub fn cargar() -> Result<Vec<Proveedor>, Box<dyn std::error::Error>> {
+    let conn = db::obtener_conexion()?;
+    let proveedores = db::proveedores::obtener_proveedores(&conn)?;
+    *get_cache().lock().unwrap() = proveedores.clone();
+    Ok(proveedores)
+}
+
+/// Proveedor de la fila indicada (mismo orden en la tabla y en el selector)
+pub fn get_proveedor_by_index(index: i32) -> Option<Proveedor> {
+    get_cache().lock().unwrap().get(index as usize).cloned()
+}
+
+/// Nombres para el selector de proveedor de la orden de compra
+pub fn nombres_para_select(proveedores: &[Proveedor]) -> ModelRc<SharedString> {
+    let nombres: Vec<SharedString> = proveedores
+        .iter()
+        .map(|p| SharedString::from(p.nombre.as_str()))
+        .collect();
+    ModelRc::from(Rc::new(VecModel::from(nombres)))
+}
+
+/// Valida nombre y RIF antes de guardar (requeridos/únicos)
+pub fn validar(nombre: &str, rif: &str, excluir_id: Option<i64>) -> Result<(), String> {
+    if nombre.trim().is_empty() {
+        return Err("El nombre del proveedor es requerido".to_string());
+    }
+
+    let conn = db::obtener_conexion().map_err(|e| e.to_string())?;
+    if db::proveedores::existe_nombre(&conn, nombre, excluir_id).map_err(|e| e.to_string())? {
+        return Err(format!("Ya existe un proveedor llamado '{}'", nombre.trim()));
+    }
+    if !rif.trim().is_empty()
+        && db::proveedores::existe_rif(&conn, rif, excluir_id).map_err(|e| e.to_string())?
+    {
+        return Err(format!("El RIF '{}' ya está registrado en otro proveedor", rif.trim()));
+    }
+    Ok(())
+}
+
+/// Crea (id = None) o actualiza un proveedor. Devuelve el id guardado.
+pub fn guardar(
+    id: Option<i64>,
+    nombre: &str,
+    rif: &str,
+    telefono: &str,
+    email: &str,
+    direccion: &str,
+) -> Result<i64, Box<dyn std::error::Error>> {
+    validar(nombre, rif, id)?;
+    let conn = db::obtener_conexion()?;
+
+    match id {
+        Some(id) => {
+            let proveedor = Proveedor {
+                id,
+                nombre: nombre.trim().to_string(),
+                rif: opcional(rif),
+                telefono: opcional(telefono),
+                email: opcional(email),
+                direccion: opcional(direccion),
+            };
+            if !db::proveedores::actualizar_proveedor(&conn, &proveedor)? {
+                return Err("El proveedor ya no existe".into());
+            }
+            Ok(id)
+        }
+        None => {
+            let proveedor = ProveedorNuevo {
+                nombre: nombre.trim().to_string(),
+                rif: opcional(rif),
+                telefono: opcional(telefono),
+                email: opcional(email),
+                direccion: opcional(direccion),
+            };
+            Ok(db::proveedores::crear_proveedor(&conn, &proveedor)?)
+        }
+    }
+}
+
+/// Texto de confirmación antes de eliminar. Falla si el proveedor tiene órdenes de compra,
+/// que deben conservarse como historial.
+pub fn advertencia_eliminar(proveedor: &Proveedor) -> Result<String, Box<dyn std::error::Error>> {
+    let conn = db::obtener_conexion()?;
+    let ordenes = db::proveedores::contar_ordenes(&conn, proveedor.id)?;
+    if ordenes > 0 {
+        return Err(format!(
+            "El proveedor '{}' tiene {} orden(es) de compra y no puede eliminarse.",
+            proveedor.nombre, ordenes
+        )
+        .into());
+    }
+    Ok(format!("¿Eliminar el proveedor '{}'?", proveedor.nombre))
+}
+
+/// Elimina el proveedor
+pub fn eliminar(id: i64) -> Result<bool, Box<dyn std::error::Error>> {
+    let conn = db::obtener_conexion()?;
+    match db::proveedores::eliminar_proveedor(&conn, id) {
+        Ok(eliminado) => Ok(eliminado),
+        Err(rusqlite::Error::SqliteFailure(e, _))
+            if e.code == rusqlite::ErrorCode::ConstraintViolation =>
+        {
+            Err("El proveedor tiene órdenes de compra registradas".into())
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Convierte los proveedores a filas de tabla (Nombre, RIF, Teléfono, Email)
+pub fn to_model_rows(proveedores: &[Proveedor]) -> ModelRc<ModelRc<StandardListViewItem>> {
+    let rows: Vec<ModelRc<StandardListViewItem>> = proveedores
+        .iter()
+        .map(|p| {
+            let row_data = vec![
+                StandardListViewItem::from(SharedString::from(p.nombre.as_str())),
+                StandardListViewItem::from(SharedString::from(p.rif.clone().unwrap_or_else(|| "-".into()))),
+                StandardListViewItem::from(SharedString::from(p.telefono.clone().unwrap_or_default())),
+                StandardListViewItem::from(SharedString::from(p.email.clone().unwrap_or_default())),
+            ];
+            ModelRc::from(Rc::new(VecModel::from(row_data)))
+        })
+        .collect();
+
+    ModelRc::from(Rc::new(VecModel::from(rows)))
+}
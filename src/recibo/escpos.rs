@@ -0,0 +1,62 @@
+//! Recibo en comandos ESC/POS para impresoras térmicas de 58/80 mm
+
+use super::{componer, Estilo};
+use crate::models::{AnchoPapel, DatosTienda, VentaConDetalles};
+
+const ESC: u8 = 0x1B;
+const GS: u8 = 0x1D;
+const LF: u8 = 0x0A;
+
+/// Página de códigos PC850 (ESC t 2), la más común en impresoras genéricas
+const PAGINA_PC850: u8 = 2;
+
+/// Codifica el texto en PC850; los caracteres sin equivalente salen como '?'
+fn codificar(texto: &str, salida: &mut Vec<u8>) {
+    for c in texto.chars() {
+        let byte = match c {
+            ' '..='~' => c as u8,
+            'ü' => 0x81,
+            'é' => 0x82,
+            'á' => 0xA0,
+            'í' => 0xA1,
+            'ó' => 0xA2,
+            'ú' => 0xA3,
+            'ñ' => 0xA4,
+            'Ñ' => 0xA5,
+            '¿' => 0xA8,
+            '¡' => 0xAD,
+            'É' => 0x90,
+            'Ü' => 0x9A,
+            'Á' => 0xB5,
+            'Í' => 0xD6,
+            'Ó' => 0xE0,
+            'Ú' => 0xE9,
+            _ => b'?',
+        };
+        salida.push(byte);
+    }
+}
+
+/// Bytes listos para enviar a la impresora: inicializa, imprime, avanza el papel y corta
+pub fn generar(venta: &VentaConDetalles, tienda: &DatosTienda, ancho: AnchoPapel) -> Vec<u8> {
+    let mut salida = vec![ESC, b'@', ESC, b't', PAGINA_PC850];
+
+    for linea in componer(venta, tienda, ancho.columnas()) {
+        match linea.estilo {
+            Estilo::Normal => {}
+            Estilo::Negrita => salida.extend_from_slice(&[ESC, b'E', 1]),
+            Estilo::Titulo => salida.extend_from_slice(&[ESC, b'E', 1, GS, b'!', 0x01]),
+        }
+        codificar(&linea.texto, &mut salida);
+        match linea.estilo {
+            Estilo::Normal => {}
+            Estilo::Negrita => salida.extend_from_slice(&[ESC, b'E', 0]),
+            Estilo::Titulo => salida.extend_from_slice(&[GS, b'!', 0x00, ESC, b'E', 0]),
+        }
+        salida.push(LF);
+    }
+
+    // Avance de 4 líneas y corte parcial
+    salida.extend_from_slice(&[ESC, b'd', 4, GS, b'V', 66, 0]);
+    salida
+}
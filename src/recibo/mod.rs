@@ -0,0 +1,419 @@
+//! Recibos de venta: la misma composición de texto monoespaciado se envía a la impresora
+//! térmica como ESC/POS (`escpos`) o se guarda como PDF (`pdf`).
+
+pub mod escpos;
+pub mod pdf;
+
+use crate::db;
+use crate::models::impuesto::etiqueta_porcentaje;
+use crate::models::{ConfiguracionRecibo, DatosTienda, EstadoVenta, Medida, VentaConDetalles};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Estilo de una línea del recibo
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Estilo {
+    Normal,
+    Negrita,
+    Titulo, // Nombre de la tienda: negrita y doble alto en la impresora
+}
+
+/// Línea ya ajustada al ancho del papel
+#[derive(Debug, Clone, PartialEq)]
+pub struct Linea {
+    pub texto: String,
+    pub estilo: Estilo,
+}
+
+impl Linea {
+    fn normal(texto: String) -> Self {
+        Self {
+            texto,
+            estilo: Estilo::Normal,
+        }
+    }
+
+    fn negrita(texto: String) -> Self {
+        Self {
+            texto,
+            estilo: Estilo::Negrita,
+        }
+    }
+}
+
+/// Parte un texto en renglones de como máximo `ancho` caracteres, cortando en los espacios
+fn partir(texto: &str, ancho: usize) -> Vec<String> {
+    let mut renglones = Vec::new();
+    let mut actual = String::new();
+    for palabra in texto.split_whitespace() {
+        let mut palabra: Vec<char> = palabra.chars().collect();
+        // Palabras más largas que el renglón se cortan a la fuerza
+        while palabra.len() > ancho {
+            if !actual.is_empty() {
+                renglones.push(std::mem::take(&mut actual));
+            }
+            renglones.push(palabra.drain(..ancho).collect());
+        }
+        let palabra: String = palabra.into_iter().collect();
+        let largo = actual.chars().count();
+        if largo > 0 && largo + 1 + palabra.chars().count() > ancho {
+            renglones.push(std::mem::take(&mut actual));
+        }
+        if !actual.is_empty() {
+            actual.push(' ');
+        }
+        actual.push_str(&palabra);
+    }
+    if !actual.is_empty() {
+        renglones.push(actual);
+    }
+    renglones
+}
+
+fn centrar(texto: &str, ancho: usize) -> String {
+    let relleno = ancho.saturating_sub(texto.chars().count()) / 2;
+    format!("{}{}", " ".repeat(relleno), texto)
+}
+
+/// Texto a la izquierda y a la derecha del mismo renglón; si no caben, la izquierda se recorta
+fn extremos(izquierda: &str, derecha: &str, ancho: usize) -> String {
+    let largo_derecha = derecha.chars().count();
+    let disponible = ancho.saturating_sub(largo_derecha + 1);
+    let izquierda: String = izquierda.chars().take(disponible).collect();
+    let relleno = ancho.saturating_sub(izquierda.chars().count() + largo_derecha);
+    format!("{}{}{}", izquierda, " ".repeat(relleno), derecha)
+}
+
+/// Arma las líneas del recibo de una venta para un papel de `columnas` caracteres
+pub fn componer(venta: &VentaConDetalles, tienda: &DatosTienda, columnas: usize) -> Vec<Linea> {
+    let mut lineas = Vec::new();
+    let separador = |c: char| Linea::normal(c.to_string().repeat(columnas));
+    let centradas = |lineas: &mut Vec<Linea>, texto: &str| {
+        for renglon in partir(texto, columnas) {
+            lineas.push(Linea::normal(centrar(&renglon, columnas)));
+        }
+    };
+
+    // --- ENCABEZADO ---
+    for renglon in partir(&tienda.nombre, columnas) {
+        lineas.push(Linea {
+            texto: centrar(&renglon, columnas),
+            estilo: Estilo::Titulo,
+        });
+    }
+    if !tienda.rif.trim().is_empty() {
+        centradas(&mut lineas, &format!("RIF: {}", tienda.rif.trim()));
+    }
+    centradas(&mut lineas, &tienda.direccion);
+    if !tienda.telefono.trim().is_empty() {
+        centradas(&mut lineas, &format!("Tel: {}", tienda.telefono.trim()));
+    }
+    lineas.push(separador('='));
+
+    let v = &venta.venta;
+    lineas.push(Linea::normal(extremos(
+        &format!("Venta #{}", v.id),
+        &v.fecha.format("%d/%m/%Y %H:%M").to_string(),
+        columnas,
+    )));
+    if let Some(cajero) = &v.usuario_nombre {
+        lineas.push(Linea::normal(extremos("Cajero:", cajero, columnas)));
+    }
+    if let Some(cliente) = v.cliente_nombre.as_deref().filter(|c| !c.trim().is_empty()) {
+        lineas.push(Linea::normal(extremos("Cliente:", cliente, columnas)));
+    }
+    if let Some(documento) = v.cliente_documento.as_deref() {
+        lineas.push(Linea::normal(extremos("C.I./RIF:", documento, columnas)));
+    }
+    lineas.push(separador('-'));
+
+    // --- PRODUCTOS ---
+    for d in &venta.detalles {
+        for renglon in partir(&d.nombre_producto, columnas) {
+            lineas.push(Linea::normal(renglon));
+        }
+        // Por peso o volumen: "0.755 kg x 12.99/kg"
+        let medida = Medida::from_i32(d.unidad_venta);
+        let precio = if medida.fraccionable() {
+            format!("{:.2}/{}", d.precio_unitario, medida.info().1)
+        } else {
+            format!("{:.2}", d.precio_unitario)
+        };
+        lineas.push(Linea::normal(extremos(
+            &format!("  {} x {}", medida.formatear(d.cantidad), precio),
+            &format!("{:.2}", d.subtotal),
+            columnas,
+        )));
+        if d.cantidad_devuelta > 0.0 {
+            lineas.push(Linea::normal(format!(
+                "  Devuelto: {}",
+                medida.formatear(d.cantidad_devuelta)
+            )));
+        }
+    }
+    lineas.push(separador('-'));
+
+    // --- TOTALES ---
+    // Base e IVA por tasa; lo exento va en una sola línea
+    for g in venta.desglose_impuestos() {
+        if g.porcentaje == 0.0 {
+            lineas.push(Linea::normal(extremos("Exento", &format!("{:.2}", g.base), columnas)));
+            continue;
+        }
+        let tasa = etiqueta_porcentaje(g.porcentaje);
+        lineas.push(Linea::normal(extremos(
+            &format!("Base IVA {}", tasa),
+            &format!("{:.2}", g.base),
+            columnas,
+        )));
+        lineas.push(Linea::normal(extremos(
+            &format!("IVA {}", tasa),
+            &format!("{:.2}", g.impuesto),
+            columnas,
+        )));
+    }
+    lineas.push(Linea::negrita(extremos(
+        "TOTAL",
+        &format!("{:.2}", v.total),
+        columnas,
+    )));
+    // Equivalente en bolívares a la tasa guardada con la venta
+    if let (Some(total_local), Some(tasa)) = (v.total_local(), v.tasa_cambio) {
+        lineas.push(Linea::normal(extremos(
+            &format!("Total Bs (tasa {:.4})", tasa),
+            &format!("{:.2}", total_local),
+            columnas,
+        )));
+    }
+    for pago in &venta.pagos {
+        let metodo = match &pago.referencia {
+            Some(referencia) => format!("{} ref. {}", pago.metodo.etiqueta(), referencia),
+            None => pago.metodo.etiqueta().to_string(),
+        };
+        lineas.push(Linea::normal(extremos(
+            &metodo,
+            &format!("{:.2}", pago.monto),
+            columnas,
+        )));
+    }
+    if v.monto_credito > 0.0 {
+        lineas.push(Linea::normal(extremos(
+            "A crédito",
+            &format!("{:.2}", v.monto_credito),
+            columnas,
+        )));
+    }
+    if v.vuelto > 0.0 {
+        lineas.push(Linea::normal(extremos(
+            "Vuelto",
+            &format!("{:.2}", v.vuelto),
+            columnas,
+        )));
+    }
+    if v.monto_devuelto > 0.0 {
+        lineas.push(Linea::normal(extremos(
+            "Devuelto",
+            &format!("-{:.2}", v.monto_devuelto),
+            columnas,
+        )));
+        lineas.push(Linea::negrita(extremos(
+            "NETO",
+            &format!("{:.2}", v.total_neto()),
+            columnas,
+        )));
+    }
+    if v.estado == EstadoVenta::Anulada {
+        lineas.push(Linea::negrita(centrar("*** VENTA ANULADA ***", columnas)));
+    }
+    lineas.push(separador('='));
+
+    centradas(&mut lineas, &tienda.pie);
+    lineas
+}
+
+/// Escribe los bytes en un archivo o en un dispositivo (p. ej. /dev/usb/lp0)
+pub fn escribir(bytes: &[u8], ruta: &Path) -> std::io::Result<()> {
+    let mut destino = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(ruta)?;
+    destino.write_all(bytes)?;
+    destino.flush()
+}
+
+/// Configuración de recibos guardada (Send-safe)
+pub fn cargar_configuracion() -> Result<ConfiguracionRecibo, Box<dyn std::error::Error>> {
+    let conn = db::obtener_conexion()?;
+    Ok(db::configuracion::obtener_recibo(&conn)?)
+}
+
+/// Guarda la configuración de recibos (Send-safe)
+pub fn guardar_configuracion(
+    config: &ConfiguracionRecibo,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let conn = db::obtener_conexion()?;
+    db::configuracion::guardar_recibo(&conn, config)?;
+    Ok(())
+}
+
+fn cargar_venta(
+    venta_id: i64,
+) -> Result<(VentaConDetalles, ConfiguracionRecibo), Box<dyn std::error::Error>> {
+    let conn = db::obtener_conexion()?;
+    let venta = db::ventas::obtener_venta_con_detalles(&conn, venta_id)?
+        .ok_or_else(|| format!("La venta #{} no existe", venta_id))?;
+    let config = db::configuracion::obtener_recibo(&conn)?;
+    Ok((venta, config))
+}
+
+/// Genera el PDF del recibo en la carpeta configurada y devuelve su ruta (Send-safe)
+pub fn guardar_pdf(venta_id: i64) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let (venta, config) = cargar_venta(venta_id)?;
+    let carpeta = config
+        .carpeta_pdf
+        .map(PathBuf::from)
+        .unwrap_or_else(|| db::ubicacion::directorio_datos().join("recibos"));
+    std::fs::create_dir_all(&carpeta)?;
+
+    let ruta = carpeta.join(format!("venta-{}.pdf", venta_id));
+    escribir(&pdf::generar(&venta, &config.tienda, config.ancho), &ruta)?;
+    Ok(ruta)
+}
+
+/// Envía el recibo en ESC/POS a la impresora configurada y devuelve su ruta (Send-safe)
+pub fn imprimir(venta_id: i64) -> Result<String, Box<dyn std::error::Error>> {
+    let (venta, config) = cargar_venta(venta_id)?;
+    let impresora = config
+        .impresora
+        .ok_or("No hay impresora configurada (ver DATOS DEL TICKET)")?;
+    escribir(
+        &escpos::generar(&venta, &config.tienda, config.ancho),
+        Path::new(&impresora),
+    )
+    .map_err(|e| format!("No se pudo escribir en '{}': {}", impresora, e))?;
+    Ok(impresora)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::impuesto::desglosar;
+    use crate::models::{AnchoPapel, DetalleVentaConProducto, MetodoPago, ModoPrecios, Pago, Venta};
+    use chrono::NaiveDate;
+
+    /// Venta fija para comparar contra los archivos de tests/golden.
+    /// Con BODEX_ACTUALIZAR_GOLDEN=1 los archivos se regeneran en lugar de compararse.
+    fn venta_de_prueba() -> VentaConDetalles {
+        let linea = |id, nombre: &str, cantidad, precio: f64, porcentaje, devuelta| {
+            let d = desglosar(precio, cantidad, porcentaje, ModoPrecios::IvaIncluido);
+            DetalleVentaConProducto {
+                id,
+                producto_id: id,
+                nombre_producto: nombre.to_string(),
+                cantidad,
+                precio_unitario: precio,
+                subtotal: d.total,
+                porcentaje_impuesto: porcentaje,
+                base: d.base,
+                impuesto: d.impuesto,
+                cantidad_devuelta: devuelta,
+                unidad_venta: 1,
+            }
+        };
+        VentaConDetalles {
+            venta: Venta {
+                id: 42,
+                fecha: NaiveDate::from_ymd_opt(2025, 3, 14)
+                    .and_then(|d| d.and_hms_opt(15, 9, 26))
+                    .unwrap(),
+                total: 19.0,
+                usuario_nombre: Some("maria".to_string()),
+                cliente_id: Some(2),
+                cliente_nombre: Some("José Pérez".to_string()),
+                cliente_documento: Some("V-12345678".to_string()),
+                monto_credito: 0.0,
+                estado: EstadoVenta::DevolucionParcial,
+                monto_devuelto: 1.5,
+                vuelto: 1.0,
+                tasa_cambio: Some(36.5),
+                impuesto: 2.21,
+                impuesto_devuelto: 0.0,
+            },
+            detalles: vec![
+                linea(1, "Harina de maíz precocida 1kg", 2.0, 1.5, 0.0, 1.0),
+                linea(2, "Café molido (250g)", 1.0, 4.0, 16.0, 0.0),
+                linea(3, "Aceite vegetal \\ girasol 1L", 3.0, 4.0, 16.0, 0.0),
+            ],
+            pagos: vec![
+                Pago {
+                    metodo: MetodoPago::PagoMovil,
+                    monto: 10.0,
+                    referencia: Some("0412-88231".to_string()),
+                },
+                Pago {
+                    metodo: MetodoPago::Efectivo,
+                    monto: 10.0,
+                    referencia: None,
+                },
+            ],
+        }
+    }
+
+    fn tienda_de_prueba() -> DatosTienda {
+        DatosTienda {
+            nombre: "Bodega La Esquina".to_string(),
+            rif: "J-12345678-9".to_string(),
+            direccion: "Av. Bolívar, local 3, Valencia".to_string(),
+            telefono: "0241-555-0101".to_string(),
+            pie: "¡Gracias por su compra!".to_string(),
+        }
+    }
+
+    fn comparar_golden(nombre: &str, generado: &[u8]) {
+        let ruta = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/golden")
+            .join(nombre);
+        if std::env::var_os("BODEX_ACTUALIZAR_GOLDEN").is_some() {
+            std::fs::create_dir_all(ruta.parent().unwrap()).unwrap();
+            std::fs::write(&ruta, generado).unwrap();
+            return;
+        }
+        let esperado = std::fs::read(&ruta)
+            .unwrap_or_else(|e| panic!("No se pudo leer {}: {}", ruta.display(), e));
+        assert!(
+            esperado == generado,
+            "{} no coincide con el archivo golden (BODEX_ACTUALIZAR_GOLDEN=1 para regenerarlo)",
+            nombre
+        );
+    }
+
+    #[test]
+    fn lineas_respetan_el_ancho() {
+        for ancho in [AnchoPapel::Mm58, AnchoPapel::Mm80] {
+            let lineas = componer(&venta_de_prueba(), &tienda_de_prueba(), ancho.columnas());
+            assert!(lineas
+                .iter()
+                .all(|l| l.texto.chars().count() <= ancho.columnas()));
+        }
+    }
+
+    #[test]
+    fn escpos_58mm() {
+        let bytes = escpos::generar(&venta_de_prueba(), &tienda_de_prueba(), AnchoPapel::Mm58);
+        comparar_golden("recibo_58mm.bin", &bytes);
+    }
+
+    #[test]
+    fn escpos_80mm() {
+        let bytes = escpos::generar(&venta_de_prueba(), &tienda_de_prueba(), AnchoPapel::Mm80);
+        comparar_golden("recibo_80mm.bin", &bytes);
+    }
+
+    #[test]
+    fn pdf_80mm() {
+        let bytes = pdf::generar(&venta_de_prueba(), &tienda_de_prueba(), AnchoPapel::Mm80);
+        comparar_golden("recibo_80mm.pdf", &bytes);
+    }
+}
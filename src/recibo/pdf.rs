@@ -0,0 +1,105 @@
+//! Recibo en PDF de una sola página, del ancho del rollo térmico.
+//! Se escribe a mano (fuentes Courier estándar, sin fechas de creación) para que la
+//! salida sea determinista.
+
+use super::{componer, Estilo};
+use crate::models::{AnchoPapel, DatosTienda, VentaConDetalles};
+
+/// Margen en puntos alrededor del texto
+const MARGEN: f64 = 8.0;
+
+/// Ancho de un carácter de Courier relativo al tamaño de la fuente
+const ANCHO_COURIER: f64 = 0.6;
+
+/// Texto en WinAnsi (igual a Latin-1 para los acentos del español) con los
+/// caracteres especiales de las cadenas PDF escapados
+fn cadena(texto: &str) -> Vec<u8> {
+    let mut salida = vec![b'('];
+    for c in texto.chars() {
+        match c {
+            '\\' | '(' | ')' => salida.extend_from_slice(&[b'\\', c as u8]),
+            ' '..='~' | '\u{A0}'..='\u{FF}' => salida.push(c as u32 as u8),
+            _ => salida.push(b'?'),
+        }
+    }
+    salida.push(b')');
+    salida
+}
+
+/// Bytes del archivo PDF
+pub fn generar(venta: &VentaConDetalles, tienda: &DatosTienda, ancho: AnchoPapel) -> Vec<u8> {
+    let lineas = componer(venta, tienda, ancho.columnas());
+
+    // La fuente se ajusta para que las columnas del papel llenen el ancho útil
+    let ancho_pagina = ancho.puntos();
+    let tamano = (ancho_pagina - 2.0 * MARGEN) / (ancho.columnas() as f64 * ANCHO_COURIER);
+    let interlineado = tamano * 1.25;
+    let alto_pagina = 2.0 * MARGEN + interlineado * lineas.len() as f64;
+
+    let mut contenido = format!(
+        "BT\n{:.2} TL\n{:.2} {:.2} Td\n",
+        interlineado,
+        MARGEN,
+        alto_pagina - MARGEN - tamano
+    )
+    .into_bytes();
+    let mut fuente_actual = "";
+    for linea in &lineas {
+        let fuente = if linea.estilo == Estilo::Normal {
+            "/F1"
+        } else {
+            "/F2"
+        };
+        if fuente != fuente_actual {
+            contenido.extend_from_slice(format!("{} {:.2} Tf\n", fuente, tamano).as_bytes());
+            fuente_actual = fuente;
+        }
+        contenido.extend_from_slice(&cadena(&linea.texto));
+        contenido.extend_from_slice(b" Tj T*\n");
+    }
+    contenido.extend_from_slice(b"ET\n");
+
+    let mut objetos: Vec<Vec<u8>> = vec![
+        b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+        b"<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_vec(),
+        format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {:.2} {:.2}] \
+             /Resources << /Font << /F1 4 0 R /F2 5 0 R >> >> /Contents 6 0 R >>",
+            ancho_pagina, alto_pagina
+        )
+        .into_bytes(),
+        b"<< /Type /Font /Subtype /Type1 /BaseFont /Courier /Encoding /WinAnsiEncoding >>".to_vec(),
+        b"<< /Type /Font /Subtype /Type1 /BaseFont /Courier-Bold /Encoding /WinAnsiEncoding >>"
+            .to_vec(),
+    ];
+    let mut flujo = format!("<< /Length {} >>\nstream\n", contenido.len()).into_bytes();
+    flujo.extend_from_slice(&contenido);
+    flujo.extend_from_slice(b"endstream");
+    objetos.push(flujo);
+
+    let mut salida = b"%PDF-1.4\n".to_vec();
+    let mut posiciones = Vec::with_capacity(objetos.len());
+    for (i, objeto) in objetos.iter().enumerate() {
+        posiciones.push(salida.len());
+        salida.extend_from_slice(format!("{} 0 obj\n", i + 1).as_bytes());
+        salida.extend_from_slice(objeto);
+        salida.extend_from_slice(b"\nendobj\n");
+    }
+
+    let inicio_xref = salida.len();
+    salida.extend_from_slice(
+        format!("xref\n0 {}\n0000000000 65535 f \n", objetos.len() + 1).as_bytes(),
+    );
+    for posicion in posiciones {
+        salida.extend_from_slice(format!("{:010} 00000 n \n", posicion).as_bytes());
+    }
+    salida.extend_from_slice(
+        format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objetos.len() + 1,
+            inicio_xref
+        )
+        .as_bytes(),
+    );
+    salida
+}
@@ -0,0 +1,59 @@
+use crate::models::{Permiso, Usuario};
+use std::sync::{Mutex, OnceLock};
+
+/// Usuario autenticado en la sesión actual
+static SESSION: OnceLock<Mutex<Option<Usuario>>> = OnceLock::new();
+
+fn get_session() -> &'static Mutex<Option<Usuario>> {
+    SESSION.get_or_init(|| Mutex::new(None))
+}
+
+/// Inicia la sesión con el usuario validado en el login
+pub fn iniciar(usuario: Usuario) {
+    *get_session().lock().unwrap() = Some(usuario);
+}
+
+/// Cierra la sesión actual
+pub fn cerrar() {
+    *get_session().lock().unwrap() = None;
+}
+
+/// Copia del usuario autenticado, si hay sesión
+pub fn usuario_actual() -> Option<Usuario> {
+    get_session().lock().unwrap().clone()
+}
+
+/// Marca en la sesión que el usuario ya cambió la contraseña por defecto
+pub fn password_cambiada() {
+    if let Some(u) = get_session().lock().unwrap().as_mut() {
+        u.debe_cambiar_password = false;
+    }
+}
+
+/// Indica si el usuario actual tiene el permiso (false si no hay sesión
+/// o si aún debe cambiar la contraseña por defecto)
+pub fn puede(permiso: Permiso) -> bool {
+    get_session()
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|u| !u.debe_cambiar_password && u.rol.puede(permiso))
+        .unwrap_or(false)
+}
+
+/// Verifica el permiso y devuelve el usuario autenticado,
+/// o un mensaje listo para mostrar en la UI si la acción no está permitida
+pub fn requerir(permiso: Permiso) -> Result<Usuario, String> {
+    match usuario_actual() {
+        None => Err("Debe iniciar sesión para continuar".to_string()),
+        Some(u) if u.debe_cambiar_password => {
+            Err("Debe cambiar la contraseña por defecto antes de continuar".to_string())
+        }
+        Some(u) if u.rol.puede(permiso) => Ok(u),
+        Some(u) => Err(format!(
+            "El rol {} no tiene permiso para {}",
+            u.rol,
+            permiso.descripcion()
+        )),
+    }
+}
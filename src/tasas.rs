@@ -0,0 +1,158 @@
+use crate::db;
+use crate::models::{Moneda, TasaCambio};
+use chrono::{Local, NaiveDate};
+use slint::{ModelRc, SharedString, StandardListViewItem, VecModel};
+use std::rc::Rc;
+use std::sync::{Mutex, OnceLock};
+
+/// Monedas registradas (la base primero), en el orden de los selectores
+static MONEDAS: OnceLock<Mutex<Vec<Moneda>>> = OnceLock::new();
+
+fn get_monedas() -> &'static Mutex<Vec<Moneda>> {
+    MONEDAS.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+/// Día local de la tienda, para elegir la tasa vigente
+pub fn hoy() -> NaiveDate {
+    Local::now().date_naive()
+}
+
+/// Carga las monedas de la base de datos y actualiza la caché de los selectores
+pub fn cargar_monedas() -> Result<Vec<Moneda>, Box<dyn std::error::Error>> {
+    let conn = db::obtener_conexion()?;
+    let monedas = db::monedas::obtener_monedas(&conn)?;
+    *get_monedas().lock().unwrap() = monedas.clone();
+    Ok(monedas)
+}
+
+/// Nombres para el selector de moneda del precio ("USD ($)", "VES (Bs)")
+pub fn monedas_para_select() -> ModelRc<SharedString> {
+    let nombres: Vec<SharedString> = get_monedas()
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|m| SharedString::from(format!("{} ({})", m.codigo, m.simbolo)))
+        .collect();
+    ModelRc::from(Rc::new(VecModel::from(nombres)))
+}
+
+/// Código de la moneda elegida en el selector de precio (la base si el índice no es válido)
+pub fn codigo_moneda(index: i32) -> String {
+    get_monedas()
+        .lock()
+        .unwrap()
+        .get(index.max(0) as usize)
+        .map(|m| m.codigo.clone())
+        .unwrap_or_else(|| Moneda::BASE.to_string())
+}
+
+/// Índice del selector de precio para la moneda indicada (0 = base)
+pub fn indice_moneda(codigo: &str) -> i32 {
+    get_monedas()
+        .lock()
+        .unwrap()
+        .iter()
+        .position(|m| m.codigo == codigo)
+        .map(|i| i as i32)
+        .unwrap_or(0)
+}
+
+/// Monedas que llevan tasa (todas menos la base), para el selector de la pantalla de tasas
+fn monedas_con_tasa() -> Vec<Moneda> {
+    get_monedas().lock().unwrap().iter().filter(|m| !m.es_base).cloned().collect()
+}
+
+/// Nombres para el selector de la pantalla de tasas
+pub fn monedas_con_tasa_para_select() -> ModelRc<SharedString> {
+    let nombres: Vec<SharedString> = monedas_con_tasa()
+        .iter()
+        .map(|m| SharedString::from(format!("{} - {}", m.codigo, m.nombre)))
+        .collect();
+    ModelRc::from(Rc::new(VecModel::from(nombres)))
+}
+
+/// Precio con su moneda: los importes en la moneda base se muestran sin sufijo
+pub fn precio_con_moneda(precio: f64, moneda: &str) -> String {
+    if moneda == Moneda::BASE {
+        format!("{:.2}", precio)
+    } else {
+        format!("{:.2} {}", precio, moneda)
+    }
+}
+
+/// Tasa vigente hoy para la moneda (None si nunca se registró una)
+pub fn tasa_del_dia(moneda: &str) -> Result<Option<f64>, Box<dyn std::error::Error>> {
+    let conn = db::obtener_conexion()?;
+    Ok(db::monedas::tasa_vigente(&conn, moneda, hoy())?)
+}
+
+/// Interpreta la fecha de la tasa (dd/mm/aaaa). Vacía = hoy.
+pub fn parse_fecha(texto: &str) -> Result<NaiveDate, String> {
+    let texto = texto.trim();
+    if texto.is_empty() {
+        return Ok(hoy());
+    }
+    NaiveDate::parse_from_str(texto, "%d/%m/%Y").map_err(|_| "La fecha debe tener el formato dd/mm/aaaa".to_string())
+}
+
+/// Registra la tasa de la moneda elegida (índice en el selector de tasas) para la fecha indicada.
+/// Devuelve el código de la moneda y la fecha registrada.
+pub fn registrar(
+    moneda_index: i32,
+    fecha: &str,
+    tasa: &str,
+    usuario_id: i64,
+) -> Result<(String, NaiveDate), Box<dyn std::error::Error>> {
+    let moneda = monedas_con_tasa()
+        .into_iter()
+        .nth(moneda_index.max(0) as usize)
+        .ok_or("Seleccione una moneda")?;
+    let fecha = parse_fecha(fecha)?;
+    let tasa: f64 = tasa
+        .trim()
+        .replace(',', ".")
+        .parse()
+        .map_err(|_| "La tasa debe ser un número válido")?;
+
+    let conn = db::obtener_conexion()?;
+    db::monedas::registrar_tasa(&conn, &moneda.codigo, fecha, tasa, Some(usuario_id))?;
+    Ok((moneda.codigo, fecha))
+}
+
+/// Histórico reciente de tasas (Send-safe)
+pub fn cargar_tasas() -> Result<Vec<TasaCambio>, Box<dyn std::error::Error>> {
+    let conn = db::obtener_conexion()?;
+    Ok(db::monedas::obtener_tasas(&conn, 200)?)
+}
+
+/// Texto con la tasa vigente hoy de cada moneda que lleva tasa
+pub fn resumen_vigentes() -> Result<String, Box<dyn std::error::Error>> {
+    let conn = db::obtener_conexion()?;
+    let mut partes = Vec::new();
+    for moneda in monedas_con_tasa() {
+        partes.push(match db::monedas::tasa_vigente(&conn, &moneda.codigo, hoy())? {
+            Some(tasa) => format!("1 {} = {:.4} {}", Moneda::BASE, tasa, moneda.simbolo),
+            None => format!("{}: sin tasa registrada", moneda.codigo),
+        });
+    }
+    Ok(format!("Vigente hoy ({}): {}", hoy().format("%d/%m/%Y"), partes.join(" · ")))
+}
+
+/// Convierte las tasas a filas de tabla (Fecha, Moneda, Tasa, Registró, Registrada)
+pub fn tasas_to_model_rows(tasas: &[TasaCambio]) -> ModelRc<ModelRc<StandardListViewItem>> {
+    let rows: Vec<ModelRc<StandardListViewItem>> = tasas
+        .iter()
+        .map(|t| {
+            let row_data = vec![
+                StandardListViewItem::from(SharedString::from(t.fecha.format("%d/%m/%Y").to_string())),
+                StandardListViewItem::from(SharedString::from(t.moneda.as_str())),
+                StandardListViewItem::from(SharedString::from(format!("{:.4}", t.tasa))),
+                StandardListViewItem::from(SharedString::from(t.usuario_nombre.clone().unwrap_or_default())),
+                StandardListViewItem::from(SharedString::from(t.registrada.format("%d/%m/%Y %H:%M").to_string())),
+            ];
+            ModelRc::from(Rc::new(VecModel::from(row_data)))
+        })
+        .collect();
+
+    ModelRc::from(Rc::new(VecModel::from(rows)))
+}
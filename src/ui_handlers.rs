@@ -1,5 +1,27 @@
+use crate::caja;
+use crate::categorias;
+use crate::clientes;
+use crate::compras;
+use crate::credito;
 use crate::db;
+use crate::historial_ventas;
+use crate::impuestos;
 use crate::inventory;
+use crate::kardex;
+use crate::lotes;
+use crate::marcas;
+use crate::models::medida::{formatear_cantidad, redondear_cantidad};
+use crate::models::{
+    AnchoPapel, CategoriaConConteo, Cliente, ConfiguracionRecibo, DatosTienda, EstadoOrden, Impuesto, Lote, MarcaConProductos,
+    Medida, ModoPrecios, OrdenCompra, Permiso, Proveedor, Rol, Venta, VentaConDetalles,
+};
+use crate::pos;
+use crate::proveedores;
+use crate::recibo;
+use crate::session;
+use crate::tasas;
+use crate::usuarios;
+use crate::valoracion;
 use crate::AppWindow;
 use slint::{ComponentHandle, SharedString, Weak};
 
@@ -75,27 +97,23 @@ pub fn setup_callbacks(ui: &AppWindow) {
                 }
                 
                 // 2. El formato es válido, ahora verificar unicidad en la base de datos
-                match db::open_connection() {
-                    Ok(conn) => {
-                        match db::productos::existe_sku(&conn, sku.as_str()) {
-                            Ok(existe) => {
-                                if existe {
-                                    // SKU duplicado en BD
-                                    ui.set_sku_duplicado(true);
-                                    ui.set_mensaje_error("El código SKU ya existe en la base de datos".into());
-                                } else {
-                                    // Todo bien: limpiar todos los errores
-                                    ui.set_sku_duplicado(false);
-                                    ui.set_mensaje_error("".into());
-                                }
-                            }
-                            Err(e) => {
-                                eprintln!("Error al verificar SKU: {}", e);
-                            }
+                // En edición, el SKU actual del propio producto no cuenta como duplicado
+                let excluir_id = (ui.get_product_screen() == "editar")
+                    .then(|| ui.get_edit_product_id() as i64);
+                match inventory::sku_existe(sku.as_str(), excluir_id) {
+                    Ok(existe) => {
+                        if existe {
+                            // SKU duplicado en BD
+                            ui.set_sku_duplicado(true);
+                            ui.set_mensaje_error("El código SKU ya existe en la base de datos".into());
+                        } else {
+                            // Todo bien: limpiar todos los errores
+                            ui.set_sku_duplicado(false);
+                            ui.set_mensaje_error("".into());
                         }
                     }
                     Err(e) => {
-                        eprintln!("Error de conexión: {}", e);
+                        eprintln!("Error al verificar SKU: {}", e);
                     }
                 }
             }
@@ -104,11 +122,10 @@ pub fn setup_callbacks(ui: &AppWindow) {
 
     // === CALLBACK PARA MANEJAR CAMBIO DE TEXTO EN SKU ===
     // Este callback se llama desde InputText cuando el usuario escribe
-    // Parámetros: (texto_actual)
-    // Limpia los errores de SKU duplicado y mensaje general
+    // Limpia los errores de SKU duplicado y mensaje general (el texto actual no se usa)
     ui.on_changed({
         let ui_handle = ui_handle.clone();
-        move |texto: SharedString| {
+        move |_| {
             if let Some(ui) = ui_handle.upgrade() {
                 // Limpiar errores cuando el usuario empieza a escribir
                 // IMPORTANTE: No limpiar 'error-sku' aquí, ya que eso es manejado por Slint
@@ -124,18 +141,76 @@ pub fn setup_callbacks(ui: &AppWindow) {
         let ui_handle = ui_handle.clone();
         move |user: SharedString, pass: SharedString| {
             if let Some(ui) = ui_handle.upgrade() {
-                match db::open_connection() {
+                match db::obtener_conexion() {
                     Ok(conn) => {
                         match db::usuarios::validar_usuario(&conn, user.as_str(), pass.as_str()) {
-                            Ok(Some(_usuario)) => {
-                                ui.set_current_view("dashboard".into());
-                                refresh_ui(ui_handle.clone());
+                            Ok(Some(usuario)) => {
+                                ui.set_current_username(usuario.username.as_str().into());
+                                ui.set_current_rol(usuario.rol.to_string().into());
+                                ui.set_mensaje_error("".into());
+                                let debe_cambiar = usuario.debe_cambiar_password;
+                                session::iniciar(usuario);
+                                aplicar_permisos(&ui);
+                                if debe_cambiar {
+                                    // Contraseña por defecto: exigir cambio antes de entrar
+                                    ui.set_current_view("cambiar-password".into());
+                                } else {
+                                    ui.set_current_view("dashboard".into());
+                                    refresh_ui(ui_handle.clone());
+                                }
+                            }
+                            Ok(None) => ui.set_mensaje_error("Usuario o contraseña incorrectos".into()),
+                            Err(e) => {
+                                eprintln!("Error DB: {}", e);
+                                ui.set_mensaje_error(format!("Error al validar el usuario: {}", e).into());
                             }
-                            Ok(None) => eprintln!("Credenciales incorrectas"),
-                            Err(e) => eprintln!("Error DB: {}", e),
                         }
                     }
-                    Err(e) => eprintln!("Error conexión: {}", e),
+                    Err(e) => {
+                        eprintln!("Error conexión: {}", e);
+                        ui.set_mensaje_error(format!("No se pudo abrir la base de datos: {}", e).into());
+                    }
+                }
+            }
+        }
+    });
+
+    ui.on_cambiar_password({
+        let ui_handle = ui_handle.clone();
+        move |nueva: SharedString, confirmacion: SharedString| {
+            let Some(ui) = ui_handle.upgrade() else { return };
+
+            if let Err(msg) = usuarios::validar_password(
+                ui.get_current_username().as_str(),
+                nueva.as_str(),
+                confirmacion.as_str(),
+            ) {
+                ui.set_mensaje_error(msg.into());
+                return;
+            }
+
+            let Some(usuario) = session::usuario_actual() else {
+                ui.set_mensaje_error("Debe iniciar sesión para continuar".into());
+                return;
+            };
+            let id = usuario.id;
+            let resultado = db::obtener_conexion()
+                .and_then(|conn| db::usuarios::cambiar_password(&conn, id, nueva.as_str()));
+
+            match resultado {
+                Ok(true) => {
+                    println!("Contraseña actualizada para el usuario #{}", id);
+                    // Recién ahora se habilitan los permisos del rol
+                    session::password_cambiada();
+                    aplicar_permisos(&ui);
+                    ui.set_mensaje_error("".into());
+                    ui.set_current_view("dashboard".into());
+                    refresh_ui(ui_handle.clone());
+                }
+                Ok(false) => ui.set_mensaje_error("El usuario ya no existe".into()),
+                Err(e) => {
+                    eprintln!("Error al cambiar la contraseña: {}", e);
+                    ui.set_mensaje_error(format!("Error al guardar: {}", e).into());
                 }
             }
         }
@@ -145,15 +220,30 @@ pub fn setup_callbacks(ui: &AppWindow) {
         let ui_handle = ui_handle.clone();
         move || {
             if let Some(ui) = ui_handle.upgrade() {
+                // Descartar la venta en curso al cerrar sesión
+                pos::limpiar();
+                refresh_pos(&ui);
+                reiniciar_cliente_pos(&ui);
+                ui.set_pos_ultima_venta(0);
+                set_pos_mensaje(&ui, "", false);
+                // Descartar también la orden de compra en preparación
+                compras::limpiar_borrador();
+                limpiar_orden_compra(&ui);
+                session::cerrar();
+                aplicar_permisos(&ui);
+                ui.set_current_username("".into());
+                ui.set_current_rol("".into());
+                ui.set_product_screen("inicio".into());
+                ui.set_mensaje_error("".into());
                 ui.set_current_view("login".into());
             }
         }
     });
 
     // 2. GESTIÓN DE PRODUCTOS
-    // Callback desde Slint: (nombre, p_neto, p_venta, stock, desc, peso, tam, u_med, pres, cod, venc, activo, m_id, cat_id, subcat_id, empaque_id)
-    // Función inventory::add_product: (nombre, precio_neto, precio_venta, stock, descripcion, codigo, activo_str, marca_id,
-    //                                   medida_p_id, cantidad_p, medida_s_id, cantidad_s, empaque_id, categoria_id, subcategoria_id)
+    // Callback desde Slint: (nombre, p_neto, p_venta, stock, desc, peso, tam, u_med, pres, cod, venc, activo, m_id, cat_id, subcat_id, empaque_id, moneda, impuesto,
+    //                       stock_min, reorden)
+    // inventory::add_product recibe estos campos en un inventory::ProductoForm, con los selectores ya traducidos a IDs
     ui.on_add_product({
         let ui_handle = ui_handle.clone();
         move |nombre,
@@ -166,12 +256,17 @@ pub fn setup_callbacks(ui: &AppWindow) {
               u_medida,  // -> medida_s_id
               pres,      // -> cantidad_s
               cod,
-              _f_venc,   // Fecha de vencimiento (aún no persistida en la nueva arquitectura)
+              _vencimiento, // Siempre vacío: el vencimiento se registra por lote
               activo,    // Es SharedString desde Slint (string)
               m_id,
               cat_id,    // -> categoria_id (nuevo parámetro 14)
               subcat_id, // -> subcategoria_id (nuevo parámetro 15)
-              empaque_id| // -> empaque_id (nuevo parámetro 16)
+              empaque_id, // -> empaque_id (nuevo parámetro 16)
+              moneda,     // -> índice de moneda del precio (nuevo parámetro 17)
+              impuesto,   // -> índice de IVA, 0 = según categoría (nuevo parámetro 18)
+              stock_min,  // -> stock_minimo (nuevo parámetro 19)
+              reorden,    // -> punto_reorden (nuevo parámetro 20)
+              unidad_venta| // -> unidad_venta, id de la medida (nuevo parámetro 21)
             
             {
             
@@ -179,9 +274,14 @@ pub fn setup_callbacks(ui: &AppWindow) {
             // Validación completa de SKU (formato + unicidad) + Validación financiera
             {
                 if let Some(ui) = ui_handle.upgrade() {
+                    if let Err(msg) = session::requerir(Permiso::CrearProducto) {
+                        ui.set_mensaje_error(msg.into());
+                        return;
+                    }
+
                     // Validar formato y unicidad del SKU
                     if !cod.trim().is_empty() {
-                        let validacion_sku = inventory::validar_sku_completo(cod.as_str());
+                        let validacion_sku = inventory::validar_sku_completo(cod.as_str(), None);
                         if !validacion_sku.es_valido {
                             ui.set_sku_duplicado(true);
                             if let Some(error_msg) = validacion_sku.error {
@@ -198,39 +298,19 @@ pub fn setup_callbacks(ui: &AppWindow) {
                     }
                     
                     // === VALIDACIÓN FINANCIERA ===
-                    // Parseo seguro de valores numéricos
-                    let neto: f64 = p_neto.as_str().trim().parse().unwrap_or(-1.0);
-                    let venta: f64 = p_venta.as_str().trim().parse().unwrap_or(-1.0);
-                    
-                    // Validar que los precios sean positivos
-                    if neto <= 0.0 {
-                        ui.set_mensaje_error("El costo neto debe ser un número positivo mayor a 0".into());
-                        return;
-                    }
-                    
-                    if venta <= 0.0 {
-                        ui.set_mensaje_error("El precio de venta debe ser un número positivo mayor a 0".into());
+                    let moneda = tasas::codigo_moneda(moneda.parse().unwrap_or(0));
+                    if let Err(msg) = inventory::validar_precios(p_neto.as_str(), p_venta.as_str(), &moneda) {
+                        ui.set_mensaje_error(msg.into());
                         return;
                     }
-                    
-                    // Validar que no haya pérdida (precio de venta >= costo)
-                    if venta < neto {
-                        ui.set_mensaje_error(
-                            format!(
-                                "¡Pérdida detectada! El precio de venta (${:.2}) es menor al costo (${:.2})",
-                                venta, neto
-                            ).into()
-                        );
+                    let medida_venta = Medida::from_i32(unidad_venta.parse().unwrap_or(1));
+                    if let Err(msg) =
+                        inventory::validar_niveles_stock(stock.as_str(), stock_min.as_str(), reorden.as_str(), medida_venta)
+                    {
+                        ui.set_mensaje_error(msg.into());
                         return;
                     }
                     
-                    // Advertencia si el margen es muy bajo (menos del 10%)
-                    let margen_porcentaje = ((venta - neto) / neto) * 100.0;
-                    if margen_porcentaje < 10.0 && margen_porcentaje > 0.0 {
-                        println!("⚠ Advertencia: Margen de ganancia bajo ({:.1}%)", margen_porcentaje);
-                        // No bloqueamos el guardado, solo mostramos advertencia en consola
-                    }
-                    
                     // Limpiar errores si todo está bien
                     ui.set_sku_duplicado(false);
                     ui.set_mensaje_error("".into());
@@ -240,22 +320,39 @@ pub fn setup_callbacks(ui: &AppWindow) {
             
             // IMPORTANTE: Convertir SharedString a String ANTES de tokio::spawn
             // SharedString no es Send, pero String sí lo es
-            let nombre = nombre.to_string();
-            let p_neto = p_neto.to_string();
-            let p_venta = p_venta.to_string();
-            let stock = stock.to_string();
-            let desc = desc.to_string();
-            let peso = peso.to_string();
-            let tam = tam.to_string();
-            let u_medida = u_medida.to_string();
-            let pres = pres.to_string();
-            let cod = cod.to_string();
-            let _f_venc = _f_venc.to_string();
-            let empaque_id = empaque_id.to_string();
-            let activo_str = activo.to_string();  // Ya viene como string desde Slint
-            let m_id = m_id.to_string();
-            let cat_id = cat_id.to_string();
-            let subcat_id = subcat_id.to_string();
+            let form = inventory::ProductoForm {
+                nombre: nombre.to_string(),
+                precio_neto: p_neto.to_string(),
+                precio_venta: p_venta.to_string(),
+                stock: stock.to_string(),
+                descripcion: desc.to_string(),
+                codigo: cod.to_string(),
+                activo: activo == "true", // Ya viene como string desde Slint
+                // El selector envía el índice en lista-marcas; la caché de marcas da el id real
+                marca_id: marcas::id_para_select(m_id.parse().unwrap_or(0))
+                    .map(|id| id.to_string())
+                    .unwrap_or_default(),
+                medida_p_id: peso.to_string(),
+                cantidad_p: tam.to_string(),
+                medida_s_id: u_medida.to_string(),
+                cantidad_s: pres.to_string(),
+                empaque_id: empaque_id.to_string(),
+                // Categoría y subcategoría también llegan como índices de sus selectores
+                categoria_id: categorias::id_categoria(cat_id.parse().unwrap_or(0))
+                    .map(|id| id.to_string())
+                    .unwrap_or_default(),
+                subcategoria_id: categorias::id_subcategoria_form(subcat_id.parse().unwrap_or(0))
+                    .map(|id| id.to_string())
+                    .unwrap_or_default(),
+                // La moneda llega como índice del selector de monedas
+                moneda: tasas::codigo_moneda(moneda.parse().unwrap_or(0)),
+                impuesto_id: impuestos::id_impuesto_producto(impuesto.parse().unwrap_or(0))
+                    .map(|id| id.to_string())
+                    .unwrap_or_default(),
+                stock_minimo: stock_min.to_string(),
+                punto_reorden: reorden.to_string(),
+                unidad_venta: unidad_venta.to_string(),
+            };
 
             // Disparamos la tarea en un hilo de Tokio
             tokio::spawn({
@@ -263,25 +360,7 @@ pub fn setup_callbacks(ui: &AppWindow) {
                 async move {
                     // --- HILO SECUNDARIO ---
                     // 1. Intentamos guardar el producto (operación pesada de DB)
-                    let save_result = inventory::add_product(
-                        SharedString::from(&nombre),
-                        SharedString::from(&p_neto),
-                        SharedString::from(&p_venta),
-                        SharedString::from(&stock),
-                        SharedString::from(&desc),
-                        SharedString::from(&cod),
-                        SharedString::from(&activo_str),
-                        SharedString::from(&m_id),
-                        // Mapeo de campos adicionales
-                        SharedString::from(&peso),    // medida_p_id
-                        SharedString::from(&tam),     // cantidad_p
-                        SharedString::from(&u_medida), // medida_s_id
-                        SharedString::from(&pres),    // cantidad_s
-                        SharedString::from(&empaque_id),
-                        // Categoría y subcategoría
-                        SharedString::from(&cat_id),    // categoria_id
-                        SharedString::from(&subcat_id), // subcategoria_id
-                    );
+                    let save_result = inventory::add_product(&form);
 
                     // 2. Procesar resultado y preparar mensaje ANTES de upgrade_in_event_loop
                     let (success, error_msg) = match save_result {
@@ -340,22 +419,34 @@ pub fn setup_callbacks(ui: &AppWindow) {
     ui.on_delete_product({
         let ui_handle = ui_handle.clone();
         move |index| {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            if let Err(msg) = session::requerir(Permiso::EliminarProducto) {
+                ui.set_mensaje_error(msg.into());
+                return;
+            }
+            ui.set_mensaje_error("".into());
+
             tokio::spawn({
                 let ui_handle = ui_handle.clone();
                 async move {
                     let result = inventory::delete_product_by_index(index);
                     
                     // Procesar resultado ANTES de upgrade_in_event_loop
-                    let success = result.is_ok();
-                    if let Err(e) = &result {
-                        eprintln!("Error al eliminar: {}", e);
-                    } else {
-                        println!("Producto eliminado exitosamente.");
-                    }
+                    let error = match &result {
+                        Err(e) => {
+                            eprintln!("Error al eliminar: {}", e);
+                            Some(e.to_string())
+                        }
+                        Ok(_) => {
+                            println!("Producto eliminado exitosamente.");
+                            None
+                        }
+                    };
                     
                     if let Err(e) = ui_handle.upgrade_in_event_loop(move |ui| {
-                        if success {
-                            refresh_ui_from_main(&ui);
+                        match error {
+                            Some(msg) => ui.set_mensaje_error(msg.into()),
+                            None => refresh_ui_from_main(&ui),
                         }
                     }) {
                         eprintln!("Error al actualizar UI: {}", e);
@@ -368,78 +459,2807 @@ pub fn setup_callbacks(ui: &AppWindow) {
     ui.on_get_product_for_edit({
         let ui_handle = ui_handle.clone();
         move |index| {
-            if let Some(product) = inventory::get_product_by_index(index) {
-                if let Some(ui) = ui_handle.upgrade() {
-                    ui.set_edit_product_id(product.id as i32);
-                    ui.set_edit_product_name(product.nombre.into());
-                    ui.set_edit_product_precio_venta(product.precio_venta.to_string().into());
-                    ui.set_edit_product_stock(product.stock.to_string().into());
+            let Some(ui) = ui_handle.upgrade() else { return };
+            if let Err(msg) = session::requerir(Permiso::EditarProducto) {
+                ui.set_mensaje_error(msg.into());
+                return;
+            }
+
+            match inventory::get_product_for_edit(index) {
+                Ok(Some(p)) => {
+                    ui.set_edit_product_id(p.id as i32);
+                    ui.set_edit_product_name(p.nombre.into());
+                    ui.set_edit_product_codigo(p.codigo.unwrap_or_default().into());
+                    ui.set_edit_product_precio_neto(format!("{:.2}", p.precio_neto).into());
+                    ui.set_edit_product_precio_venta(format!("{:.2}", p.precio_venta).into());
+                    ui.set_edit_product_stock(formatear_cantidad(p.stock).into());
+                    ui.set_edit_product_descripcion(p.descripcion.unwrap_or_default().into());
+                    ui.set_edit_product_cantidad_p(p.cantidad_p.to_string().into());
+                    ui.set_edit_product_cantidad_s(
+                        p.cantidad_s.map(|c| c.to_string()).unwrap_or_default().into(),
+                    );
+                    // Los selectores trabajan con índices base 0; los IDs en BD empiezan en 1
+                    ui.set_edit_product_medida_p_index(p.medida_p_id - 1);
+                    ui.set_edit_product_medida_s_index(p.medida_s_id.map(|m| m - 1).unwrap_or(-1));
+                    ui.set_edit_product_empaque_index(p.empaque_id - 1);
+                    ui.set_edit_product_marca_index(marcas::indice_para_select(p.marca_id));
+                    ui.set_edit_product_categoria_index(categorias::indice_categoria(p.categoria_id as i64));
+                    // Las subcategorías ofrecidas dependen de la categoría del producto
+                    match categorias::cargar_subcategorias_form(Some(p.categoria_id as i64)) {
+                        Ok(nombres) => ui.set_lista_subcategorias(
+                            slint::ModelRc::from(std::rc::Rc::new(slint::VecModel::from(nombres))),
+                        ),
+                        Err(e) => eprintln!("Error al cargar subcategorías: {}", e),
+                    }
+                    ui.set_edit_product_subcategoria_index(categorias::indice_subcategoria_form(
+                        p.subcategoria_id.map(i64::from),
+                    ));
+                    ui.set_edit_product_moneda_index(tasas::indice_moneda(&p.moneda));
+                    ui.set_edit_product_impuesto_index(impuestos::indice_impuesto_producto(p.impuesto_id));
+                    ui.set_edit_product_stock_minimo(formatear_cantidad(p.stock_minimo).into());
+                    ui.set_edit_product_punto_reorden(formatear_cantidad(p.punto_reorden).into());
+                    ui.set_edit_product_unidad_venta_index(p.unidad_venta - 1);
+                    ui.set_edit_product_activo(p.activo);
+
+                    ui.set_sku_duplicado(false);
+                    ui.set_mensaje_error("".into());
+                    ui.set_product_screen("editar".into());
                 }
+                Ok(None) => eprintln!("No se encontró el producto en la fila {}", index),
+                Err(e) => eprintln!("Error al cargar el producto: {}", e),
             }
         }
     });
 
-    ui.on_refresh_inventory({
+    ui.on_ver_kardex({
+        let ui_handle = ui_handle.clone();
+        move |index| {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            if let Err(msg) = session::requerir(Permiso::VerKardex) {
+                ui.set_mensaje_error(msg.into());
+                return;
+            }
+
+            match kardex::cargar(index) {
+                Ok(Some(kardex::KardexProducto { producto, movimientos })) => {
+                    // El saldo del último movimiento coincide con el stock guardado
+                    let stock = movimientos.last().map(|m| m.stock_resultante).unwrap_or(producto.stock);
+                    ui.set_kardex_producto(producto.nombre.into());
+                    ui.set_kardex_stock(Medida::from_i32(producto.unidad_venta).formatear(stock).into());
+                    ui.set_kardex_rows(kardex::to_model_rows(&movimientos));
+                    ui.set_mensaje_error("".into());
+                    ui.set_product_screen("kardex".into());
+                }
+                Ok(None) => eprintln!("No se encontró el producto en la fila {}", index),
+                Err(e) => ui.set_mensaje_error(format!("Error al cargar el kardex: {}", e).into()),
+            }
+        }
+    });
+
+    ui.on_ver_lotes({
         let ui_handle = ui_handle.clone();
-        move || refresh_ui(ui_handle.clone())
+        move |index| {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            if let Err(msg) = session::requerir(Permiso::VerKardex) {
+                ui.set_mensaje_error(msg.into());
+                return;
+            }
+
+            match lotes::cargar(index) {
+                Ok(Some(lotes::LotesProducto { producto, lotes: lista, sin_lote })) => {
+                    ui.set_lotes_producto_id(producto.id as i32);
+                    ui.set_lotes_producto(producto.nombre.into());
+                    mostrar_lotes(&ui, &lista, sin_lote);
+                    ui.set_lote_numero("".into());
+                    ui.set_lote_vencimiento("".into());
+                    ui.set_lote_cantidad("".into());
+                    set_lotes_mensaje(&ui, "", false);
+                    ui.set_mensaje_error("".into());
+                    ui.set_product_screen("lotes".into());
+                }
+                Ok(None) => eprintln!("No se encontró el producto en la fila {}", index),
+                Err(e) => ui.set_mensaje_error(format!("Error al cargar los lotes: {}", e).into()),
+            }
+        }
     });
 
-    ui.on_close_app({
+    ui.on_lote_asignar({
         let ui_handle = ui_handle.clone();
         move || {
-            if let Some(ui) = ui_handle.upgrade() {
-                let _ = ui.hide();
+            let Some(ui) = ui_handle.upgrade() else { return };
+            if let Err(msg) = session::requerir(Permiso::EditarProducto) {
+                set_lotes_mensaje(&ui, &msg, true);
+                return;
             }
+            let producto_id = ui.get_lotes_producto_id() as i64;
+            let numero = ui.get_lote_numero().to_string();
+            let vencimiento = ui.get_lote_vencimiento().to_string();
+            let cantidad = ui.get_lote_cantidad().to_string();
+            ui.set_procesando(true);
+
+            tokio::spawn({
+                let ui_handle = ui_handle.clone();
+                async move {
+                    let resultado = lotes::asignar(producto_id, &numero, &vencimiento, &cantidad)
+                        .and_then(|_| lotes::cargar_lotes(producto_id))
+                        .map_err(|e| e.to_string());
+
+                    if let Err(e) = ui_handle.upgrade_in_event_loop(move |ui| {
+                        ui.set_procesando(false);
+                        match resultado {
+                            Ok((lista, sin_lote)) => {
+                                mostrar_lotes(&ui, &lista, sin_lote);
+                                ui.set_lote_numero("".into());
+                                ui.set_lote_vencimiento("".into());
+                                ui.set_lote_cantidad("".into());
+                                set_lotes_mensaje(&ui, &format!("Lote '{}' actualizado", numero.trim()), false);
+                            }
+                            Err(msg) => set_lotes_mensaje(&ui, &msg, true),
+                        }
+                    }) {
+                        eprintln!("Error al actualizar UI: {}", e);
+                    }
+                }
+            });
         }
     });
-}
 
-/// Refresca la UI cargando datos en segundo plano (versión con Weak<AppWindow>)
-fn refresh_ui(ui_handle: Weak<AppWindow>) {
-    tokio::spawn(async move {
-        // --- HILO SECUNDARIO ---
-        // 1. Buscamos los datos crudos (operación pesada de DB)
-        // Usamos get_inventory_rows_raw() que devuelve tipos Send-safe
-        let filas_res = inventory::get_inventory_rows_raw();
+    ui.on_vencimientos_buscar({
+        let ui_handle = ui_handle.clone();
+        move |dias| {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            if let Err(msg) = session::requerir(Permiso::VerKardex) {
+                set_vencimientos_mensaje(&ui, &msg, true);
+                return;
+            }
+            let dias = dias.to_string();
 
-        if let Ok(filas) = filas_res {
-            // 2. Volvemos al hilo de la UI para actualizar la tabla
-            // Los datos crudos (Vec<ProductRowData>) son Send
-            if let Err(e) = ui_handle.upgrade_in_event_loop(move |ui| {
-                // Convertir datos crudos a ModelRc DENTRO del hilo de UI
-                let model_rows = inventory::raw_to_model_rows(filas);
-                ui.set_inventory_rows(model_rows);
-                println!("Tabla actualizada en segundo plano.");
-            }) {
-                eprintln!("Error al actualizar UI: {}", e);
+            tokio::spawn({
+                let ui_handle = ui_handle.clone();
+                async move {
+                    let resultado = lotes::por_vencer(&dias).map_err(|e| e.to_string());
+
+                    if let Err(e) = ui_handle.upgrade_in_event_loop(move |ui| match resultado {
+                        Ok(lista) => {
+                            let resumen = match lista.len() {
+                                0 => "Ningún lote vence en ese plazo".to_string(),
+                                n => format!("{} lote(s) por vencer o vencidos", n),
+                            };
+                            ui.set_vencimientos_rows(lotes::por_vencer_to_model_rows(&lista));
+                            set_vencimientos_mensaje(&ui, &resumen, false);
+                        }
+                        Err(msg) => set_vencimientos_mensaje(&ui, &msg, true),
+                    }) {
+                        eprintln!("Error al actualizar UI: {}", e);
+                    }
+                }
+            });
+        }
+    });
+
+    ui.on_valoracion_calcular({
+        let ui_handle = ui_handle.clone();
+        move |fecha: SharedString| {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            if let Err(msg) = session::requerir(Permiso::VerValoracion) {
+                set_valoracion_mensaje(&ui, &msg, true);
+                return;
             }
+            if let Err(msg) = valoracion::parse_fecha_corte(fecha.as_str()) {
+                set_valoracion_mensaje(&ui, &msg, true);
+                return;
+            }
+
+            ui.set_procesando(true);
+            let fecha = fecha.trim().to_string();
+
+            tokio::spawn({
+                let ui_handle = ui_handle.clone();
+                async move {
+                    let resultado = valoracion::cargar(&fecha).map_err(|e| e.to_string());
+
+                    if let Err(e) = ui_handle.upgrade_in_event_loop(move |ui| {
+                        ui.set_procesando(false);
+                        match resultado {
+                            Ok(productos) => {
+                                let marcas = valoracion::agrupar(&productos, |p| p.marca.as_str());
+                                let categorias = valoracion::agrupar(&productos, |p| p.categoria.as_str());
+                                ui.set_valoracion_productos(valoracion::productos_to_model_rows(&productos));
+                                ui.set_valoracion_marcas(valoracion::grupos_to_model_rows(&marcas));
+                                ui.set_valoracion_categorias(valoracion::grupos_to_model_rows(&categorias));
+                                ui.set_valoracion_total(format!("{:.2}", valoracion::total(&productos)).into());
+                                ui.set_valoracion_corte(fecha.into());
+                                set_valoracion_mensaje(&ui, "", false);
+                            }
+                            Err(msg) => set_valoracion_mensaje(&ui, &msg, true),
+                        }
+                    }) {
+                        eprintln!("Error al actualizar UI: {}", e);
+                    }
+                }
+            });
         }
     });
-}
 
-/// Refresca la UI desde el hilo principal (recibe referencia directa)
-fn refresh_ui_from_main(ui: &AppWindow) {
-    let ui_handle = ui.as_weak();
-    
-    tokio::spawn(async move {
-        let filas_res = inventory::get_inventory_rows_raw();
+    ui.on_update_product({
+        let ui_handle = ui_handle.clone();
+        move || {
+            let Some(ui) = ui_handle.upgrade() else { return };
 
-        if let Ok(filas) = filas_res {
-            if let Err(e) = ui_handle.upgrade_in_event_loop(move |ui| {
-                let model_rows = inventory::raw_to_model_rows(filas);
-                ui.set_inventory_rows(model_rows);
-                println!("Tabla actualizada en segundo plano.");
+            let id = ui.get_edit_product_id() as i64;
+            let nombre = ui.get_edit_product_name();
+            let codigo = ui.get_edit_product_codigo();
+            let p_neto = ui.get_edit_product_precio_neto();
+            let p_venta = ui.get_edit_product_precio_venta();
+            let moneda = tasas::codigo_moneda(ui.get_edit_product_moneda_index());
+
+            // === VALIDACIÓN PREVIA EN RUST (mismas reglas que el alta) ===
+            if nombre.trim().is_empty() {
+                ui.set_mensaje_error("El nombre del producto es requerido".into());
+                return;
+            }
+
+            if !codigo.trim().is_empty() {
+                let validacion_sku = inventory::validar_sku_completo(codigo.as_str(), Some(id));
+                if !validacion_sku.es_valido {
+                    ui.set_sku_duplicado(true);
+                    if let Some(error_msg) = validacion_sku.error {
+                        ui.set_mensaje_error(error_msg.into());
+                    }
+                    return;
+                }
+            }
+
+            if let Err(msg) = inventory::validar_precios(p_neto.as_str(), p_venta.as_str(), &moneda) {
+                ui.set_mensaje_error(msg.into());
+                return;
+            }
+
+            let stock = ui.get_edit_product_stock().to_string();
+            let stock_min = ui.get_edit_product_stock_minimo().to_string();
+            let reorden = ui.get_edit_product_punto_reorden().to_string();
+            // Índice del selector -> id de la medida (base 1)
+            let unidad_venta = ui.get_edit_product_unidad_venta_index() + 1;
+            if let Err(msg) =
+                inventory::validar_niveles_stock(&stock, &stock_min, &reorden, Medida::from_i32(unidad_venta))
+            {
+                ui.set_mensaje_error(msg.into());
+                return;
+            }
+
+            // Cambiar precios requiere un permiso aparte de la edición general
+            if let Err(msg) = session::requerir(Permiso::EditarProducto).and_then(|_| {
+                if inventory::precios_modificados(id, p_neto.as_str(), p_venta.as_str(), &moneda) {
+                    session::requerir(Permiso::EditarPrecio).map(|_| ())
+                } else {
+                    Ok(())
+                }
             }) {
-                eprintln!("Error al actualizar UI: {}", e);
+                ui.set_mensaje_error(msg.into());
+                return;
+            }
+
+            ui.set_sku_duplicado(false);
+            ui.set_mensaje_error("".into());
+            ui.set_procesando(true);
+
+            // Convertir a String ANTES de tokio::spawn (mismo patrón que add_product)
+            // Índices de los selectores -> IDs en BD (base 1); -1 significa "sin unidad secundaria"
+            let medida_s_index = ui.get_edit_product_medida_s_index();
+            let form = inventory::ProductoForm {
+                nombre: nombre.to_string(),
+                precio_neto: p_neto.to_string(),
+                precio_venta: p_venta.to_string(),
+                stock,
+                descripcion: ui.get_edit_product_descripcion().to_string(),
+                codigo: codigo.to_string(),
+                activo: ui.get_edit_product_activo(),
+                marca_id: marcas::id_para_select(ui.get_edit_product_marca_index())
+                    .map(|id| id.to_string())
+                    .unwrap_or_default(),
+                medida_p_id: (ui.get_edit_product_medida_p_index() + 1).to_string(),
+                cantidad_p: ui.get_edit_product_cantidad_p().to_string(),
+                medida_s_id: if medida_s_index < 0 { String::new() } else { (medida_s_index + 1).to_string() },
+                cantidad_s: ui.get_edit_product_cantidad_s().to_string(),
+                empaque_id: (ui.get_edit_product_empaque_index() + 1).to_string(),
+                categoria_id: categorias::id_categoria(ui.get_edit_product_categoria_index())
+                    .map(|id| id.to_string())
+                    .unwrap_or_default(),
+                subcategoria_id: categorias::id_subcategoria_form(ui.get_edit_product_subcategoria_index())
+                    .map(|id| id.to_string())
+                    .unwrap_or_default(),
+                moneda,
+                impuesto_id: impuestos::id_impuesto_producto(ui.get_edit_product_impuesto_index())
+                    .map(|id| id.to_string())
+                    .unwrap_or_default(),
+                stock_minimo: stock_min,
+                punto_reorden: reorden,
+                unidad_venta: unidad_venta.to_string(),
+            };
+
+            tokio::spawn({
+                let ui_handle = ui_handle.clone();
+                async move {
+                    let result = inventory::update_product(id, &form);
+
+                    let (success, error_msg) = match result {
+                        Ok(true) => {
+                            println!("Producto actualizado exitosamente.");
+                            (true, String::new())
+                        }
+                        Ok(false) => (false, "El producto ya no existe en la base de datos".to_string()),
+                        Err(e) => {
+                            let error_str = e.to_string();
+                            eprintln!("Error al actualizar el producto: {}", error_str);
+                            let msg = if error_str.contains("UNIQUE constraint") {
+                                "El código SKU ya existe en la base de datos".to_string()
+                            } else {
+                                format!("Error al guardar: {}", error_str)
+                            };
+                            (false, msg)
+                        }
+                    };
+
+                    if let Err(e) = ui_handle.upgrade_in_event_loop(move |ui| {
+                        ui.set_procesando(false);
+
+                        if success {
+                            ui.set_mensaje_error("".into());
+                            ui.set_sku_duplicado(false);
+                            refresh_ui_from_main(&ui);
+                            ui.set_product_screen("lista".into());
+                        } else {
+                            let es_error_sku = error_msg.contains("SKU");
+                            ui.set_mensaje_error(error_msg.into());
+                            if es_error_sku {
+                                ui.set_sku_duplicado(true);
+                            }
+                        }
+                    }) {
+                        eprintln!("Error al actualizar UI: {}", e);
+                    }
+                }
+            });
+        }
+    });
+
+    // 3. PUNTO DE VENTA
+    ui.on_pos_buscar({
+        let ui_handle = ui_handle.clone();
+        move |texto: SharedString| {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            if let Err(msg) = session::requerir(Permiso::RegistrarVenta) {
+                set_pos_mensaje(&ui, &msg, true);
+                return;
+            }
+
+            match pos::buscar(texto.as_str()) {
+                Ok(resultados) => {
+                    ui.set_pos_resultados(pos::resultados_to_model_rows(&resultados));
+                    // Código exacto (lector de barras o SKU tecleado): agregar directamente
+                    if let Some(index) = pos::indice_por_sku(texto.as_str()) {
+                        let _ = pos::agregar_resultado(index);
+                        refresh_pos(&ui);
+                    }
+                    if resultados.is_empty() && !texto.trim().is_empty() {
+                        set_pos_mensaje(&ui, "No se encontraron productos", true);
+                    } else {
+                        set_pos_mensaje(&ui, "", false);
+                    }
+                }
+                Err(e) => set_pos_mensaje(&ui, &format!("Error al buscar: {}", e), true),
             }
         }
     });
-}
 
-/// Carga los datos iniciales de forma asíncrona
-pub fn load_initial_data(ui: &AppWindow) {
-    let ui_handle = ui.as_weak();
-    
+    ui.on_pos_agregar({
+        let ui_handle = ui_handle.clone();
+        move |index| {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            if let Err(msg) = session::requerir(Permiso::RegistrarVenta) {
+                set_pos_mensaje(&ui, &msg, true);
+                return;
+            }
+            match pos::agregar_resultado(index) {
+                Ok(()) => set_pos_mensaje(&ui, "", false),
+                Err(msg) => set_pos_mensaje(&ui, &msg, true),
+            }
+            refresh_pos(&ui);
+        }
+    });
+
+    ui.on_pos_cambiar_cantidad({
+        let ui_handle = ui_handle.clone();
+        move |index, cantidad: SharedString| {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            match pos::cambiar_cantidad(index, cantidad.as_str()) {
+                Ok(()) => set_pos_mensaje(&ui, "", false),
+                Err(msg) => set_pos_mensaje(&ui, &msg, true),
+            }
+            refresh_pos(&ui);
+        }
+    });
+
+    ui.on_pos_quitar({
+        let ui_handle = ui_handle.clone();
+        move |index| {
+            if let Some(ui) = ui_handle.upgrade() {
+                pos::quitar(index);
+                refresh_pos(&ui);
+            }
+        }
+    });
+
+    ui.on_pos_agregar_pago({
+        let ui_handle = ui_handle.clone();
+        move |metodo, monto: SharedString, referencia: SharedString| {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            if let Err(msg) = session::requerir(Permiso::RegistrarVenta) {
+                set_pos_mensaje(&ui, &msg, true);
+                return;
+            }
+            match pos::agregar_pago(metodo, monto.as_str(), referencia.as_str()) {
+                Ok(()) => set_pos_mensaje(&ui, "", false),
+                Err(msg) => set_pos_mensaje(&ui, &msg, true),
+            }
+            refresh_pos(&ui);
+        }
+    });
+
+    ui.on_pos_quitar_pago({
+        let ui_handle = ui_handle.clone();
+        move |index| {
+            if let Some(ui) = ui_handle.upgrade() {
+                pos::quitar_pago(index);
+                refresh_pos(&ui);
+            }
+        }
+    });
+
+    ui.on_pos_alternar_credito({
+        let ui_handle = ui_handle.clone();
+        move || {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            match pos::alternar_credito() {
+                Ok(()) => set_pos_mensaje(&ui, "", false),
+                Err(msg) => set_pos_mensaje(&ui, &msg, true),
+            }
+            refresh_pos(&ui);
+        }
+    });
+
+    ui.on_pos_cancelar({
+        let ui_handle = ui_handle.clone();
+        move || {
+            if let Some(ui) = ui_handle.upgrade() {
+                pos::limpiar();
+                ui.set_pos_resultados(pos::resultados_to_model_rows(&[]));
+                set_pos_mensaje(&ui, "", false);
+                refresh_pos(&ui);
+                reiniciar_cliente_pos(&ui);
+            }
+        }
+    });
+
+    ui.on_pos_buscar_cliente({
+        let ui_handle = ui_handle.clone();
+        move |texto: SharedString| {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            if let Err(msg) = session::requerir(Permiso::RegistrarVenta) {
+                set_pos_mensaje(&ui, &msg, true);
+                return;
+            }
+
+            match clientes::buscar_para_pos(texto.as_str()) {
+                Ok(resultados) => {
+                    ui.set_pos_lista_clientes(clientes::clientes_pos_para_select(&resultados));
+                    // Con una sola coincidencia (aparte del consumidor final) se elige directamente
+                    let coincidencias = resultados.iter().filter(|c| c.id != Cliente::CONSUMIDOR_FINAL).count();
+                    ui.set_pos_cliente_index(if coincidencias == 1 && !texto.trim().is_empty() { 1 } else { 0 });
+                    if coincidencias == 0 && !texto.trim().is_empty() {
+                        set_pos_mensaje(&ui, "No se encontraron clientes", true);
+                    } else {
+                        set_pos_mensaje(&ui, "", false);
+                    }
+                }
+                Err(e) => set_pos_mensaje(&ui, &format!("Error al buscar clientes: {}", e), true),
+            }
+        }
+    });
+
+    ui.on_pos_confirmar({
+        let ui_handle = ui_handle.clone();
+        move |cliente_index| {
+            let Some(ui) = ui_handle.upgrade() else { return };
+
+            let usuario_id = match session::requerir(Permiso::RegistrarVenta) {
+                Ok(usuario) => usuario.id,
+                Err(msg) => {
+                    set_pos_mensaje(&ui, &msg, true);
+                    return;
+                }
+            };
+
+            ui.set_procesando(true);
+            let cliente_id = clientes::id_cliente_pos(cliente_index);
+
+            tokio::spawn({
+                let ui_handle = ui_handle.clone();
+                async move {
+                    // Preparar el resultado como String (Send) antes de volver al hilo de UI
+                    let resultado = pos::confirmar_venta(usuario_id, cliente_id).map_err(|e| e.to_string());
+
+                    if let Err(e) = ui_handle.upgrade_in_event_loop(move |ui| {
+                        ui.set_procesando(false);
+                        match resultado {
+                            Ok(venta_id) => {
+                                println!("Venta #{} registrada.", venta_id);
+                                let vuelto = pos::vuelto();
+                                let credito = pos::credito();
+                                pos::limpiar();
+                                ui.set_pos_resultados(pos::resultados_to_model_rows(&[]));
+                                reiniciar_cliente_pos(&ui);
+                                ui.set_pos_ultima_venta(venta_id as i32);
+                                refresh_pos(&ui);
+                                let mensaje = if vuelto > 0.0 {
+                                    format!("Venta #{} registrada. Vuelto: ${:.2}", venta_id, vuelto)
+                                } else if credito > 0.0 {
+                                    format!("Venta #{} registrada. A crédito: ${:.2}", venta_id, credito)
+                                } else {
+                                    format!("Venta #{} registrada correctamente", venta_id)
+                                };
+                                set_pos_mensaje(&ui, &mensaje, false);
+                                // El stock cambió: refrescar caché y tabla de inventario
+                                refresh_ui_from_main(&ui);
+                            }
+                            Err(msg) => {
+                                eprintln!("Error al registrar la venta: {}", msg);
+                                set_pos_mensaje(&ui, &format!("No se pudo registrar la venta: {}", msg), true);
+                            }
+                        }
+                    }) {
+                        eprintln!("Error al actualizar UI: {}", e);
+                    }
+                }
+            });
+        }
+    });
+
+    // Recibo de la última venta registrada en el punto de venta
+    for imprimir in [false, true] {
+        let ui_handle = ui_handle.clone();
+        let emitir = move || {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            if let Err(msg) = session::requerir(Permiso::RegistrarVenta) {
+                set_pos_mensaje(&ui, &msg, true);
+                return;
+            }
+            match ui.get_pos_ultima_venta() {
+                0 => set_pos_mensaje(&ui, "Aún no se ha registrado ninguna venta", true),
+                venta_id => emitir_recibo(ui_handle.clone(), venta_id as i64, imprimir, set_pos_mensaje),
+            }
+        };
+        if imprimir {
+            ui.on_pos_imprimir_ticket(emitir);
+        } else {
+            ui.on_pos_ticket_pdf(emitir);
+        }
+    }
+
+    // CAJA
+    ui.on_caja_refrescar({
+        let ui_handle = ui_handle.clone();
+        move || {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            // La pantalla sirve tanto al cajero como a quien revisa los cierres
+            if let Err(msg) = session::requerir(Permiso::OperarCaja)
+                .or_else(|msg| session::requerir(Permiso::VerCierresCaja).map_err(|_| msg))
+            {
+                set_caja_mensaje(&ui, &msg, true);
+                return;
+            }
+            refresh_caja(&ui);
+        }
+    });
+
+    ui.on_caja_abrir({
+        let ui_handle = ui_handle.clone();
+        move |fondo: SharedString| {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            let usuario_id = match session::requerir(Permiso::OperarCaja) {
+                Ok(usuario) => usuario.id,
+                Err(msg) => {
+                    set_caja_mensaje(&ui, &msg, true);
+                    return;
+                }
+            };
+
+            match caja::abrir(usuario_id, fondo.as_str()) {
+                Ok(id) => {
+                    ui.set_caja_fondo("".into());
+                    refresh_caja(&ui);
+                    set_caja_mensaje(&ui, &format!("Caja #{} abierta", id), false);
+                }
+                Err(e) => set_caja_mensaje(&ui, &e.to_string(), true),
+            }
+        }
+    });
+
+    ui.on_caja_cerrar({
+        let ui_handle = ui_handle.clone();
+        move |efectivo: SharedString,
+              tarjeta: SharedString,
+              transferencia: SharedString,
+              pago_movil: SharedString,
+              notas: SharedString| {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            let usuario_id = match session::requerir(Permiso::OperarCaja) {
+                Ok(usuario) => usuario.id,
+                Err(msg) => {
+                    set_caja_mensaje(&ui, &msg, true);
+                    return;
+                }
+            };
+
+            let contado = [efectivo.as_str(), tarjeta.as_str(), transferencia.as_str(), pago_movil.as_str()];
+            match caja::cerrar(usuario_id, &contado, notas.as_str()) {
+                Ok(caja::CajaConConteo { sesion, conteo: arqueo }) => {
+                    refresh_caja(&ui);
+                    // Mostrar el arqueo recién cerrado aunque el usuario no vea el reporte
+                    ui.set_caja_cierre_titulo(caja::resumen(&sesion).into());
+                    ui.set_caja_cierre_arqueo(caja::arqueo_to_model_rows(&arqueo, true));
+                    set_caja_mensaje(
+                        &ui,
+                        &format!("Caja #{} cerrada. Diferencia: {:+.2}", sesion.id, sesion.diferencia()),
+                        sesion.diferencia() != 0.0,
+                    );
+                }
+                Err(e) => set_caja_mensaje(&ui, &e.to_string(), true),
+            }
+        }
+    });
+
+    ui.on_caja_seleccionar_cierre({
+        let ui_handle = ui_handle.clone();
+        move |index| {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            if let Err(msg) = session::requerir(Permiso::VerCierresCaja) {
+                set_caja_mensaje(&ui, &msg, true);
+                return;
+            }
+            match caja::detalle_cierre(index) {
+                Ok(Some(caja::CajaConConteo { sesion, conteo: arqueo })) => {
+                    ui.set_caja_cierre_titulo(caja::resumen(&sesion).into());
+                    ui.set_caja_cierre_arqueo(caja::arqueo_to_model_rows(&arqueo, true));
+                }
+                Ok(None) => {}
+                Err(e) => set_caja_mensaje(&ui, &format!("Error al cargar el cierre: {}", e), true),
+            }
+        }
+    });
+
+    // HISTORIAL DE VENTAS
+    ui.on_historial_refrescar({
+        let ui_handle = ui_handle.clone();
+        move || {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            if let Err(msg) = session::requerir(Permiso::VerHistorialVentas) {
+                set_historial_mensaje(&ui, &msg, true);
+                return;
+            }
+            match historial_ventas::cargar_cajeros() {
+                Ok(cajeros) => ui.set_historial_cajeros(historial_ventas::cajeros_para_select(&cajeros)),
+                Err(e) => eprintln!("Error al cargar cajeros: {}", e),
+            }
+            ui.invoke_historial_buscar(
+                ui.get_historial_desde(),
+                ui.get_historial_hasta(),
+                ui.get_historial_cajero_index(),
+                ui.get_historial_cliente(),
+            );
+        }
+    });
+
+    ui.on_historial_buscar({
+        let ui_handle = ui_handle.clone();
+        move |desde: SharedString, hasta: SharedString, cajero, cliente: SharedString| {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            if let Err(msg) = session::requerir(Permiso::VerHistorialVentas) {
+                set_historial_mensaje(&ui, &msg, true);
+                return;
+            }
+            let filtro = match historial_ventas::filtro(desde.as_str(), hasta.as_str(), cajero, cliente.as_str()) {
+                Ok(filtro) => filtro,
+                Err(msg) => {
+                    set_historial_mensaje(&ui, &msg, true);
+                    return;
+                }
+            };
+            ui.set_procesando(true);
+
+            tokio::spawn({
+                let ui_handle = ui_handle.clone();
+                async move {
+                    let resultado = historial_ventas::buscar(&filtro).map_err(|e| e.to_string());
+
+                    if let Err(e) = ui_handle.upgrade_in_event_loop(move |ui| {
+                        ui.set_procesando(false);
+                        // La selección anterior ya no corresponde a la tabla nueva
+                        limpiar_detalle_historial(&ui);
+                        match resultado {
+                            Ok(ventas) => {
+                                ui.set_historial_ventas(historial_ventas::ventas_to_model_rows(&ventas));
+                                mostrar_totales_historial(&ui, &ventas);
+                                if ventas.is_empty() {
+                                    set_historial_mensaje(&ui, "No hay ventas con esos criterios", false);
+                                } else {
+                                    set_historial_mensaje(&ui, "", false);
+                                }
+                            }
+                            Err(msg) => set_historial_mensaje(&ui, &msg, true),
+                        }
+                    }) {
+                        eprintln!("Error al actualizar UI: {}", e);
+                    }
+                }
+            });
+        }
+    });
+
+    ui.on_historial_seleccionar({
+        let ui_handle = ui_handle.clone();
+        move |index| {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            if let Err(msg) = session::requerir(Permiso::VerHistorialVentas) {
+                set_historial_mensaje(&ui, &msg, true);
+                return;
+            }
+            match historial_ventas::detalle(index) {
+                Ok(Some(v)) => mostrar_venta_historial(&ui, &v),
+                Ok(None) => set_historial_mensaje(&ui, "La venta ya no existe", true),
+                Err(e) => set_historial_mensaje(&ui, &format!("Error al cargar la venta: {}", e), true),
+            }
+        }
+    });
+
+    ui.on_historial_devolver({
+        let ui_handle = ui_handle.clone();
+        move |index, cantidad: SharedString, motivo: SharedString| {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            let usuario_id = match session::requerir(Permiso::RegistrarDevolucion) {
+                Ok(usuario) => usuario.id,
+                Err(msg) => {
+                    set_historial_mensaje(&ui, &msg, true);
+                    return;
+                }
+            };
+
+            let resultado = historial_ventas::devolver_linea(index, cantidad.as_str(), motivo.as_str(), usuario_id)
+                .map(|id| format!("Devolución #{} registrada; el stock fue repuesto", id))
+                .map_err(|e| e.to_string());
+            despues_de_devolver(&ui, resultado);
+        }
+    });
+
+    ui.on_historial_anular({
+        let ui_handle = ui_handle.clone();
+        move |motivo: SharedString| {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            let usuario_id = match session::requerir(Permiso::AnularVenta) {
+                Ok(usuario) => usuario.id,
+                Err(msg) => {
+                    set_historial_mensaje(&ui, &msg, true);
+                    return;
+                }
+            };
+
+            let venta_id = historial_ventas::venta_seleccionada();
+            let resultado = historial_ventas::anular(motivo.as_str(), usuario_id)
+                .map(|_| format!("Venta #{} anulada; el stock fue repuesto", venta_id.unwrap_or_default()))
+                .map_err(|e| e.to_string());
+            despues_de_devolver(&ui, resultado);
+        }
+    });
+
+    // Recibo de la venta abierta en el historial
+    for imprimir in [false, true] {
+        let ui_handle = ui_handle.clone();
+        let emitir = move || {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            if let Err(msg) = session::requerir(Permiso::VerHistorialVentas) {
+                set_historial_mensaje(&ui, &msg, true);
+                return;
+            }
+            match historial_ventas::venta_seleccionada() {
+                Some(venta_id) => emitir_recibo(ui_handle.clone(), venta_id, imprimir, set_historial_mensaje),
+                None => set_historial_mensaje(&ui, "Seleccione una venta", true),
+            }
+        };
+        if imprimir {
+            ui.on_historial_imprimir_ticket(emitir);
+        } else {
+            ui.on_historial_ticket_pdf(emitir);
+        }
+    }
+
+    // 4. GESTIÓN DE MARCAS
+    ui.on_marcas_refrescar({
+        let ui_handle = ui_handle.clone();
+        move || {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            if let Err(msg) = session::requerir(Permiso::GestionarMarcas) {
+                set_marca_mensaje(&ui, &msg, true);
+                return;
+            }
+            refresh_marcas(ui_handle.clone());
+        }
+    });
+
+    ui.on_marca_seleccionar({
+        let ui_handle = ui_handle.clone();
+        move |index| {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            if let Err(msg) = session::requerir(Permiso::GestionarMarcas) {
+                set_marca_mensaje(&ui, &msg, true);
+                return;
+            }
+            if let Some(m) = marcas::get_marca_by_index(index) {
+                cargar_formulario_marca(&ui, m);
+                set_marca_mensaje(&ui, "", false);
+            }
+        }
+    });
+
+    ui.on_marca_nueva({
+        let ui_handle = ui_handle.clone();
+        move || {
+            if let Some(ui) = ui_handle.upgrade() {
+                limpiar_formulario_marca(&ui);
+            }
+        }
+    });
+
+    ui.on_marca_guardar({
+        let ui_handle = ui_handle.clone();
+        move || {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            if let Err(msg) = session::requerir(Permiso::GestionarMarcas) {
+                set_marca_mensaje(&ui, &msg, true);
+                return;
+            }
+
+            let id = match ui.get_marca_edit_id() {
+                0 => None,
+                id => Some(id as i64),
+            };
+            let nombre = ui.get_marca_nombre().to_string();
+            let rif = ui.get_marca_rif().to_string();
+            let descripcion = ui.get_marca_descripcion().to_string();
+            let logo = ui.get_marca_logo().to_string();
+            ui.set_procesando(true);
+
+            tokio::spawn({
+                let ui_handle = ui_handle.clone();
+                async move {
+                    let resultado = marcas::guardar(id, &nombre, &rif, &descripcion, &logo)
+                        .map_err(|e| e.to_string());
+
+                    if let Err(e) = ui_handle.upgrade_in_event_loop(move |ui| {
+                        ui.set_procesando(false);
+                        match resultado {
+                            Ok(_) => {
+                                let accion = if id.is_some() { "actualizada" } else { "creada" };
+                                limpiar_formulario_marca(&ui);
+                                set_marca_mensaje(&ui, &format!("Marca '{}' {}", nombre.trim(), accion), false);
+                                refresh_marcas(ui.as_weak());
+                            }
+                            Err(msg) => set_marca_mensaje(&ui, &msg, true),
+                        }
+                    }) {
+                        eprintln!("Error al actualizar UI: {}", e);
+                    }
+                }
+            });
+        }
+    });
+
+    ui.on_marca_solicitar_eliminar({
+        let ui_handle = ui_handle.clone();
+        move |index| {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            if let Err(msg) = session::requerir(Permiso::GestionarMarcas) {
+                set_marca_mensaje(&ui, &msg, true);
+                return;
+            }
+
+            let Some(marca) = marcas::get_marca_by_index(index) else {
+                set_marca_mensaje(&ui, "Seleccione una marca de la tabla", true);
+                return;
+            };
+
+            match marcas::advertencia_eliminar(&marca) {
+                Ok(texto) => {
+                    // La confirmación actúa sobre la marca cargada en el formulario
+                    cargar_formulario_marca(&ui, marca);
+                    ui.set_marca_confirmacion(texto.into());
+                    set_marca_mensaje(&ui, "", false);
+                }
+                Err(e) => set_marca_mensaje(&ui, &e.to_string(), true),
+            }
+        }
+    });
+
+    ui.on_marca_cancelar_eliminar({
+        let ui_handle = ui_handle.clone();
+        move || {
+            if let Some(ui) = ui_handle.upgrade() {
+                ui.set_marca_confirmacion("".into());
+            }
+        }
+    });
+
+    ui.on_marca_confirmar_eliminar({
+        let ui_handle = ui_handle.clone();
+        move || {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            if let Err(msg) = session::requerir(Permiso::GestionarMarcas) {
+                set_marca_mensaje(&ui, &msg, true);
+                return;
+            }
+            let id = ui.get_marca_edit_id() as i64;
+            if id <= 0 {
+                ui.set_marca_confirmacion("".into());
+                return;
+            }
+            ui.set_procesando(true);
+
+            tokio::spawn({
+                let ui_handle = ui_handle.clone();
+                async move {
+                    let resultado = marcas::eliminar(id).map_err(|e| e.to_string());
+
+                    if let Err(e) = ui_handle.upgrade_in_event_loop(move |ui| {
+                        ui.set_procesando(false);
+                        limpiar_formulario_marca(&ui);
+                        match resultado {
+                            Ok(true) => {
+                                set_marca_mensaje(&ui, "Marca eliminada", false);
+                                refresh_marcas(ui.as_weak());
+                                // Los productos afectados ahora aparecen "Sin Marca"
+                                refresh_ui_from_main(&ui);
+                            }
+                            Ok(false) => set_marca_mensaje(&ui, "La marca ya no existe", true),
+                            Err(msg) => set_marca_mensaje(&ui, &msg, true),
+                        }
+                    }) {
+                        eprintln!("Error al actualizar UI: {}", e);
+                    }
+                }
+            });
+        }
+    });
+
+    // 5. GESTIÓN DE CATEGORÍAS
+    ui.on_categorias_refrescar({
+        let ui_handle = ui_handle.clone();
+        move || {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            if let Err(msg) = session::requerir(Permiso::GestionarCategorias) {
+                set_categoria_mensaje(&ui, &msg, true);
+                return;
+            }
+            refresh_categorias(ui_handle.clone());
+        }
+    });
+
+    ui.on_categoria_seleccionar({
+        let ui_handle = ui_handle.clone();
+        move |index| {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            if let Err(msg) = session::requerir(Permiso::GestionarCategorias) {
+                set_categoria_mensaje(&ui, &msg, true);
+                return;
+            }
+            if let Some(c) = categorias::get_categoria_by_index(index) {
+                cargar_formulario_categoria(&ui, c);
+                set_categoria_mensaje(&ui, "", false);
+            }
+        }
+    });
+
+    ui.on_categoria_nueva({
+        let ui_handle = ui_handle.clone();
+        move || {
+            if let Some(ui) = ui_handle.upgrade() {
+                limpiar_formulario_categoria(&ui);
+            }
+        }
+    });
+
+    ui.on_categoria_guardar({
+        let ui_handle = ui_handle.clone();
+        move || {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            if let Err(msg) = session::requerir(Permiso::GestionarCategorias) {
+                set_categoria_mensaje(&ui, &msg, true);
+                return;
+            }
+
+            let id = match ui.get_categoria_edit_id() {
+                0 => None,
+                id => Some(id as i64),
+            };
+            let nombre = ui.get_categoria_nombre().to_string();
+            let descripcion = ui.get_categoria_descripcion().to_string();
+            let impuesto_id = impuestos::id_impuesto(ui.get_categoria_impuesto_index());
+            ui.set_procesando(true);
+
+            tokio::spawn({
+                let ui_handle = ui_handle.clone();
+                async move {
+                    let resultado = categorias::guardar_categoria(id, &nombre, &descripcion, impuesto_id)
+                        .map_err(|e| e.to_string());
+
+                    if let Err(e) = ui_handle.upgrade_in_event_loop(move |ui| {
+                        ui.set_procesando(false);
+                        match resultado {
+                            Ok(guardado) => {
+                                let accion = if id.is_some() { "actualizada" } else { "creada" };
+                                // Se mantiene seleccionada para poder agregarle subcategorías
+                                ui.set_categoria_edit_id(guardado as i32);
+                                set_categoria_mensaje(&ui, &format!("Categoría '{}' {}", nombre.trim(), accion), false);
+                                refresh_categorias(ui.as_weak());
+                            }
+                            Err(msg) => set_categoria_mensaje(&ui, &msg, true),
+                        }
+                    }) {
+                        eprintln!("Error al actualizar UI: {}", e);
+                    }
+                }
+            });
+        }
+    });
+
+    ui.on_categoria_solicitar_eliminar({
+        let ui_handle = ui_handle.clone();
+        move |index| {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            if let Err(msg) = session::requerir(Permiso::GestionarCategorias) {
+                set_categoria_mensaje(&ui, &msg, true);
+                return;
+            }
+
+            let Some(categoria) = categorias::get_categoria_by_index(index) else {
+                set_categoria_mensaje(&ui, "Seleccione una categoría de la tabla", true);
+                return;
+            };
+
+            match categorias::advertencia_eliminar(&categoria) {
+                Ok(texto) => {
+                    // La confirmación actúa sobre la categoría cargada en el formulario
+                    cargar_formulario_categoria(&ui, categoria);
+                    ui.set_categoria_confirmacion(texto.into());
+                    set_categoria_mensaje(&ui, "", false);
+                }
+                Err(e) => set_categoria_mensaje(&ui, &e.to_string(), true),
+            }
+        }
+    });
+
+    ui.on_categoria_cancelar_eliminar({
+        let ui_handle = ui_handle.clone();
+        move || {
+            if let Some(ui) = ui_handle.upgrade() {
+                ui.set_categoria_confirmacion("".into());
+            }
+        }
+    });
+
+    ui.on_categoria_confirmar_eliminar({
+        let ui_handle = ui_handle.clone();
+        move || {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            if let Err(msg) = session::requerir(Permiso::GestionarCategorias) {
+                set_categoria_mensaje(&ui, &msg, true);
+                return;
+            }
+            let id = ui.get_categoria_edit_id() as i64;
+            if id <= 0 {
+                ui.set_categoria_confirmacion("".into());
+                return;
+            }
+            ui.set_procesando(true);
+
+            tokio::spawn({
+                let ui_handle = ui_handle.clone();
+                async move {
+                    let resultado = categorias::eliminar_categoria(id).map_err(|e| e.to_string());
+
+                    if let Err(e) = ui_handle.upgrade_in_event_loop(move |ui| {
+                        ui.set_procesando(false);
+                        limpiar_formulario_categoria(&ui);
+                        match resultado {
+                            Ok(true) => {
+                                set_categoria_mensaje(&ui, "Categoría eliminada", false);
+                                refresh_categorias(ui.as_weak());
+                            }
+                            Ok(false) => set_categoria_mensaje(&ui, "La categoría ya no existe", true),
+                            Err(msg) => set_categoria_mensaje(&ui, &msg, true),
+                        }
+                    }) {
+                        eprintln!("Error al actualizar UI: {}", e);
+                    }
+                }
+            });
+        }
+    });
+
+    ui.on_subcategoria_agregar({
+        let ui_handle = ui_handle.clone();
+        move |nombre| {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            if let Err(msg) = session::requerir(Permiso::GestionarCategorias) {
+                set_categoria_mensaje(&ui, &msg, true);
+                return;
+            }
+            let categoria_id = ui.get_categoria_edit_id() as i64;
+            if categoria_id <= 0 {
+                set_categoria_mensaje(&ui, "Guarde la categoría antes de agregarle subcategorías", true);
+                return;
+            }
+
+            match categorias::agregar_subcategoria(categoria_id, nombre.as_str()) {
+                Ok(_) => {
+                    set_categoria_mensaje(&ui, &format!("Subcategoría '{}' agregada", nombre.trim()), false);
+                    refresh_subcategorias(&ui, categoria_id);
+                    refresh_categorias(ui.as_weak());
+                }
+                Err(e) => set_categoria_mensaje(&ui, &e.to_string(), true),
+            }
+        }
+    });
+
+    ui.on_subcategoria_eliminar({
+        let ui_handle = ui_handle.clone();
+        move |index| {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            if let Err(msg) = session::requerir(Permiso::GestionarCategorias) {
+                set_categoria_mensaje(&ui, &msg, true);
+                return;
+            }
+
+            match categorias::eliminar_subcategoria_por_indice(index) {
+                Ok(true) => {
+                    // Los productos que la usaban quedan sin subcategoría (ON DELETE SET NULL)
+                    set_categoria_mensaje(&ui, "Subcategoría eliminada", false);
+                    refresh_subcategorias(&ui, ui.get_categoria_edit_id() as i64);
+                    refresh_categorias(ui.as_weak());
+                }
+                Ok(false) => set_categoria_mensaje(&ui, "Seleccione una subcategoría", true),
+                Err(e) => set_categoria_mensaje(&ui, &e.to_string(), true),
+            }
+        }
+    });
+
+    ui.on_categoria_form_cambiada({
+        let ui_handle = ui_handle.clone();
+        move |index| {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            match categorias::cargar_subcategorias_form(categorias::id_categoria(index)) {
+                Ok(nombres) => {
+                    ui.set_lista_subcategorias(slint::ModelRc::from(std::rc::Rc::new(slint::VecModel::from(nombres))));
+                    ui.set_edit_product_subcategoria_index(0);
+                }
+                Err(e) => eprintln!("Error al cargar subcategorías: {}", e),
+            }
+        }
+    });
+
+    // 6. GESTIÓN DE PROVEEDORES
+    ui.on_proveedores_refrescar({
+        let ui_handle = ui_handle.clone();
+        move || {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            if let Err(msg) = session::requerir(Permiso::GestionarCompras) {
+                set_proveedor_mensaje(&ui, &msg, true);
+                return;
+            }
+            refresh_proveedores(ui_handle.clone());
+        }
+    });
+
+    ui.on_proveedor_seleccionar({
+        let ui_handle = ui_handle.clone();
+        move |index| {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            if let Err(msg) = session::requerir(Permiso::GestionarCompras) {
+                set_proveedor_mensaje(&ui, &msg, true);
+                return;
+            }
+            if let Some(p) = proveedores::get_proveedor_by_index(index) {
+                cargar_formulario_proveedor(&ui, p);
+                set_proveedor_mensaje(&ui, "", false);
+            }
+        }
+    });
+
+    ui.on_proveedor_nuevo({
+        let ui_handle = ui_handle.clone();
+        move || {
+            if let Some(ui) = ui_handle.upgrade() {
+                limpiar_formulario_proveedor(&ui);
+            }
+        }
+    });
+
+    ui.on_proveedor_guardar({
+        let ui_handle = ui_handle.clone();
+        move || {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            if let Err(msg) = session::requerir(Permiso::GestionarCompras) {
+                set_proveedor_mensaje(&ui, &msg, true);
+                return;
+            }
+
+            let id = match ui.get_proveedor_edit_id() {
+                0 => None,
+                id => Some(id as i64),
+            };
+            let nombre = ui.get_proveedor_nombre().to_string();
+            let rif = ui.get_proveedor_rif().to_string();
+            let telefono = ui.get_proveedor_telefono().to_string();
+            let email = ui.get_proveedor_email().to_string();
+            let direccion = ui.get_proveedor_direccion().to_string();
+            ui.set_procesando(true);
+
+            tokio::spawn({
+                let ui_handle = ui_handle.clone();
+                async move {
+                    let resultado = proveedores::guardar(id, &nombre, &rif, &telefono, &email, &direccion)
+                        .map_err(|e| e.to_string());
+
+                    if let Err(e) = ui_handle.upgrade_in_event_loop(move |ui| {
+                        ui.set_procesando(false);
+                        match resultado {
+                            Ok(_) => {
+                                let accion = if id.is_some() { "actualizado" } else { "creado" };
+                                limpiar_formulario_proveedor(&ui);
+                                set_proveedor_mensaje(&ui, &format!("Proveedor '{}' {}", nombre.trim(), accion), false);
+                                refresh_proveedores(ui.as_weak());
+                            }
+                            Err(msg) => set_proveedor_mensaje(&ui, &msg, true),
+                        }
+                    }) {
+                        eprintln!("Error al actualizar UI: {}", e);
+                    }
+                }
+            });
+        }
+    });
+
+    ui.on_proveedor_solicitar_eliminar({
+        let ui_handle = ui_handle.clone();
+        move |index| {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            if let Err(msg) = session::requerir(Permiso::GestionarCompras) {
+                set_proveedor_mensaje(&ui, &msg, true);
+                return;
+            }
+
+            let Some(proveedor) = proveedores::get_proveedor_by_index(index) else {
+                set_proveedor_mensaje(&ui, "Seleccione un proveedor de la tabla", true);
+                return;
+            };
+
+            match proveedores::advertencia_eliminar(&proveedor) {
+                Ok(texto) => {
+                    // La confirmación actúa sobre el proveedor cargado en el formulario
+                    cargar_formulario_proveedor(&ui, proveedor);
+                    ui.set_proveedor_confirmacion(texto.into());
+                    set_proveedor_mensaje(&ui, "", false);
+                }
+                Err(e) => set_proveedor_mensaje(&ui, &e.to_string(), true),
+            }
+        }
+    });
+
+    ui.on_proveedor_cancelar_eliminar({
+        let ui_handle = ui_handle.clone();
+        move || {
+            if let Some(ui) = ui_handle.upgrade() {
+                ui.set_proveedor_confirmacion("".into());
+            }
+        }
+    });
+
+    ui.on_proveedor_confirmar_eliminar({
+        let ui_handle = ui_handle.clone();
+        move || {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            if let Err(msg) = session::requerir(Permiso::GestionarCompras) {
+                set_proveedor_mensaje(&ui, &msg, true);
+                return;
+            }
+            let id = ui.get_proveedor_edit_id() as i64;
+            if id <= 0 {
+                ui.set_proveedor_confirmacion("".into());
+                return;
+            }
+            ui.set_procesando(true);
+
+            tokio::spawn({
+                let ui_handle = ui_handle.clone();
+                async move {
+                    let resultado = proveedores::eliminar(id).map_err(|e| e.to_string());
+
+                    if let Err(e) = ui_handle.upgrade_in_event_loop(move |ui| {
+                        ui.set_procesando(false);
+                        limpiar_formulario_proveedor(&ui);
+                        match resultado {
+                            Ok(true) => {
+                                set_proveedor_mensaje(&ui, "Proveedor eliminado", false);
+                                refresh_proveedores(ui.as_weak());
+                            }
+                            Ok(false) => set_proveedor_mensaje(&ui, "El proveedor ya no existe", true),
+                            Err(msg) => set_proveedor_mensaje(&ui, &msg, true),
+                        }
+                    }) {
+                        eprintln!("Error al actualizar UI: {}", e);
+                    }
+                }
+            });
+        }
+    });
+
+    // 7. ÓRDENES DE COMPRA
+    ui.on_compras_refrescar({
+        let ui_handle = ui_handle.clone();
+        move || {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            if let Err(msg) = session::requerir(Permiso::GestionarCompras) {
+                set_compras_mensaje(&ui, &msg, true);
+                return;
+            }
+            refresh_proveedores(ui_handle.clone());
+            refresh_compras(ui_handle.clone());
+        }
+    });
+
+    ui.on_compras_seleccionar({
+        let ui_handle = ui_handle.clone();
+        move |index| {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            if let Err(msg) = session::requerir(Permiso::GestionarCompras) {
+                set_compras_mensaje(&ui, &msg, true);
+                return;
+            }
+            if let Some(orden) = compras::get_orden_by_index(index) {
+                mostrar_orden_compra(&ui, &orden);
+                set_compras_mensaje(&ui, "", false);
+            }
+        }
+    });
+
+    ui.on_compras_nueva({
+        let ui_handle = ui_handle.clone();
+        move || {
+            if let Some(ui) = ui_handle.upgrade() {
+                limpiar_orden_compra(&ui);
+                set_compras_mensaje(&ui, "", false);
+            }
+        }
+    });
+
+    ui.on_compras_buscar({
+        let ui_handle = ui_handle.clone();
+        move |texto: SharedString| {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            if let Err(msg) = session::requerir(Permiso::GestionarCompras) {
+                set_compras_mensaje(&ui, &msg, true);
+                return;
+            }
+            match compras::buscar(texto.as_str()) {
+                Ok(resultados) => {
+                    ui.set_compras_resultados(compras::resultados_to_model_rows(&resultados));
+                    if resultados.is_empty() && !texto.trim().is_empty() {
+                        set_compras_mensaje(&ui, "No se encontraron productos", true);
+                    } else {
+                        set_compras_mensaje(&ui, "", false);
+                    }
+                }
+                Err(e) => set_compras_mensaje(&ui, &format!("Error al buscar: {}", e), true),
+            }
+        }
+    });
+
+    ui.on_compras_agregar({
+        let ui_handle = ui_handle.clone();
+        move |index, cantidad: SharedString, costo: SharedString| {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            match compras::agregar_al_borrador(index, cantidad.as_str(), costo.as_str()) {
+                Ok(()) => set_compras_mensaje(&ui, "", false),
+                Err(msg) => set_compras_mensaje(&ui, &msg, true),
+            }
+            refresh_borrador_compra(&ui);
+        }
+    });
+
+    ui.on_compras_quitar({
+        let ui_handle = ui_handle.clone();
+        move |index| {
+            if let Some(ui) = ui_handle.upgrade() {
+                compras::quitar_del_borrador(index);
+                refresh_borrador_compra(&ui);
+            }
+        }
+    });
+
+    ui.on_compras_guardar({
+        let ui_handle = ui_handle.clone();
+        move |proveedor_index, notas: SharedString| {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            let usuario_id = match session::requerir(Permiso::GestionarCompras) {
+                Ok(usuario) => usuario.id,
+                Err(msg) => {
+                    set_compras_mensaje(&ui, &msg, true);
+                    return;
+                }
+            };
+            let Some(proveedor) = proveedores::get_proveedor_by_index(proveedor_index) else {
+                set_compras_mensaje(&ui, "Seleccione un proveedor", true);
+                return;
+            };
+
+            ui.set_procesando(true);
+            let notas = notas.to_string();
+
+            tokio::spawn({
+                let ui_handle = ui_handle.clone();
+                async move {
+                    let resultado = compras::guardar_orden(proveedor.id, &notas, Some(usuario_id))
+                        .map_err(|e| e.to_string());
+
+                    if let Err(e) = ui_handle.upgrade_in_event_loop(move |ui| {
+                        ui.set_procesando(false);
+                        match resultado {
+                            Ok(orden_id) => {
+                                limpiar_orden_compra(&ui);
+                                set_compras_mensaje(
+                                    &ui,
+                                    &format!("Orden #{} registrada para '{}'", orden_id, proveedor.nombre),
+                                    false,
+                                );
+                                refresh_compras(ui.as_weak());
+                            }
+                            Err(msg) => set_compras_mensaje(&ui, &msg, true),
+                        }
+                    }) {
+                        eprintln!("Error al actualizar UI: {}", e);
+                    }
+                }
+            });
+        }
+    });
+
+    ui.on_compras_recibir_linea({
+        let ui_handle = ui_handle.clone();
+        move |index, cantidad: SharedString, costo: SharedString, lote: SharedString, vencimiento: SharedString| {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            let usuario_id = match session::requerir(Permiso::GestionarCompras) {
+                Ok(usuario) => usuario.id,
+                Err(msg) => {
+                    set_compras_mensaje(&ui, &msg, true);
+                    return;
+                }
+            };
+            let orden_id = ui.get_compras_orden_id() as i64;
+            let resultado = compras::recibir_linea(
+                orden_id,
+                index,
+                cantidad.as_str(),
+                costo.as_str(),
+                lote.as_str(),
+                vencimiento.as_str(),
+                Some(usuario_id),
+            )
+                .map_err(|e| e.to_string());
+            despues_de_recibir(&ui, orden_id, resultado);
+        }
+    });
+
+    ui.on_compras_recibir_todo({
+        let ui_handle = ui_handle.clone();
+        move || {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            let usuario_id = match session::requerir(Permiso::GestionarCompras) {
+                Ok(usuario) => usuario.id,
+                Err(msg) => {
+                    set_compras_mensaje(&ui, &msg, true);
+                    return;
+                }
+            };
+            let orden_id = ui.get_compras_orden_id() as i64;
+            let resultado = compras::recibir_todo(orden_id, Some(usuario_id)).map_err(|e| e.to_string());
+            despues_de_recibir(&ui, orden_id, resultado);
+        }
+    });
+
+    ui.on_compras_cancelar_orden({
+        let ui_handle = ui_handle.clone();
+        move || {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            if let Err(msg) = session::requerir(Permiso::GestionarCompras) {
+                set_compras_mensaje(&ui, &msg, true);
+                return;
+            }
+            let orden_id = ui.get_compras_orden_id() as i64;
+            match compras::cancelar(orden_id) {
+                Ok(()) => {
+                    limpiar_orden_compra(&ui);
+                    set_compras_mensaje(&ui, &format!("Orden #{} cancelada", orden_id), false);
+                    refresh_compras(ui.as_weak());
+                }
+                Err(e) => set_compras_mensaje(&ui, &e.to_string(), true),
+            }
+        }
+    });
+
+    // 8. DATOS DEL TICKET
+    ui.on_ticket_cargar({
+        let ui_handle = ui_handle.clone();
+        move || {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            set_ticket_mensaje(&ui, "", false);
+            match recibo::cargar_configuracion() {
+                Ok(config) => cargar_formulario_ticket(&ui, &config),
+                Err(e) => set_ticket_mensaje(&ui, &format!("Error al cargar la configuración: {}", e), true),
+            }
+            match impuestos::modo_precios() {
+                Ok(modo) => ui.set_ticket_modo_precios_index(
+                    ModoPrecios::TODOS.iter().position(|m| *m == modo).unwrap_or(0) as i32,
+                ),
+                Err(e) => set_ticket_mensaje(&ui, &format!("Error al cargar el modo de precios: {}", e), true),
+            }
+        }
+    });
+
+    ui.on_ticket_guardar({
+        let ui_handle = ui_handle.clone();
+        move || {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            if let Err(msg) = session::requerir(Permiso::ConfigurarTienda) {
+                set_ticket_mensaje(&ui, &msg, true);
+                return;
+            }
+            let nombre = ui.get_ticket_nombre().trim().to_string();
+            if nombre.is_empty() {
+                set_ticket_mensaje(&ui, "El nombre de la tienda es obligatorio", true);
+                return;
+            }
+            let opcional = |s: SharedString| Some(s.trim().to_string()).filter(|s| !s.is_empty());
+
+            let config = ConfiguracionRecibo {
+                tienda: DatosTienda {
+                    nombre,
+                    rif: ui.get_ticket_rif().trim().to_string(),
+                    direccion: ui.get_ticket_direccion().trim().to_string(),
+                    telefono: ui.get_ticket_telefono().trim().to_string(),
+                    pie: ui.get_ticket_pie().trim().to_string(),
+                },
+                ancho: if ui.get_ticket_ancho_index() == 0 { AnchoPapel::Mm58 } else { AnchoPapel::Mm80 },
+                impresora: opcional(ui.get_ticket_impresora()),
+                carpeta_pdf: opcional(ui.get_ticket_carpeta()),
+            };
+            let modo = ModoPrecios::TODOS
+                .get(ui.get_ticket_modo_precios_index().max(0) as usize)
+                .copied()
+                .unwrap_or(ModoPrecios::IvaIncluido);
+            match recibo::guardar_configuracion(&config).and_then(|()| impuestos::guardar_modo_precios(modo)) {
+                Ok(()) => set_ticket_mensaje(&ui, "Configuración guardada", false),
+                Err(e) => set_ticket_mensaje(&ui, &format!("No se pudo guardar: {}", e), true),
+            }
+        }
+    });
+
+    // 9. TASAS DE CAMBIO
+    ui.on_tasas_refrescar({
+        let ui_handle = ui_handle.clone();
+        move || {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            if let Err(msg) = session::requerir(Permiso::GestionarTasas) {
+                set_tasas_mensaje(&ui, &msg, true);
+                return;
+            }
+            set_tasas_mensaje(&ui, "", false);
+            refresh_tasas(&ui);
+        }
+    });
+
+    ui.on_tasas_guardar({
+        let ui_handle = ui_handle.clone();
+        move || {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            let usuario_id = match session::requerir(Permiso::GestionarTasas) {
+                Ok(usuario) => usuario.id,
+                Err(msg) => {
+                    set_tasas_mensaje(&ui, &msg, true);
+                    return;
+                }
+            };
+
+            match tasas::registrar(
+                ui.get_tasas_moneda_index(),
+                ui.get_tasas_fecha().as_str(),
+                ui.get_tasas_valor().as_str(),
+                usuario_id,
+            ) {
+                Ok((moneda, fecha)) => {
+                    ui.set_tasas_valor("".into());
+                    refresh_tasas(&ui);
+                    set_tasas_mensaje(
+                        &ui,
+                        &format!("Tasa de {} del {} registrada", moneda, fecha.format("%d/%m/%Y")),
+                        false,
+                    );
+                }
+                Err(e) => set_tasas_mensaje(&ui, &e.to_string(), true),
+            }
+        }
+    });
+
+    // 10. GESTIÓN DE CLIENTES
+    ui.on_clientes_refrescar({
+        let ui_handle = ui_handle.clone();
+        move || {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            if let Err(msg) = session::requerir(Permiso::GestionarClientes) {
+                set_cliente_mensaje(&ui, &msg, true);
+                return;
+            }
+            refresh_clientes(ui_handle.clone());
+        }
+    });
+
+    ui.on_cliente_seleccionar({
+        let ui_handle = ui_handle.clone();
+        move |index| {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            if let Err(msg) = session::requerir(Permiso::GestionarClientes) {
+                set_cliente_mensaje(&ui, &msg, true);
+                return;
+            }
+            if let Some(c) = clientes::get_cliente_by_index(index) {
+                let cliente_id = c.id;
+                cargar_formulario_cliente(&ui, c);
+                set_cliente_mensaje(&ui, "", false);
+                refresh_compras_cliente(ui_handle.clone(), cliente_id);
+            }
+        }
+    });
+
+    ui.on_cliente_nuevo({
+        let ui_handle = ui_handle.clone();
+        move || {
+            if let Some(ui) = ui_handle.upgrade() {
+                limpiar_formulario_cliente(&ui);
+            }
+        }
+    });
+
+    ui.on_cliente_guardar({
+        let ui_handle = ui_handle.clone();
+        move || {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            if let Err(msg) = session::requerir(Permiso::GestionarClientes) {
+                set_cliente_mensaje(&ui, &msg, true);
+                return;
+            }
+
+            let id = match ui.get_cliente_edit_id() {
+                0 => None,
+                id => Some(id as i64),
+            };
+            let documento = ui.get_cliente_documento().to_string();
+            let nombre = ui.get_cliente_nombre().to_string();
+            let direccion = ui.get_cliente_direccion().to_string();
+            let telefono = ui.get_cliente_telefono().to_string();
+            let email = ui.get_cliente_email().to_string();
+            // Sin permiso para asignar crédito el límite guardado no se toca
+            let limite = session::puede(Permiso::AsignarCredito).then(|| ui.get_cliente_limite().to_string());
+            ui.set_procesando(true);
+
+            tokio::spawn({
+                let ui_handle = ui_handle.clone();
+                async move {
+                    let resultado = clientes::guardar(id, &documento, &nombre, &direccion, &telefono, &email, limite.as_deref())
+                            .map_err(|e| e.to_string());
+
+                    if let Err(e) = ui_handle.upgrade_in_event_loop(move |ui| {
+                        ui.set_procesando(false);
+                        match resultado {
+                            Ok(_) => {
+                                let accion = if id.is_some() { "actualizado" } else { "registrado" };
+                                limpiar_formulario_cliente(&ui);
+                                set_cliente_mensaje(&ui, &format!("Cliente '{}' {}", nombre.trim(), accion), false);
+                                refresh_clientes(ui.as_weak());
+                            }
+                            Err(msg) => set_cliente_mensaje(&ui, &msg, true),
+                        }
+                    }) {
+                        eprintln!("Error al actualizar UI: {}", e);
+                    }
+                }
+            });
+        }
+    });
+
+    ui.on_cliente_solicitar_eliminar({
+        let ui_handle = ui_handle.clone();
+        move |index| {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            if let Err(msg) = session::requerir(Permiso::GestionarClientes) {
+                set_cliente_mensaje(&ui, &msg, true);
+                return;
+            }
+
+            let Some(cliente) = clientes::get_cliente_by_index(index) else {
+                set_cliente_mensaje(&ui, "Seleccione un cliente de la tabla", true);
+                return;
+            };
+
+            match clientes::advertencia_eliminar(&cliente) {
+                Ok(texto) => {
+                    // La confirmación actúa sobre el cliente cargado en el formulario
+                    cargar_formulario_cliente(&ui, cliente);
+                    ui.set_cliente_confirmacion(texto.into());
+                    set_cliente_mensaje(&ui, "", false);
+                }
+                Err(e) => set_cliente_mensaje(&ui, &e.to_string(), true),
+            }
+        }
+    });
+
+    ui.on_cliente_cancelar_eliminar({
+        let ui_handle = ui_handle.clone();
+        move || {
+            if let Some(ui) = ui_handle.upgrade() {
+                ui.set_cliente_confirmacion("".into());
+            }
+        }
+    });
+
+    ui.on_cliente_confirmar_eliminar({
+        let ui_handle = ui_handle.clone();
+        move || {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            if let Err(msg) = session::requerir(Permiso::GestionarClientes) {
+                set_cliente_mensaje(&ui, &msg, true);
+                return;
+            }
+            let id = ui.get_cliente_edit_id() as i64;
+            if id <= 0 {
+                ui.set_cliente_confirmacion("".into());
+                return;
+            }
+            ui.set_procesando(true);
+
+            tokio::spawn({
+                let ui_handle = ui_handle.clone();
+                async move {
+                    let resultado = clientes::eliminar(id).map_err(|e| e.to_string());
+
+                    if let Err(e) = ui_handle.upgrade_in_event_loop(move |ui| {
+                        ui.set_procesando(false);
+                        limpiar_formulario_cliente(&ui);
+                        match resultado {
+                            Ok(true) => {
+                                set_cliente_mensaje(&ui, "Cliente eliminado", false);
+                                refresh_clientes(ui.as_weak());
+                            }
+                            Ok(false) => set_cliente_mensaje(&ui, "El cliente ya no existe", true),
+                            Err(msg) => set_cliente_mensaje(&ui, &msg, true),
+                        }
+                    }) {
+                        eprintln!("Error al actualizar UI: {}", e);
+                    }
+                }
+            });
+        }
+    });
+
+    // 11. CUENTAS POR COBRAR
+    ui.on_cuentas_refrescar({
+        let ui_handle = ui_handle.clone();
+        move || refresh_cuentas(ui_handle.clone())
+    });
+
+    ui.on_cuenta_seleccionar({
+        let ui_handle = ui_handle.clone();
+        move |index| {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            if let Err(msg) = session::requerir(Permiso::GestionarClientes) {
+                set_cuentas_mensaje(&ui, &msg, true);
+                return;
+            }
+            if let Some(cuenta) = credito::get_cuenta_by_index(index) {
+                ui.set_cuenta_cliente_id(cuenta.cliente_id as i32);
+                ui.set_cuenta_cliente(
+                    match cuenta.documento.as_deref() {
+                        Some(documento) => format!("{} · {}", documento, cuenta.nombre),
+                        None => cuenta.nombre.clone(),
+                    }
+                    .into(),
+                );
+                ui.set_abono_monto("".into());
+                ui.set_abono_referencia("".into());
+                set_cuentas_mensaje(&ui, "", false);
+                refresh_estado_de_cuenta(ui_handle.clone(), cuenta.cliente_id);
+            }
+        }
+    });
+
+    ui.on_cuenta_registrar_abono({
+        let ui_handle = ui_handle.clone();
+        move || {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            // El abono entra a la caja de quien lo recibe
+            let usuario_id = match session::requerir(Permiso::OperarCaja) {
+                Ok(usuario) => usuario.id,
+                Err(msg) => {
+                    set_cuentas_mensaje(&ui, &msg, true);
+                    return;
+                }
+            };
+            let cliente_id = match ui.get_cuenta_cliente_id() {
+                0 => {
+                    set_cuentas_mensaje(&ui, "Seleccione un cliente del reporte", true);
+                    return;
+                }
+                id => id as i64,
+            };
+            let metodo_index = ui.get_abono_metodo_index();
+            let monto = ui.get_abono_monto().to_string();
+            let referencia = ui.get_abono_referencia().to_string();
+            ui.set_procesando(true);
+
+            tokio::spawn({
+                let ui_handle = ui_handle.clone();
+                async move {
+                    let resultado = credito::registrar_abono(cliente_id, usuario_id, metodo_index, &monto, &referencia)
+                        .map_err(|e| e.to_string());
+
+                    if let Err(e) = ui_handle.upgrade_in_event_loop(move |ui| {
+                        ui.set_procesando(false);
+                        match resultado {
+                            Ok(abono_id) => {
+                                ui.set_abono_monto("".into());
+                                ui.set_abono_referencia("".into());
+                                set_cuentas_mensaje(&ui, &format!("Abono #{} registrado", abono_id), false);
+                                refresh_cuentas(ui.as_weak());
+                            }
+                            Err(msg) => set_cuentas_mensaje(&ui, &msg, true),
+                        }
+                    }) {
+                        eprintln!("Error al actualizar UI: {}", e);
+                    }
+                }
+            });
+        }
+    });
+
+    // 12. USUARIOS
+    ui.on_usuarios_refrescar({
+        let ui_handle = ui_handle.clone();
+        move || {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            if let Err(msg) = session::requerir(Permiso::GestionarUsuarios) {
+                set_usuario_mensaje(&ui, &msg, true);
+                return;
+            }
+            refresh_usuarios(ui_handle.clone());
+        }
+    });
+
+    ui.on_usuario_seleccionar({
+        let ui_handle = ui_handle.clone();
+        move |index| {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            if let Err(msg) = session::requerir(Permiso::GestionarUsuarios) {
+                set_usuario_mensaje(&ui, &msg, true);
+                return;
+            }
+            if let Some(u) = usuarios::get_usuario_by_index(index) {
+                ui.set_usuario_edit_id(u.id as i32);
+                ui.set_usuario_username(u.username.into());
+                ui.set_usuario_rol_index(usuarios::indice_rol(&u.rol));
+                ui.set_usuario_password("".into());
+                ui.set_usuario_confirmacion("".into());
+                set_usuario_mensaje(&ui, "", false);
+            }
+        }
+    });
+
+    ui.on_usuario_nuevo({
+        let ui_handle = ui_handle.clone();
+        move || {
+            if let Some(ui) = ui_handle.upgrade() {
+                limpiar_formulario_usuario(&ui);
+            }
+        }
+    });
+
+    ui.on_usuario_guardar({
+        let ui_handle = ui_handle.clone();
+        move || {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            let sesion_id = match session::requerir(Permiso::GestionarUsuarios) {
+                Ok(usuario) => usuario.id,
+                Err(msg) => {
+                    set_usuario_mensaje(&ui, &msg, true);
+                    return;
+                }
+            };
+
+            let id = match ui.get_usuario_edit_id() {
+                0 => None,
+                id => Some(id as i64),
+            };
+            let username = ui.get_usuario_username().to_string();
+            let password = ui.get_usuario_password().to_string();
+            let confirmacion = ui.get_usuario_confirmacion().to_string();
+            let rol = usuarios::rol_para_select(ui.get_usuario_rol_index());
+            ui.set_procesando(true);
+
+            // El hash bcrypt de la contraseña es lento: se calcula fuera del hilo de la UI
+            tokio::spawn({
+                let ui_handle = ui_handle.clone();
+                async move {
+                    let resultado = usuarios::guardar(id, &username, &password, &confirmacion, rol, sesion_id)
+                        .map_err(|e| e.to_string());
+
+                    if let Err(e) = ui_handle.upgrade_in_event_loop(move |ui| {
+                        ui.set_procesando(false);
+                        match resultado {
+                            Ok(()) => {
+                                let accion = if id.is_some() { "actualizado" } else { "creado" };
+                                limpiar_formulario_usuario(&ui);
+                                set_usuario_mensaje(&ui, &format!("Usuario '{}' {}", username.trim(), accion), false);
+                                refresh_usuarios(ui.as_weak());
+                            }
+                            Err(msg) => set_usuario_mensaje(&ui, &msg, true),
+                        }
+                    }) {
+                        eprintln!("Error al actualizar UI: {}", e);
+                    }
+                }
+            });
+        }
+    });
+
+    ui.on_usuario_alternar_activo({
+        let ui_handle = ui_handle.clone();
+        move |index| {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            let sesion_id = match session::requerir(Permiso::GestionarUsuarios) {
+                Ok(usuario) => usuario.id,
+                Err(msg) => {
+                    set_usuario_mensaje(&ui, &msg, true);
+                    return;
+                }
+            };
+            let Some(usuario) = usuarios::get_usuario_by_index(index) else {
+                set_usuario_mensaje(&ui, "Seleccione un usuario de la tabla", true);
+                return;
+            };
+
+            match usuarios::alternar_activo(&usuario, sesion_id) {
+                Ok(activo) => {
+                    let estado = if activo { "activado" } else { "desactivado" };
+                    set_usuario_mensaje(&ui, &format!("Usuario '{}' {}", usuario.username, estado), false);
+                    refresh_usuarios(ui_handle.clone());
+                }
+                Err(e) => set_usuario_mensaje(&ui, &e.to_string(), true),
+            }
+        }
+    });
+
+    ui.on_usuario_eliminar({
+        let ui_handle = ui_handle.clone();
+        move |index| {
+            let Some(ui) = ui_handle.upgrade() else { return };
+            let sesion_id = match session::requerir(Permiso::GestionarUsuarios) {
+                Ok(usuario) => usuario.id,
+                Err(msg) => {
+                    set_usuario_mensaje(&ui, &msg, true);
+                    return;
+                }
+            };
+            let Some(usuario) = usuarios::get_usuario_by_index(index) else {
+                set_usuario_mensaje(&ui, "Seleccione un usuario de la tabla", true);
+                return;
+            };
+
+            match usuarios::eliminar(&usuario, sesion_id) {
+                Ok(true) => {
+                    limpiar_formulario_usuario(&ui);
+                    set_usuario_mensaje(&ui, &format!("Usuario '{}' eliminado", usuario.username), false);
+                    refresh_usuarios(ui_handle.clone());
+                }
+                Ok(false) => set_usuario_mensaje(&ui, "El usuario ya no existe", true),
+                Err(e) => set_usuario_mensaje(&ui, &e.to_string(), true),
+            }
+        }
+    });
+
+    ui.on_dashboard_refrescar({
+        let ui_handle = ui_handle.clone();
+        move || refresh_stock_bajo(ui_handle.clone())
+    });
+
+    ui.on_refresh_inventory({
+        let ui_handle = ui_handle.clone();
+        move || {
+            if let Err(msg) = session::requerir(Permiso::VerInventario) {
+                if let Some(ui) = ui_handle.upgrade() {
+                    ui.set_mensaje_error(msg.into());
+                }
+                return;
+            }
+            refresh_ui(ui_handle.clone())
+        }
+    });
+
+    ui.on_close_app({
+        let ui_handle = ui_handle.clone();
+        move || {
+            if let Some(ui) = ui_handle.upgrade() {
+                let _ = ui.hide();
+            }
+        }
+    });
+}
+
+/// Refresca la UI cargando datos en segundo plano (versión con Weak<AppWindow>)
+fn refresh_ui(ui_handle: Weak<AppWindow>) {
+    // Marcas y categorías alimentan los selectores del formulario y sus conteos de productos
+    refresh_marcas(ui_handle.clone());
+    refresh_categorias(ui_handle.clone());
+    refresh_stock_bajo(ui_handle.clone());
+
+    tokio::spawn(async move {
+        // --- HILO SECUNDARIO ---
+        // 1. Buscamos los datos crudos (operación pesada de DB)
+        // Usamos get_inventory_rows_raw() que devuelve tipos Send-safe
+        let filas_res = inventory::get_inventory_rows_raw();
+
+        if let Ok(filas) = filas_res {
+            // 2. Volvemos al hilo de la UI para actualizar la tabla
+            // Los datos crudos (Vec<ProductRowData>) son Send
+            if let Err(e) = ui_handle.upgrade_in_event_loop(move |ui| {
+                // Convertir datos crudos a ModelRc DENTRO del hilo de UI
+                let model_rows = inventory::raw_to_model_rows(filas);
+                ui.set_inventory_rows(model_rows);
+                println!("Tabla actualizada en segundo plano.");
+            }) {
+                eprintln!("Error al actualizar UI: {}", e);
+            }
+        }
+    });
+}
+
+/// Refresca la UI desde el hilo principal (recibe referencia directa)
+fn refresh_ui_from_main(ui: &AppWindow) {
+    let ui_handle = ui.as_weak();
+    refresh_marcas(ui_handle.clone());
+    refresh_categorias(ui_handle.clone());
+    refresh_stock_bajo(ui_handle.clone());
+
+    tokio::spawn(async move {
+        let filas_res = inventory::get_inventory_rows_raw();
+
+        if let Ok(filas) = filas_res {
+            if let Err(e) = ui_handle.upgrade_in_event_loop(move |ui| {
+                let model_rows = inventory::raw_to_model_rows(filas);
+                ui.set_inventory_rows(model_rows);
+                println!("Tabla actualizada en segundo plano.");
+            }) {
+                eprintln!("Error al actualizar UI: {}", e);
+            }
+        }
+    });
+}
+
+/// Recarga las alertas de existencias del dashboard
+fn refresh_stock_bajo(ui_handle: Weak<AppWindow>) {
+    tokio::spawn(async move {
+        match inventory::stock_bajo() {
+            Ok(lista) => {
+                if let Err(e) = ui_handle.upgrade_in_event_loop(move |ui| {
+                    ui.set_stock_bajo_rows(inventory::stock_bajo_to_model_rows(&lista));
+                }) {
+                    eprintln!("Error al actualizar UI: {}", e);
+                }
+            }
+            Err(e) => eprintln!("Error al cargar las alertas de stock: {}", e),
+        }
+    });
+}
+
+/// Recarga la tabla de marcas y las opciones del selector de marca de los formularios
+fn refresh_marcas(ui_handle: Weak<AppWindow>) {
+    tokio::spawn(async move {
+        match marcas::cargar() {
+            Ok(lista) => {
+                if let Err(e) = ui_handle.upgrade_in_event_loop(move |ui| {
+                    ui.set_marcas_rows(marcas::to_model_rows(&lista));
+                    ui.set_lista_marcas(marcas::nombres_para_select(&lista));
+                }) {
+                    eprintln!("Error al actualizar UI: {}", e);
+                }
+            }
+            Err(e) => eprintln!("Error al cargar marcas: {}", e),
+        }
+    });
+}
+
+/// Carga una marca en el formulario de edición
+fn cargar_formulario_marca(ui: &AppWindow, m: MarcaConProductos) {
+    ui.set_marca_edit_id(m.id as i32);
+    ui.set_marca_nombre(m.nombre.into());
+    ui.set_marca_rif(m.rif.unwrap_or_default().into());
+    ui.set_marca_descripcion(m.descripcion.unwrap_or_default().into());
+    ui.set_marca_logo(m.logo.unwrap_or_default().into());
+    ui.set_marca_confirmacion("".into());
+}
+
+/// Deja el formulario listo para una marca nueva
+fn limpiar_formulario_marca(ui: &AppWindow) {
+    ui.set_marca_edit_id(0);
+    ui.set_marca_nombre("".into());
+    ui.set_marca_rif("".into());
+    ui.set_marca_descripcion("".into());
+    ui.set_marca_logo("".into());
+    ui.set_marca_confirmacion("".into());
+}
+
+/// Muestra un mensaje (informativo o de error) en la gestión de marcas
+fn set_marca_mensaje(ui: &AppWindow, mensaje: &str, es_error: bool) {
+    ui.set_marca_mensaje(mensaje.into());
+    ui.set_marca_mensaje_es_error(es_error);
+}
+
+/// Recarga la tabla de categorías y las opciones del selector de categoría de los formularios
+fn refresh_categorias(ui_handle: Weak<AppWindow>) {
+    tokio::spawn(async move {
+        match categorias::cargar() {
+            Ok(lista) => {
+                if let Err(e) = ui_handle.upgrade_in_event_loop(move |ui| {
+                    ui.set_categorias_rows(categorias::to_model_rows(&lista));
+                    ui.set_lista_categorias(categorias::nombres_para_select(&lista));
+                }) {
+                    eprintln!("Error al actualizar UI: {}", e);
+                }
+            }
+            Err(e) => eprintln!("Error al cargar categorías: {}", e),
+        }
+    });
+}
+
+/// Recarga la tabla de subcategorías de la categoría en edición
+fn refresh_subcategorias(ui: &AppWindow, categoria_id: i64) {
+    match categorias::cargar_subcategorias(categoria_id) {
+        Ok(lista) => ui.set_subcategorias_rows(categorias::subcategorias_to_model_rows(&lista)),
+        Err(e) => set_categoria_mensaje(ui, &e.to_string(), true),
+    }
+}
+
+/// Carga una categoría en el formulario de edición junto con sus subcategorías
+fn cargar_formulario_categoria(ui: &AppWindow, c: CategoriaConConteo) {
+    ui.set_categoria_edit_id(c.id as i32);
+    ui.set_categoria_nombre(c.nombre.into());
+    ui.set_categoria_descripcion(c.descripcion.unwrap_or_default().into());
+    ui.set_categoria_impuesto_index(impuestos::indice_impuesto(c.impuesto_id));
+    ui.set_categoria_confirmacion("".into());
+    refresh_subcategorias(ui, c.id);
+}
+
+/// Deja el formulario listo para una categoría nueva
+fn limpiar_formulario_categoria(ui: &AppWindow) {
+    ui.set_categoria_edit_id(0);
+    ui.set_categoria_nombre("".into());
+    ui.set_categoria_descripcion("".into());
+    ui.set_categoria_impuesto_index(impuestos::indice_impuesto(Impuesto::GENERAL));
+    ui.set_categoria_confirmacion("".into());
+    ui.set_subcategorias_rows(categorias::subcategorias_to_model_rows(&[]));
+}
+
+/// Muestra un mensaje (informativo o de error) en la gestión de categorías
+fn set_categoria_mensaje(ui: &AppWindow, mensaje: &str, es_error: bool) {
+    ui.set_categoria_mensaje(mensaje.into());
+    ui.set_categoria_mensaje_es_error(es_error);
+}
+
+/// Recarga la tabla de proveedores y las opciones del selector de la orden de compra
+fn refresh_proveedores(ui_handle: Weak<AppWindow>) {
+    tokio::spawn(async move {
+        match proveedores::cargar() {
+            Ok(lista) => {
+                if let Err(e) = ui_handle.upgrade_in_event_loop(move |ui| {
+                    ui.set_proveedores_rows(proveedores::to_model_rows(&lista));
+                    ui.set_lista_proveedores(proveedores::nombres_para_select(&lista));
+                }) {
+                    eprintln!("Error al actualizar UI: {}", e);
+                }
+            }
+            Err(e) => eprintln!("Error al cargar proveedores: {}", e),
+        }
+    });
+}
+
+/// Carga un proveedor en el formulario de edición
+fn cargar_formulario_proveedor(ui: &AppWindow, p: Proveedor) {
+    ui.set_proveedor_edit_id(p.id as i32);
+    ui.set_proveedor_nombre(p.nombre.into());
+    ui.set_proveedor_rif(p.rif.unwrap_or_default().into());
+    ui.set_proveedor_telefono(p.telefono.unwrap_or_default().into());
+    ui.set_proveedor_email(p.email.unwrap_or_default().into());
+    ui.set_proveedor_direccion(p.direccion.unwrap_or_default().into());
+    ui.set_proveedor_confirmacion("".into());
+}
+
+/// Deja el formulario listo para un proveedor nuevo
+fn limpiar_formulario_proveedor(ui: &AppWindow) {
+    ui.set_proveedor_edit_id(0);
+    ui.set_proveedor_nombre("".into());
+    ui.set_proveedor_rif("".into());
+    ui.set_proveedor_telefono("".into());
+    ui.set_proveedor_email("".into());
+    ui.set_proveedor_direccion("".into());
+    ui.set_proveedor_confirmacion("".into());
+}
+
+/// Muestra un mensaje (informativo o de error) en la gestión de proveedores
+fn set_proveedor_mensaje(ui: &AppWindow, mensaje: &str, es_error: bool) {
+    ui.set_proveedor_mensaje(mensaje.into());
+    ui.set_proveedor_mensaje_es_error(es_error);
+}
+
+/// Recarga la tabla de clientes
+fn refresh_clientes(ui_handle: Weak<AppWindow>) {
+    tokio::spawn(async move {
+        match clientes::cargar() {
+            Ok(lista) => {
+                if let Err(e) = ui_handle.upgrade_in_event_loop(move |ui| {
+                    ui.set_clientes_rows(clientes::to_model_rows(&lista));
+                }) {
+                    eprintln!("Error al actualizar UI: {}", e);
+                }
+            }
+            Err(e) => eprintln!("Error al cargar clientes: {}", e),
+        }
+    });
+}
+
+/// Carga el historial de compras del cliente seleccionado
+fn refresh_compras_cliente(ui_handle: Weak<AppWindow>, cliente_id: i64) {
+    tokio::spawn(async move {
+        match clientes::historial_compras(cliente_id) {
+            Ok(ventas) => {
+                if let Err(e) = ui_handle.upgrade_in_event_loop(move |ui| {
+                    // El usuario pudo elegir otro cliente mientras se cargaba
+                    if ui.get_cliente_edit_id() as i64 == cliente_id {
+                        ui.set_cliente_compras(clientes::compras_to_model_rows(&ventas));
+                        ui.set_cliente_resumen_compras(clientes::resumen_compras(&ventas).into());
+                    }
+                }) {
+                    eprintln!("Error al actualizar UI: {}", e);
+                }
+            }
+            Err(e) => eprintln!("Error al cargar las compras del cliente: {}", e),
+        }
+    });
+}
+
+/// Carga un cliente en el formulario de edición
+fn cargar_formulario_cliente(ui: &AppWindow, c: Cliente) {
+    ui.set_cliente_edit_id(c.id as i32);
+    ui.set_cliente_documento(c.documento.unwrap_or_default().into());
+    ui.set_cliente_nombre(c.nombre.into());
+    ui.set_cliente_direccion(c.direccion.unwrap_or_default().into());
+    ui.set_cliente_telefono(c.telefono.unwrap_or_default().into());
+    ui.set_cliente_email(c.email.unwrap_or_default().into());
+    ui.set_cliente_limite(format!("{:.2}", c.limite_credito).into());
+    ui.set_cliente_saldo(format!("{:.2}", c.saldo).into());
+    ui.set_cliente_confirmacion("".into());
+}
+
+/// Deja el formulario listo para un cliente nuevo
+fn limpiar_formulario_cliente(ui: &AppWindow) {
+    ui.set_cliente_edit_id(0);
+    ui.set_cliente_documento("".into());
+    ui.set_cliente_nombre("".into());
+    ui.set_cliente_direccion("".into());
+    ui.set_cliente_telefono("".into());
+    ui.set_cliente_email("".into());
+    ui.set_cliente_limite("".into());
+    ui.set_cliente_saldo("0.00".into());
+    ui.set_cliente_confirmacion("".into());
+    ui.set_cliente_compras(clientes::compras_to_model_rows(&[]));
+    ui.set_cliente_resumen_compras("".into());
+}
+
+/// Muestra un mensaje (informativo o de error) en la gestión de clientes
+fn set_cliente_mensaje(ui: &AppWindow, mensaje: &str, es_error: bool) {
+    ui.set_cliente_mensaje(mensaje.into());
+    ui.set_cliente_mensaje_es_error(es_error);
+}
+
+/// Recarga el reporte de cuentas por cobrar y, si hay un cliente seleccionado, su estado de cuenta
+fn refresh_cuentas(ui_handle: Weak<AppWindow>) {
+    // Los saldos y movimientos de los clientes solo los ve quien gestiona clientes
+    if let Err(msg) = session::requerir(Permiso::GestionarClientes) {
+        if let Some(ui) = ui_handle.upgrade() {
+            set_cuentas_mensaje(&ui, &msg, true);
+        }
+        return;
+    }
+
+    tokio::spawn(async move {
+        match credito::cargar_cuentas() {
+            Ok(lista) => {
+                if let Err(e) = ui_handle.upgrade_in_event_loop(move |ui| {
+                    ui.set_cuentas_rows(credito::cuentas_to_model_rows(&lista));
+                    ui.set_cuentas_resumen(credito::resumen(&lista).into());
+                    match ui.get_cuenta_cliente_id() {
+                        0 => {}
+                        cliente_id => refresh_estado_de_cuenta(ui.as_weak(), cliente_id as i64),
+                    }
+                }) {
+                    eprintln!("Error al actualizar UI: {}", e);
+                }
+            }
+            Err(e) => eprintln!("Error al cargar las cuentas por cobrar: {}", e),
+        }
+    });
+}
+
+/// Carga el estado de cuenta del cliente seleccionado en el reporte
+fn refresh_estado_de_cuenta(ui_handle: Weak<AppWindow>, cliente_id: i64) {
+    tokio::spawn(async move {
+        match credito::estado_de_cuenta(cliente_id) {
+            Ok(movimientos) => {
+                if let Err(e) = ui_handle.upgrade_in_event_loop(move |ui| {
+                    // El usuario pudo elegir otro cliente mientras se cargaba
+                    if ui.get_cuenta_cliente_id() as i64 == cliente_id {
+                        ui.set_cuenta_movimientos(credito::movimientos_to_model_rows(&movimientos));
+                    }
+                }) {
+                    eprintln!("Error al actualizar UI: {}", e);
+                }
+            }
+            Err(e) => eprintln!("Error al cargar el estado de cuenta: {}", e),
+        }
+    });
+}
+
+/// Muestra los lotes del producto con su stock total (lotes más lo que está sin lote)
+fn mostrar_lotes(ui: &AppWindow, lista: &[Lote], sin_lote: f64) {
+    let en_lotes: f64 = lista.iter().map(|l| l.cantidad).sum();
+    ui.set_lotes_stock(formatear_cantidad(redondear_cantidad(en_lotes + sin_lote)).into());
+    ui.set_lotes_sin_lote(formatear_cantidad(sin_lote.max(0.0)).into());
+    ui.set_lotes_rows(lotes::lotes_to_model_rows(lista));
+}
+
+/// Muestra un mensaje (informativo o de error) en los lotes del producto
+fn set_lotes_mensaje(ui: &AppWindow, mensaje: &str, es_error: bool) {
+    ui.set_lotes_mensaje(mensaje.into());
+    ui.set_lotes_mensaje_es_error(es_error);
+}
+
+/// Muestra un mensaje (informativo o de error) en el reporte de vencimientos
+fn set_vencimientos_mensaje(ui: &AppWindow, mensaje: &str, es_error: bool) {
+    ui.set_vencimientos_mensaje(mensaje.into());
+    ui.set_vencimientos_mensaje_es_error(es_error);
+}
+
+/// Muestra un mensaje (informativo o de error) en las cuentas por cobrar
+fn set_cuentas_mensaje(ui: &AppWindow, mensaje: &str, es_error: bool) {
+    ui.set_cuentas_mensaje(mensaje.into());
+    ui.set_cuentas_mensaje_es_error(es_error);
+}
+
+/// Recarga la tabla de órdenes de compra
+fn refresh_compras(ui_handle: Weak<AppWindow>) {
+    tokio::spawn(async move {
+        match compras::cargar_ordenes() {
+            Ok(lista) => {
+                if let Err(e) = ui_handle.upgrade_in_event_loop(move |ui| {
+                    ui.set_compras_ordenes(compras::ordenes_to_model_rows(&lista));
+                }) {
+                    eprintln!("Error al actualizar UI: {}", e);
+                }
+            }
+            Err(e) => eprintln!("Error al cargar órdenes de compra: {}", e),
+        }
+    });
+}
+
+/// Sincroniza la tabla y el total de la orden en preparación con el estado de `compras`
+fn refresh_borrador_compra(ui: &AppWindow) {
+    ui.set_compras_borrador(compras::borrador_to_model_rows(&compras::borrador()));
+    ui.set_compras_total_borrador(format!("{:.2}", compras::total_borrador()).into());
+}
+
+/// Muestra el detalle de una orden registrada con sus líneas
+fn mostrar_orden_compra(ui: &AppWindow, orden: &OrdenCompra) {
+    match compras::cargar_lineas(orden.id) {
+        Ok(lineas) => {
+            ui.set_compras_orden_id(orden.id as i32);
+            ui.set_compras_orden_titulo(
+                format!("ORDEN #{} · {} · {}", orden.id, orden.proveedor_nombre, orden.estado.etiqueta()).into(),
+            );
+            ui.set_compras_orden_abierta(orden.estado.abierta());
+            ui.set_compras_lineas(compras::lineas_to_model_rows(&lineas));
+        }
+        Err(e) => set_compras_mensaje(ui, &format!("Error al cargar la orden: {}", e), true),
+    }
+}
+
+/// Vuelve al formulario de orden nueva, conservando las líneas ya agregadas
+fn limpiar_orden_compra(ui: &AppWindow) {
+    ui.set_compras_orden_id(0);
+    ui.set_compras_orden_titulo("".into());
+    ui.set_compras_orden_abierta(false);
+    ui.set_compras_lineas(compras::lineas_to_model_rows(&[]));
+    ui.set_compras_resultados(compras::resultados_to_model_rows(&[]));
+    ui.set_compras_notas("".into());
+    refresh_borrador_compra(ui);
+}
+
+/// Refleja el resultado de una recepción: detalle de la orden, tabla de órdenes e inventario
+fn despues_de_recibir(ui: &AppWindow, orden_id: i64, resultado: Result<EstadoOrden, String>) {
+    match resultado {
+        Ok(estado) => {
+            set_compras_mensaje(
+                ui,
+                &format!("Mercancía recibida. Orden #{}: {}", orden_id, estado.etiqueta()),
+                false,
+            );
+            if let Ok(Some(orden)) = compras::cargar_ordenes().map(|l| l.into_iter().find(|o| o.id == orden_id)) {
+                mostrar_orden_compra(ui, &orden);
+            }
+            refresh_compras(ui.as_weak());
+            // El stock y el costo cambiaron: refrescar caché y tabla de inventario
+            refresh_ui_from_main(ui);
+        }
+        Err(msg) => set_compras_mensaje(ui, &msg, true),
+    }
+}
+
+/// Muestra un mensaje (informativo o de error) en las órdenes de compra
+fn set_compras_mensaje(ui: &AppWindow, mensaje: &str, es_error: bool) {
+    ui.set_compras_mensaje(mensaje.into());
+    ui.set_compras_mensaje_es_error(es_error);
+}
+
+/// Muestra una venta del historial con sus líneas, sus pagos y si admite devoluciones
+fn mostrar_venta_historial(ui: &AppWindow, v: &VentaConDetalles) {
+    let cliente = v.venta.cliente_nombre.clone().filter(|c| !c.is_empty());
+    let mut pagos = v
+        .pagos
+        .iter()
+        .map(|p| format!("{} ${:.2}", p.metodo.etiqueta(), p.monto))
+        .collect::<Vec<_>>()
+        .join(" + ");
+    if v.venta.vuelto > 0.0 {
+        pagos.push_str(&format!(" (vuelto ${:.2})", v.venta.vuelto));
+    }
+    // El equivalente en bolívares usa la tasa guardada con la venta, no la del día
+    let total_local = match (v.venta.total_local(), v.venta.tasa_cambio) {
+        (Some(total), Some(tasa)) => format!(" (Bs {:.2} a {:.4})", total, tasa),
+        _ => String::new(),
+    };
+    ui.set_historial_detalle_titulo(
+        format!(
+            "VENTA #{} · {} · {} · ${:.2}{} · {} · {}",
+            v.venta.id,
+            v.venta.fecha.format("%d/%m/%Y %H:%M"),
+            cliente.as_deref().unwrap_or("Sin cliente"),
+            v.venta.total,
+            total_local,
+            v.venta.estado.etiqueta(),
+            if pagos.is_empty() { "Sin pagos" } else { pagos.as_str() }
+        )
+        .into(),
+    );
+    ui.set_historial_detalle(historial_ventas::detalles_to_model_rows(&v.detalles));
+    ui.set_historial_admite_devolucion(v.venta.estado.admite_devolucion());
+}
+
+/// Total neto de las ventas listadas y su desglose en base e IVA
+fn mostrar_totales_historial(ui: &AppWindow, ventas: &[Venta]) {
+    ui.set_historial_total(format!("{:.2}", historial_ventas::total(ventas)).into());
+    let (base, impuesto) = historial_ventas::base_e_impuesto(ventas);
+    ui.set_historial_impuestos(
+        if ventas.is_empty() {
+            String::new()
+        } else {
+            format!("BASE IMPONIBLE: ${:.2}  //  IVA: ${:.2}", base, impuesto)
+        }
+        .into(),
+    );
+}
+
+/// Vacía el panel de detalle del historial
+fn limpiar_detalle_historial(ui: &AppWindow) {
+    historial_ventas::limpiar_seleccion();
+    ui.set_historial_detalle_titulo("".into());
+    ui.set_historial_detalle(historial_ventas::detalles_to_model_rows(&[]));
+    ui.set_historial_admite_devolucion(false);
+}
+
+/// Refleja el resultado de una devolución o anulación: detalle, historial e inventario
+fn despues_de_devolver(ui: &AppWindow, resultado: Result<String, String>) {
+    match resultado {
+        Ok(mensaje) => {
+            // Repetir la búsqueda con los mismos filtros actualiza estados y totales
+            let ventas = historial_ventas::filtro(
+                ui.get_historial_desde().as_str(),
+                ui.get_historial_hasta().as_str(),
+                ui.get_historial_cajero_index(),
+                ui.get_historial_cliente().as_str(),
+            )
+            .map_err(|e| e.into())
+            .and_then(|filtro| historial_ventas::buscar(&filtro));
+            if let Ok(ventas) = ventas {
+                ui.set_historial_ventas(historial_ventas::ventas_to_model_rows(&ventas));
+                mostrar_totales_historial(ui, &ventas);
+            }
+            if let Some(Ok(Some(v))) = historial_ventas::venta_seleccionada().map(historial_ventas::recargar_venta) {
+                mostrar_venta_historial(ui, &v);
+            }
+            set_historial_mensaje(ui, &mensaje, false);
+            // El stock cambió: refrescar caché y tabla de inventario
+            refresh_ui_from_main(ui);
+        }
+        Err(msg) => set_historial_mensaje(ui, &msg, true),
+    }
+}
+
+/// Muestra un mensaje (informativo o de error) en el historial de ventas
+fn set_historial_mensaje(ui: &AppWindow, mensaje: &str, es_error: bool) {
+    ui.set_historial_mensaje(mensaje.into());
+    ui.set_historial_mensaje_es_error(es_error);
+}
+
+/// Carga la caja del usuario en sesión y, si tiene permiso, el reporte de cierres
+fn refresh_caja(ui: &AppWindow) {
+    set_caja_mensaje(ui, "", false);
+    let abierta = match session::usuario_actual() {
+        Some(usuario) => caja::estado_actual(usuario.id),
+        None => Ok(None),
+    };
+    match abierta {
+        Ok(Some(caja::CajaConConteo { sesion, conteo: esperado })) => {
+            ui.set_caja_abierta(true);
+            ui.set_caja_resumen(caja::resumen(&sesion).into());
+            ui.set_caja_esperado(caja::arqueo_to_model_rows(&esperado, false));
+        }
+        Ok(None) => {
+            ui.set_caja_abierta(false);
+            ui.set_caja_resumen("".into());
+            ui.set_caja_esperado(caja::arqueo_to_model_rows(&[], false));
+        }
+        Err(e) => set_caja_mensaje(ui, &format!("Error al cargar la caja: {}", e), true),
+    }
+
+    ui.set_caja_cierre_titulo("".into());
+    ui.set_caja_cierre_arqueo(caja::arqueo_to_model_rows(&[], true));
+    if session::puede(Permiso::VerCierresCaja) {
+        match caja::cargar_cierres() {
+            Ok(cierres) => ui.set_caja_cierres(caja::cierres_to_model_rows(&cierres)),
+            Err(e) => set_caja_mensaje(ui, &format!("Error al cargar los cierres: {}", e), true),
+        }
+    }
+}
+
+/// Muestra un mensaje (informativo o de error) en la caja
+fn set_caja_mensaje(ui: &AppWindow, mensaje: &str, es_error: bool) {
+    ui.set_caja_mensaje(mensaje.into());
+    ui.set_caja_mensaje_es_error(es_error);
+}
+
+/// Genera el PDF o envía a la impresora el recibo de una venta en segundo plano;
+/// el resultado se muestra con `set_mensaje` en la pantalla de origen
+fn emitir_recibo(
+    ui_handle: Weak<AppWindow>,
+    venta_id: i64,
+    imprimir: bool,
+    set_mensaje: fn(&AppWindow, &str, bool),
+) {
+    tokio::spawn(async move {
+        let resultado = if imprimir {
+            recibo::imprimir(venta_id).map(|destino| format!("Recibo de la venta #{} enviado a {}", venta_id, destino))
+        } else {
+            recibo::guardar_pdf(venta_id).map(|ruta| format!("Recibo guardado en {}", ruta.display()))
+        }
+        .map_err(|e| e.to_string());
+
+        if let Err(e) = ui_handle.upgrade_in_event_loop(move |ui| match resultado {
+            Ok(mensaje) => set_mensaje(&ui, &mensaje, false),
+            Err(msg) => set_mensaje(&ui, &msg, true),
+        }) {
+            eprintln!("Error al actualizar UI: {}", e);
+        }
+    });
+}
+
+/// Vuelca la configuración de recibos en el formulario de datos del ticket
+fn cargar_formulario_ticket(ui: &AppWindow, config: &ConfiguracionRecibo) {
+    ui.set_ticket_nombre(config.tienda.nombre.as_str().into());
+    ui.set_ticket_rif(config.tienda.rif.as_str().into());
+    ui.set_ticket_direccion(config.tienda.direccion.as_str().into());
+    ui.set_ticket_telefono(config.tienda.telefono.as_str().into());
+    ui.set_ticket_pie(config.tienda.pie.as_str().into());
+    ui.set_ticket_ancho_index(if config.ancho == AnchoPapel::Mm58 { 0 } else { 1 });
+    ui.set_ticket_impresora(config.impresora.as_deref().unwrap_or_default().into());
+    ui.set_ticket_carpeta(config.carpeta_pdf.as_deref().unwrap_or_default().into());
+}
+
+/// Muestra un mensaje (informativo o de error) en los datos del ticket
+fn set_ticket_mensaje(ui: &AppWindow, mensaje: &str, es_error: bool) {
+    ui.set_ticket_mensaje(mensaje.into());
+    ui.set_ticket_mensaje_es_error(es_error);
+}
+
+/// Carga las monedas, la tasa vigente de cada una y el histórico de tasas
+fn refresh_tasas(ui: &AppWindow) {
+    if let Err(e) = tasas::cargar_monedas() {
+        set_tasas_mensaje(ui, &format!("Error al cargar las monedas: {}", e), true);
+        return;
+    }
+    ui.set_tasas_monedas(tasas::monedas_con_tasa_para_select());
+    ui.set_lista_monedas(tasas::monedas_para_select());
+    match tasas::resumen_vigentes() {
+        Ok(resumen) => ui.set_tasas_vigentes(resumen.into()),
+        Err(e) => set_tasas_mensaje(ui, &format!("Error al leer la tasa vigente: {}", e), true),
+    }
+    match tasas::cargar_tasas() {
+        Ok(lista) => ui.set_tasas_rows(tasas::tasas_to_model_rows(&lista)),
+        Err(e) => set_tasas_mensaje(ui, &format!("Error al cargar las tasas: {}", e), true),
+    }
+}
+
+/// Muestra un mensaje (informativo o de error) en las tasas de cambio
+fn set_tasas_mensaje(ui: &AppWindow, mensaje: &str, es_error: bool) {
+    ui.set_tasas_mensaje(mensaje.into());
+    ui.set_tasas_mensaje_es_error(es_error);
+}
+
+/// Recarga la tabla de usuarios y las opciones del selector de rol
+fn refresh_usuarios(ui_handle: Weak<AppWindow>) {
+    tokio::spawn(async move {
+        match usuarios::cargar() {
+            Ok(lista) => {
+                if let Err(e) = ui_handle.upgrade_in_event_loop(move |ui| {
+                    ui.set_usuarios_rows(usuarios::to_model_rows(&lista));
+                    ui.set_lista_roles(usuarios::roles_para_select());
+                }) {
+                    eprintln!("Error al actualizar UI: {}", e);
+                }
+            }
+            Err(e) => eprintln!("Error al cargar usuarios: {}", e),
+        }
+    });
+}
+
+/// Deja el formulario listo para un usuario nuevo (rol Vendedor, el de menos permisos)
+fn limpiar_formulario_usuario(ui: &AppWindow) {
+    ui.set_usuario_edit_id(0);
+    ui.set_usuario_username("".into());
+    ui.set_usuario_rol_index(usuarios::indice_rol(&Rol::Vendedor));
+    ui.set_usuario_password("".into());
+    ui.set_usuario_confirmacion("".into());
+}
+
+/// Muestra un mensaje (informativo o de error) en la gestión de usuarios
+fn set_usuario_mensaje(ui: &AppWindow, mensaje: &str, es_error: bool) {
+    ui.set_usuario_mensaje(mensaje.into());
+    ui.set_usuario_mensaje_es_error(es_error);
+}
+
+/// Muestra un mensaje (informativo o de error) en la valoración de inventario
+fn set_valoracion_mensaje(ui: &AppWindow, mensaje: &str, es_error: bool) {
+    ui.set_valoracion_mensaje(mensaje.into());
+    ui.set_valoracion_mensaje_es_error(es_error);
+}
+
+/// Refleja en la UI los permisos del usuario en sesión (todo en false sin sesión)
+fn aplicar_permisos(ui: &AppWindow) {
+    ui.set_puede_ver_inventario(session::puede(Permiso::VerInventario));
+    ui.set_puede_crear_producto(session::puede(Permiso::CrearProducto));
+    ui.set_puede_editar_producto(session::puede(Permiso::EditarProducto));
+    ui.set_puede_eliminar_producto(session::puede(Permiso::EliminarProducto));
+    ui.set_puede_vender(session::puede(Permiso::RegistrarVenta));
+    ui.set_puede_gestionar_marcas(session::puede(Permiso::GestionarMarcas));
+    ui.set_puede_gestionar_categorias(session::puede(Permiso::GestionarCategorias));
+    ui.set_puede_ver_kardex(session::puede(Permiso::VerKardex));
+    ui.set_puede_gestionar_compras(session::puede(Permiso::GestionarCompras));
+    ui.set_puede_ver_valoracion(session::puede(Permiso::VerValoracion));
+    ui.set_puede_ver_historial(session::puede(Permiso::VerHistorialVentas));
+    ui.set_puede_devolver(session::puede(Permiso::RegistrarDevolucion));
+    ui.set_puede_anular_venta(session::puede(Permiso::AnularVenta));
+    ui.set_puede_configurar(session::puede(Permiso::ConfigurarTienda));
+    ui.set_puede_operar_caja(session::puede(Permiso::OperarCaja));
+    ui.set_puede_ver_cierres(session::puede(Permiso::VerCierresCaja));
+    ui.set_puede_gestionar_tasas(session::puede(Permiso::GestionarTasas));
+    ui.set_puede_gestionar_clientes(session::puede(Permiso::GestionarClientes));
+    ui.set_puede_asignar_credito(session::puede(Permiso::AsignarCredito));
+    ui.set_puede_gestionar_usuarios(session::puede(Permiso::GestionarUsuarios));
+}
+
+/// Sincroniza el carrito, los pagos y los totales con el estado de `pos`
+fn refresh_pos(ui: &AppWindow) {
+    ui.set_pos_carrito(pos::carrito_to_model_rows(&pos::items()));
+    ui.set_pos_total(format!("{:.2}", pos::total()).into());
+    ui.set_pos_desglose(
+        if pos::items().is_empty() {
+            String::new()
+        } else {
+            let (base, impuesto) = pos::base_e_impuesto();
+            format!("Base {:.2} · IVA {:.2} ({})", base, impuesto, pos::modo_precios().etiqueta())
+        }
+        .into(),
+    );
+    ui.set_pos_total_local(
+        match (pos::total_local(), pos::tasa()) {
+            (Some(total), Some(tasa)) => format!("Bs {:.2} (tasa {:.4})", total, tasa),
+            _ if pos::items().is_empty() => String::new(),
+            _ => "Sin tasa de cambio registrada".to_string(),
+        }
+        .into(),
+    );
+    ui.set_pos_pagos(pos::pagos_to_model_rows(&pos::pagos()));
+    ui.set_pos_pagado(format!("{:.2}", pos::pagado()).into());
+    ui.set_pos_restante(format!("{:.2}", pos::restante()).into());
+    ui.set_pos_vuelto(format!("{:.2}", pos::vuelto()).into());
+    ui.set_pos_credito(format!("{:.2}", pos::credito()).into());
+}
+
+/// Vuelve el cliente del punto de venta al consumidor final y limpia su búsqueda
+fn reiniciar_cliente_pos(ui: &AppWindow) {
+    ui.set_pos_cliente_busqueda("".into());
+    match clientes::buscar_para_pos("") {
+        Ok(lista) => ui.set_pos_lista_clientes(clientes::clientes_pos_para_select(&lista)),
+        Err(e) => eprintln!("Error al cargar clientes: {}", e),
+    }
+    ui.set_pos_cliente_index(0);
+}
+
+/// Muestra un mensaje (informativo o de error) en el punto de venta
+fn set_pos_mensaje(ui: &AppWindow, mensaje: &str, es_error: bool) {
+    ui.set_pos_mensaje(mensaje.into());
+    ui.set_pos_mensaje_es_error(es_error);
+}
+
+/// Carga los datos iniciales de forma asíncrona
+pub fn load_initial_data(ui: &AppWindow) {
+    let ui_handle = ui.as_weak();
+    refresh_marcas(ui_handle.clone());
+    refresh_categorias(ui_handle.clone());
+    ui.set_pos_metodos(pos::metodos_para_select());
+    match tasas::cargar_monedas() {
+        Ok(_) => ui.set_lista_monedas(tasas::monedas_para_select()),
+        Err(e) => eprintln!("Error al cargar monedas: {}", e),
+    }
+    match impuestos::cargar_impuestos() {
+        Ok(_) => {
+            ui.set_lista_impuestos(impuestos::impuestos_para_select());
+            ui.set_lista_impuestos_producto(impuestos::impuestos_producto_para_select());
+        }
+        Err(e) => eprintln!("Error al cargar impuestos: {}", e),
+    }
+
     tokio::spawn(async move {
         // --- HILO SECUNDARIO ---
         // 1. Buscamos los datos crudos (operación pesada de DB)
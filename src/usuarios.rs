@@ -0,0 +1,183 @@
+use crate::db;
+use crate::models::{Rol, Usuario};
+use rusqlite::Connection;
+use slint::{ModelRc, SharedString, StandardListViewItem, VecModel};
+use std::rc::Rc;
+use std::sync::{Mutex, OnceLock};
+
+/// Caché de usuarios en el orden de la tabla de gestión
+static USUARIOS: OnceLock<Mutex<Vec<Usuario>>> = OnceLock::new();
+
+fn get_cache() -> &'static Mutex<Vec<Usuario>> {
+    USUARIOS.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+/// Reglas de una contraseña nueva (cambio obligatorio, alta o restablecimiento)
+pub fn validar_password(username: &str, nueva: &str, confirmacion: &str) -> Result<(), String> {
+    if nueva.chars().count() < 6 {
+        return Err("La contraseña debe tener al menos 6 caracteres".to_string());
+    }
+    if nueva != confirmacion {
+        return Err("Las contraseñas no coinciden".to_string());
+    }
+    if nueva == username || nueva == "admin" {
+        return Err("La nueva contraseña no puede ser el nombre de usuario ni la contraseña por defecto".to_string());
+    }
+    Ok(())
+}
+
+/// Lee los usuarios y actualiza la caché (Send-safe)
+pub fn cargar() -> Result<Vec<Usuario>, Box<dyn std::error::Error>> {
+    let conn = db::obtener_conexion()?;
+    let usuarios = db::usuarios::obtener_usuarios(&conn)?;
+    *get_cache().lock().unwrap() = usuarios.clone();
+    Ok(usuarios)
+}
+
+/// Usuario de la fila indicada en la tabla de gestión
+pub fn get_usuario_by_index(index: i32) -> Option<Usuario> {
+    get_cache().lock().unwrap().get(index as usize).cloned()
+}
+
+/// Usuario de la caché con el ID indicado (el cargado en el formulario)
+fn get_usuario_by_id(id: i64) -> Option<Usuario> {
+    get_cache().lock().unwrap().iter().find(|u| u.id == id).cloned()
+}
+
+/// Índice del selector de rol -> Rol (un índice inválido cae en el de menos permisos)
+pub fn rol_para_select(index: i32) -> Rol {
+    Rol::TODOS.get(index as usize).cloned().unwrap_or(Rol::Vendedor)
+}
+
+/// Rol -> índice del selector de rol
+pub fn indice_rol(rol: &Rol) -> i32 {
+    Rol::TODOS.iter().position(|r| r == rol).unwrap_or(0) as i32
+}
+
+/// Nombres para el selector de rol, en el orden de `Rol::TODOS`
+pub fn roles_para_select() -> ModelRc<SharedString> {
+    let nombres: Vec<SharedString> = Rol::TODOS.iter().map(|r| SharedString::from(r.to_string())).collect();
+    ModelRc::from(Rc::new(VecModel::from(nombres)))
+}
+
+/// Falla si el usuario es el único administrador activo (quitarle el rol o desactivarlo
+/// dejaría la aplicación sin nadie que gestione usuarios)
+fn verificar_otro_admin(conn: &Connection, usuario: &Usuario) -> Result<(), Box<dyn std::error::Error>> {
+    if usuario.rol == Rol::Admin
+        && usuario.activo
+        && db::usuarios::contar_otros_admins_activos(conn, usuario.id)? == 0
+    {
+        return Err(format!("'{}' es el único administrador activo", usuario.username).into());
+    }
+    Ok(())
+}
+
+/// Crea un usuario (id = None) o actualiza el rol y, si se indica, la contraseña de uno existente.
+/// La contraseña queda provisional: se pedirá cambiarla al iniciar sesión.
+/// `sesion_id` es el usuario que hace el cambio, que no puede modificar su propio rol.
+pub fn guardar(
+    id: Option<i64>,
+    username: &str,
+    password: &str,
+    confirmacion: &str,
+    rol: Rol,
+    sesion_id: i64,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut conn = db::obtener_conexion()?;
+
+    let Some(id) = id else {
+        let username = username.trim();
+        if username.is_empty() || username.contains(char::is_whitespace) {
+            return Err("El nombre de usuario es requerido y no puede tener espacios".into());
+        }
+        if db::usuarios::existe_username(&conn, username)? {
+            return Err(format!("Ya existe el usuario '{}'", username).into());
+        }
+        validar_password(username, password, confirmacion)?;
+        db::usuarios::crear_usuario(&conn, username, password, rol)?;
+        return Ok(());
+    };
+
+    let Some(usuario) = get_usuario_by_id(id) else {
+        return Err("El usuario ya no existe".into());
+    };
+    if !password.is_empty() {
+        validar_password(&usuario.username, password, confirmacion)?;
+    }
+    if rol != usuario.rol {
+        if id == sesion_id {
+            return Err("No puede cambiar su propio rol".into());
+        }
+        verificar_otro_admin(&conn, &usuario)?;
+    }
+
+    // El rol y la contraseña se guardan juntos (o ninguno)
+    let tx = conn.transaction()?;
+    if !db::usuarios::cambiar_rol(&tx, id, rol)? {
+        return Err("El usuario ya no existe".into());
+    }
+    if !password.is_empty() {
+        db::usuarios::restablecer_password(&tx, id, password)?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// Activa o desactiva al usuario. Devuelve el nuevo estado.
+pub fn alternar_activo(usuario: &Usuario, sesion_id: i64) -> Result<bool, Box<dyn std::error::Error>> {
+    if usuario.id == sesion_id {
+        return Err("No puede desactivar su propio usuario".into());
+    }
+    let conn = db::obtener_conexion()?;
+    verificar_otro_admin(&conn, usuario)?;
+    if !db::usuarios::cambiar_activo(&conn, usuario.id, !usuario.activo)? {
+        return Err("El usuario ya no existe".into());
+    }
+    Ok(!usuario.activo)
+}
+
+/// Elimina al usuario. Quien ya registró ventas, movimientos o cajas no se puede eliminar
+/// (se conserva quién hizo cada operación): se debe desactivar.
+pub fn eliminar(usuario: &Usuario, sesion_id: i64) -> Result<bool, Box<dyn std::error::Error>> {
+    if usuario.id == sesion_id {
+        return Err("No puede eliminar su propio usuario".into());
+    }
+    let conn = db::obtener_conexion()?;
+    verificar_otro_admin(&conn, usuario)?;
+    match db::usuarios::eliminar_usuario(&conn, usuario.id) {
+        Ok(eliminado) => Ok(eliminado),
+        Err(rusqlite::Error::SqliteFailure(e, _)) if e.code == rusqlite::ErrorCode::ConstraintViolation => {
+            Err(format!(
+                "No se puede eliminar '{}': tiene operaciones registradas. Desactívelo.",
+                usuario.username
+            )
+            .into())
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Convierte los usuarios a filas de tabla (Usuario, Rol, Estado, Último acceso)
+pub fn to_model_rows(usuarios: &[Usuario]) -> ModelRc<ModelRc<StandardListViewItem>> {
+    let rows: Vec<ModelRc<StandardListViewItem>> = usuarios
+        .iter()
+        .map(|u| {
+            let estado = match (u.activo, u.debe_cambiar_password) {
+                (false, _) => "INACTIVO",
+                (true, true) => "CAMBIO DE CONTRASEÑA PENDIENTE",
+                (true, false) => "ACTIVO",
+            };
+            let row_data = vec![
+                StandardListViewItem::from(SharedString::from(u.username.as_str())),
+                StandardListViewItem::from(SharedString::from(u.rol.to_string())),
+                StandardListViewItem::from(SharedString::from(estado)),
+                StandardListViewItem::from(SharedString::from(
+                    u.ultimo_login.map(|f| f.format("%d/%m/%Y %H:%M").to_string()).unwrap_or_else(|| "-".into()),
+                )),
+            ];
+            ModelRc::from(Rc::new(VecModel::from(row_data)))
+        })
+        .collect();
+
+    ModelRc::from(Rc::new(VecModel::from(rows)))
+}
@@ -0,0 +1,90 @@
+use crate::db;
+use crate::models::medida::{formatear_cantidad, redondear_cantidad};
+use crate::models::{ValoracionGrupo, ValoracionProducto};
+use chrono::{NaiveDate, NaiveDateTime};
+use slint::{ModelRc, SharedString, StandardListViewItem, VecModel};
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+/// Interpreta la fecha de corte (dd/mm/aaaa). Vacía = valoración actual.
+/// Incluye todos los movimientos de ese día local (el corte se pasa a UTC, como el kardex).
+pub fn parse_fecha_corte(texto: &str) -> Result<Option<NaiveDateTime>, String> {
+    let texto = texto.trim();
+    if texto.is_empty() {
+        return Ok(None);
+    }
+    let fecha = NaiveDate::parse_from_str(texto, "%d/%m/%Y")
+        .map_err(|_| "La fecha debe tener el formato dd/mm/aaaa".to_string())?;
+    Ok(fecha.and_hms_opt(23, 59, 59).map(db::local_a_utc))
+}
+
+/// Valoración del inventario a la fecha de corte indicada (Send-safe)
+pub fn cargar(fecha: &str) -> Result<Vec<ValoracionProducto>, Box<dyn std::error::Error>> {
+    let hasta = parse_fecha_corte(fecha)?;
+    let conn = db::obtener_conexion()?;
+    Ok(db::movimientos::valoracion_al(&conn, hasta)?)
+}
+
+/// Suma la valoración por la clave indicada (marca o categoría), ordenada por nombre
+pub fn agrupar(
+    productos: &[ValoracionProducto],
+    clave: impl Fn(&ValoracionProducto) -> &str,
+) -> Vec<ValoracionGrupo> {
+    let mut grupos: BTreeMap<String, ValoracionGrupo> = BTreeMap::new();
+    for p in productos {
+        let grupo = grupos.entry(clave(p).to_string()).or_insert_with(|| ValoracionGrupo {
+            nombre: clave(p).to_string(),
+            productos: 0,
+            unidades: 0.0,
+            valor: 0.0,
+        });
+        grupo.productos += 1;
+        grupo.unidades = redondear_cantidad(grupo.unidades + p.stock);
+        grupo.valor += p.valor();
+    }
+    grupos.into_values().collect()
+}
+
+/// Valor total del inventario
+pub fn total(productos: &[ValoracionProducto]) -> f64 {
+    productos.iter().map(|p| p.valor()).sum()
+}
+
+/// Convierte la valoración a filas de tabla (Código, Producto, Marca, Categoría, Stock, Costo prom., Valor)
+pub fn productos_to_model_rows(productos: &[ValoracionProducto]) -> ModelRc<ModelRc<StandardListViewItem>> {
+    let rows: Vec<ModelRc<StandardListViewItem>> = productos
+        .iter()
+        .map(|p| {
+            let row_data = vec![
+                StandardListViewItem::from(SharedString::from(p.codigo.clone().unwrap_or_else(|| "S/C".into()))),
+                StandardListViewItem::from(SharedString::from(p.nombre.as_str())),
+                StandardListViewItem::from(SharedString::from(p.marca.as_str())),
+                StandardListViewItem::from(SharedString::from(p.categoria.as_str())),
+                StandardListViewItem::from(SharedString::from(formatear_cantidad(p.stock))),
+                StandardListViewItem::from(SharedString::from(format!("{:.2}", p.costo_promedio))),
+                StandardListViewItem::from(SharedString::from(format!("{:.2}", p.valor()))),
+            ];
+            ModelRc::from(Rc::new(VecModel::from(row_data)))
+        })
+        .collect();
+
+    ModelRc::from(Rc::new(VecModel::from(rows)))
+}
+
+/// Convierte los totales agrupados a filas de tabla (Nombre, Productos, Unidades, Valor)
+pub fn grupos_to_model_rows(grupos: &[ValoracionGrupo]) -> ModelRc<ModelRc<StandardListViewItem>> {
+    let rows: Vec<ModelRc<StandardListViewItem>> = grupos
+        .iter()
+        .map(|g| {
+            let row_data = vec![
+                StandardListViewItem::from(SharedString::from(g.nombre.as_str())),
+                StandardListViewItem::from(SharedString::from(g.productos.to_string())),
+                StandardListViewItem::from(SharedString::from(formatear_cantidad(g.unidades))),
+                StandardListViewItem::from(SharedString::from(format!("{:.2}", g.valor))),
+            ];
+            ModelRc::from(Rc::new(VecModel::from(row_data)))
+        })
+        .collect();
+
+    ModelRc::from(Rc::new(VecModel::from(rows)))
+}